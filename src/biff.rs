@@ -0,0 +1,311 @@
+//! Just enough of the binary record formats of Excel workbooks to find and update the records
+//! of protection and sheet visibility. Excel files between 1997 & 2003 use [BIFF8](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-xls/cd03cb5f-ca02-4934-a391-bb674cb8aa06)
+//! and the parts of xlsb files use [BIFF12](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-xlsb/acc8aa92-1f02-4167-99f5-84f9f676b95a)
+
+use std::io::{Read, Seek, Write};
+use std::ops::Range;
+
+use crate::error::{UnlockError, UnlockResult};
+use crate::ovba::types::number::Integer;
+use cfb::CompoundFile;
+use tracing::{info, trace};
+
+/// The streams that might hold the workbook within an Excel file between 1997 & 2003. Workbook is
+/// used since Excel 97, Book by earlier versions
+const WORKBOOK_STREAMS: [&str; 2] = ["/Workbook", "/Book"];
+
+/// The BIFF8 record that starts a substream, either the workbook globals or a sheet
+const BOF: u16 = 0x0809;
+/// The BIFF8 record that ends a substream
+const EOF: u16 = 0x000A;
+/// The BIFF8 record that marks the rest of the stream as encrypted
+const FILEPASS: u16 = 0x002F;
+
+/// The BIFF8 record that protects a sheet, or the workbook structure in the globals substream
+pub const PROTECT: u16 = 0x0012;
+/// The BIFF8 record holding the hash of the password of the protection
+pub const PASSWORD: u16 = 0x0013;
+/// The BIFF8 record that protects the objects of a sheet
+pub const OBJPROTECT: u16 = 0x0063;
+/// The BIFF8 record that protects the scenarios of a sheet
+pub const SCENPROTECT: u16 = 0x00DD;
+
+/// The BIFF8 record that protects the windows of the workbook
+pub const WINDOWPROTECT: u16 = 0x0019;
+
+/// The BIFF8 record that stops the change history of a shared workbook being turned off
+pub const PROT4REV: u16 = 0x01AF;
+/// The BIFF8 record holding the hash of the password of the shared workbook protection
+pub const PROT4REVPASS: u16 = 0x01BC;
+
+/// The BIFF8 record marking the workbook as having a password to write to it
+pub const WRITEPROT: u16 = 0x0086;
+/// The BIFF8 record with the password to write to the workbook, and whether it is recommended to
+/// open it read-only
+pub const FILESHARING: u16 = 0x005B;
+/// The BIFF8 record, with no data, marking the file as saved by Excel 2000 or later. It changes
+/// nothing when read, so it can stand in for a record with no data that has to go
+pub const EXCEL9FILE: u16 = 0x01C0;
+
+/// The BIFF8 record naming a sheet of the workbook, along with whether it is hidden
+pub const BOUNDSHEET: u16 = 0x0085;
+
+/// The BIFF12 record protecting a sheet, and its variant with a stronger password hash
+pub const BRT_SHEET_PROTECTION: [u16; 2] = [0x0217, 0x0416];
+/// The BIFF12 record protecting the workbook structure and windows, and its variant with a
+/// stronger password hash
+pub const BRT_BOOK_PROTECTION: [u16; 2] = [0x0216, 0x0428];
+/// The offsets of the two byte password hash and flag of the shared workbook protection, within
+/// the data of each of the workbook protection records
+pub const BRT_BOOK_PROTECTION_REVISION: [&[usize]; 2] = [&[2, 8], &[12]];
+
+/// The BIFF12 record with the password to write to the workbook, and whether it is recommended to
+/// open it read-only, and its variant with a stronger password hash
+pub const BRT_FILE_SHARING: [u16; 2] = [0x0224, 0x0423];
+/// The BIFF12 record naming a sheet of the workbook, along with whether it is hidden
+pub const BRT_BUNDLE_SH: u16 = 0x009C;
+
+/// The state of a sheet that can only be shown again from VBA, as stored in both BIFF8 and BIFF12
+pub const VERY_HIDDEN: u8 = 2;
+
+/// Which part of the BIFF8 workbook stream a record is in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Substream {
+    /// The first substream, with the settings of the whole workbook
+    Globals,
+    /// A worksheet, chart sheet or macro sheet, which starts at this offset of the stream
+    Sheet(usize),
+}
+
+/// Pass the type and data of every record of the BIFF8 workbook stream to the update, which
+/// changes them in place and says whether it did. Returns how many records were changed. The
+/// length of the stream is unchanged, so all the offsets within it stay valid
+pub fn update_records_8<F>(stream: &mut [u8], mut update: F) -> UnlockResult<usize>
+where
+    F: FnMut(Substream, &mut u16, &mut [u8]) -> bool,
+{
+    let mut updated = 0;
+    let mut depth = 0_usize;
+    let mut globals = true;
+    let mut start = 0;
+    let mut pos = 0;
+    while pos + 4 <= stream.len() {
+        let record = u16::from_le_slice(&stream[pos..pos + 2]);
+        let length = usize::from(u16::from_le_slice(&stream[pos + 2..pos + 4]));
+        let data = pos + 4..pos + 4 + length;
+        if data.end > stream.len() {
+            return Err(UnlockError::SheetRecords("the workbook stream".to_string()));
+        }
+        match record {
+            BOF => {
+                if depth == 0 {
+                    start = pos;
+                }
+                depth += 1;
+            }
+            FILEPASS => return Err(UnlockError::Encrypted),
+            _ => (),
+        }
+        let substream = if globals {
+            Substream::Globals
+        } else {
+            Substream::Sheet(start)
+        };
+        let mut new_record = record;
+        if update(substream, &mut new_record, &mut stream[data.clone()]) {
+            trace!("updated record {record:#06x} at {pos}");
+            stream[pos..pos + 2].copy_from_slice(&new_record.to_le_bytes());
+            updated += 1;
+        }
+        if record == EOF {
+            depth = depth.saturating_sub(1);
+            // Everything after the globals is a sheet, however the substreams are nested
+            globals &= depth > 0;
+        }
+        pos = data.end;
+    }
+    Ok(updated)
+}
+
+/// The name of every sheet in the BIFF8 workbook stream, along with the offset of the start of
+/// the sheet within the stream
+pub fn sheets_8(stream: &[u8]) -> UnlockResult<Vec<(String, usize)>> {
+    let mut sheets = Vec::new();
+    let mut valid = true;
+    update_records_8(&mut stream.to_vec(), |substream, record, data| {
+        if substream == Substream::Globals && *record == BOUNDSHEET {
+            match bound_sheet(data) {
+                Some(sheet) => sheets.push(sheet),
+                None => valid = false,
+            }
+        }
+        false
+    })?;
+    if !valid {
+        return Err(UnlockError::SheetRecords("the workbook stream".to_string()));
+    }
+    Ok(sheets)
+}
+
+/// Read the offset and name from the data of a BIFF8 sheet record. The name is a count of
+/// characters then a flag saying whether they take one or two bytes
+fn bound_sheet(data: &[u8]) -> Option<(String, usize)> {
+    let offset = u32::from_le_slice(data.get(0..4)?);
+    let count = usize::from(*data.get(6)?);
+    let chars = data.get(8..)?;
+    let name = if data.get(7)? & 0x01 == 0 {
+        chars.get(..count)?.iter().map(|&c| char::from(c)).collect()
+    } else {
+        utf_16(chars.get(..count * 2)?)
+    };
+    Some((name, usize::try_from(offset).ok()?))
+}
+
+/// Update the records of the workbook stream of the CFB file, as for [`update_records_8`]
+pub fn update_workbook_stream<T, F>(file: &mut CompoundFile<T>, update: F) -> UnlockResult<()>
+where
+    T: Read + Write + Seek,
+    F: FnMut(Substream, &mut u16, &mut [u8]) -> bool,
+{
+    let (path, mut stream) = read_workbook_stream(file)?;
+    let updated = update_records_8(&mut stream, update)?;
+    info!("updated {updated} records of the workbook stream");
+    if updated > 0 {
+        let mut workbook = file.open_stream(path)?;
+        workbook.write_all(&stream)?;
+        workbook.flush()?;
+    }
+    Ok(())
+}
+
+/// Read the workbook stream of the CFB file, returning its path along with its contents
+pub fn read_workbook_stream<T: Read + Seek>(
+    file: &mut CompoundFile<T>,
+) -> UnlockResult<(&'static str, Vec<u8>)> {
+    let path = WORKBOOK_STREAMS
+        .into_iter()
+        .find(|p| file.is_stream(p))
+        .ok_or_else(|| UnlockError::NoStream(WORKBOOK_STREAMS[0].to_string()))?;
+    let mut stream = Vec::new();
+    file.open_stream(path)?.read_to_end(&mut stream)?;
+    Ok((path, stream))
+}
+
+/// Copy a BIFF12 part of an xlsb file, leaving out every record of the given types. Returns the
+/// new part and how many records were dropped
+pub fn remove_records_12(part: &[u8], name: &str, types: &[u16]) -> UnlockResult<(Vec<u8>, usize)> {
+    let mut output = Vec::with_capacity(part.len());
+    let mut removed = 0;
+    walk_records_12(part, name, |record, whole, _| {
+        if types.contains(&record) {
+            trace!("dropping record {record:#06x} from {name}");
+            removed += 1;
+        } else {
+            output.extend_from_slice(&part[whole]);
+        }
+    })?;
+    Ok((output, removed))
+}
+
+/// Copy a BIFF12 part of an xlsb file, passing the data of every record to the update, which
+/// changes it in place and says whether it did. Returns the new part and how many records were
+/// changed
+pub fn update_records_12<F>(
+    part: &[u8],
+    name: &str,
+    mut update: F,
+) -> UnlockResult<(Vec<u8>, usize)>
+where
+    F: FnMut(u16, &mut [u8]) -> bool,
+{
+    let mut output = part.to_vec();
+    let mut updated = 0;
+    walk_records_12(part, name, |record, _, data| {
+        if update(record, &mut output[data]) {
+            trace!("updated record {record:#06x} of {name}");
+            updated += 1;
+        }
+    })?;
+    Ok((output, updated))
+}
+
+/// The relationship id and name of every sheet in the workbook part of an xlsb file
+pub fn sheets_12(part: &[u8], name: &str) -> UnlockResult<Vec<(String, String)>> {
+    let mut sheets = Vec::new();
+    let mut valid = true;
+    walk_records_12(part, name, |record, _, data| {
+        if record == BRT_BUNDLE_SH {
+            // The relationship id and name follow the four byte state and tab id
+            let mut pos = 8;
+            match (
+                wide_string(&part[data.clone()], &mut pos),
+                wide_string(&part[data], &mut pos),
+            ) {
+                (Some(id), Some(sheet)) => sheets.push((id, sheet)),
+                _ => valid = false,
+            }
+        }
+    })?;
+    if !valid {
+        return Err(UnlockError::SheetRecords(name.to_string()));
+    }
+    Ok(sheets)
+}
+
+/// Read a BIFF12 string: a four byte count of characters, then the characters in UTF-16. A
+/// count of all ones is a missing string, which is read as empty
+fn wide_string(data: &[u8], pos: &mut usize) -> Option<String> {
+    let count = u32::from_le_slice(data.get(*pos..*pos + 4)?);
+    *pos += 4;
+    if count == u32::MAX {
+        return Some(String::new());
+    }
+    let end = usize::try_from(count).ok()?.checked_mul(2)? + *pos;
+    let string = utf_16(data.get(*pos..end)?);
+    *pos = end;
+    Some(string)
+}
+
+pub fn utf_16(data: &[u8]) -> String {
+    let units: Vec<u16> = data.chunks_exact(2).map(u16::from_le_slice).collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// Call the function with the type of every record of a BIFF12 part, along with where the whole
+/// record and just its data are found
+fn walk_records_12<F>(part: &[u8], name: &str, mut f: F) -> UnlockResult<()>
+where
+    F: FnMut(u16, Range<usize>, Range<usize>),
+{
+    let error = || UnlockError::SheetRecords(name.to_string());
+    let mut pos = 0;
+    while pos < part.len() {
+        let start = pos;
+        let record = read_varint(part, &mut pos, 2).ok_or_else(error)?;
+        let length = read_varint(part, &mut pos, 4).ok_or_else(error)?;
+        let end = pos
+            .checked_add(usize::try_from(length).map_err(|_| error())?)
+            .filter(|&end| end <= part.len())
+            .ok_or_else(error)?;
+        // Record types are at most two bytes of seven bits, so always fit
+        let record = u16::try_from(record).map_err(|_| error())?;
+        f(record, start..end, pos..end);
+        pos = end;
+    }
+    Ok(())
+}
+
+/// Read a BIFF12 variable length number: seven bits a byte, lowest first, with the top bit set
+/// while there are more bytes to come
+fn read_varint(data: &[u8], pos: &mut usize, max_bytes: u32) -> Option<u32> {
+    let mut value = 0;
+    for i in 0..max_bytes {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        value |= u32::from(byte & 0x7F) << (7 * i);
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    Some(value)
+}
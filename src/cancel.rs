@@ -0,0 +1,67 @@
+//! Cancelling long-running operations from another thread.
+//!
+//! An operation run with [`CancellationToken::run`] checks the token as it goes, in the loops that
+//! try password candidates and that copy the entries of a zip archive. Once the token is
+//! cancelled, the operation stops at the next check with [`UnlockError::Cancelled`], removing any
+//! file it was part way through writing
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::error::{UnlockError, UnlockResult};
+
+thread_local! {
+    static CURRENT: RefCell<Option<CancellationToken>> = const { RefCell::new(None) };
+}
+
+/// A handle for stopping an operation from another thread. Clones share the same state, so one
+/// clone can be kept to cancel the operation run with another
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// A token that has not been cancelled
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask every operation run with the token, or any of its clones, to stop
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the token has been cancelled
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Run the operation on this thread, stopping it early if the token is cancelled
+    ///
+    /// # Errors
+    /// Will return [`UnlockError::Cancelled`] if the operation is stopped by the token, or any
+    /// error of the operation itself
+    pub fn run<T>(&self, operation: impl FnOnce() -> UnlockResult<T>) -> UnlockResult<T> {
+        let previous = CURRENT.with(|current| current.replace(Some(self.clone())));
+        let result = operation();
+        CURRENT.with(|current| current.replace(previous));
+        result
+    }
+}
+
+/// The token of the operation running on this thread, if there is one
+pub(crate) fn current() -> Option<CancellationToken> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Stop with an error if the operation running on this thread has been cancelled
+pub(crate) fn check() -> UnlockResult<()> {
+    if current().is_some_and(|token| token.is_cancelled()) {
+        return Err(UnlockError::Cancelled);
+    }
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use std::path::PathBuf;
+
+use crate::error::{UnlockError, UnlockResult};
+use crate::read::Format;
+use serde::Deserialize;
+
+/// The environment variable that can point at a config file in a non-standard location
+const CONFIG_ENV: &str = "UNLOCK_EXCEL_CONFIG";
+
+/// Default options, read from a TOML config file, to use where they are not given on the command
+/// line e.g.
+///
+/// ```toml
+/// wordlist = "/usr/share/wordlists/rockyou.txt"
+/// threads = 8
+/// time_limit = 600
+/// potfile = "/home/me/.local/share/unlock_excel/unlock_excel.pot"
+/// format = "json"
+/// inplace = false
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The wordlist used by `crack`, when none are given on the command line
+    pub wordlist: Option<PathBuf>,
+    /// The number of threads used by `crack`
+    pub threads: Option<usize>,
+    /// The time limit, in seconds, used by `crack`
+    pub time_limit: Option<u64>,
+    /// The potfile of cracked passwords used by `crack`, and by `read` when decoding
+    pub potfile: Option<PathBuf>,
+    /// The format `read` prints in. This was once called `output`, which is still read
+    #[serde(alias = "output")]
+    pub format: Option<Format>,
+    /// Whether commands that update a file overwrite it, rather than saving a copy alongside it
+    pub inplace: bool,
+}
+
+impl Config {
+    /// Load the config file. This is found at the path in the `UNLOCK_EXCEL_CONFIG` environment
+    /// variable if it is set, or `unlock_excel/config.toml` within the user's config directory
+    /// otherwise, which is `~/.config` on Linux. A config file that does not exist gives the
+    /// default, empty, config
+    ///
+    /// # Errors
+    /// Will return an error if the config file exists but cannot be read or parsed
+    pub fn load() -> UnlockResult<Self> {
+        match Self::path() {
+            Some(path) if path.is_file() => Self::from_file(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Load the config from the given TOML file
+    ///
+    /// # Errors
+    /// Will return an error if the file cannot be read or parsed
+    pub fn from_file(path: &std::path::Path) -> UnlockResult<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        toml::from_str(&contents)
+            .map_err(|e| UnlockError::Config(path.to_string_lossy().to_string(), e.to_string()))
+    }
+
+    fn path() -> Option<PathBuf> {
+        std::env::var_os(CONFIG_ENV).map(PathBuf::from).or_else(|| {
+            dirs::config_dir().map(|mut p| {
+                p.push("unlock_excel");
+                p.push("config.toml");
+                p
+            })
+        })
+    }
+}
@@ -1,14 +1,32 @@
+use crate::ovba::types::guid::Guid;
+
 // The path to the vba file within an xlsx or xlsb file
 pub const ZIP_VBA_PATH: &str = "xl/vbaProject.bin";
 
+// The path to the storage holding the whole vba project within an xls file
+pub const CFB_VBA_PROJECT_PATH: &str = "/_VBA_PROJECT_CUR";
+
 // The path to the vba project stream within an xls file
 pub const CFB_VBA_PATH: &str = "/_VBA_PROJECT_CUR/PROJECT";
 
+// The path to the vba dir stream within an xls file
+pub const CFB_DIR_PATH: &str = "/_VBA_PROJECT_CUR/VBA/dir";
+
+// The path to the vba storage, which holds the module streams, within an xls file
+pub const CFB_VBA_STORAGE_PATH: &str = "/_VBA_PROJECT_CUR/VBA";
+
 // The path to the project stream within a VBA compound file
 pub const PROJECT_PATH: &str = "/PROJECT";
 
-// The project properties of an unlocked project
-pub const UNLOCKED_ID: &str = "ID=\"{3C6F1B8B-BDBE-4F1B-AA02-BCA23D695691}\"\r\n";
-pub const UNLOCKED_CMG: &str = "CMG=\"1E1C02263E5A585E585E585E585E\"\r\n";
-pub const UNLOCKED_DPB: &str = "DPB=\"3C3E2044206321632163\"\r\n";
-pub const UNLOCKED_GC: &str = "GC=\"5A58466A656B656B9A\"\r\n";
+// The path to the dir stream within a VBA compound file
+pub const DIR_PATH: &str = "/VBA/dir";
+
+// The path to the storage that holds the module streams within a VBA compound file
+pub const VBA_STORAGE_PATH: &str = "/VBA";
+
+// The project ID given to a project when it is unlocked i.e.
+// {3C6F1B8B-BDBE-4F1B-AA02-BCA23D695691}
+pub const UNLOCKED_ID: Guid = Guid::from_u128(0x3C6F_1B8B_BDBE_4F1B_AA02_BCA2_3D69_5691);
+
+// The project ID written by Excel when a project is locked
+pub const LOCKED_ID: Guid = Guid::NIL;
@@ -0,0 +1,188 @@
+//! Candidate passwords harvested from the file itself.
+//!
+//! The names in a workbook, such as those of its VBA project, modules and sheets, its file name,
+//! and the author and company it was saved with, are often the password or the stem of it. Each
+//! name is tried as it is, with its spaces taken out, and each of its words on its own
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::biff;
+use crate::error::{UnlockError, UnlockResult};
+use crate::lenient::Options;
+use crate::ovba::records::project::Project;
+use crate::read::{xl_97_project, xl_project};
+use crate::unprotect_sheets::{read_part, sheet_names, unescape};
+use zip::ZipArchive;
+
+/// The parts of the zip archive holding the document properties, and the elements within them
+/// worth trying
+const CORE_XML: &str = "docProps/core.xml";
+const CORE_ELEMENTS: [&str; 6] = [
+    "dc:creator",
+    "cp:lastModifiedBy",
+    "dc:title",
+    "dc:subject",
+    "cp:keywords",
+    "cp:category",
+];
+const APP_XML: &str = "docProps/app.xml";
+const APP_ELEMENTS: [&str; 2] = ["Company", "Manager"];
+
+/// The property set streams of an xls file, and the ids of the properties within them worth trying
+/// i.e. the title, subject, author, keywords and last author, then the category, manager and
+/// company
+const SUMMARY_STREAM: &str = "\u{5}SummaryInformation";
+const SUMMARY_IDS: [u32; 5] = [0x02, 0x03, 0x04, 0x05, 0x08];
+const DOCUMENT_SUMMARY_STREAM: &str = "\u{5}DocumentSummaryInformation";
+const DOCUMENT_SUMMARY_IDS: [u32; 3] = [0x02, 0x0E, 0x0F];
+
+/// The types of property that hold a string of single or two byte characters
+const VT_LPSTR: u32 = 0x1E;
+const VT_LPWSTR: u32 = 0x1F;
+
+/// The candidates harvested from an Excel file.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// Sheet names and document properties are taken where they can be found, but a file without them
+/// is not an error
+///
+/// # Errors
+/// Will return an error if the file cannot be opened as a zip file, or its VBA project cannot be
+/// read, in the same situations as [`xl_project`]
+pub fn xl(filename: &Path, options: &Options) -> UnlockResult<Vec<String>> {
+    let (project, _) = xl_project(filename, false, options)?;
+    let mut names = project_names(filename, &project);
+    let mut archive = ZipArchive::new(File::open(filename)?)?;
+    names.extend(sheet_names(&mut archive).unwrap_or_default());
+    for (part, elements) in [(CORE_XML, &CORE_ELEMENTS[..]), (APP_XML, &APP_ELEMENTS[..])] {
+        let Ok(xml) = read_part(&mut archive, part) else {
+            continue;
+        };
+        let xml = String::from_utf8_lossy(&xml);
+        names.extend(elements.iter().filter_map(|e| element_text(&xml, e)));
+    }
+    Ok(candidates(&names))
+}
+
+/// The candidates harvested from an Excel file.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// Sheet names and document properties are taken where they can be found, but a file without them
+/// is not an error
+///
+/// # Errors
+/// Will return an error if the file cannot be opened as a CFB file, or its VBA project cannot be
+/// read, in the same situations as [`xl_97_project`]
+pub fn xl_97(filename: &Path, options: &Options) -> UnlockResult<Vec<String>> {
+    let (project, _) = xl_97_project(filename, false, options)?;
+    let mut names = project_names(filename, &project);
+    let mut file = cfb::open(filename).map_err(UnlockError::CFBOpen)?;
+    if let Ok((_, stream)) = biff::read_workbook_stream(&mut file) {
+        let sheets = biff::sheets_8(&stream).unwrap_or_default();
+        names.extend(sheets.into_iter().map(|(name, _)| name));
+    }
+    for (path, ids) in [
+        (SUMMARY_STREAM, &SUMMARY_IDS[..]),
+        (DOCUMENT_SUMMARY_STREAM, &DOCUMENT_SUMMARY_IDS[..]),
+    ] {
+        let mut stream = Vec::new();
+        if file
+            .open_stream(path)
+            .and_then(|mut s| s.read_to_end(&mut stream))
+            .is_ok()
+        {
+            names.extend(properties(&stream, ids));
+        }
+    }
+    Ok(candidates(&names))
+}
+
+/// The names of the VBA project and its modules, and the name of the file without its extension
+fn project_names(filename: &Path, project: &Project) -> Vec<String> {
+    let mut names = vec![project.name().to_string()];
+    names.extend(project.description().map(ToString::to_string));
+    names.extend(
+        project
+            .modules()
+            .into_iter()
+            .map(|(name, _)| name.to_string()),
+    );
+    names.extend(
+        filename
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string()),
+    );
+    names
+}
+
+/// Each name as it is, with everything but its letters and digits taken out, and each of its
+/// words, once each and in the order they are first found
+fn candidates(names: &[String]) -> Vec<String> {
+    let mut candidates: Vec<String> = Vec::new();
+    for name in names {
+        let name = name.trim();
+        let words: Vec<&str> = name
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .collect();
+        let forms = [name.to_string(), words.concat()]
+            .into_iter()
+            .chain(words.iter().map(ToString::to_string));
+        for candidate in forms {
+            if !candidate.is_empty() && !candidates.contains(&candidate) {
+                candidates.push(candidate);
+            }
+        }
+    }
+    candidates
+}
+
+/// The text of the first `<name>` element of the xml, if it has any
+fn element_text(xml: &str, name: &str) -> Option<String> {
+    let open = format!("<{name}>");
+    let start = xml.find(&open)? + open.len();
+    let length = xml[start..].find(&format!("</{name}>"))?;
+    Some(unescape(&xml[start..start + length]))
+}
+
+/// The string properties with the ids given, from the first section of a property set stream
+///
+/// The stream starts with a 28 byte header, followed by the id and offset of each section. A
+/// section starts with its size and number of properties, then the id and offset of each property
+fn properties(stream: &[u8], ids: &[u32]) -> Vec<String> {
+    let u32_at = |at: usize| {
+        let bytes = stream.get(at..at.checked_add(4)?)?;
+        Some(u32::from_le_bytes(bytes.try_into().ok()?))
+    };
+    let Some(section) = u32_at(44).map(|s| s as usize) else {
+        return Vec::new();
+    };
+    let count = u32_at(section + 4).unwrap_or_default() as usize;
+    let Some(entries) = stream.get(section + 8..) else {
+        return Vec::new();
+    };
+    entries
+        .chunks_exact(8)
+        .take(count)
+        .filter_map(|entry| {
+            let id = u32::from_le_bytes(entry[..4].try_into().ok()?);
+            if !ids.contains(&id) {
+                return None;
+            }
+            let at = section + u32::from_le_bytes(entry[4..].try_into().ok()?) as usize;
+            let length = u32_at(at + 4)? as usize;
+            let text = match u32_at(at)? {
+                VT_LPSTR => stream
+                    .get(at + 8..at + 8 + length)?
+                    .iter()
+                    .map(|&c| char::from(c))
+                    .collect(),
+                VT_LPWSTR => biff::utf_16(stream.get(at + 8..at + 8 + length * 2)?),
+                _ => return None,
+            };
+            Some(text.trim_end_matches('\0').to_string())
+        })
+        .collect()
+}
@@ -0,0 +1,1793 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::ops::{Range, RangeInclusive};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::cancel::{self, CancellationToken};
+use crate::error::{UnlockError, UnlockResult};
+use crate::hash::PasswordHash;
+use crate::lenient;
+use crate::ovba::algorithms::password_hash::{Hash, Salt};
+use crate::ovba::records::project::Password;
+use crate::potfile::Potfile;
+use crate::progress;
+use crate::read::{xl_97_project, xl_project};
+use crate::wordlists;
+use indicatif::ProgressBar;
+use serde::Serialize;
+use sha1::digest::Output;
+use sha1::{Digest, Sha1};
+
+/// The list of common passwords built into the tool, unless it was left out of the build
+#[cfg(feature = "wordlist")]
+const WORDLIST: Option<&[u8]> = Some(include_bytes!("password.lst"));
+#[cfg(not(feature = "wordlist"))]
+const WORDLIST: Option<&[u8]> = None;
+
+/// Roughly how many bytes of a wordlist are read in at a time
+const WORDLIST_BATCH: usize = 1 << 20;
+
+/// How many candidates each thread tests between checks of the time limit
+const CHECK_INTERVAL: usize = 4096;
+
+/// How long the hash rate is measured for, and how many candidates are hashed between looks at
+/// the clock while it is
+const BENCHMARK: Duration = Duration::from_millis(200);
+const BENCHMARK_BATCH: u32 = 4096;
+
+/// How often a search with a progress callback reports on how far it has got
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The rules tried when checking the strength of a password, the changes most often made to a
+/// common password to get past a password policy
+const STRENGTH_RULES: [&str; 10] = ["c", "u", "r", "d", "L", "cL", "$1", "c$1", "$!", "c$!"];
+
+/// The longest password a strength check tries every printable password up to
+const STRENGTH_LENGTH: usize = 4;
+
+/// Roughly how many joined up candidates are built at a time by the combinator attack
+const COMBINATION_BATCH: usize = 1 << 20;
+
+/// Symbols commonly put at the start or end of a word to meet a password policy
+const HYBRID_SYMBOLS: [&str; 9] = ["!", "!!", "?", "@", "#", "$", "*", ".", "1!"];
+
+/// The years most likely to be found at the start or end of a password
+const HYBRID_YEARS: RangeInclusive<u32> = 1950..=2039;
+
+/// How much less likely, in bits, the least likely candidate of each round of the Markov
+/// generator is than the least likely of the round before
+const MARKOV_BAND: f64 = 1.0;
+
+/// The state of the Markov model before the first character, and the symbol for the end of a word
+const MARKOV_EDGE: usize = 256;
+
+/// The steps from a key to each of its neighbours, as changes of row and column. Each row of keys
+/// sits about half a key to the right of the row above, so the keys below any key are the ones in
+/// the same and the previous columns of the next row
+const WALK_STEPS: [(isize, isize); 6] = [(0, 1), (0, -1), (1, 0), (-1, 0), (1, -1), (-1, 1)];
+
+/// The most times a keyboard walk can change direction or start again beside where it began
+const WALK_TURNS: usize = 2;
+
+/// The settings used when trying to recover a password
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Files of candidate passwords, one per line, tried in turn. If none are supplied, the
+    /// built-in list of 1.7 million common passwords is used
+    pub wordlists: Vec<PathBuf>,
+    /// Words to try before anything else, such as those harvested from the file by
+    /// [`crate::context`]. They are also used by a hybrid search, and the rules apply to them
+    pub context: Vec<String>,
+    /// The number of threads to share the candidates between
+    pub threads: usize,
+    /// Give up after this long
+    pub time_limit: Option<Duration>,
+    /// Two wordlists to join together, in place of the wordlists above
+    pub combinator: Option<Combinator>,
+    /// Try every possible password instead of any wordlist
+    pub brute_force: Option<BruteForce>,
+    /// Try every password that fits a mask instead of any wordlist
+    pub mask: Option<Mask>,
+    /// Rules applied to every word of the wordlists, each making one more candidate from the word
+    pub rules: Vec<Rule>,
+    /// Also try the lower case, capitalised and upper case forms of every candidate the words and
+    /// rules make e.g. "finance", "Finance" and "FINANCE"
+    pub cases: bool,
+    /// Also try every word of the wordlists with numbers, years and symbols added to its end or
+    /// start e.g. "Finance2023" or "!finance"
+    pub hybrid: bool,
+    /// Try the passwords a Markov model of real passwords finds likeliest instead of any wordlist
+    pub markov: Option<Markov>,
+    /// Try the passwords made by walking across neighbouring keys of a keyboard instead of any
+    /// wordlist
+    pub walk: Option<Walk>,
+    /// The password policy a candidate must meet to be tried. Candidates of a brute force or mask
+    /// search are all tried, as their shape is already given
+    pub policy: Policy,
+    /// Leave out this many passwords from the start of a brute force or mask search, so the search
+    /// can be split between machines
+    pub skip: u64,
+    /// Try at most this many passwords of a brute force or mask search, after any skipped
+    pub limit: Option<u64>,
+    /// A potfile of passwords that have been cracked before, checked before searching and added to
+    /// when a password is found
+    pub potfile: Option<PathBuf>,
+}
+
+/// A combinator attack, which tries every word of one list followed by every word of another e.g.
+/// "Finance" and "2023" make "Finance2023"
+#[derive(Debug, Clone)]
+pub struct Combinator {
+    /// The file of words that go first, one per line
+    pub left: PathBuf,
+    /// The file of words that go second, one per line
+    pub right: PathBuf,
+    /// Put between the two words, which can be left empty
+    pub separator: String,
+}
+
+/// How far a search has got, as passed to the callback of [`many_with_progress`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// The candidates tried so far
+    pub tried: u64,
+    /// The candidates to try. For searches that do not know how many candidates there are up
+    /// front, this grows as the search goes on
+    pub total: u64,
+    /// The candidates tried a second, on average since the search started
+    pub rate: f64,
+    /// How many of the passwords have been found so far
+    pub found: usize,
+}
+
+/// How hard a password is to crack, going by the quickest attack that finds it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Strength {
+    /// In the built-in list of common passwords
+    Common,
+    /// A common password with a simple change, such as a capital letter or a digit on the end
+    Rules,
+    /// Short enough for every printable password of its length to be tried
+    Short,
+    /// Not found by any of the quick attacks
+    Strong,
+}
+
+impl std::fmt::Display for Strength {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Common => write!(f, "very weak, it is in the list of common passwords"),
+            Self::Rules => write!(
+                f,
+                "weak, it is a common password with a simple change such as a capital letter or a \
+                number on the end"
+            ),
+            Self::Short => write!(
+                f,
+                "weak, it is short enough to try every password that long"
+            ),
+            Self::Strong => write!(f, "strong, none of the quick attacks found it"),
+        }
+    }
+}
+
+/// An exhaustive search of every password made from a set of characters, from the shortest to
+/// the longest length given
+#[derive(Debug, Clone, Copy)]
+pub struct BruteForce {
+    /// The characters the password may be made of
+    pub charset: Charset,
+    /// The length of the shortest password to try
+    pub min: usize,
+    /// The length of the longest password to try
+    pub max: usize,
+}
+
+/// A search of the passwords a Markov model finds likeliest, from the most likely down. The model
+/// learns how often each character follows another in a corpus of real passwords
+#[derive(Debug, Clone)]
+pub struct Markov {
+    /// The file of passwords, one per line, to learn from. If not given, the model learns from the
+    /// built-in list of common passwords
+    pub corpus: Option<PathBuf>,
+    /// The length of the shortest password to try
+    pub min: usize,
+    /// The length of the longest password to try
+    pub max: usize,
+}
+
+/// A search of the passwords made by walking across neighbouring keys of a keyboard e.g.
+/// "qwerty", "zxcvfdsa" or "1qaz2wsx", with the walks that change direction least tried first
+#[derive(Debug, Clone, Copy)]
+pub struct Walk {
+    /// The keyboard the walks are made on
+    pub layout: Layout,
+    /// The length of the shortest password to try
+    pub min: usize,
+    /// The length of the longest password to try
+    pub max: usize,
+}
+
+/// The keyboard layouts that walks can be made on. Only the keys that type ASCII characters
+/// without shift are walked, except for the digits of an AZERTY keyboard, which need shift
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Layout {
+    /// The US and UK layout
+    #[default]
+    Qwerty,
+    /// The French and Belgian layout
+    Azerty,
+    /// The German, Austrian, Swiss and central European layout
+    Qwertz,
+}
+
+impl Layout {
+    /// The rows of keys, from the digits down
+    const fn rows(self) -> [&'static [u8]; 4] {
+        match self {
+            Self::Qwerty => [
+                b"1234567890-=",
+                b"qwertyuiop[]",
+                b"asdfghjkl;'",
+                b"zxcvbnm,./",
+            ],
+            Self::Azerty => [b"1234567890", b"azertyuiop", b"qsdfghjklm", b"wxcvbn,;:!"],
+            Self::Qwertz => [b"1234567890", b"qwertzuiop", b"asdfghjkl", b"yxcvbnm,.-"],
+        }
+    }
+
+    /// The layout most used where the system locale is set, from the `LC_ALL`, `LC_CTYPE` or
+    /// `LANG` environment variables e.g. "fr_FR.UTF-8" gives AZERTY. Defaults to QWERTY
+    #[must_use]
+    pub fn from_locale() -> Self {
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .find_map(|v| std::env::var(v).ok().filter(|l| !l.is_empty()))
+            .unwrap_or_default();
+        let (language, territory) = locale.split_once('_').unwrap_or((&locale, ""));
+        let territory = territory.split(['.', '@']).next().unwrap_or_default();
+        match (language, territory) {
+            (_, "CH") | ("de" | "cs" | "sk" | "hu" | "sl" | "hr", _) => Self::Qwertz,
+            (_, "BE") | ("fr", "FR" | "" | "LU" | "MC") => Self::Azerty,
+            _ => Self::Qwerty,
+        }
+    }
+}
+
+/// A password policy, such as the one of the organisation that wrote the file, which rules out the
+/// candidates that could not be the password before they are hashed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Policy {
+    /// The fewest characters the password can have
+    pub min_length: Option<usize>,
+    /// The most characters the password can have
+    pub max_length: Option<usize>,
+    /// The kinds of character the password has at least one of
+    pub required: Vec<Class>,
+}
+
+/// The kinds of character a password policy can require
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Class {
+    /// 0-9
+    Digit,
+    /// A-Z
+    Upper,
+    /// a-z
+    Lower,
+    /// Anything that is not an ASCII letter or digit
+    Symbol,
+}
+
+impl Class {
+    /// Whether the byte is a character of this kind, or part of one
+    const fn matches(self, b: u8) -> bool {
+        match self {
+            Self::Digit => b.is_ascii_digit(),
+            Self::Upper => b.is_ascii_uppercase(),
+            Self::Lower => b.is_ascii_lowercase(),
+            Self::Symbol => !b.is_ascii_alphanumeric(),
+        }
+    }
+}
+
+impl Policy {
+    /// Whether the candidate meets the policy, counting its length in characters rather than bytes
+    fn allows(&self, candidate: &[u8]) -> bool {
+        let length = candidate.iter().filter(|&&b| b & 0xC0 != 0x80).count();
+        self.min_length.is_none_or(|min| length >= min)
+            && self.max_length.is_none_or(|max| length <= max)
+            && self
+                .required
+                .iter()
+                .all(|&class| candidate.iter().any(|&b| class.matches(b)))
+    }
+}
+
+/// The sets of characters a brute force search can be made over
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Charset {
+    /// 0-9
+    Digits,
+    /// a-z
+    Lower,
+    /// A-Z
+    Upper,
+    /// a-z and 0-9
+    #[default]
+    #[value(name = "lowernum")]
+    LowerNum,
+    /// a-z and A-Z
+    Alpha,
+    /// a-z, A-Z and 0-9
+    #[value(name = "alphanum")]
+    AlphaNum,
+    /// Every printable ASCII character, including the space
+    Printable,
+}
+
+impl Charset {
+    const DIGITS: &'static [u8] = b"0123456789";
+    const LOWER: &'static [u8] = b"abcdefghijklmnopqrstuvwxyz";
+    const UPPER: &'static [u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    const LOWER_NUM: &'static [u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+    const ALPHA: &'static [u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    const ALPHA_NUM: &'static [u8] =
+        b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+    const PRINTABLE: &'static [u8] = b" !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~";
+    const SYMBOLS: &'static [u8] = b" !\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+    /// The characters of the set, in the order they are tried
+    #[must_use]
+    pub const fn characters(self) -> &'static [u8] {
+        match self {
+            Self::Digits => Self::DIGITS,
+            Self::Lower => Self::LOWER,
+            Self::Upper => Self::UPPER,
+            Self::LowerNum => Self::LOWER_NUM,
+            Self::Alpha => Self::ALPHA,
+            Self::AlphaNum => Self::ALPHA_NUM,
+            Self::Printable => Self::PRINTABLE,
+        }
+    }
+}
+
+/// A mask attack, which tries every password that fits a pattern of characters
+///
+/// Masks are written the way hashcat writes them e.g. "?u?l?l?l?d?d?d?d" for a capital letter,
+/// three lower case letters and four digits. The placeholders are:
+/// - ?l: a-z
+/// - ?u: A-Z
+/// - ?d: 0-9
+/// - ?s: the printable symbols, including the space
+/// - ?a: every printable character
+/// - ??: a question mark
+///
+/// Any other character stands for itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mask {
+    positions: Vec<&'static [u8]>,
+}
+
+impl FromStr for Mask {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut positions = Vec::new();
+        let mut chars = s.char_indices();
+        while let Some((i, c)) = chars.next() {
+            let characters = match c {
+                '?' => match chars.next().map(|(_, c)| c) {
+                    Some('l') => Charset::LOWER,
+                    Some('u') => Charset::UPPER,
+                    Some('d') => Charset::DIGITS,
+                    Some('s') => Charset::SYMBOLS,
+                    Some('a') => Charset::PRINTABLE,
+                    Some('?') => literal('?').unwrap_or_default(),
+                    Some(other) => {
+                        return Err(format!("unknown placeholder ?{other} at position {i}"))
+                    }
+                    None => return Err("the mask cannot end with a lone ?".to_string()),
+                },
+                _ => literal(c).ok_or_else(|| {
+                    format!("{c} at position {i} is not a printable ASCII character")
+                })?,
+            };
+            positions.push(characters);
+        }
+        if positions.is_empty() {
+            return Err("the mask is empty".to_string());
+        }
+        Ok(Self { positions })
+    }
+}
+
+/// The single character as a set of its own, if it is printable ASCII
+fn literal(c: char) -> Option<&'static [u8]> {
+    let p = Charset::PRINTABLE
+        .iter()
+        .position(|&b| char::from(b) == c)?;
+    Some(&Charset::PRINTABLE[p..=p])
+}
+
+/// A rule for making a new candidate from a word of a wordlist, so more passwords can be tried
+/// without a bigger list
+///
+/// A rule is a run of functions applied one after the other, written with the letters hashcat
+/// uses where it has them e.g. "cL" capitalises the word and then swaps letters for look-alike
+/// symbols, turning "password" into "P@$$w0rd". The functions are:
+/// - `:`: leave the word as it is
+/// - `l`: lower case the whole word
+/// - `u`: upper case the whole word
+/// - `c`: capitalise the first letter and lower case the rest
+/// - `r`: reverse the word
+/// - `d`: write the word twice
+/// - `L`: leetspeak, swapping a, e, i, o and s for @, 3, 1, 0 and $
+/// - `sXY`: swap every X for Y
+/// - `$X`: add X to the end
+/// - `^X`: add X to the start
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    functions: Vec<Function>,
+}
+
+/// One step of a [`Rule`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Function {
+    Nothing,
+    Lower,
+    Upper,
+    Capitalise,
+    Reverse,
+    Duplicate,
+    Leetspeak,
+    Swap(u8, u8),
+    Append(u8),
+    Prepend(u8),
+}
+
+impl Rule {
+    /// The candidate made by applying the rule to the word
+    #[must_use]
+    pub fn apply(&self, word: &[u8]) -> Vec<u8> {
+        let mut word = word.to_vec();
+        for function in &self.functions {
+            match *function {
+                Function::Nothing => {}
+                Function::Lower => word.make_ascii_lowercase(),
+                Function::Upper => word.make_ascii_uppercase(),
+                Function::Capitalise => {
+                    word.make_ascii_lowercase();
+                    if let Some(first) = word.first_mut() {
+                        first.make_ascii_uppercase();
+                    }
+                }
+                Function::Reverse => word.reverse(),
+                Function::Duplicate => word.extend_from_within(..),
+                Function::Leetspeak => {
+                    for b in &mut word {
+                        *b = match *b {
+                            b'a' | b'A' => b'@',
+                            b'e' | b'E' => b'3',
+                            b'i' | b'I' => b'1',
+                            b'o' | b'O' => b'0',
+                            b's' | b'S' => b'$',
+                            other => other,
+                        };
+                    }
+                }
+                Function::Swap(from, to) => {
+                    for b in word.iter_mut().filter(|b| **b == from) {
+                        *b = to;
+                    }
+                }
+                Function::Append(b) => word.push(b),
+                Function::Prepend(b) => word.insert(0, b),
+            }
+        }
+        word
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.is_ascii() {
+            return Err("rules can only be written in ASCII".to_string());
+        }
+        let mut functions = Vec::new();
+        let mut bytes = s.bytes();
+        while let Some(b) = bytes.next() {
+            let mut argument = || {
+                bytes
+                    .next()
+                    .ok_or_else(|| format!("{} needs a character after it", char::from(b)))
+            };
+            functions.push(match b {
+                b':' => Function::Nothing,
+                b'l' => Function::Lower,
+                b'u' => Function::Upper,
+                b'c' => Function::Capitalise,
+                b'r' => Function::Reverse,
+                b'd' => Function::Duplicate,
+                b'L' => Function::Leetspeak,
+                b's' => Function::Swap(argument()?, argument()?),
+                b'$' => Function::Append(argument()?),
+                b'^' => Function::Prepend(argument()?),
+                // Spaces can be used to make long rules easier to read
+                b' ' => continue,
+                other => return Err(format!("unknown rule function {}", char::from(other))),
+            });
+        }
+        if functions.is_empty() {
+            return Err("the rule is empty".to_string());
+        }
+        Ok(Self { functions })
+    }
+}
+
+impl Options {
+    /// The rules to apply to every word, with each rule, and the word as it is, followed by every
+    /// change of case when case variants are asked for
+    fn rules(&self) -> Cow<'_, [Rule]> {
+        if !self.cases {
+            return Cow::Borrowed(&self.rules);
+        }
+        let mut rules = self.rules.clone();
+        for functions in std::iter::once(&Vec::new()).chain(self.rules.iter().map(|r| &r.functions))
+        {
+            for case in [Function::Lower, Function::Capitalise, Function::Upper] {
+                let mut functions = functions.clone();
+                functions.push(case);
+                rules.push(Rule { functions });
+            }
+        }
+        Cow::Owned(rules)
+    }
+
+    /// The numbers of the passwords of a brute force or mask search to try
+    const fn slice(&self) -> Range<u64> {
+        let end = match self.limit {
+            Some(limit) => self.skip.saturating_add(limit),
+            None => u64::MAX,
+        };
+        self.skip..end
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            wordlists: Vec::new(),
+            context: Vec::new(),
+            threads: std::thread::available_parallelism().map_or(1, usize::from),
+            time_limit: None,
+            combinator: None,
+            brute_force: None,
+            mask: None,
+            rules: Vec::new(),
+            cases: false,
+            hybrid: false,
+            markov: None,
+            walk: None,
+            policy: Policy::default(),
+            skip: 0,
+            limit: None,
+            potfile: None,
+        }
+    }
+}
+
+/// Try to recover the VBA project password.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// Every line of the wordlist is tested against the salted SHA1 hash stored in the file. If the
+/// password has been stored as plain text then it is returned directly
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - There is no VBA file within the zip archive, found at "/xl/vbaProject.bin"
+/// - The VBA file within the archive cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   cannot be found or parsed
+/// - The VBA project has no password
+/// - A wordlist cannot be read
+/// - None of the candidates match, or the time limit is reached first
+pub fn xl(filename: &Path, options: &Options, leniency: &lenient::Options) -> UnlockResult<String> {
+    let (project, _) = xl_project(filename, false, leniency)?;
+    crack(project.password(), options)
+}
+
+/// Try to recover the VBA project password.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// Every line of the wordlist is tested against the salted SHA1 hash stored in the file. If the
+/// password has been stored as plain text then it is returned directly
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   cannot be found or parsed
+/// - The VBA project has no password
+/// - A wordlist cannot be read
+/// - None of the candidates match, or the time limit is reached first
+pub fn xl_97(
+    filename: &Path,
+    options: &Options,
+    leniency: &lenient::Options,
+) -> UnlockResult<String> {
+    let (project, _) = xl_97_project(filename, false, leniency)?;
+    crack(project.password(), options)
+}
+
+fn crack(password: &Password, options: &Options) -> UnlockResult<String> {
+    match password {
+        Password::None => Err(UnlockError::NoPassword),
+        Password::Plain(text) => Ok(text.clone()),
+        Password::Hash(salt, hash) => {
+            search_options(*salt, hash, options)?.ok_or(UnlockError::PasswordNotFound)
+        }
+    }
+}
+
+/// Try to decode the password of a project that has already been read, using the wordlists of
+/// the options. Nothing is returned if the password is not stored as a hash, or is not found
+///
+/// # Errors
+/// Will return an error if a wordlist cannot be read
+pub fn decode(password: &Password, options: &Options) -> UnlockResult<Option<String>> {
+    match password {
+        Password::Hash(salt, hash) => search_options(*salt, hash, options),
+        Password::None | Password::Plain(_) => Ok(None),
+    }
+}
+
+/// Check how hard the password of a project that has already been read is to crack, by trying the
+/// quickest attacks on it in turn
+///
+/// These are the built-in list of common passwords, then the same list with a few simple rules
+/// applied, and lastly every printable password of up to four characters. Nothing is returned if
+/// the password is not stored as a hash
+///
+/// # Errors
+/// Will return an error if the built-in list was left out of the build
+pub fn strength(password: &Password, threads: usize) -> UnlockResult<Option<Strength>> {
+    let Password::Hash(salt, hash) = password else {
+        return Ok(None);
+    };
+    let targets = Targets::new(&[(*salt, *hash)]);
+    let common = Options {
+        threads,
+        ..Options::default()
+    };
+    let rules = Options {
+        rules: STRENGTH_RULES
+            .iter()
+            .filter_map(|rule| rule.parse().ok())
+            .collect(),
+        ..common.clone()
+    };
+    let short = Options {
+        brute_force: Some(BruteForce {
+            charset: Charset::Printable,
+            min: 1,
+            max: STRENGTH_LENGTH,
+        }),
+        ..common.clone()
+    };
+    for (strength, options) in [
+        (Strength::Common, common),
+        (Strength::Rules, rules),
+        (Strength::Short, short),
+    ] {
+        search_attack(&targets, &options)?;
+        if targets.cancelled() {
+            return Err(UnlockError::Cancelled);
+        }
+        if targets.done() {
+            return Ok(Some(strength));
+        }
+    }
+    Ok(Some(Strength::Strong))
+}
+
+/// Try to recover the passwords of many VBA projects at once
+///
+/// Every candidate is tested against all of the projects, so the candidates are only gone through
+/// once however many projects there are, and projects that share a salt share the hashing too.
+/// The results are in the same order as the passwords
+///
+/// # Errors
+/// Will return an error if a wordlist cannot be read. The result for each project is an error if
+/// it has no password or its password is not found
+pub fn many(passwords: &[&Password], options: &Options) -> UnlockResult<Vec<UnlockResult<String>>> {
+    many_watched(passwords, options, None)
+}
+
+/// Try to recover the passwords of many VBA projects at once, in the same way as [`many`], calling
+/// back with how far the search has got
+///
+/// This is for front ends that show their own progress. The search runs on other threads, while
+/// the callback is called on this one, every tenth of a second and once more when the search is
+/// over
+///
+/// # Errors
+/// Will return an error in the same situations as [`many`]
+pub fn many_with_progress(
+    passwords: &[&Password],
+    options: &Options,
+    mut on_progress: impl FnMut(Progress),
+) -> UnlockResult<Vec<UnlockResult<String>>> {
+    many_watched(passwords, options, Some(&mut on_progress))
+}
+
+fn many_watched(
+    passwords: &[&Password],
+    options: &Options,
+    on_progress: Option<&mut dyn FnMut(Progress)>,
+) -> UnlockResult<Vec<UnlockResult<String>>> {
+    let hashes: Vec<(Salt, Hash)> = passwords
+        .iter()
+        .filter_map(|p| match p {
+            Password::Hash(salt, hash) => Some((*salt, *hash)),
+            Password::None | Password::Plain(_) => None,
+        })
+        .collect();
+    let mut found = search_hashes(&hashes, options, on_progress)?.into_iter();
+    Ok(passwords
+        .iter()
+        .map(|p| match p {
+            Password::None => Err(UnlockError::NoPassword),
+            Password::Plain(text) => Ok(text.clone()),
+            Password::Hash(..) => found.next().flatten().ok_or(UnlockError::PasswordNotFound),
+        })
+        .collect())
+}
+
+/// Search for the password of a single salt and hash
+fn search_options(salt: Salt, hash: &Hash, options: &Options) -> UnlockResult<Option<String>> {
+    Ok(search_hashes(&[(salt, *hash)], options, None)?
+        .pop()
+        .flatten())
+}
+
+/// Search for the passwords of the salts and hashes, looking in the potfile first if there is one.
+/// The passwords found are in the same order as the hashes
+fn search_hashes(
+    hashes: &[(Salt, Hash)],
+    options: &Options,
+    on_progress: Option<&mut dyn FnMut(Progress)>,
+) -> UnlockResult<Vec<Option<String>>> {
+    let potfile = options.potfile.as_deref().map(Potfile::new);
+    let mut found = vec![None; hashes.len()];
+    if let Some(potfile) = &potfile {
+        for (password, &(salt, hash)) in found.iter_mut().zip(hashes) {
+            *password = potfile.find(&PasswordHash { salt, hash })?;
+        }
+    }
+
+    // Each hash still to be found is only searched for once, however many files share it
+    let mut unique: Vec<(Salt, Hash)> = Vec::new();
+    for (password, pair) in found.iter().zip(hashes) {
+        if password.is_none() && !unique.contains(pair) {
+            unique.push(*pair);
+        }
+    }
+    if unique.is_empty() {
+        return Ok(found);
+    }
+    let targets = Targets::new(&unique);
+    match on_progress {
+        Some(on_progress) => search_watched(&targets, options, on_progress)?,
+        None => search_attack(&targets, options)?,
+    }
+    let cancelled = targets.cancelled();
+    for (&(salt, hash), password) in unique.iter().zip(targets.into_found()) {
+        let Some(password) = password else {
+            continue;
+        };
+        if let Some(potfile) = &potfile {
+            potfile.add(&PasswordHash { salt, hash }, &password)?;
+        }
+        for (slot, pair) in found.iter_mut().zip(hashes) {
+            if *pair == (salt, hash) {
+                *slot = Some(password.clone());
+            }
+        }
+    }
+    // Any passwords found before the search was cancelled are still kept in the potfile
+    if cancelled {
+        return Err(UnlockError::Cancelled);
+    }
+    Ok(found)
+}
+
+/// How many candidates a search with the options would try, so that its length can be estimated
+/// before it starts
+///
+/// Rules are counted as making a candidate from every word, even those they leave unchanged, and
+/// every candidate is counted whether or not it meets the password policy.
+/// Nothing is returned for a Markov search, which goes on until it runs out of passwords
+///
+/// # Errors
+/// Will return an error if a wordlist cannot be read, as each is read through to count its words
+pub fn candidates(options: &Options) -> UnlockResult<Option<u64>> {
+    let context = with_rules(options.context.len() as u64, options);
+    Ok(attack_candidates(options)?.map(|attack| attack.saturating_add(context)))
+}
+
+/// How many candidates the attack the options ask for would try, leaving out the context words
+fn attack_candidates(options: &Options) -> UnlockResult<Option<u64>> {
+    let slice = options.slice();
+    if let Some(brute_force) = options.brute_force {
+        let size = brute_force_size(brute_force);
+        return Ok(Some(slice.end.min(size).saturating_sub(slice.start)));
+    }
+    if let Some(mask) = &options.mask {
+        let size = keyspace(&mask.positions);
+        return Ok(Some(slice.end.min(size).saturating_sub(slice.start)));
+    }
+    if options.markov.is_some() {
+        return Ok(None);
+    }
+    if let Some(walk) = options.walk {
+        return Ok(Some(with_rules(
+            count_words(&keyboard_walks(walk)),
+            options,
+        )));
+    }
+    let words = match &options.combinator {
+        Some(combinator) => count_words(&std::fs::read(&combinator.left)?)
+            .saturating_mul(count_words(&std::fs::read(&combinator.right)?)),
+        None if options.hybrid => {
+            let affixes: usize = hybrid_affixes().iter().map(Vec::len).sum();
+            count_words(&hybrid_words(options)?).saturating_mul(affixes as u64)
+        }
+        None => {
+            let mut words = 0;
+            for batch in Wordlist::new(options)? {
+                words += lines(&batch?).count() as u64;
+            }
+            words
+        }
+    };
+    Ok(Some(with_rules(words, options)))
+}
+
+/// How many candidates a second the threads can test against a single password, measured by
+/// hashing candidates for a moment
+#[must_use]
+pub fn hash_rate(threads: usize) -> f64 {
+    benchmark(threads, BENCHMARK)
+}
+
+/// How many candidates a second the threads can test against a single password, measured by
+/// hashing candidates for as long as given. The longer the measure, the steadier the rate
+#[must_use]
+pub fn benchmark(threads: usize, duration: Duration) -> f64 {
+    let targets = Targets::new(&[([0; 4], [0; 20])]);
+    let start = Instant::now();
+    let deadline = start + duration;
+    let hashed = AtomicU32::new(0);
+    std::thread::scope(|s| {
+        for _ in 0..threads.max(1) {
+            s.spawn(|| {
+                let mut checker = Checker::new(&targets);
+                let mut trial = *b"password";
+                let mut count = 0_u32;
+                while Instant::now() < deadline {
+                    for n in 0..BENCHMARK_BATCH {
+                        trial[..4].copy_from_slice(&n.to_le_bytes());
+                        checker.check(&trial);
+                    }
+                    count = count.saturating_add(BENCHMARK_BATCH);
+                }
+                hashed.fetch_add(count, Ordering::Relaxed);
+            });
+        }
+    });
+    f64::from(hashed.into_inner()) / start.elapsed().as_secs_f64()
+}
+
+/// Search for the passwords on another thread, calling back with how far the search has got at
+/// regular intervals and once more when it is over
+fn search_watched(
+    targets: &Targets,
+    options: &Options,
+    on_progress: &mut dyn FnMut(Progress),
+) -> UnlockResult<()> {
+    let start = Instant::now();
+    std::thread::scope(|s| {
+        let search = s.spawn(|| search_attack(targets, options));
+        while !search.is_finished() {
+            on_progress(targets.progress(start));
+            std::thread::sleep(PROGRESS_INTERVAL);
+        }
+        let result = search
+            .join()
+            .unwrap_or_else(|e| std::panic::resume_unwind(e));
+        on_progress(targets.progress(start));
+        result
+    })
+}
+
+/// Search for the passwords with whichever attack the options ask for, after the context words
+fn search_attack(targets: &Targets, options: &Options) -> UnlockResult<()> {
+    if !options.context.is_empty() {
+        let words = options.context.join("\n").into_bytes();
+        let total = with_rules(options.context.len() as u64, options);
+        search_batches(targets, options, std::iter::once(Ok(words)), Some(total))?;
+        if targets.done() {
+            return Ok(());
+        }
+    }
+    if let Some(brute_force) = options.brute_force {
+        search_brute_force(targets, brute_force, options);
+        return Ok(());
+    }
+    if let Some(mask) = &options.mask {
+        search_mask(targets, mask, options);
+        return Ok(());
+    }
+    if let Some(markov) = &options.markov {
+        return search_markov(targets, markov, options);
+    }
+    if let Some(walk) = options.walk {
+        let words = keyboard_walks(walk);
+        let total = with_rules(count_words(&words), options);
+        return search_batches(targets, options, std::iter::once(Ok(words)), Some(total));
+    }
+    match &options.combinator {
+        Some(combinator) => search_combinations(targets, combinator, options),
+        None if options.hybrid => search_hybrid(targets, options),
+        // The size of the built-in list is known, but wordlist files are only read as they are
+        // needed
+        None => {
+            let total = if options.wordlists.is_empty() {
+                let words = builtin_wordlist()?.split_inclusive(|&b| b == b'\n').count();
+                Some(with_rules(words as u64, options))
+            } else {
+                None
+            };
+            search_batches(targets, options, Wordlist::new(options)?, total)
+        }
+    }
+}
+
+/// Join every word of the left list to every word of the right list
+fn search_combinations(
+    targets: &Targets,
+    combinator: &Combinator,
+    options: &Options,
+) -> UnlockResult<()> {
+    let left = std::fs::read(&combinator.left)?;
+    let right = std::fs::read(&combinator.right)?;
+    let left: Vec<&[u8]> = lines(&left).filter(|w| !w.is_empty()).collect();
+    let right: Vec<&[u8]> = lines(&right).filter(|w| !w.is_empty()).collect();
+    let total = with_rules(left.len() as u64 * right.len() as u64, options);
+    let batches = joined_batches(&left, &right, combinator.separator.as_bytes());
+    search_batches(targets, options, batches, Some(total))
+}
+
+/// Try every word of the wordlists with the likeliest numbers, years and symbols added to its end,
+/// then to its start, and finally with every other number up to 9999 added to its end
+fn search_hybrid(targets: &Targets, options: &Options) -> UnlockResult<()> {
+    let words = hybrid_words(options)?;
+    let words: Vec<&[u8]> = lines(&words).filter(|w| !w.is_empty()).collect();
+    let [prefixes, suffixes, numbers] = hybrid_affixes();
+    let (prefixes, suffixes, numbers) = (bytes(&prefixes), bytes(&suffixes), bytes(&numbers));
+
+    let affixes = suffixes.len() + prefixes.len() + numbers.len();
+    let total = with_rules(words.len() as u64 * affixes as u64, options);
+    let batches = joined_batches(&words, &suffixes, b"")
+        .chain(joined_batches(&prefixes, &words, b""))
+        .chain(joined_batches(&words, &numbers, b""));
+    search_batches(targets, options, batches, Some(total))
+}
+
+/// All the words of the wordlists, or of the built-in list if there are none, and the context
+/// words, for a hybrid search
+fn hybrid_words(options: &Options) -> UnlockResult<Vec<u8>> {
+    let mut words = Vec::new();
+    if options.wordlists.is_empty() {
+        words.extend_from_slice(builtin_wordlist()?);
+    }
+    for path in &options.wordlists {
+        words.extend(std::fs::read(wordlists::resolve(path))?);
+        words.push(b'\n');
+    }
+    for word in &options.context {
+        words.extend_from_slice(word.as_bytes());
+        words.push(b'\n');
+    }
+    Ok(words)
+}
+
+/// The prefixes, suffixes and numbers a hybrid search adds to each word
+fn hybrid_affixes() -> [Vec<String>; 3] {
+    let prefixes: Vec<String> = HYBRID_SYMBOLS
+        .iter()
+        .map(ToString::to_string)
+        .chain((0..=99).map(|n| n.to_string()))
+        .chain((0..=9).map(|n| format!("0{n}")))
+        .chain(HYBRID_YEARS.map(|y| y.to_string()))
+        .collect();
+    // The word on its own, the prefixes and a few other common endings, such as "2023!"
+    let suffixes: Vec<String> = std::iter::once(String::new())
+        .chain(prefixes.iter().cloned())
+        .chain(["123", "123!", "1234"].map(String::from))
+        .chain(HYBRID_YEARS.map(|y| format!("{y}!")))
+        .collect();
+    let numbers: Vec<String> = (100..=9999)
+        .filter(|n| !HYBRID_YEARS.contains(n))
+        .map(|n| n.to_string())
+        .collect();
+    [prefixes, suffixes, numbers]
+}
+
+/// The bytes of each of the strings
+fn bytes(strings: &[String]) -> Vec<&[u8]> {
+    strings.iter().map(String::as_bytes).collect()
+}
+
+/// Learn a Markov model from the corpus and try the passwords it generates, a batch at a time
+fn search_markov(targets: &Targets, markov: &Markov, options: &Options) -> UnlockResult<()> {
+    let model = match &markov.corpus {
+        Some(path) => Model::train(&std::fs::read(path)?),
+        None => Model::train(builtin_wordlist()?),
+    };
+    let mut generator = Generator::new(&model, markov.min, markov.max);
+    let batches = std::iter::from_fn(|| {
+        let mut batch = Vec::new();
+        for word in generator.by_ref().take(COMBINATION_BATCH) {
+            batch.extend_from_slice(&word);
+            batch.push(b'\n');
+        }
+        (!batch.is_empty()).then_some(Ok(batch))
+    });
+    search_batches(targets, options, batches, None)
+}
+
+/// A first order Markov model of passwords, holding for each character the characters that have
+/// been seen to follow it, or the end of the word, along with the cost in bits of each
+struct Model {
+    transitions: Vec<Vec<(Option<u8>, f64)>>,
+}
+
+impl Model {
+    /// Count how often each character follows each other in the words, one per line
+    fn train(words: &[u8]) -> Self {
+        let mut counts = vec![[0_u32; MARKOV_EDGE + 1]; MARKOV_EDGE + 1];
+        for word in lines(words).filter(|w| !w.is_empty()) {
+            let mut state = MARKOV_EDGE;
+            for &b in word {
+                counts[state][usize::from(b)] += 1;
+                state = usize::from(b);
+            }
+            counts[state][MARKOV_EDGE] += 1;
+        }
+        let transitions = counts
+            .iter()
+            .map(|row| {
+                let total: f64 = row.iter().copied().map(f64::from).sum();
+                let mut next: Vec<_> = row
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &count)| count > 0)
+                    .map(|(symbol, &count)| {
+                        let symbol = u8::try_from(symbol).ok();
+                        (symbol, -(f64::from(count) / total).log2())
+                    })
+                    .collect();
+                // Cheapest first, so a search can stop at the first that costs too much
+                next.sort_by(|a, b| a.1.total_cmp(&b.1));
+                next
+            })
+            .collect();
+        Self { transitions }
+    }
+}
+
+/// Walks the words of a [`Model`] in rounds, each round finding the words whose cost falls within
+/// the next band. The rounds run from the cheapest i.e. likeliest words up, until there are no
+/// words left within the length limits
+struct Generator<'a> {
+    model: &'a Model,
+    min: usize,
+    max: usize,
+    /// The band of costs of the words found in this round
+    low: f64,
+    high: f64,
+    /// Whether a word was left out of this round for costing too much
+    pruned: bool,
+    /// The word so far, the cost of each of its prefixes and the next choice to try after each
+    word: Vec<u8>,
+    costs: Vec<f64>,
+    choices: Vec<usize>,
+}
+
+impl<'a> Generator<'a> {
+    fn new(model: &'a Model, min: usize, max: usize) -> Self {
+        Self {
+            model,
+            min,
+            max,
+            low: f64::NEG_INFINITY,
+            high: MARKOV_BAND,
+            pruned: false,
+            word: Vec::new(),
+            costs: vec![0.0],
+            choices: vec![0],
+        }
+    }
+}
+
+impl Iterator for Generator<'_> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some(choice) = self.choices.last_mut() else {
+                // The round is over, so start the next one if anything was left out of it
+                if !self.pruned {
+                    return None;
+                }
+                self.low = self.high;
+                self.high += MARKOV_BAND;
+                self.pruned = false;
+                self.costs = vec![0.0];
+                self.choices = vec![0];
+                continue;
+            };
+            let state = self.word.last().map_or(MARKOV_EDGE, |&b| usize::from(b));
+            let next = &self.model.transitions[state];
+            let Some(&(symbol, step)) = next.get(*choice) else {
+                // Every choice after this prefix has been tried, so go back a character
+                self.choices.pop();
+                self.costs.pop();
+                self.word.pop();
+                continue;
+            };
+            *choice += 1;
+            let cost = self.costs.last().copied().unwrap_or_default() + step;
+            if cost > self.high {
+                self.pruned = true;
+                *choice = next.len();
+                continue;
+            }
+            match symbol {
+                None if cost > self.low && (self.min..=self.max).contains(&self.word.len()) => {
+                    return Some(self.word.clone());
+                }
+                Some(b) if self.word.len() < self.max => {
+                    self.word.push(b);
+                    self.costs.push(cost);
+                    self.choices.push(0);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Every walk across the keyboard within the lengths of the search, one per line, each once. The
+/// walks with the fewest turns come first, and the shortest first for each number of turns
+fn keyboard_walks(walk: Walk) -> Vec<u8> {
+    let mut walker = Walker {
+        rows: walk.layout.rows(),
+        min: walk.min.max(2),
+        max: walk.max,
+        path: Vec::new(),
+        found: Vec::new(),
+    };
+    for (r, row) in walker.rows.into_iter().enumerate() {
+        for (c, &key) in row.iter().enumerate() {
+            for step in WALK_STEPS {
+                walker.path.push(key);
+                walker.extend((r, c), (r, c), step, 1, 0);
+                walker.path.pop();
+            }
+        }
+    }
+    walker
+        .found
+        .sort_by_key(|(turns, word)| (*turns, word.len()));
+    let mut seen = HashSet::new();
+    let mut words = Vec::new();
+    for (_, word) in walker.found {
+        if seen.insert(word.clone()) {
+            words.extend_from_slice(&word);
+            words.push(b'\n');
+        }
+    }
+    words
+}
+
+/// Walks across a keyboard, a key at a time, gathering every walk found along with its number of
+/// turns
+struct Walker {
+    rows: [&'static [u8]; 4],
+    min: usize,
+    max: usize,
+    path: Vec<u8>,
+    found: Vec<(usize, Vec<u8>)>,
+}
+
+impl Walker {
+    /// Carry on the walk so far in every way it can go. A walk is made of straight strokes of at
+    /// least two keys, each either turning off from the end of the stroke before, or starting
+    /// again beside the start of the stroke before and going the same way e.g. "1qaz" then "2wsx"
+    fn extend(
+        &mut self,
+        start: (usize, usize),
+        at: (usize, usize),
+        step: (isize, isize),
+        stroke: usize,
+        turns: usize,
+    ) {
+        if stroke >= 2 && self.path.len() >= self.min {
+            self.found.push((turns, self.path.clone()));
+        }
+        if self.path.len() >= self.max {
+            return;
+        }
+        let mut moves = Vec::new();
+        if let Some(next) = self.neighbour(at, step) {
+            moves.push((start, next, step, stroke + 1, turns));
+        }
+        if turns < WALK_TURNS && stroke >= 2 {
+            for turn in WALK_STEPS {
+                if turn != step && turn != (-step.0, -step.1) {
+                    if let Some(next) = self.neighbour(at, turn) {
+                        moves.push((at, next, turn, 2, turns + 1));
+                    }
+                }
+            }
+            // A row starts again on the row above or below, and any other stroke beside it
+            let sides = if step.0 == 0 {
+                [(1, 0), (-1, 0)]
+            } else {
+                [(0, 1), (0, -1)]
+            };
+            for side in sides {
+                if let Some(next) = self.neighbour(start, side) {
+                    moves.push((next, next, step, 1, turns + 1));
+                }
+            }
+        }
+        for (start, next, step, stroke, turns) in moves {
+            self.path.push(self.rows[next.0][next.1]);
+            self.extend(start, next, step, stroke, turns);
+            self.path.pop();
+        }
+    }
+
+    /// The key one step away from the key given, if there is one
+    fn neighbour(&self, at: (usize, usize), step: (isize, isize)) -> Option<(usize, usize)> {
+        let r = at.0.checked_add_signed(step.0)?;
+        let c = at.1.checked_add_signed(step.1)?;
+        self.rows.get(r)?.get(c)?;
+        Some((r, c))
+    }
+}
+
+/// Every word of the left list joined to every word of the right list, built a batch of left words
+/// at a time to keep the memory used down
+fn joined_batches<'a>(
+    left: &'a [&[u8]],
+    right: &'a [&[u8]],
+    separator: &'a [u8],
+) -> impl Iterator<Item = UnlockResult<Vec<u8>>> + 'a {
+    let batch_size = (COMBINATION_BATCH / right.len().max(1)).max(1);
+    left.chunks(batch_size).map(move |batch| {
+        let mut candidates = Vec::new();
+        for first in batch {
+            for second in right {
+                candidates.extend_from_slice(first);
+                candidates.extend_from_slice(separator);
+                candidates.extend_from_slice(second);
+                candidates.push(b'\n');
+            }
+        }
+        Ok(candidates)
+    })
+}
+
+/// Try every password of every length in turn, sharing the passwords of each length out between
+/// the threads
+fn search_brute_force(targets: &Targets, brute_force: BruteForce, options: &Options) {
+    let characters = brute_force.charset.characters();
+    let lengths = brute_force.min..=brute_force.max;
+    let deadline = options.time_limit.map(|limit| Instant::now() + limit);
+    let stop = AtomicBool::new(false);
+    let total = brute_force_size(brute_force);
+    let slice = options.slice();
+    let bar = targets.bar(
+        slice.end.min(total).saturating_sub(slice.start),
+        "Trying every password",
+    );
+
+    // The passwords of every length are numbered in one run, shortest first, so the slice can
+    // take in several lengths
+    let mut offset = 0_u64;
+    for length in lengths {
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        let positions = vec![characters; length];
+        let size = keyspace(&positions);
+        let range = slice.start.saturating_sub(offset).min(size)
+            ..slice.end.saturating_sub(offset).min(size);
+        offset = offset.saturating_add(size);
+        if !range.is_empty() {
+            let threads = options.threads;
+            search_positions(targets, &positions, range, threads, deadline, &stop, &bar);
+        }
+    }
+    bar.finish_and_clear();
+}
+
+/// The number of passwords of every length a brute force search goes through
+fn brute_force_size(brute_force: BruteForce) -> u64 {
+    let characters = brute_force.charset.characters();
+    (brute_force.min..=brute_force.max)
+        .map(|length| keyspace(&vec![characters; length]))
+        .fold(0, u64::saturating_add)
+}
+
+/// Try every password that fits the mask, sharing them out between the threads
+fn search_mask(targets: &Targets, mask: &Mask, options: &Options) {
+    let deadline = options.time_limit.map(|limit| Instant::now() + limit);
+    let stop = AtomicBool::new(false);
+    let size = keyspace(&mask.positions);
+    let slice = options.slice();
+    let range = slice.start.min(size)..slice.end.min(size);
+    let bar = targets.bar(range.end - range.start, "Trying the mask");
+    let positions = &mask.positions;
+    search_positions(
+        targets,
+        positions,
+        range,
+        options.threads,
+        deadline,
+        &stop,
+        &bar,
+    );
+    bar.finish_and_clear();
+}
+
+/// Try the passwords in the range, of those with one of the given characters at each position,
+/// splitting the range between the threads
+fn search_positions(
+    targets: &Targets,
+    positions: &[&[u8]],
+    range: Range<u64>,
+    threads: usize,
+    deadline: Option<Instant>,
+    stop: &AtomicBool,
+    bar: &ProgressBar,
+) {
+    let threads = threads.max(1) as u64;
+    let share = (range.end - range.start).div_ceil(threads).max(1);
+    std::thread::scope(|s| {
+        for start in (0..threads)
+            .map(|t| range.start + t * share)
+            .take_while(|&start| start < range.end)
+        {
+            let range = start..(start + share).min(range.end);
+            s.spawn(move || search_range(targets, positions, range, deadline, stop, bar));
+        }
+    });
+}
+
+/// The number of passwords with one of the given characters at each position
+fn keyspace(positions: &[&[u8]]) -> u64 {
+    positions.iter().fold(1, |size, characters| {
+        size.saturating_mul(characters.len() as u64)
+    })
+}
+
+/// Test a range of the passwords, where each password is numbered by counting with each position
+/// in base however many characters it can hold
+fn search_range(
+    targets: &Targets,
+    positions: &[&[u8]],
+    range: Range<u64>,
+    deadline: Option<Instant>,
+    stop: &AtomicBool,
+    bar: &ProgressBar,
+) {
+    // The digits of the first password in the range, with the most significant first
+    let mut digits = vec![0; positions.len()];
+    let mut index = range.start;
+    for (digit, characters) in digits.iter_mut().zip(positions).rev() {
+        let base = characters.len() as u64;
+        *digit = usize::try_from(index % base).unwrap_or_default();
+        index /= base;
+    }
+    let mut trial: Vec<u8> = digits
+        .iter()
+        .zip(positions)
+        .map(|(&d, characters)| characters[d])
+        .collect();
+
+    let mut checker = Checker::new(targets);
+    for i in 0..range.end - range.start {
+        if i % CHECK_INTERVAL as u64 == 0 {
+            if i > 0 {
+                targets.advance(bar, CHECK_INTERVAL as u64);
+            }
+            if interrupted(targets, stop, deadline) {
+                return;
+            }
+        }
+        if checker.check(&trial) {
+            stop.store(true, Ordering::Relaxed);
+            return;
+        }
+        // Move on to the next password, carrying over like an odometer
+        for (position, characters) in positions.iter().enumerate().rev() {
+            digits[position] += 1;
+            if digits[position] < characters.len() {
+                trial[position] = characters[digits[position]];
+                break;
+            }
+            digits[position] = 0;
+            trial[position] = characters[0];
+        }
+    }
+    targets.advance(bar, uncounted(range.end - range.start));
+}
+
+/// Search each batch of candidates in turn until every password is found. The time limit and the
+/// progress bar cover all of them. If the total number of candidates is not known up front, the
+/// progress bar grows as each batch is reached
+fn search_batches<I>(
+    targets: &Targets,
+    options: &Options,
+    batches: I,
+    total: Option<u64>,
+) -> UnlockResult<()>
+where
+    I: Iterator<Item = UnlockResult<Vec<u8>>>,
+{
+    let deadline = options.time_limit.map(|limit| Instant::now() + limit);
+    let bar = targets.bar(total.unwrap_or_default(), "Trying passwords");
+    let rules = options.rules();
+    for words in batches {
+        if targets.done() || targets.cancelled() || deadline.is_some_and(|d| Instant::now() >= d) {
+            break;
+        }
+        let words = words?;
+        let words = with_rules_applied(&words, &rules);
+        let candidates: Vec<&[u8]> = lines(&words).filter(|w| options.policy.allows(w)).collect();
+        if total.is_none() {
+            targets.grow(&bar, candidates.len() as u64);
+        } else {
+            // The candidates left out by the policy were counted in the total
+            let skipped = lines(&words).count() - candidates.len();
+            targets.advance(&bar, skipped as u64);
+        }
+        search_candidates(targets, &candidates, options.threads, deadline, &bar);
+    }
+    bar.finish_and_clear();
+    Ok(())
+}
+
+/// The number of candidates once the rules have been applied to each of the words, at most
+fn with_rules(words: u64, options: &Options) -> u64 {
+    words.saturating_mul(options.rules().len() as u64 + 1)
+}
+
+/// The words of the wordlist with the rules applied, or the words as they are if there are no
+/// rules
+fn with_rules_applied<'a>(words: &'a [u8], rules: &[Rule]) -> Cow<'a, [u8]> {
+    if rules.is_empty() {
+        Cow::Borrowed(words)
+    } else {
+        Cow::Owned(apply_rules(words, rules))
+    }
+}
+
+/// The wordlists of the options, or the built-in list if there are none, read a batch of whole
+/// lines at a time. Only one batch is held in memory, so a wordlist never has to be read or split
+/// into lines all at once
+struct Wordlist<'a> {
+    paths: std::slice::Iter<'a, PathBuf>,
+    reader: Option<Box<dyn BufRead>>,
+}
+
+impl<'a> Wordlist<'a> {
+    fn new(options: &'a Options) -> UnlockResult<Self> {
+        let reader: Option<Box<dyn BufRead>> = if options.wordlists.is_empty() {
+            Some(Box::new(builtin_wordlist()?))
+        } else {
+            None
+        };
+        Ok(Self {
+            paths: options.wordlists.iter(),
+            reader,
+        })
+    }
+}
+
+/// The built-in list of common passwords, or an error if it was left out of the build
+fn builtin_wordlist() -> UnlockResult<&'static [u8]> {
+    WORDLIST.ok_or(UnlockError::NoWordlist)
+}
+
+/// Each batch ends at the end of a line, and never runs on from one wordlist into the next. A
+/// wordlist that cannot be opened or read gives an error when it is reached
+impl Iterator for Wordlist<'_> {
+    type Item = UnlockResult<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut batch = Vec::with_capacity(WORDLIST_BATCH);
+        while batch.is_empty() {
+            let reader = match &mut self.reader {
+                Some(reader) => reader,
+                None => match File::open(wordlists::resolve(self.paths.next()?)) {
+                    Ok(file) => self.reader.insert(Box::new(BufReader::new(file))),
+                    Err(e) => return Some(Err(e.into())),
+                },
+            };
+            while batch.len() < WORDLIST_BATCH {
+                match reader.read_until(b'\n', &mut batch) {
+                    Ok(0) => {
+                        self.reader = None;
+                        break;
+                    }
+                    Ok(_) => {}
+                    Err(e) => return Some(Err(e.into())),
+                }
+            }
+        }
+        if batch.last() == Some(&b'\n') {
+            batch.pop();
+        }
+        Some(Ok(batch))
+    }
+}
+
+/// The number of words in a wordlist, leaving out any blank lines
+fn count_words(words: &[u8]) -> u64 {
+    lines(words).filter(|w| !w.is_empty()).count() as u64
+}
+
+/// The lines of a wordlist, without their line endings
+fn lines(words: &[u8]) -> impl Iterator<Item = &[u8]> {
+    words
+        .split(|&b| b == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+}
+
+/// Every word of the wordlist, each followed by the candidates the rules make from it. A rule that
+/// leaves a word unchanged does not add it again
+fn apply_rules(words: &[u8], rules: &[Rule]) -> Vec<u8> {
+    let mut candidates = Vec::with_capacity(words.len() * (rules.len() + 1));
+    for word in lines(words).filter(|w| !w.is_empty()) {
+        candidates.extend_from_slice(word);
+        candidates.push(b'\n');
+        for rule in rules {
+            let candidate = rule.apply(word);
+            if candidate != word {
+                candidates.extend_from_slice(&candidate);
+                candidates.push(b'\n');
+            }
+        }
+    }
+    candidates
+}
+
+/// Test the candidates against the targets, splitting them between the threads
+fn search_candidates(
+    targets: &Targets,
+    candidates: &[&[u8]],
+    threads: usize,
+    deadline: Option<Instant>,
+    bar: &ProgressBar,
+) {
+    let chunk_size = candidates.len().div_ceil(threads.max(1)).max(1);
+    let stop = AtomicBool::new(false);
+    std::thread::scope(|s| {
+        for chunk in candidates.chunks(chunk_size) {
+            s.spawn(|| search_chunk(targets, chunk, deadline, &stop, bar));
+        }
+    });
+}
+
+fn search_chunk(
+    targets: &Targets,
+    candidates: &[&[u8]],
+    deadline: Option<Instant>,
+    stop: &AtomicBool,
+    bar: &ProgressBar,
+) {
+    let mut checker = Checker::new(targets);
+    for (i, trial) in candidates.iter().enumerate() {
+        if i % CHECK_INTERVAL == 0 {
+            if i > 0 {
+                targets.advance(bar, CHECK_INTERVAL as u64);
+            }
+            if interrupted(targets, stop, deadline) {
+                return;
+            }
+        }
+        if checker.check(trial) {
+            stop.store(true, Ordering::Relaxed);
+            return;
+        }
+    }
+    // Count the candidates since the last check too, as the whole chunk has been tried
+    targets.advance(bar, uncounted(candidates.len() as u64));
+}
+
+/// How many of a run of candidates, all tried, were tried since the last check of the time limit
+const fn uncounted(tried: u64) -> u64 {
+    let interval = CHECK_INTERVAL as u64;
+    tried - tried.saturating_sub(1) / interval * interval
+}
+
+/// The salted hashes of the passwords being searched for. Those with the same salt are kept
+/// together, so each candidate only has to be hashed once for each salt
+struct Targets {
+    salts: Vec<(Salt, Vec<(Hash, usize)>)>,
+    found: Mutex<Vec<Option<String>>>,
+    remaining: AtomicUsize,
+    /// The candidates tried and to try, kept alongside the progress bars so they can be read
+    /// while the search runs
+    tried: AtomicU64,
+    total: AtomicU64,
+    /// The token of the operation the search is part of, taken from the thread that starts it
+    cancel: Option<CancellationToken>,
+}
+
+impl Targets {
+    fn new(hashes: &[(Salt, Hash)]) -> Self {
+        let mut salts: Vec<(Salt, Vec<(Hash, usize)>)> = Vec::new();
+        for (index, &(salt, hash)) in hashes.iter().enumerate() {
+            match salts.iter_mut().find(|(s, _)| *s == salt) {
+                Some((_, hashes)) => hashes.push((hash, index)),
+                None => salts.push((salt, vec![(hash, index)])),
+            }
+        }
+        Self {
+            salts,
+            found: Mutex::new(vec![None; hashes.len()]),
+            remaining: AtomicUsize::new(hashes.len()),
+            tried: AtomicU64::new(0),
+            total: AtomicU64::new(0),
+            cancel: cancel::current(),
+        }
+    }
+
+    /// Whether the operation the search is part of has been cancelled
+    fn cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// A progress bar for a search of the given number of candidates
+    fn bar(&self, len: u64, message: &'static str) -> ProgressBar {
+        self.total.fetch_add(len, Ordering::Relaxed);
+        progress::bar(len, message)
+    }
+
+    /// Add to the number of candidates to try, for searches that only find out as they go
+    fn grow(&self, bar: &ProgressBar, len: u64) {
+        bar.inc_length(len);
+        self.total.fetch_add(len, Ordering::Relaxed);
+    }
+
+    /// Count more of the candidates as tried
+    fn advance(&self, bar: &ProgressBar, tried: u64) {
+        bar.inc(tried);
+        self.tried.fetch_add(tried, Ordering::Relaxed);
+    }
+
+    /// How far the search that started at the given time has got
+    // The rate does not need every digit of the number of candidates tried
+    #[allow(clippy::cast_precision_loss)]
+    fn progress(&self, start: Instant) -> Progress {
+        let tried = self.tried.load(Ordering::Relaxed);
+        let elapsed = start.elapsed().as_secs_f64();
+        Progress {
+            tried,
+            total: self.total.load(Ordering::Relaxed),
+            rate: if elapsed > 0.0 {
+                tried as f64 / elapsed
+            } else {
+                0.0
+            },
+            found: self.hashes() - self.remaining.load(Ordering::Relaxed),
+        }
+    }
+
+    /// The number of passwords being searched for
+    fn hashes(&self) -> usize {
+        self.salts.iter().map(|(_, hashes)| hashes.len()).sum()
+    }
+
+    /// Keep the password of one of the targets, returning whether every target has now been found
+    fn record(&self, index: usize, password: &[u8]) -> bool {
+        let mut found = self.found.lock().unwrap_or_else(PoisonError::into_inner);
+        if found[index].is_none() {
+            found[index] = Some(String::from_utf8_lossy(password).to_string());
+            self.remaining.fetch_sub(1, Ordering::Relaxed);
+        }
+        drop(found);
+        self.done()
+    }
+
+    /// Whether the password of every target has been found
+    fn done(&self) -> bool {
+        self.remaining.load(Ordering::Relaxed) == 0
+    }
+
+    /// The passwords found, in the same order as the hashes the targets were made from
+    fn into_found(self) -> Vec<Option<String>> {
+        self.found
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Tests candidates against the targets. Hashing is what takes the time when cracking, so the
+/// hasher and the buffer it writes to are kept from one candidate to the next
+struct Checker<'a> {
+    targets: &'a Targets,
+    hasher: Sha1,
+    digest: Output<Sha1>,
+}
+
+impl<'a> Checker<'a> {
+    fn new(targets: &'a Targets) -> Self {
+        Self {
+            targets,
+            hasher: Sha1::new(),
+            digest: Output::<Sha1>::default(),
+        }
+    }
+
+    /// Test the candidate against every target, keeping it if it is the password of any of them.
+    /// Returns whether every target has now been found
+    #[inline]
+    fn check(&mut self, trial: &[u8]) -> bool {
+        for (salt, hashes) in &self.targets.salts {
+            self.hasher.update(trial);
+            self.hasher.update(salt);
+            self.hasher.finalize_into_reset(&mut self.digest);
+            for (hash, index) in hashes {
+                if self.digest[..] == hash[..] && self.targets.record(*index, trial) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+/// Whether a thread should give up, because another thread has found every password, the time
+/// limit has been reached or the search has been cancelled
+fn interrupted(targets: &Targets, stop: &AtomicBool, deadline: Option<Instant>) -> bool {
+    if stop.load(Ordering::Relaxed) {
+        return true;
+    }
+    if deadline.is_some_and(|d| Instant::now() > d) || targets.cancelled() {
+        stop.store(true, Ordering::Relaxed);
+        return true;
+    }
+    false
+}
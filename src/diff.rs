@@ -0,0 +1,178 @@
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use crate::consts;
+use crate::error::{UnlockError, UnlockResult};
+use crate::export::module_sources;
+use crate::info;
+use crate::lenient::Options;
+use crate::list_modules::ModuleType;
+use crate::read::xl_vba;
+use cfb::CompoundFile;
+
+/// The parts of a VBA project that are compared: the properties of the PROJECT stream and the
+/// source code of each module
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    properties: Vec<String>,
+    modules: Vec<ModuleSource>,
+}
+
+#[derive(Debug, Clone)]
+struct ModuleSource {
+    name: String,
+    module_type: ModuleType,
+    source: String,
+}
+
+/// Read the parts of the VBA project to compare.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - There is no VBA file within the zip archive, found at "/xl/vbaProject.bin"
+/// - The VBA file within the archive cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   or [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+/// - A module stream cannot be found, or its source code cannot be decompressed
+pub fn xl(filename: &Path, options: &Options) -> UnlockResult<Snapshot> {
+    let mut vba_cfb = xl_vba(filename)?;
+    snapshot(
+        &mut vba_cfb,
+        consts::PROJECT_PATH,
+        consts::DIR_PATH,
+        consts::VBA_STORAGE_PATH,
+        options,
+    )
+}
+
+/// Read the parts of the VBA project to compare.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   or [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+/// - A module stream cannot be found, or its source code cannot be decompressed
+pub fn xl_97(filename: &Path, options: &Options) -> UnlockResult<Snapshot> {
+    let mut file = cfb::open(filename).map_err(UnlockError::CFBOpen)?;
+    snapshot(
+        &mut file,
+        consts::CFB_VBA_PATH,
+        consts::CFB_DIR_PATH,
+        consts::CFB_VBA_STORAGE_PATH,
+        options,
+    )
+}
+
+fn snapshot<F: Read + Seek>(
+    cfb: &mut CompoundFile<F>,
+    project_path: &str,
+    dir_path: &str,
+    storage_path: &str,
+    options: &Options,
+) -> UnlockResult<Snapshot> {
+    let (project, modules) = module_sources(cfb, project_path, dir_path, storage_path, options)?;
+    Ok(Snapshot {
+        properties: info::report(&project),
+        modules: modules
+            .into_iter()
+            .map(|m| ModuleSource {
+                name: m.name,
+                module_type: m.module_type,
+                source: String::from_utf8_lossy(&m.source).to_string(),
+            })
+            .collect(),
+    })
+}
+
+/// The differences between two VBA projects, as lines ready to print
+///
+/// Lines only in the first project start with '-' and lines only in the second start with '+'.
+/// Nothing is returned if the projects are the same
+#[must_use]
+pub fn differences(a: &Snapshot, b: &Snapshot) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    let properties = changed_lines(&a.properties, &b.properties);
+    if !properties.is_empty() {
+        lines.push("Properties".to_string());
+        lines.extend(properties);
+    }
+
+    let summary = |m: &ModuleSource| format!("{} ({:?})", m.name, m.module_type);
+    let modules = changed_lines(
+        &a.modules.iter().map(summary).collect::<Vec<_>>(),
+        &b.modules.iter().map(summary).collect::<Vec<_>>(),
+    );
+    if !modules.is_empty() {
+        lines.push("Modules".to_string());
+        lines.extend(modules);
+    }
+
+    for module_a in &a.modules {
+        let Some(module_b) = b.modules.iter().find(|m| m.name == module_a.name) else {
+            continue;
+        };
+        let source_a: Vec<&str> = module_a.source.lines().collect();
+        let source_b: Vec<&str> = module_b.source.lines().collect();
+        let source = changed_lines(&source_a, &source_b);
+        if !source.is_empty() {
+            lines.push(format!("Source of {}", module_a.name));
+            lines.extend(source);
+        }
+    }
+
+    lines
+}
+
+/// The lines removed from, or added to, the first list to make the second. Found from the longest
+/// common subsequence, after trimming off any matching start and end
+fn changed_lines<S: AsRef<str>>(a: &[S], b: &[S]) -> Vec<String> {
+    let prefix = a
+        .iter()
+        .zip(b)
+        .take_while(|(x, y)| x.as_ref() == y.as_ref())
+        .count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x.as_ref() == y.as_ref())
+        .count();
+    let a = &a[prefix..a.len() - suffix];
+    let b = &b[prefix..b.len() - suffix];
+
+    // lcs[i][j] is the length of the longest common subsequence of a[i..] and b[j..]
+    let mut lcs = vec![vec![0_usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i].as_ref() == b[j].as_ref() {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i].as_ref() == b[j].as_ref() {
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(format!("- {}", a[i].as_ref()));
+            i += 1;
+        } else {
+            lines.push(format!("+ {}", b[j].as_ref()));
+            j += 1;
+        }
+    }
+    lines
+}
@@ -0,0 +1,99 @@
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use crate::error::{UnlockError, UnlockResult};
+use crate::read::xl_vba;
+use cfb::CompoundFile;
+
+/// How many bytes are shown on each line of the dump
+const BYTES_PER_LINE: usize = 16;
+
+/// Print a hex dump of a stream of the VBA file to standard out.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - There is no VBA file within the zip archive, found at "/xl/vbaProject.bin"
+/// - The VBA file within the archive cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - There is no stream at the path within the VBA file
+pub fn print_xl(filename: &Path, path: &str) -> UnlockResult<()> {
+    print_dump(&xl(filename, path)?);
+    Ok(())
+}
+
+/// Read the bytes of a stream of the VBA file, e.g. `/PROJECT` or `/VBA/dir`.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// # Errors
+/// Will return an error in the same situations as [`print_xl`]
+pub fn xl(filename: &Path, path: &str) -> UnlockResult<Vec<u8>> {
+    read_stream(&mut xl_vba(filename)?, path)
+}
+
+/// Print a hex dump of a stream of the file to standard out.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - There is no stream at the path within the file
+pub fn print_xl_97(filename: &Path, path: &str) -> UnlockResult<()> {
+    print_dump(&xl_97(filename, path)?);
+    Ok(())
+}
+
+/// Read the bytes of a stream of the file, e.g. `/_VBA_PROJECT_CUR/PROJECT`.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// # Errors
+/// Will return an error in the same situations as [`print_xl_97`]
+pub fn xl_97(filename: &Path, path: &str) -> UnlockResult<Vec<u8>> {
+    let mut file = cfb::open(filename).map_err(UnlockError::CFBOpen)?;
+    read_stream(&mut file, path)
+}
+
+fn read_stream<F: Read + Seek>(cfb: &mut CompoundFile<F>, path: &str) -> UnlockResult<Vec<u8>> {
+    if !cfb.is_stream(path) {
+        return Err(UnlockError::NoStream(path.to_string()));
+    }
+    let mut data = Vec::new();
+    cfb.open_stream(path)?.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Lay the bytes out as a hex dump, ready to print. Each line has the offset, the bytes in hex
+/// and then the bytes as ASCII, with a '.' for anything that can't be printed
+#[must_use]
+pub fn hex_dump(data: &[u8]) -> Vec<String> {
+    data.chunks(BYTES_PER_LINE)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|b| format!("{b:02x}")).collect();
+            let (left, right) = hex.split_at(hex.len().min(BYTES_PER_LINE / 2));
+            let ascii: String = chunk
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        char::from(b)
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!(
+                "{:08x}  {:23}  {:23}  |{ascii}|",
+                i * BYTES_PER_LINE,
+                left.join(" "),
+                right.join(" ")
+            )
+        })
+        .collect()
+}
+
+fn print_dump(data: &[u8]) {
+    for line in hex_dump(data) {
+        println!("{line}");
+    }
+}
@@ -2,19 +2,83 @@ use std::{
     fmt::{Debug, Display},
     io,
     num::ParseIntError,
+    path::PathBuf,
 };
 
+use crate::ovba::records::project::PartialProject;
+
 pub type UnlockResult<T> = Result<T, UnlockError>;
 
 #[allow(clippy::module_name_repetitions)]
 pub enum UnlockError {
     FileOpen(io::Error),
-    NotExcel(String),
-    XlsX(String),
+    NotExcel(PathBuf),
+    XlsX(PathBuf),
     Zip(zip::result::ZipError),
     NoVBAFile,
+    NoStream(String),
     CFBOpen(io::Error),
     ProjectStructure(ProjectStructure),
+    /// The PROJECT stream could not be parsed, along with what could be read of it
+    PartialProject(Box<PartialProject>, ProjectStructure),
+    DirStructure(DirStructure),
+    ModuleSource(String, Compression),
+    Config(String, String),
+    Stdin,
+    Url,
+    NotConvertible(&'static str),
+    Encrypted,
+    SheetRecords(String),
+    NoSheet(String),
+    NoModule(String),
+    Download(String, String),
+    Watch(notify::Error),
+    Serve(String),
+    NoWordlist,
+    UnknownWordlist(String),
+    NotConfirmed,
+    Cancelled,
+    NoPassword,
+    NoPasswordHash,
+    PasswordNotFound,
+}
+
+impl UnlockError {
+    /// The process exit code for the error, grouped by category:
+    /// - 2: the input is not something the tool can work with
+    /// - 3: a file could not be read or written
+    /// - 4: the Excel file, or the VBA file within it, could not be opened
+    /// - 5: the VBA project could not be parsed
+    /// - 6: the password could not be recovered
+    /// - 7: the operation was cancelled, which only happens when the crate is used as a library
+    ///
+    /// Exit code 0 is reserved for success and 1 for a file that is locked
+    #[must_use]
+    pub const fn exit_code(&self) -> u8 {
+        match self {
+            Self::NotExcel(_)
+            | Self::XlsX(_)
+            | Self::Config(..)
+            | Self::Stdin
+            | Self::Url
+            | Self::NotConvertible(_)
+            | Self::Encrypted
+            | Self::NoSheet(_)
+            | Self::NoModule(_)
+            | Self::NoWordlist
+            | Self::UnknownWordlist(_)
+            | Self::NotConfirmed => 2,
+            Self::FileOpen(_) | Self::Download(..) | Self::Watch(_) | Self::Serve(_) => 3,
+            Self::Zip(_) | Self::NoVBAFile | Self::NoStream(_) | Self::CFBOpen(_) => 4,
+            Self::ProjectStructure(_)
+            | Self::PartialProject(..)
+            | Self::DirStructure(_)
+            | Self::ModuleSource(..)
+            | Self::SheetRecords(_) => 5,
+            Self::NoPassword | Self::NoPasswordHash | Self::PasswordNotFound => 6,
+            Self::Cancelled => 7,
+        }
+    }
 }
 
 impl From<io::Error> for UnlockError {
@@ -35,6 +99,18 @@ impl From<ProjectStructure> for UnlockError {
     }
 }
 
+impl From<DirStructure> for UnlockError {
+    fn from(value: DirStructure) -> Self {
+        Self::DirStructure(value)
+    }
+}
+
+impl From<Compression> for UnlockError {
+    fn from(value: Compression) -> Self {
+        Self::DirStructure(DirStructure::Compression(value))
+    }
+}
+
 impl From<ProtectionState> for UnlockError {
     fn from(value: ProtectionState) -> Self {
         Self::ProjectStructure(ProjectStructure::ProtectionState(value))
@@ -57,10 +133,13 @@ impl Display for UnlockError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::FileOpen(e) => write!(f, "{e}"),
-            Self::NotExcel(file) => write!(f, "{file} is not an Excel file. Try harder"),
+            Self::NotExcel(file) => {
+                write!(f, "{} is not an Excel file. Try harder", file.display())
+            }
             Self::XlsX(file) => write!(
                 f,
-                "{file} is Excel's format for files with no VBA. There is nothing to operate on"
+                "{} is Excel's format for files with no VBA. There is nothing to operate on",
+                file.display()
             ),
             Self::Zip(e) => write!(
                 f,
@@ -70,11 +149,61 @@ impl Display for UnlockError {
                 f,
                 "Could not find the 'xl/vbaProject.bin' file within the extracted archive"
             ),
+            Self::NoStream(path) => write!(f, "Could not find a stream at {path}"),
             Self::CFBOpen(e) => write!(
                 f,
                 "There was a problem reading the CFB format vbaProject.bin file: {e}"
             ),
-            Self::ProjectStructure(e) => write!(f, "{e}"),
+            Self::ProjectStructure(e) | Self::PartialProject(_, e) => write!(f, "{e}"),
+            Self::DirStructure(e) => write!(f, "{e}"),
+            Self::ModuleSource(module, e) => {
+                write!(
+                    f,
+                    "Could not decompress the source code of module {module}: {e}"
+                )
+            }
+            Self::Config(path, e) => write!(f, "Could not parse the config file {path}: {e}"),
+            Self::Stdin => write!(
+                f,
+                "Only the read and remove commands can take a file from standard input"
+            ),
+            Self::NotConvertible(reason) => {
+                write!(f, "The file cannot be converted to xlsx: {reason}")
+            }
+            Self::Encrypted => write!(
+                f,
+                "The workbook is encrypted, so its protection cannot be removed"
+            ),
+            Self::SheetRecords(part) => write!(f, "Could not read the records of {part}"),
+            Self::NoSheet(name) => write!(f, "Could not find a sheet named {name}"),
+            Self::NoModule(name) => write!(f, "Could not find a VBA module named {name}"),
+            Self::Url => write!(f, "Only the read and remove commands can take a URL"),
+            Self::Download(url, e) => write!(f, "Could not download {url}: {e}"),
+            Self::Watch(e) => write!(f, "Could not watch the directory: {e}"),
+            Self::Serve(e) => write!(f, "Could not start the server: {e}"),
+            Self::NoWordlist => write!(
+                f,
+                "This build has no built-in list of passwords, so a wordlist must be given"
+            ),
+            Self::UnknownWordlist(name) => write!(
+                f,
+                "There is no known wordlist called {name}. Others need a --url, and a name made \
+                of letters, digits, '-' and '_'"
+            ),
+            Self::NotConfirmed => write!(
+                f,
+                "The search was not started, as it could take over an hour. Pass --yes to start \
+                it without asking"
+            ),
+            Self::Cancelled => write!(f, "The operation was cancelled"),
+            Self::NoPassword => write!(f, "The VBA project has no password to recover"),
+            Self::NoPasswordHash => write!(
+                f,
+                "The VBA project password is not stored as a hash, try the read subcommand"
+            ),
+            Self::PasswordNotFound => {
+                write!(f, "Could not find the password among the candidates tried")
+            }
         }
     }
 }
@@ -86,24 +215,33 @@ impl Debug for UnlockError {
 }
 
 pub enum ProjectStructure {
-    NomParseError(Vec<u8>, Vec<u8>),
+    NomParseError(usize, String, String),
     ProtectionState(ProtectionState),
     Password(Password),
     Visibility(Visibility),
+    NameMap(usize),
+    VbaProject(Vec<u8>),
 }
 
 impl Display for ProjectStructure {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::NomParseError(left, whole) => write!(
+            Self::NomParseError(offset, record, context) => write!(
                 f,
-                "Had issue parsing the project information at {} \n\nThis was the full text to be parsed {}",
-                String::from_utf8_lossy(left),
-                String::from_utf8_lossy(whole),
+                "Had issue parsing the PROJECT stream at byte 0x{offset:x} while parsing the {record}, context: {context:?}"
             ),
             Self::ProtectionState(e) => write!(f, "{e}"),
             Self::Password(e) => write!(f, "{e}"),
             Self::Visibility(e) => write!(f, "{e}"),
+            Self::NameMap(offset) => write!(
+                f,
+                "The PROJECTwm stream ended part way through the name of a module, at byte 0x{offset:x}"
+            ),
+            Self::VbaProject(start) => write!(
+                f,
+                "The _VBA_PROJECT stream MUST start with the bytes cc 61 and then the version of VBA, not {}",
+                start.iter().map(|b| format!("{b:02x}")).collect::<Vec<_>>().join(" ")
+            ),
         }
     }
 }
@@ -126,6 +264,48 @@ impl From<Visibility> for ProjectStructure {
     }
 }
 
+pub enum DirStructure {
+    Compression(Compression),
+    NomParseError(usize, usize),
+}
+
+impl Display for DirStructure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Compression(e) => write!(f, "{e}"),
+            Self::NomParseError(offset, length) => write!(
+                f,
+                "Had issue parsing the VBA dir stream at byte 0x{offset:x} of the 0x{length:x} decompressed bytes"
+            ),
+        }
+    }
+}
+
+impl From<Compression> for DirStructure {
+    fn from(value: Compression) -> Self {
+        Self::Compression(value)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Compression {
+    Signature(u8),
+    ChunkSignature(u16),
+    CopyTokenOffset(usize, usize),
+    Truncated,
+}
+
+impl Display for Compression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Signature(b) => write!(f, "A VBA compressed container MUST start with the signature byte 0x01, not 0x{b:02x}"),
+            Self::ChunkSignature(h) => write!(f, "The chunk header 0x{h:04x} of a VBA compressed container does not have the signature bits 0b011"),
+            Self::CopyTokenOffset(offset, available) => write!(f, "A copy token in a VBA compressed container points {offset} bytes back, but only {available} bytes of the chunk have been decompressed"),
+            Self::Truncated => write!(f, "The VBA compressed container ended part way through a chunk"),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum ProtectionState {
     Decrypt(DataEncryption),
@@ -345,3 +525,22 @@ impl Display for InvalidHex {
         )
     }
 }
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct InvalidGuid(String);
+
+impl From<String> for InvalidGuid {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl Display for InvalidGuid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} is not a GUID of the form {{XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX}}",
+            self.0
+        )
+    }
+}
@@ -0,0 +1,236 @@
+use std::fs;
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use crate::consts;
+use crate::error::{UnlockError, UnlockResult};
+use crate::lenient::Options;
+use crate::list_modules::{self, module_type, ModuleType};
+use crate::ovba::records::designer::Designer;
+use crate::ovba::records::dir::{self, Dir};
+use crate::ovba::records::module::ModuleStream;
+use crate::ovba::records::project::Project;
+use crate::read::xl_vba;
+use cfb::CompoundFile;
+
+/// Write the source code of every module of the VBA project into the given directory.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// Each module is saved with the extension the VBE would use when exporting it: '.bas' for
+/// standard modules, '.frm' for designer modules and '.cls' for everything else. The directory is
+/// created if it does not already exist and any existing files of the same name are overwritten.
+/// A '.frm' file starts with the properties of the form, read from its designer storage. The
+/// source is written out as stored, i.e. in the code page of the project
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - There is no VBA file within the zip archive, found at "/xl/vbaProject.bin"
+/// - The VBA file within the archive cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   or [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+/// - A module stream cannot be found, or its source code cannot be decompressed
+/// - The directory or any of the files cannot be written
+pub fn xl(filename: &Path, directory: &Path, options: &Options) -> UnlockResult<()> {
+    let mut vba_cfb = xl_vba(filename)?;
+    export(
+        &mut vba_cfb,
+        consts::PROJECT_PATH,
+        consts::DIR_PATH,
+        consts::VBA_STORAGE_PATH,
+        directory,
+        options,
+    )
+}
+
+/// Write the source code of every module of the VBA project into the given directory.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// Each module is saved with the extension the VBE would use when exporting it: '.bas' for
+/// standard modules, '.frm' for designer modules and '.cls' for everything else. The directory is
+/// created if it does not already exist and any existing files of the same name are overwritten.
+/// A '.frm' file starts with the properties of the form, read from its designer storage. The
+/// source is written out as stored, i.e. in the code page of the project
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   or [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+/// - A module stream cannot be found, or its source code cannot be decompressed
+/// - The directory or any of the files cannot be written
+pub fn xl_97(filename: &Path, directory: &Path, options: &Options) -> UnlockResult<()> {
+    let mut file = cfb::open(filename).map_err(UnlockError::CFBOpen)?;
+    export(
+        &mut file,
+        consts::CFB_VBA_PATH,
+        consts::CFB_DIR_PATH,
+        consts::CFB_VBA_STORAGE_PATH,
+        directory,
+        options,
+    )
+}
+
+fn export<F: Read + Seek>(
+    cfb: &mut CompoundFile<F>,
+    project_path: &str,
+    dir_path: &str,
+    storage_path: &str,
+    directory: &Path,
+    options: &Options,
+) -> UnlockResult<()> {
+    let (_, modules) = module_sources(cfb, project_path, dir_path, storage_path, options)?;
+    fs::create_dir_all(directory)?;
+
+    for module in modules {
+        let extension = match module.module_type {
+            ModuleType::Standard => "bas",
+            ModuleType::Designer => "frm",
+            ModuleType::Document | ModuleType::Class => "cls",
+        };
+        // A form starts with the properties of the form itself, as the VBE writes them
+        let mut contents = module
+            .designer
+            .map(|d| d.frame().to_vec())
+            .unwrap_or_default();
+        if !contents.is_empty() && !contents.ends_with(b"\n") {
+            contents.extend(b"\r\n");
+        }
+        contents.extend(module.source);
+        fs::write(
+            directory.join(format!("{}.{extension}", module.name)),
+            contents,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// The source code of the module with the given name, ignoring case as the VBE does.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// Only the dir stream is needed to find the module, so the source can be read even when the
+/// PROJECT stream is damaged. Any bytes that are not valid UTF-8 are replaced, as the source is
+/// stored in the code page of the project
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - There is no VBA file within the zip archive, found at "/xl/vbaProject.bin"
+/// - The VBA file within the archive cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+/// - There is no module with the name
+/// - The module stream cannot be found, or its source code cannot be decompressed
+pub fn xl_module_source(filename: &Path, name: &str, options: &Options) -> UnlockResult<String> {
+    let mut vba_cfb = xl_vba(filename)?;
+    module_source(
+        &mut vba_cfb,
+        consts::DIR_PATH,
+        consts::VBA_STORAGE_PATH,
+        name,
+        options,
+    )
+}
+
+/// The source code of the module with the given name, ignoring case as the VBE does.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// Only the dir stream is needed to find the module, so the source can be read even when the
+/// PROJECT stream is damaged. Any bytes that are not valid UTF-8 are replaced, as the source is
+/// stored in the code page of the project
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+/// - There is no module with the name
+/// - The module stream cannot be found, or its source code cannot be decompressed
+pub fn xl_97_module_source(filename: &Path, name: &str, options: &Options) -> UnlockResult<String> {
+    let mut file = cfb::open(filename).map_err(UnlockError::CFBOpen)?;
+    module_source(
+        &mut file,
+        consts::CFB_DIR_PATH,
+        consts::CFB_VBA_STORAGE_PATH,
+        name,
+        options,
+    )
+}
+
+fn module_source<F: Read + Seek>(
+    cfb: &mut CompoundFile<F>,
+    dir_path: &str,
+    storage_path: &str,
+    name: &str,
+    options: &Options,
+) -> UnlockResult<String> {
+    let dir = Dir::from_stream(cfb.open_stream(dir_path)?, options)?;
+    let module = dir
+        .modules()
+        .iter()
+        .find(|m| m.name().eq_ignore_ascii_case(name))
+        .ok_or_else(|| UnlockError::NoModule(name.to_string()))?;
+    let source = read_module(cfb, storage_path, module)?.into_source();
+    Ok(String::from_utf8_lossy(&source).to_string())
+}
+
+/// Read the stream of a module, decompressing the source code found after the p-code
+pub(crate) fn read_module<F: Read + Seek>(
+    cfb: &mut CompoundFile<F>,
+    storage_path: &str,
+    module: &dir::Module,
+) -> UnlockResult<ModuleStream> {
+    let mut stream = Vec::new();
+    cfb.open_stream(format!("{storage_path}/{}", module.stream_name()))?
+        .read_to_end(&mut stream)?;
+    ModuleStream::from_bytes(&stream, module.text_offset())
+        .map_err(|e| UnlockError::ModuleSource(module.name(), e))
+}
+
+/// A module of the VBA project along with its decompressed source code
+pub(crate) struct ModuleSource {
+    pub name: String,
+    pub module_type: ModuleType,
+    pub source: Vec<u8>,
+    /// A string constant of the compiled p-code that is missing from the source, if there is one
+    pub stomped: Option<String>,
+    /// The designer storage of a user form, if the module is one
+    pub designer: Option<Designer>,
+}
+
+/// Read the PROJECT stream, and the source code of every module listed in the dir stream
+pub(crate) fn module_sources<F: Read + Seek>(
+    cfb: &mut CompoundFile<F>,
+    project_path: &str,
+    dir_path: &str,
+    storage_path: &str,
+    options: &Options,
+) -> UnlockResult<(Project, Vec<ModuleSource>)> {
+    let dir = Dir::from_stream(cfb.open_stream(dir_path)?, options)?;
+    let code_page = dir.information().code_page();
+    let project = list_modules::project(cfb, project_path, dir.information().locale(), options)?;
+
+    let mut modules = Vec::new();
+    for module in dir.modules() {
+        let stream = read_module(cfb, storage_path, module)?;
+        let module_type = module_type(&project, module);
+        let designer = match module_type {
+            ModuleType::Designer => list_modules::designer(cfb, project_path, module, code_page)?,
+            _ => None,
+        };
+        modules.push(ModuleSource {
+            module_type,
+            designer,
+            name: module.name(),
+            stomped: stream.stomped(),
+            source: stream.into_source(),
+        });
+    }
+
+    Ok((project, modules))
+}
@@ -0,0 +1,100 @@
+use std::fs;
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+use crate::consts;
+use crate::error::{UnlockError, UnlockResult};
+use crate::read::zip_to_raw_vba;
+use cfb::CompoundFile;
+use tracing::{debug, info};
+
+/// Save the raw VBA project, vbaProject.bin, to the output path so that it can be looked at with
+/// other OLE tools.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// The bytes are written exactly as they are stored within the zip archive
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - There is no VBA file within the zip archive, found at "/xl/vbaProject.bin"
+/// - The output file cannot be written
+pub fn xl(filename: &Path, output: &Path) -> UnlockResult<()> {
+    let zipfile = fs::File::open(filename)?;
+    let mut archive = zip::ZipArchive::new(zipfile)?;
+    let vba_raw = zip_to_raw_vba(&mut archive)?;
+    info!("writing {}", output.display());
+    fs::write(output, vba_raw.into_inner())?;
+    Ok(())
+}
+
+/// Save the VBA project as a standalone CFB file, laid out like the vbaProject.bin of later files.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// Every storage and stream beneath the `_VBA_PROJECT_CUR` storage is copied to the root of a new
+/// [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// file
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - There is no VBA storage within the file
+/// - The output file cannot be created, or any of the storages or streams written to it
+pub fn xl_97(filename: &Path, output: &Path) -> UnlockResult<()> {
+    let mut file = cfb::open(filename).map_err(UnlockError::CFBOpen)?;
+    if !file.is_storage(consts::CFB_VBA_PROJECT_PATH) {
+        return Err(UnlockError::NoVBAFile);
+    }
+
+    info!("writing {}", output.display());
+    let mut new_file = cfb::create(output)?;
+    copy_storage(&mut file, consts::CFB_VBA_PROJECT_PATH, &mut new_file, "/")?;
+    new_file.flush()?;
+    Ok(())
+}
+
+/// The default name to save the VBA project to: alongside the Excel file, with "_vbaProject.bin"
+/// in place of the extension
+#[must_use]
+pub fn default_output(filename: &Path) -> PathBuf {
+    let mut name = filename.file_stem().unwrap_or_default().to_owned();
+    name.push("_vbaProject.bin");
+    filename.with_file_name(name)
+}
+
+/// Copy every storage and stream beneath a storage of one CFB file into a storage of another. The
+/// destination storage must already exist
+pub(crate) fn copy_storage<F, G>(
+    from: &mut CompoundFile<F>,
+    from_path: &str,
+    to: &mut CompoundFile<G>,
+    to_path: &str,
+) -> UnlockResult<()>
+where
+    F: Read + Seek,
+    G: Read + Write + Seek,
+{
+    // Gather the entries up front, as the file can't be read from while it is being walked
+    let entries: Vec<_> = from
+        .walk_storage(from_path)?
+        .map(|e| (e.path().to_path_buf(), e.is_storage(), *e.clsid()))
+        .collect();
+
+    for (path, is_storage, clsid) in entries {
+        let new_path = Path::new(to_path).join(path.strip_prefix(from_path).unwrap_or(&path));
+        if is_storage {
+            if new_path != Path::new(to_path) {
+                debug!("creating the {} storage", new_path.display());
+                to.create_storage(&new_path)?;
+            }
+            to.set_storage_clsid(&new_path, clsid)?;
+        } else {
+            debug!("copying the {} stream", new_path.display());
+            let mut data = Vec::new();
+            from.open_stream(&path)?.read_to_end(&mut data)?;
+            to.create_stream(&new_path)?.write_all(&data)?;
+        }
+    }
+    Ok(())
+}
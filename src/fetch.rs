@@ -0,0 +1,65 @@
+use std::io::Read;
+
+use crate::error::{UnlockError, UnlockResult};
+use tracing::info;
+
+/// The largest workbook that will be downloaded
+const MAX_DOWNLOAD: u64 = 100 * 1024 * 1024;
+
+/// Whether the input is an http or https URL, rather than a path
+#[must_use]
+pub fn is_url(input: &str) -> bool {
+    let input = input.to_lowercase();
+    input.starts_with("http://") || input.starts_with("https://")
+}
+
+/// The name of the file at the end of the URL, ignoring any query string. Falls back to
+/// "download" if the URL does not end in a file name
+#[must_use]
+pub fn file_name(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    match path.rsplit('/').next() {
+        Some(name) if !name.is_empty() && path.matches('/').count() > 2 => name,
+        _ => "download",
+    }
+}
+
+/// Download the file at the URL into memory
+///
+/// # Errors
+/// Will return an error if the request fails, the server does not return the file or the file is
+/// larger than 100MB
+pub fn download(url: &str) -> UnlockResult<Vec<u8>> {
+    let fail = |e: &dyn std::fmt::Display| UnlockError::Download(url.to_string(), e.to_string());
+    let mut data = Vec::new();
+    get(url)?
+        .into_reader()
+        .take(MAX_DOWNLOAD + 1)
+        .read_to_end(&mut data)
+        .map_err(|e| fail(&e))?;
+    if data.len() as u64 > MAX_DOWNLOAD {
+        return Err(fail(&"the file is larger than 100MB"));
+    }
+    info!("downloaded {} bytes", data.len());
+    Ok(data)
+}
+
+/// Start downloading the file at the URL, ready for its body to be read
+pub(crate) fn get(url: &str) -> UnlockResult<ureq::Response> {
+    info!("downloading {url}");
+    let fail = |e: &dyn std::fmt::Display| UnlockError::Download(url.to_string(), e.to_string());
+    ureq::get(url).call().map_err(|e| match e {
+        ureq::Error::Status(code, _) => fail(&format!("the server returned status {code}")),
+        ureq::Error::Transport(t) => {
+            // The transport error repeats the URL, so build the message from its parts
+            let mut message = t.kind().to_string();
+            if let Some(detail) = t.message() {
+                message = format!("{message}: {detail}");
+            }
+            if let Some(source) = std::error::Error::source(&t) {
+                message = format!("{message}: {source}");
+            }
+            fail(&message)
+        }
+    })
+}
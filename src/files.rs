@@ -0,0 +1,113 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::consts;
+use crate::fetch;
+use walkdir::WalkDir;
+
+/// The kind of container an Excel file is stored in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Container {
+    /// A zip archive, used by Excel files since 2003 i.e. xlsm and xlsb
+    Zip,
+    /// A Compound File Binary, used by Excel files between 1997 & 2003 i.e. xls
+    Cfb,
+}
+
+/// Work out the container of an Excel file from its first few bytes
+#[must_use]
+pub fn sniff(data: &[u8]) -> Option<Container> {
+    match data {
+        [b'P', b'K', 0x03, 0x04, ..] => Some(Container::Zip),
+        [0xd0, 0xcf, 0x11, 0xe0, 0xa1, 0xb1, 0x1a, 0xe1, ..] => Some(Container::Cfb),
+        _ => None,
+    }
+}
+
+/// Work out the container of an Excel file from its contents, whatever it is named
+///
+/// The first few bytes tell a Compound File Binary from a zip archive. A zip archive only counts
+/// if it holds a VBA project, so that other zip based files, such as xlsx files, are not mistaken
+/// for one. Nothing is returned if the file cannot be read or is neither
+#[must_use]
+pub fn detect(path: &Path) -> Option<Container> {
+    let mut header = [0; 8];
+    File::open(path).ok()?.read_exact(&mut header).ok()?;
+    match sniff(&header)? {
+        Container::Cfb => Some(Container::Cfb),
+        Container::Zip => zip::ZipArchive::new(File::open(path).ok()?)
+            .ok()?
+            .by_name(consts::ZIP_VBA_PATH)
+            .is_ok()
+            .then_some(Container::Zip),
+    }
+}
+
+/// Turn the inputs given on the command line into the list of files to operate on
+///
+/// Files are passed through untouched, in the order given. Glob patterns, such as
+/// `reports/**/*.xlsm`, are expanded to the files they match so that they work the same on every
+/// platform, whether or not the shell globs. A pattern that matches nothing is passed through
+/// as-is, as are URLs. If recursive is set, any directory is walked and replaced with every Excel
+/// file found beneath it that contains a VBA project. Excel's own lock files, which start with
+/// '~$', are skipped
+pub fn expand<S: AsRef<Path>>(inputs: &[S], recursive: bool) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for input in inputs {
+        let path = input.as_ref();
+        // Only names that are valid Unicode can be URLs or patterns, anything else is taken as is
+        let text = path.to_str().unwrap_or_default();
+        if fetch::is_url(text) {
+            files.push(path.to_path_buf());
+        } else if is_pattern(text) && !path.exists() {
+            let matches: Vec<PathBuf> = glob::glob(text)
+                .map(|paths| {
+                    paths
+                        .filter_map(Result::ok)
+                        .filter(|p| p.is_file())
+                        .collect()
+                })
+                .unwrap_or_default();
+            if matches.is_empty() {
+                files.push(path.to_path_buf());
+            } else {
+                files.extend(matches);
+            }
+        } else if recursive && path.is_dir() {
+            files.extend(
+                WalkDir::new(path)
+                    .sort_by_file_name()
+                    .into_iter()
+                    .filter_map(Result::ok)
+                    .filter(|e| e.file_type().is_file())
+                    .map(walkdir::DirEntry::into_path)
+                    .filter(|p| is_excel_with_vba(p)),
+            );
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+    files
+}
+
+/// Whether the input contains any of the glob special characters
+fn is_pattern(input: &str) -> bool {
+    input.contains(['*', '?', '['])
+}
+
+/// Whether the file is an Excel file that has a VBA project within it, found from its contents
+#[must_use]
+pub fn is_excel_with_vba(path: &Path) -> bool {
+    if path
+        .file_name()
+        .is_some_and(|n| n.as_encoded_bytes().starts_with(b"~$"))
+    {
+        return false;
+    }
+    match detect(path) {
+        Some(Container::Cfb) => cfb::open(path).is_ok_and(|f| f.is_stream(consts::CFB_VBA_PATH)),
+        Some(Container::Zip) => true,
+        None => false,
+    }
+}
@@ -0,0 +1,152 @@
+use std::path::Path;
+use std::sync::Once;
+
+use crate::error::{UnlockError, UnlockResult};
+use crate::lenient::Options;
+use crate::ovba::records::project::Password;
+use crate::read::{to_hex, xl_97_project, xl_project};
+
+/// The format to print the password salt and hash in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// The salt and hash as hex, on separate labelled lines
+    #[default]
+    Hex,
+    /// The hash and salt as hex, separated by a colon i.e. `HASH:SALT`
+    Colon,
+    /// The line hashcat takes for its salted SHA1 mode, with a note on standard error of how to
+    /// run hashcat against it
+    Hashcat,
+    /// The line John the Ripper takes for its salted SHA1 dynamic format, with the file name as
+    /// the login i.e. `FILENAME:$dynamic_24$HASH$HEX$SALT`
+    John,
+}
+
+/// The hashcat hash mode for `sha1($pass.$salt)`, which is how VBA project passwords are hashed
+pub const HASHCAT_MODE: u32 = 110;
+
+/// Whether the note on how to run hashcat has been printed, as it is only needed once however many
+/// files are given
+static HASHCAT_HELP_SHOWN: Once = Once::new();
+
+/// The salt and SHA1 hash of a VBA project password
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasswordHash {
+    /// The 4 byte salt, appended to the password before hashing
+    pub salt: [u8; 4],
+    /// The SHA1 hash of the password followed by the salt
+    pub hash: [u8; 20],
+}
+
+impl PasswordHash {
+    /// The hash as hashcat takes it for mode [`HASHCAT_MODE`] with `--hex-salt` i.e. `HASH:SALT`
+    /// with both as lower case hex
+    #[must_use]
+    pub fn hashcat(&self) -> String {
+        format!("{}:{}", to_hex(&self.hash), to_hex(&self.salt))
+    }
+
+    /// The hash as John the Ripper takes it for the `dynamic_24` format, which is
+    /// `sha1($p.$s)`, with the login field first. Any colons in the login are swapped for
+    /// underscores, as John splits the line on them
+    #[must_use]
+    pub fn john(&self, login: &str) -> String {
+        format!(
+            "{}:$dynamic_24${}$HEX${}",
+            login.replace(':', "_"),
+            to_hex(&self.hash),
+            to_hex(&self.salt)
+        )
+    }
+}
+
+/// Print the salt and hash of the VBA project password to standard out.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - There is no VBA file within the zip archive, found at "/xl/vbaProject.bin"
+/// - The VBA file within the archive cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   cannot be found or parsed
+/// - The VBA project password is not stored as a hash
+pub fn print_xl(filename: &Path, format: Format, options: &Options) -> UnlockResult<()> {
+    print_hash(&xl(filename, options)?, filename, format);
+    Ok(())
+}
+
+/// Get the salt and hash of the VBA project password.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - There is no VBA file within the zip archive, found at "/xl/vbaProject.bin"
+/// - The VBA file within the archive cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   cannot be found or parsed
+/// - The VBA project password is not stored as a hash
+pub fn xl(filename: &Path, options: &Options) -> UnlockResult<PasswordHash> {
+    let (project, _) = xl_project(filename, false, options)?;
+    password_hash(project.password())
+}
+
+/// Print the salt and hash of the VBA project password to standard out.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   cannot be found or parsed
+/// - The VBA project password is not stored as a hash
+pub fn print_xl_97(filename: &Path, format: Format, options: &Options) -> UnlockResult<()> {
+    print_hash(&xl_97(filename, options)?, filename, format);
+    Ok(())
+}
+
+/// Get the salt and hash of the VBA project password.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   cannot be found or parsed
+/// - The VBA project password is not stored as a hash
+pub fn xl_97(filename: &Path, options: &Options) -> UnlockResult<PasswordHash> {
+    let (project, _) = xl_97_project(filename, false, options)?;
+    password_hash(project.password())
+}
+
+const fn password_hash(password: &Password) -> UnlockResult<PasswordHash> {
+    match password {
+        Password::Hash(salt, hash) => Ok(PasswordHash {
+            salt: *salt,
+            hash: *hash,
+        }),
+        _ => Err(UnlockError::NoPasswordHash),
+    }
+}
+
+fn print_hash(h: &PasswordHash, filename: &Path, format: Format) {
+    match format {
+        Format::Hex => {
+            println!("Salt: {}", to_hex(&h.salt));
+            println!("Hash: {}", to_hex(&h.hash));
+        }
+        Format::Colon => println!("{}", h.hashcat()),
+        Format::Hashcat => {
+            println!("{}", h.hashcat());
+            HASHCAT_HELP_SHOWN.call_once(|| {
+                eprintln!("# Save the hash lines to a file, e.g. hashes.txt, and crack them with:");
+                eprintln!("#   hashcat -m {HASHCAT_MODE} --hex-salt hashes.txt wordlist.txt");
+                eprintln!("# The salt is binary, so it is written as hex and --hex-salt is needed");
+            });
+        }
+        Format::John => println!("{}", h.john(&filename.to_string_lossy())),
+    }
+}
@@ -0,0 +1,273 @@
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use crate::consts;
+use crate::error::{UnlockError, UnlockResult};
+use crate::lenient::Options;
+use crate::list_modules;
+use crate::ovba::records::dir::Dir;
+use crate::ovba::records::project::{Password, Project, Visibility};
+use crate::ovba::records::vba_project::VbaProject;
+use crate::read::xl_vba;
+use cfb::CompoundFile;
+
+/// Print all the properties of the PROJECT and dir streams to standard out.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// Anywhere the two streams do not agree, such as a module listed in one and not the other, is
+/// printed last
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - There is no VBA file within the zip archive, found at "/xl/vbaProject.bin"
+/// - The VBA file within the archive cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   or [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+pub fn print_xl(filename: &Path, options: &Options) -> UnlockResult<()> {
+    let mut vba_cfb = xl_vba(filename)?;
+    print_info(
+        &mut vba_cfb,
+        consts::PROJECT_PATH,
+        consts::DIR_PATH,
+        consts::VBA_STORAGE_PATH,
+        options,
+    )
+}
+
+/// Print all the properties of the PROJECT and dir streams to standard out.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   or [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+pub fn print_xl_97(filename: &Path, options: &Options) -> UnlockResult<()> {
+    let mut file = cfb::open(filename).map_err(UnlockError::CFBOpen)?;
+    print_info(
+        &mut file,
+        consts::CFB_VBA_PATH,
+        consts::CFB_DIR_PATH,
+        consts::CFB_VBA_STORAGE_PATH,
+        options,
+    )
+}
+
+/// A conditional compilation argument of a VBA project, as set in its Project Properties. These
+/// decide which of the `#If` blocks of the source code are compiled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Constant {
+    /// The name of the argument, as used in `#If` statements
+    pub name: String,
+    /// The value of the argument
+    pub value: i16,
+}
+
+/// Read the conditional compilation arguments of the VBA project.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - There is no VBA file within the zip archive, found at "/xl/vbaProject.bin"
+/// - The VBA file within the archive cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+pub fn xl_constants(filename: &Path, options: &Options) -> UnlockResult<Vec<Constant>> {
+    let mut vba_cfb = xl_vba(filename)?;
+    constants(&mut vba_cfb, consts::DIR_PATH, options)
+}
+
+/// Read the conditional compilation arguments of the VBA project.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+pub fn xl_97_constants(filename: &Path, options: &Options) -> UnlockResult<Vec<Constant>> {
+    let mut file = cfb::open(filename).map_err(UnlockError::CFBOpen)?;
+    constants(&mut file, consts::CFB_DIR_PATH, options)
+}
+
+fn constants<F: Read + Seek>(
+    cfb: &mut CompoundFile<F>,
+    dir_path: &str,
+    options: &Options,
+) -> UnlockResult<Vec<Constant>> {
+    let dir = Dir::from_stream(cfb.open_stream(dir_path)?, options)?;
+    Ok(dir
+        .information()
+        .constant_values()
+        .into_iter()
+        .map(|(name, value)| Constant { name, value })
+        .collect())
+}
+
+fn print_info<F: Read + Seek>(
+    cfb: &mut CompoundFile<F>,
+    project_path: &str,
+    dir_path: &str,
+    storage_path: &str,
+    options: &Options,
+) -> UnlockResult<()> {
+    let dir = Dir::from_stream(cfb.open_stream(dir_path)?, options)?;
+    let project = list_modules::project(cfb, project_path, dir.information().locale(), options)?;
+    // The version is only of interest, so a missing or unreadable stream is not an error
+    let vba_project = cfb
+        .open_stream(format!("{storage_path}/_VBA_PROJECT"))
+        .ok()
+        .and_then(|stream| VbaProject::from_stream(stream).ok());
+    for line in report(&project)
+        .into_iter()
+        .chain(dir_report(&dir, &project, vba_project))
+    {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+/// The lines of the report on the PROJECT stream, as printed by the info command
+pub(crate) fn report(p: &Project) -> Vec<String> {
+    let mut lines = vec![
+        format!("Name:         {}", p.name()),
+        format!("ID:           {}", p.id()),
+        format!("Description:  {}", p.description().unwrap_or_default()),
+        format!("Help file:    {}", p.help_file().unwrap_or_default()),
+        format!("Help context: {}", p.help_id()),
+        format!("Exe name:     {}", p.exe_name().unwrap_or_default()),
+    ];
+    // Both come from the dir stream, which is not always read
+    if let Some(code_page) = p.code_page() {
+        lines.push(format!("Code page:    {code_page}"));
+    }
+    if let Some(lcid) = p.lcid() {
+        lines.push(format!("LCID:         {lcid}"));
+    }
+
+    lines.push(String::new());
+    lines.push("Protection".to_string());
+    let state = p.protection_state();
+    lines.push(format!("  Locked for viewing:     {}", yes_no(state.vbe())));
+    lines.push(format!(
+        "  Locked references:      {}",
+        yes_no(state.user())
+    ));
+    lines.push(format!(
+        "  Locked to host changes: {}",
+        yes_no(state.host())
+    ));
+    let password = match p.password() {
+        Password::None => "none".to_string(),
+        Password::Hash(..) => "SHA1 hash".to_string(),
+        Password::Plain(text) => format!("plain text \"{text}\""),
+    };
+    lines.push(format!("  Password:               {password}"));
+    let visibility = match p.visibility() {
+        Visibility::Visible => "visible",
+        Visibility::NotVisible => "not visible",
+    };
+    lines.push(format!("  Visibility:             {visibility}"));
+
+    lines.push(String::new());
+    lines.push("Modules".to_string());
+    for (name, module_type) in p.modules() {
+        lines.push(format!("  {} ({module_type:?})", p.unicode_name(name)));
+    }
+
+    let packages = p.packages();
+    if !packages.is_empty() {
+        lines.push(String::new());
+        lines.push("Packages".to_string());
+        for package in packages {
+            lines.push(format!("  {package}"));
+        }
+    }
+
+    lines.push(String::new());
+    lines.push("Host extenders".to_string());
+    for host_extender in p.host_extenders() {
+        lines.push(format!("  {host_extender}"));
+    }
+
+    if let Some(workspace) = p.workspace() {
+        lines.push(String::new());
+        lines.push("Workspace".to_string());
+        for window in workspace {
+            lines.push(format!("  {window}"));
+        }
+    }
+
+    lines
+}
+
+/// The lines of the report on the dir stream, and on where it does not agree with the PROJECT
+/// stream
+fn dir_report(dir: &Dir, p: &Project, vba_project: Option<VbaProject>) -> Vec<String> {
+    let information = dir.information();
+    let (major, minor) = information.version();
+    let mut lines = vec![
+        String::new(),
+        "Dir stream".to_string(),
+        format!("  Platform:     {}", information.sys_kind()),
+        format!("  Version:      {major}.{minor}"),
+    ];
+    let constants = information.constant_values();
+    if constants.is_empty() {
+        lines.push("  Constants:    none".to_string());
+    } else {
+        lines.push("  Constants:".to_string());
+        for (name, value) in constants {
+            lines.push(format!("    {name} = {value}"));
+        }
+    }
+    if let Some(compat_version) = information.compat_version() {
+        lines.push(format!("  Compat:       {compat_version}"));
+    }
+    if let Some(vba_project) = vba_project {
+        lines.push(format!("  VBA build:    0x{:04x}", vba_project.version()));
+    }
+
+    lines.push(String::new());
+    lines.push("References".to_string());
+    for reference in dir.references() {
+        lines.push(format!(
+            "  {} ({:?})",
+            reference.name().unwrap_or_default(),
+            reference.kind(),
+        ));
+        let library = reference.library();
+        if let Some(id) = library.guid {
+            lines.push(format!("    GUID:        {id}"));
+        }
+        if let Some(version) = library.version {
+            lines.push(format!("    Version:     {version}"));
+        }
+        lines.push(format!("    Path:        {}", library.path));
+        if let Some(description) = library.description {
+            lines.push(format!("    Description: {description}"));
+        }
+    }
+
+    let mismatches = dir.mismatches(p);
+    if !mismatches.is_empty() {
+        lines.push(String::new());
+        lines.push("Mismatches with the PROJECT stream".to_string());
+        lines.extend(mismatches.into_iter().map(|m| format!("  {m}")));
+    }
+    lines
+}
+
+const fn yes_no(flag: bool) -> &'static str {
+    if flag {
+        "yes"
+    } else {
+        "no"
+    }
+}
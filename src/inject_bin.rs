@@ -0,0 +1,134 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use crate::consts;
+use crate::error::{UnlockError, UnlockResult};
+use crate::extract_bin::copy_storage;
+use crate::lenient::Options;
+use crate::list_modules;
+use crate::ovba::records::project::Project;
+use crate::read::zip_to_raw_vba;
+use crate::remove::{replace_vba, rewrite_cfb, rewrite_zip, Destination};
+use cfb::CompoundFile;
+use tracing::info;
+
+/// Put a VBA project, vbaProject.bin, back into an Excel file in place of the one it holds.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// This is the reverse of [`crate::extract_bin::xl`], for when the VBA project has been changed
+/// with other OLE tools. Every other entry of the zip archive is copied across unchanged.
+///
+/// The inplace flag, if set to true, will overwrite the source file. Otherwise a copy is saved
+/// alongside the source file, with '_injected' appended to the filename.
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The VBA project cannot be read, or cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   of the VBA project cannot be found or parsed
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - There is no VBA file within the zip archive, found at "/xl/vbaProject.bin", to replace
+/// - An updated zip file cannot be created
+/// - If being run inplace, the new zip file cannot be copied back over the original
+pub fn xl(filename: &Path, bin: &Path, inplace: bool, options: &Options) -> UnlockResult<()> {
+    inject_zip(
+        filename,
+        bin,
+        Destination::new(inplace, "_injected"),
+        options,
+    )
+}
+
+/// Put a VBA project, vbaProject.bin, back into an Excel file, saving the updated copy to the
+/// output path.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// # Errors
+/// Will return an error in the same situations as [`xl`], plus if the output file cannot be
+/// created
+pub fn xl_to(filename: &Path, bin: &Path, output: &Path, options: &Options) -> UnlockResult<()> {
+    inject_zip(filename, bin, Destination::Path(output), options)
+}
+
+/// Put a VBA project, saved as a standalone CFB file, back into an Excel file in place of the one
+/// it holds.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// This is the reverse of [`crate::extract_bin::xl_97`]. The whole of the `_VBA_PROJECT_CUR`
+/// storage is replaced with the contents of the VBA project.
+///
+/// The inplace flag, if set to true, will overwrite the source file. Otherwise a copy is saved
+/// alongside the source file, with '_injected' appended to the filename.
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The VBA project cannot be read, or cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   of the VBA project cannot be found or parsed
+/// - The file cannot be copied (for not inplace only) or opened for read/write
+/// - The file cannot be opened as a CFB file
+/// - There is no VBA storage within the file to replace
+/// - Any of the storages or streams cannot be written
+pub fn xl_97(filename: &Path, bin: &Path, inplace: bool, options: &Options) -> UnlockResult<()> {
+    inject_cfb(
+        filename,
+        bin,
+        Destination::new(inplace, "_injected"),
+        options,
+    )
+}
+
+/// Put a VBA project, saved as a standalone CFB file, back into an Excel file, saving the updated
+/// copy to the output path.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// # Errors
+/// Will return an error in the same situations as [`xl_97`], plus if the file cannot be copied
+/// to the output path
+pub fn xl_97_to(filename: &Path, bin: &Path, output: &Path, options: &Options) -> UnlockResult<()> {
+    inject_cfb(filename, bin, Destination::Path(output), options)
+}
+
+fn inject_zip(
+    filename: &Path,
+    bin: &Path,
+    destination: Destination,
+    options: &Options,
+) -> UnlockResult<()> {
+    let vba = read_bin(bin, options)?.into_inner().into_inner();
+    rewrite_zip(filename, destination, |archive, new_file| {
+        // Check there is a VBA project to replace, rather than quietly adding one
+        zip_to_raw_vba(archive)?;
+        replace_vba(archive, new_file, &vba)
+    })
+}
+
+fn inject_cfb(
+    filename: &Path,
+    bin: &Path,
+    destination: Destination,
+    options: &Options,
+) -> UnlockResult<()> {
+    let mut vba = read_bin(bin, options)?;
+    rewrite_cfb(filename, destination, |file| {
+        if !file.is_storage(consts::CFB_VBA_PROJECT_PATH) {
+            return Err(UnlockError::NoVBAFile);
+        }
+        info!("replacing the {} storage", consts::CFB_VBA_PROJECT_PATH);
+        file.remove_storage_all(consts::CFB_VBA_PROJECT_PATH)?;
+        file.create_storage(consts::CFB_VBA_PROJECT_PATH)?;
+        copy_storage(&mut vba, "/", file, consts::CFB_VBA_PROJECT_PATH)
+    })
+}
+
+/// Read the VBA project to put back, checking that it looks like one before going any further
+fn read_bin(bin: &Path, options: &Options) -> UnlockResult<CompoundFile<Cursor<Vec<u8>>>> {
+    info!("reading {}", bin.display());
+    let data = fs::read(bin)?;
+    let mut vba = CompoundFile::open(Cursor::new(data)).map_err(UnlockError::CFBOpen)?;
+    let code_page = list_modules::code_page(&mut vba, consts::DIR_PATH);
+    Project::from_stream(vba.open_stream(consts::PROJECT_PATH)?, code_page, options)?;
+    Ok(vba)
+}
@@ -0,0 +1,107 @@
+//! One JSON object per file, for batch runs whose results are read by other tools.
+//!
+//! Each record is written on its own line as soon as the file has been dealt with, so the results
+//! can be consumed while the rest of the batch is still running
+
+use std::fmt;
+use std::fs::File;
+use std::path::Path;
+
+use crate::fetch;
+use crate::files::{self, Container};
+use crate::lenient::Options;
+use crate::ovba::records::project::{Password, Project};
+use crate::read::{xl_97_project, xl_project};
+use serde::Serialize;
+
+/// The outcome of running a command against one file
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct Record {
+    /// The file, as given or found on the command line
+    pub path: String,
+    /// The format of the file e.g. xlsm, taken from its contents where they can be made out and
+    /// otherwise from its extension
+    pub format: Option<String>,
+    /// Whether the VBA project was locked, before the command ran
+    pub locked: Option<bool>,
+    /// How the VBA project password is stored: none, hash or plain
+    pub password: Option<&'static str>,
+    /// Whether the VBA project was shown in the VBE, before the command ran
+    pub visible: Option<bool>,
+    /// The command that was run, if it succeeded
+    pub action: Option<&'static str>,
+    /// Why the command failed, if it did
+    pub error: Option<String>,
+}
+
+impl Record {
+    /// Start the record for a file, with its format read from its contents where they can be made
+    /// out and otherwise from its extension
+    #[must_use]
+    pub fn new(filename: &Path) -> Self {
+        let path = filename.to_string_lossy().to_string();
+        let format = if fetch::is_url(&path) {
+            None
+        } else {
+            match files::detect(filename) {
+                Some(Container::Cfb) => Some("xls".to_string()),
+                Some(Container::Zip) if is_binary(filename) => Some("xlsb".to_string()),
+                Some(Container::Zip) => Some("xlsm".to_string()),
+                None => None,
+            }
+        }
+        .or_else(|| {
+            filename
+                .extension()
+                .and_then(|s| s.to_str())
+                .map(str::to_lowercase)
+                .filter(|e| matches!(e.as_str(), "xls" | "xlsm" | "xlsb" | "xlsx"))
+        });
+        Self {
+            path,
+            format,
+            ..Self::default()
+        }
+    }
+
+    /// Fill in the VBA protection of the record from a project that has already been read
+    pub fn set_project(&mut self, project: &Project) {
+        self.locked = Some(project.is_locked());
+        self.password = Some(match project.password() {
+            Password::None => "none",
+            Password::Hash(..) => "hash",
+            Password::Plain(_) => "plain",
+        });
+        self.visible = Some(project.visibility().is_visible());
+    }
+
+    /// Read the VBA protection of the file, for a command that does not hand back the project it
+    /// read. This is done before the command is run against the file. Anything that cannot be
+    /// read is left empty, as the command will report the problem
+    pub fn read_project(&mut self, filename: &Path, options: &Options) {
+        let project = match self.format.as_deref() {
+            _ if fetch::is_url(&self.path) => None,
+            Some("xls") => xl_97_project(filename, false, options).ok(),
+            Some("xlsm" | "xlsb") => xl_project(filename, false, options).ok(),
+            _ => None,
+        };
+        if let Some((project, _)) = project {
+            self.set_project(&project);
+        }
+    }
+}
+
+/// Whether a zip based Excel file keeps its workbook in binary records, as an xlsb file does
+fn is_binary(filename: &Path) -> bool {
+    File::open(filename)
+        .ok()
+        .and_then(|f| zip::ZipArchive::new(f).ok())
+        .is_some_and(|mut archive| archive.by_name("xl/workbook.bin").is_ok())
+}
+
+/// The record as a single line of JSON, with no trailing new line
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&serde_json::to_string(self).map_err(|_| fmt::Error)?)
+    }
+}
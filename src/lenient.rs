@@ -0,0 +1,75 @@
+//! How to deal with files that do not quite follow the specification.
+//!
+//! Real PROJECT and dir streams sometimes break its rules in harmless ways. Minor deviations, such
+//! as reserved bits being set or protection properties and records of an odd length, are recorded
+//! as warnings and the best guess at the value is used instead, unless [`Options::strict`] is set.
+//! Lines of the PROJECT stream that are not in the specification at all are only skipped once
+//! [`Options::force`] is set
+//!
+//! The options are given to each parser, which returns the warnings it raised along with what it
+//! read, and to each function that reads or rewrites a whole file
+
+use std::cell::RefCell;
+use std::fmt::Display;
+use tracing::warn;
+
+/// How strictly a stream is held to the specification when it is parsed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Options {
+    /// Skip lines that are not in the specification with a warning, rather than them being errors
+    pub force: bool,
+    /// Make minor deviations from the specification errors, rather than warnings
+    pub strict: bool,
+}
+
+/// The warnings raised while parsing a stream, along with the options that decide what is only a
+/// warning. This is passed through the parsers of the stream
+#[derive(Debug)]
+pub(crate) struct Warnings {
+    options: Options,
+    messages: RefCell<Vec<String>>,
+}
+
+impl Warnings {
+    pub(crate) fn new(options: &Options) -> Self {
+        Self {
+            options: options.clone(),
+            messages: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Whether lines that are not in the specification are skipped, rather than being errors
+    pub(crate) const fn force(&self) -> bool {
+        self.options.force
+    }
+
+    /// Log a warning and keep it
+    pub(crate) fn push(&self, message: String) {
+        warn!("{message}");
+        self.messages.borrow_mut().push(message);
+    }
+
+    /// Unless in strict mode, swap an error for the fallback value, keeping the error as a
+    /// warning. Errors with no fallback are returned as they are
+    pub(crate) fn recover<T, E: Display>(
+        &self,
+        result: Result<T, E>,
+        fallback: impl FnOnce() -> Option<T>,
+    ) -> Result<T, E> {
+        match result {
+            Err(e) if !self.options.strict => match fallback() {
+                Some(value) => {
+                    self.push(format!("{e}. Carrying on with a best guess"));
+                    Ok(value)
+                }
+                None => Err(e),
+            },
+            result => result,
+        }
+    }
+
+    /// The warnings that were raised
+    pub(crate) fn into_messages(self) -> Vec<String> {
+        self.messages.into_inner()
+    }
+}
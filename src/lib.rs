@@ -1,7 +1,42 @@
 #![warn(clippy::all, clippy::pedantic, clippy::nursery)]
 
+mod biff;
+pub mod cancel;
+pub mod config;
 mod consts;
+pub mod context;
+pub mod crack;
+pub mod diff;
+pub mod dump_stream;
 pub mod error;
+pub mod export;
+pub mod extract_bin;
+pub mod fetch;
+pub mod files;
+pub mod hash;
+pub mod info;
+pub mod inject_bin;
+pub mod jsonl;
+pub mod lenient;
+pub mod list_modules;
+pub mod list_references;
+pub mod lock;
 mod ovba;
+pub mod pcode;
+pub mod potfile;
+pub mod progress;
 pub mod read;
 pub mod remove;
+pub mod scan;
+pub mod serve;
+pub mod set_password;
+pub mod streams;
+pub mod strip;
+pub mod style;
+pub mod tui;
+pub mod unprotect;
+pub mod unprotect_sheets;
+pub mod validate;
+pub mod verify;
+pub mod watch;
+pub mod wordlists;
@@ -0,0 +1,333 @@
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use crate::consts;
+use crate::error::{UnlockError, UnlockResult};
+use crate::lenient::Options;
+use crate::ovba::records::designer::Designer;
+use crate::ovba::records::dir::{self, Dir};
+use crate::ovba::records::project::Project;
+use crate::ovba::records::project_wm::NameMap;
+use crate::ovba::types::{locale::Locale, mbcs};
+use crate::read::xl_vba;
+use cfb::CompoundFile;
+
+pub use crate::ovba::records::project::ModuleType;
+
+/// The summary of a single module of a VBA project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleInfo {
+    /// The name of the module, as shown in the VBE
+    pub name: String,
+    /// The kind of module
+    pub module_type: ModuleType,
+    /// The name of the stream, within the VBA storage, that holds the module source
+    pub stream_name: String,
+    /// Whether the module is read-only
+    pub read_only: bool,
+    /// Whether the module is private to the project
+    pub private: bool,
+}
+
+/// The summary of a user form, read from the designer storage of its module
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormInfo {
+    /// The name of the module of the form
+    pub name: String,
+    /// The caption shown in the title bar of the form, if it has been set
+    pub caption: Option<String>,
+    /// The number of controls on the form, if they could be counted
+    pub controls: Option<u32>,
+}
+
+/// Print the modules of the VBA project to standard out, followed by any user forms.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - There is no VBA file within the zip archive, found at "/xl/vbaProject.bin"
+/// - The VBA file within the archive cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   or [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+pub fn print_xl(filename: &Path, options: &Options) -> UnlockResult<()> {
+    print_modules(&xl_modules(filename, options)?);
+    print_forms(&xl_forms(filename, options)?);
+    Ok(())
+}
+
+/// List the modules of the VBA project.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - There is no VBA file within the zip archive, found at "/xl/vbaProject.bin"
+/// - The VBA file within the archive cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   or [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+pub fn xl_modules(filename: &Path, options: &Options) -> UnlockResult<Vec<ModuleInfo>> {
+    let mut vba_cfb = xl_vba(filename)?;
+    modules(
+        &mut vba_cfb,
+        consts::PROJECT_PATH,
+        consts::DIR_PATH,
+        options,
+    )
+}
+
+/// Print the modules of the VBA project to standard out, followed by any user forms.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   or [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+pub fn print_xl_97(filename: &Path, options: &Options) -> UnlockResult<()> {
+    print_modules(&xl_97_modules(filename, options)?);
+    print_forms(&xl_97_forms(filename, options)?);
+    Ok(())
+}
+
+/// List the modules of the VBA project.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   or [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+pub fn xl_97_modules(filename: &Path, options: &Options) -> UnlockResult<Vec<ModuleInfo>> {
+    let mut file = cfb::open(filename).map_err(UnlockError::CFBOpen)?;
+    modules(
+        &mut file,
+        consts::CFB_VBA_PATH,
+        consts::CFB_DIR_PATH,
+        options,
+    )
+}
+
+/// List the user forms of the VBA project, with their captions and the number of controls.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - There is no VBA file within the zip archive, found at "/xl/vbaProject.bin"
+/// - The VBA file within the archive cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   or [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+/// - The designer storage of a form cannot be read
+pub fn xl_forms(filename: &Path, options: &Options) -> UnlockResult<Vec<FormInfo>> {
+    let mut vba_cfb = xl_vba(filename)?;
+    forms(
+        &mut vba_cfb,
+        consts::PROJECT_PATH,
+        consts::DIR_PATH,
+        options,
+    )
+}
+
+/// List the user forms of the VBA project, with their captions and the number of controls.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   or [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+/// - The designer storage of a form cannot be read
+pub fn xl_97_forms(filename: &Path, options: &Options) -> UnlockResult<Vec<FormInfo>> {
+    let mut file = cfb::open(filename).map_err(UnlockError::CFBOpen)?;
+    forms(
+        &mut file,
+        consts::CFB_VBA_PATH,
+        consts::CFB_DIR_PATH,
+        options,
+    )
+}
+
+/// Combine the dir stream, which lists the modules, with the PROJECT stream, which records what
+/// type of module each one is
+fn modules<F: Read + Seek>(
+    cfb: &mut CompoundFile<F>,
+    project_path: &str,
+    dir_path: &str,
+    options: &Options,
+) -> UnlockResult<Vec<ModuleInfo>> {
+    let dir = Dir::from_stream(cfb.open_stream(dir_path)?, options)?;
+    let project = project(cfb, project_path, dir.information().locale(), options)?;
+    Ok(dir
+        .modules()
+        .iter()
+        .map(|m| ModuleInfo {
+            name: m.name(),
+            module_type: module_type(&project, m),
+            stream_name: m.stream_name(),
+            read_only: m.is_read_only(),
+            private: m.is_private(),
+        })
+        .collect())
+}
+
+/// Read the designer storage of every designer module that has one
+fn forms<F: Read + Seek>(
+    cfb: &mut CompoundFile<F>,
+    project_path: &str,
+    dir_path: &str,
+    options: &Options,
+) -> UnlockResult<Vec<FormInfo>> {
+    let dir = Dir::from_stream(cfb.open_stream(dir_path)?, options)?;
+    let code_page = dir.information().code_page();
+    let project = project(cfb, project_path, dir.information().locale(), options)?;
+    let mut forms = Vec::new();
+    for module in dir.modules() {
+        if module_type(&project, module) != ModuleType::Designer {
+            continue;
+        }
+        if let Some(designer) = designer(cfb, project_path, module, code_page)? {
+            forms.push(FormInfo {
+                name: module.name(),
+                caption: designer.caption().map(str::to_string),
+                controls: designer.controls(),
+            });
+        }
+    }
+    Ok(forms)
+}
+
+/// Read the designer storage of a module, which sits beside the PROJECT stream and is named after
+/// the stream of the module. Returns None if there is no such storage
+pub(crate) fn designer<F: Read + Seek>(
+    cfb: &mut CompoundFile<F>,
+    project_path: &str,
+    module: &dir::Module,
+    code_page: u16,
+) -> UnlockResult<Option<Designer>> {
+    let storage = format!(
+        "{}{}",
+        project_path.trim_end_matches("PROJECT"),
+        module.stream_name()
+    );
+    let frame_path = format!("{storage}/\u{3}VBFrame");
+    if !cfb.is_stream(&frame_path) {
+        return Ok(None);
+    }
+    let mut frame = Vec::new();
+    cfb.open_stream(frame_path)?.read_to_end(&mut frame)?;
+    let form_path = format!("{storage}/f");
+    let form = if cfb.is_stream(&form_path) {
+        let mut form = Vec::new();
+        cfb.open_stream(form_path)?.read_to_end(&mut form)?;
+        Some(form)
+    } else {
+        None
+    };
+    Ok(Some(Designer::from_bytes(
+        &frame,
+        form.as_deref(),
+        code_page,
+    )))
+}
+
+/// Read the PROJECT stream, along with the Unicode names of the modules from the `PROJECTwm`
+/// stream beside it when there is one. The text of both is decoded in the code page of the locale
+/// given, which is recorded in the dir stream
+pub(crate) fn project<F: Read + Seek>(
+    cfb: &mut CompoundFile<F>,
+    project_path: &str,
+    locale: Locale,
+    options: &Options,
+) -> UnlockResult<Project> {
+    let code_page = locale.code_page();
+    let mut project = Project::from_stream(cfb.open_stream(project_path)?, code_page, options)?;
+    project.set_locale(locale);
+    let name_map_path = format!("{project_path}wm");
+    if cfb.is_stream(&name_map_path) {
+        project.set_name_map(NameMap::from_stream(
+            cfb.open_stream(name_map_path)?,
+            code_page,
+        )?);
+    }
+    Ok(project)
+}
+
+/// The language and code page of the project, as recorded in the dir stream, if it can be read.
+/// This is only ever a best guess, so the dir stream is read with the default options
+pub(crate) fn locale<F: Read + Seek>(cfb: &mut CompoundFile<F>, dir_path: &str) -> Option<Locale> {
+    cfb.open_stream(dir_path)
+        .ok()
+        .and_then(|stream| Dir::from_stream(stream, &Options::default()).ok())
+        .map(|dir| dir.information().locale())
+}
+
+/// The code page of the project, as recorded in the dir stream. Falls back on the default code
+/// page if the dir stream cannot be read, so that the PROJECT stream can still be looked at
+pub(crate) fn code_page<F: Read + Seek>(cfb: &mut CompoundFile<F>, dir_path: &str) -> u16 {
+    locale(cfb, dir_path).map_or(mbcs::DEFAULT_CODE_PAGE, Locale::code_page)
+}
+
+/// The type of a module, taken from the PROJECT stream where it is listed there
+pub(crate) fn module_type(project: &Project, module: &dir::Module) -> ModuleType {
+    // The dir stream can only tell a procedural module apart from the rest
+    project.module_type(&module.name()).unwrap_or_else(|| {
+        if module.is_procedural() {
+            ModuleType::Standard
+        } else {
+            ModuleType::Class
+        }
+    })
+}
+
+fn print_modules(modules: &[ModuleInfo]) {
+    let width = modules
+        .iter()
+        .map(|m| m.name.len())
+        .max()
+        .unwrap_or_default()
+        .max("Name".len());
+    println!("{:width$}  {:8}  Stream", "Name", "Type");
+    for m in modules {
+        let flags: Vec<_> = [(m.read_only, "read-only"), (m.private, "private")]
+            .into_iter()
+            .filter_map(|(set, flag)| set.then_some(flag))
+            .collect();
+        let flags = if flags.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", flags.join(", "))
+        };
+        println!(
+            "{:width$}  {:8}  {}{flags}",
+            m.name,
+            format!("{:?}", m.module_type),
+            m.stream_name
+        );
+    }
+}
+
+fn print_forms(forms: &[FormInfo]) {
+    for f in forms {
+        let caption = f.caption.as_ref().map_or_else(
+            || "without a caption".to_string(),
+            |c| format!("captioned \"{c}\""),
+        );
+        let controls = f
+            .controls
+            .map_or_else(|| "an unknown number of".to_string(), |c| c.to_string());
+        println!(
+            "\n📋 {} is a form {caption}, with {controls} controls",
+            f.name
+        );
+    }
+}
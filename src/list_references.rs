@@ -0,0 +1,124 @@
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use crate::consts;
+use crate::error::{UnlockError, UnlockResult};
+use crate::lenient::Options;
+use crate::ovba::records::dir::Dir;
+use crate::read::xl_vba;
+use cfb::CompoundFile;
+
+pub use crate::ovba::records::dir::ReferenceKind;
+pub use crate::ovba::types::guid::Guid;
+
+/// A reference of a VBA project to a library or another project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceInfo {
+    /// The name of the reference, as shown in the References dialog of the VBE. Older projects do
+    /// not always record one
+    pub name: Option<String>,
+    /// The kind of library referred to
+    pub kind: ReferenceKind,
+    /// The identifier of the library referred to, or the path to the project
+    pub libid: String,
+    /// The GUID the library is registered under, taken from the identifier. A project has none
+    pub guid: Option<Guid>,
+    /// The version of the library, taken from the identifier
+    pub version: Option<String>,
+    /// Where the library, or project, was found on the machine that last saved the file, taken
+    /// from the identifier
+    pub path: String,
+    /// The description of the library, taken from the identifier
+    pub description: Option<String>,
+}
+
+/// Print the references of the VBA project to standard out.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - There is no VBA file within the zip archive, found at "/xl/vbaProject.bin"
+/// - The VBA file within the archive cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+pub fn print_xl(filename: &Path, options: &Options) -> UnlockResult<()> {
+    print_references(&xl_references(filename, options)?);
+    Ok(())
+}
+
+/// List the references of the VBA project.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// # Errors
+/// Will return an error in the same situations as [`print_xl`]
+pub fn xl_references(filename: &Path, options: &Options) -> UnlockResult<Vec<ReferenceInfo>> {
+    let mut vba_cfb = xl_vba(filename)?;
+    references(&mut vba_cfb, consts::DIR_PATH, options)
+}
+
+/// Print the references of the VBA project to standard out.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+pub fn print_xl_97(filename: &Path, options: &Options) -> UnlockResult<()> {
+    print_references(&xl_97_references(filename, options)?);
+    Ok(())
+}
+
+/// List the references of the VBA project.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// # Errors
+/// Will return an error in the same situations as [`print_xl_97`]
+pub fn xl_97_references(filename: &Path, options: &Options) -> UnlockResult<Vec<ReferenceInfo>> {
+    let mut file = cfb::open(filename).map_err(UnlockError::CFBOpen)?;
+    references(&mut file, consts::CFB_DIR_PATH, options)
+}
+
+fn references<F: Read + Seek>(
+    cfb: &mut CompoundFile<F>,
+    dir_path: &str,
+    options: &Options,
+) -> UnlockResult<Vec<ReferenceInfo>> {
+    let dir = Dir::from_stream(cfb.open_stream(dir_path)?, options)?;
+    Ok(dir
+        .references()
+        .iter()
+        .map(|r| {
+            let library = r.library();
+            ReferenceInfo {
+                name: r.name(),
+                kind: r.kind(),
+                libid: r.libid(),
+                guid: library.guid,
+                version: library.version,
+                path: library.path,
+                description: library.description,
+            }
+        })
+        .collect())
+}
+
+fn print_references(references: &[ReferenceInfo]) {
+    let width = references
+        .iter()
+        .map(|r| r.name.as_deref().unwrap_or_default().len())
+        .max()
+        .unwrap_or_default()
+        .max("Name".len());
+    println!("{:width$}  {:10}  Library", "Name", "Kind");
+    for r in references {
+        println!(
+            "{:width$}  {:10}  {}",
+            r.name.as_deref().unwrap_or_default(),
+            format!("{:?}", r.kind),
+            r.libid
+        );
+    }
+}
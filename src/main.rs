@@ -1,17 +1,60 @@
 #![warn(clippy::all, clippy::pedantic, clippy::nursery)]
 
-use clap::{Args, Parser, Subcommand};
-use std::path::Path;
+use clap::{ArgGroup, Args, Parser, Subcommand};
+use std::io::{IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
 
+use unlock_excel::config::Config;
 use unlock_excel::error::{UnlockError, UnlockResult};
-use unlock_excel::{read, remove};
+use unlock_excel::files::Container;
+use unlock_excel::read::Format;
+use unlock_excel::{
+    context, crack, diff, dump_stream, export, extract_bin, fetch, files, hash, info, inject_bin,
+    jsonl, lenient, list_modules, list_references, lock, pcode, progress, read, remove, scan,
+    serve, set_password, streams, strip, style, tui, unprotect, unprotect_sheets, validate, verify,
+    watch, wordlists,
+};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
+#[allow(clippy::struct_excessive_bools)]
 struct Cli {
     /// Mode to run in
     #[command(subcommand)]
     command: Commands,
+
+    /// Log what the tool is doing to standard error. Repeat for more detail: -v for progress,
+    /// -vv for the streams and byte offsets being worked on, -vvv for everything
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Print nothing to standard out, only errors to standard error. The outcome can be read from
+    /// the exit code
+    #[arg(short, long, global = true, default_value_t = false)]
+    quiet: bool,
+
+    /// Print without colour. Colour is also left off when the output is not a terminal, or the
+    /// `NO_COLOR` environment variable is set
+    #[arg(long, global = true, default_value_t = false)]
+    no_color: bool,
+
+    /// Print one line of JSON per file, as each file is dealt with, in place of the usual output.
+    /// Each line gives the path, format, whether it was locked, how the password is stored, the
+    /// action taken and any error
+    #[arg(long, global = true, default_value_t = false, conflicts_with = "quiet")]
+    json_lines: bool,
+
+    /// Carry on past lines in the PROJECT stream that are not in the specification, logging a
+    /// warning for each
+    #[arg(long, global = true, default_value_t = false)]
+    force: bool,
+
+    /// Treat minor deviations from the specification in the PROJECT stream, such as reserved bits
+    /// being set, as errors rather than warnings
+    #[arg(long, global = true, default_value_t = false, conflicts_with = "force")]
+    strict: bool,
 }
 
 #[derive(Subcommand)]
@@ -21,6 +64,84 @@ enum Commands {
 
     /// Update the file to remove all protection
     Remove(RemoveArgs),
+
+    /// Update the file to remove the protection from every sheet
+    UnprotectSheets(UnprotectSheetsArgs),
+
+    /// Update the file to remove the chosen kinds of protection, all in one pass
+    Unprotect(UnprotectArgs),
+
+    /// Update the file to lock the VBA project with a password
+    SetPassword(SetPasswordArgs),
+
+    /// Update the file to lock the VBA project for viewing, without setting a password
+    Lock(LockArgs),
+
+    /// List the modules of the VBA project
+    ListModules(ListModulesArgs),
+
+    /// List the references of the VBA project to libraries and other projects
+    ListReferences(ListReferencesArgs),
+
+    /// List the storages and streams of the VBA file, with their sizes
+    Streams(StreamsArgs),
+
+    /// Print a hex dump of a stream of the VBA file
+    DumpStream(DumpStreamArgs),
+
+    /// Write the source code of each VBA module out to a directory
+    Export(ExportArgs),
+
+    /// Print the compiled p-code of each VBA module as hex, line by line, with the strings it
+    /// holds. Useful when the source code has been stomped away
+    Pcode(PcodeArgs),
+
+    /// Save the raw VBA project, vbaProject.bin, for use with other OLE tools
+    ExtractBin(ExtractBinArgs),
+
+    /// Put a modified vbaProject.bin back into the file, in place of the VBA project it holds
+    InjectBin(InjectBinArgs),
+
+    /// Print all the properties of the VBA project
+    Info(InfoArgs),
+
+    /// Try to recover the VBA project password, printing it if found
+    Crack(CrackArgs),
+
+    /// Print the salt and hash of the VBA project password, for use with other tools
+    Hash(HashArgs),
+
+    /// Open an interactive view of the VBA project, to explore it and unlock it from a menu
+    Tui(TuiArgs),
+
+    /// Remove the VBA project from the file entirely, leaving a workbook with no macros
+    Strip(StripArgs),
+
+    /// Convert an xlsm file into an xlsx file, without its macros
+    Convert(ConvertArgs),
+
+    /// Compare the VBA projects of two Excel files, printing any differences
+    Diff(DiffArgs),
+
+    /// Look through the VBA source code for auto-exec procedures, suspicious calls and web
+    /// addresses
+    Scan(ScanArgs),
+
+    /// Check that a file made by remove is sound, when compared to the file it was made from
+    Verify(VerifyArgs),
+
+    /// Check that the VBA project follows every rule of the MS-OVBA specification, rather than
+    /// reading past the ones that are broken
+    Validate(ValidateArgs),
+
+    /// Run an HTTP server that reads or unlocks workbooks sent to it
+    Serve(ServeArgs),
+
+    /// Download a well-known wordlist into the cache, so it can be given as a wordlist by name
+    FetchWordlist(FetchWordlistArgs),
+
+    /// Measure how many passwords a second this machine can try when cracking
+    Bench(BenchArgs),
 }
 
 #[derive(Args)]
@@ -29,8 +150,43 @@ struct ReadArgs {
     #[arg(short, long, default_value_t = false)]
     decode: bool,
 
-    /// Excel file to read / unlock
-    filename: String,
+    /// File of candidate passwords to decode with, one per line, in place of the built-in list of
+    /// common passwords. Repeat to try several files in turn
+    #[arg(short, long, requires = "decode")]
+    wordlist: Vec<PathBuf>,
+
+    /// Rule for making more candidates from each word when decoding e.g. "cL" or "$1". Repeat to
+    /// apply several rules, each to the original word
+    #[arg(long, requires = "decode")]
+    rule: Vec<crack::Rule>,
+
+    /// Number of threads to decode with. Defaults to the number of available cores
+    #[arg(short, long, requires = "decode")]
+    threads: Option<usize>,
+
+    /// File of passwords cracked before, looked in before decoding and added to when a password
+    /// is decoded
+    #[arg(long, requires = "decode")]
+    potfile: Option<PathBuf>,
+
+    /// Check how hard the password is to crack, by trying the quickest attacks on it in turn: the
+    /// built-in list of common passwords, the list with simple changes made to each word, then
+    /// every short password
+    #[arg(long, default_value_t = false)]
+    strength: bool,
+
+    /// Format to print the results in [default: text]
+    // -o/--output is the name this flag was first given, so scripts that use it keep working
+    #[arg(short, long, value_enum, alias = "output", short_alias = 'o')]
+    format: Option<Format>,
+
+    /// Walk any directories given, reading every Excel file with VBA found within them
+    #[arg(short, long, default_value_t = false)]
+    recursive: bool,
+
+    /// Excel files, or directories with --recursive, to read. Pass - to read from standard in
+    #[arg(required = true)]
+    filenames: Vec<PathBuf>,
 }
 
 #[derive(Args)]
@@ -40,34 +196,1154 @@ struct RemoveArgs {
     #[arg(short, long, default_value_t = false)]
     inplace: bool,
 
-    /// Excel file to read / unlock
-    filename: String,
+    /// Save the unlocked file to this path instead. If a directory is given, the file keeps its
+    /// name within that directory
+    #[arg(short, long, conflicts_with = "inplace")]
+    output: Option<PathBuf>,
+
+    /// Walk any directories given, unlocking every Excel file with VBA found within them
+    #[arg(short, long, default_value_t = false)]
+    recursive: bool,
+
+    /// Also remove the protection of the workbook structure and windows
+    #[arg(long, default_value_t = false)]
+    workbook: bool,
+
+    /// Keep running, unlocking every locked Excel file with VBA that is saved into this directory
+    #[arg(long, conflicts_with_all = ["recursive", "filenames"])]
+    watch: Option<PathBuf>,
+
+    /// Excel files, or directories with --recursive, to unlock. Pass - to read from standard in
+    /// and write the unlocked file to standard out
+    #[arg(required_unless_present = "watch")]
+    filenames: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+struct UnprotectSheetsArgs {
+    /// Modify the file in-place, if not selected a new file will be generated and saved alongside
+    /// the original
+    #[arg(short, long, default_value_t = false)]
+    inplace: bool,
+
+    /// Save the unprotected file to this path instead. If a directory is given, the file keeps its
+    /// name within that directory
+    #[arg(short, long, conflicts_with = "inplace")]
+    output: Option<PathBuf>,
+
+    /// Only unprotect the sheet with this name. Can be given more than once
+    #[arg(long = "sheet", value_name = "NAME")]
+    sheet_names: Vec<String>,
+
+    /// Excel file to unprotect
+    filename: PathBuf,
+}
+
+#[derive(Args)]
+#[allow(clippy::struct_excessive_bools)]
+#[command(group(ArgGroup::new("layers").required(true).multiple(true)))]
+struct UnprotectArgs {
+    /// Modify the file in-place, if not selected a new file will be generated and saved alongside
+    /// the original
+    #[arg(short, long, default_value_t = false)]
+    inplace: bool,
+
+    /// Save the unprotected file to this path instead. If a directory is given, the file keeps its
+    /// name within that directory
+    #[arg(short, long, conflicts_with = "inplace")]
+    output: Option<PathBuf>,
+
+    /// Remove the lock on viewing the VBA project
+    #[arg(long, group = "layers", default_value_t = false)]
+    vba: bool,
+
+    /// Remove the protection of the workbook structure and windows
+    #[arg(long, group = "layers", default_value_t = false)]
+    workbook: bool,
+
+    /// Remove the protection from every sheet
+    #[arg(long, group = "layers", default_value_t = false)]
+    sheets: bool,
+
+    /// Remove the protection from the sheet with this name only. Can be given more than once
+    #[arg(long = "sheet", value_name = "NAME", group = "layers")]
+    sheet_names: Vec<String>,
+
+    /// Make every very hidden sheet visible, which can otherwise only be done from VBA
+    #[arg(long, group = "layers", default_value_t = false)]
+    very_hidden: bool,
+
+    /// Remove the password to write to the workbook, and the recommendation to open it read-only
+    #[arg(long, group = "layers", default_value_t = false)]
+    file_sharing: bool,
+
+    /// Remove the protection of a shared workbook, which stops it being unshared
+    #[arg(long, group = "layers", default_value_t = false)]
+    shared: bool,
+
+    /// Remove every kind of protection, and show any very hidden sheets. The VBA project is only
+    /// unlocked if the file has one
+    #[arg(long, group = "layers", default_value_t = false)]
+    all: bool,
+
+    /// Excel file to unprotect
+    filename: PathBuf,
+}
+
+#[derive(Args)]
+struct SetPasswordArgs {
+    /// Modify the file in-place, if not selected a new file will be generated and saved alongside
+    /// the original
+    #[arg(short, long, default_value_t = false)]
+    inplace: bool,
+
+    /// Excel file to lock
+    filename: PathBuf,
+
+    /// Password to lock the VBA project with
+    password: String,
+}
+
+#[derive(Args)]
+struct LockArgs {
+    /// Modify the file in-place, if not selected a new file will be generated and saved alongside
+    /// the original
+    #[arg(short, long, default_value_t = false)]
+    inplace: bool,
+
+    /// Excel file to lock
+    filename: PathBuf,
+}
+
+#[derive(Args)]
+struct ListModulesArgs {
+    /// Excel file to read
+    filename: PathBuf,
+}
+
+#[derive(Args)]
+struct ListReferencesArgs {
+    /// Excel file to read
+    filename: PathBuf,
+}
+
+#[derive(Args)]
+struct PcodeArgs {
+    /// Excel file to read
+    filename: PathBuf,
+}
+
+#[derive(Args)]
+struct StreamsArgs {
+    /// Excel file to read
+    filename: PathBuf,
 }
 
+#[derive(Args)]
+struct DumpStreamArgs {
+    /// Excel file to read
+    filename: PathBuf,
+
+    /// Path of the stream within the VBA file, e.g. `/PROJECT`. For xls files the path is from the
+    /// root of the whole file, e.g. `/_VBA_PROJECT_CUR/PROJECT`
+    stream: String,
+}
+
+#[derive(Args)]
+struct ExportArgs {
+    /// Excel file to read
+    filename: PathBuf,
+
+    /// Directory to write the module files into
+    directory: PathBuf,
+}
+
+#[derive(Args)]
+struct ExtractBinArgs {
+    /// Path to save the VBA project to. If a directory is given, the file is saved within it.
+    /// Defaults to the Excel file's name with "_vbaProject.bin" in place of the extension
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Excel file to read
+    filename: PathBuf,
+}
+
+#[derive(Args)]
+struct InjectBinArgs {
+    /// Modify the file in-place, if not selected a new file will be generated and saved alongside
+    /// the original
+    #[arg(short, long, default_value_t = false)]
+    inplace: bool,
+
+    /// Save the updated file to this path instead. If a directory is given, the file keeps its
+    /// name within that directory
+    #[arg(short, long, conflicts_with = "inplace")]
+    output: Option<PathBuf>,
+
+    /// Excel file to update
+    filename: PathBuf,
+
+    /// VBA project to put into the Excel file
+    bin: PathBuf,
+}
+
+#[derive(Args)]
+struct InfoArgs {
+    /// Excel file to read
+    filename: PathBuf,
+}
+
+#[derive(Args)]
+#[allow(clippy::struct_excessive_bools)]
+struct CrackArgs {
+    /// File of candidate passwords, one per line. Repeat to try several files in turn. Defaults
+    /// to the built-in list of common passwords
+    #[arg(short, long)]
+    wordlist: Vec<PathBuf>,
+
+    /// Try the names found in the file itself first, such as those of its VBA project, modules
+    /// and sheets, its file name, and its author and company, along with each of their words
+    #[arg(long, default_value_t = false)]
+    context: bool,
+
+    /// Two files of words to join together, trying every word of the first followed by every word
+    /// of the second e.g. "Finance2023". Used in place of any wordlist
+    #[arg(long, num_args = 2, value_names = ["FIRST", "SECOND"], conflicts_with = "wordlist")]
+    combine: Vec<PathBuf>,
+
+    /// Put this between the two words when joining them with --combine
+    #[arg(long, requires = "combine")]
+    separator: Option<String>,
+
+    /// Try every password made from the characters of the charset, from the shortest to the
+    /// longest, in place of any wordlist
+    #[arg(long, default_value_t = false, group = "generator", group = "keyspace")]
+    #[arg(conflicts_with_all = ["wordlist", "combine"])]
+    brute: bool,
+
+    /// The characters to make passwords from with --brute [default: lowernum]
+    #[arg(long, value_enum, requires = "brute")]
+    charset: Option<crack::Charset>,
+
+    /// The length of the shortest password to try with --brute, --markov or --walk [default: 1]
+    #[arg(long, requires = "generator")]
+    min: Option<usize>,
+
+    /// The length of the longest password to try with --brute, --markov or --walk [default: 8, or
+    /// 12 with --walk]
+    #[arg(long, requires = "generator")]
+    max: Option<usize>,
+
+    /// Try the passwords that a Markov model of real passwords finds likeliest, most likely first,
+    /// in place of any wordlist
+    #[arg(long, default_value_t = false, group = "generator")]
+    #[arg(conflicts_with_all = ["wordlist", "combine", "mask", "rule", "hybrid"])]
+    markov: bool,
+
+    /// File of real passwords, one per line, for the Markov model to learn from, in place of the
+    /// built-in list of common passwords
+    #[arg(long, requires = "markov")]
+    corpus: Option<PathBuf>,
+
+    /// Try the passwords made by walking across neighbouring keys of the keyboard e.g. "qwerty",
+    /// "zxcvfdsa" or "1qaz2wsx", in place of any wordlist
+    #[arg(long, default_value_t = false, group = "generator")]
+    #[arg(conflicts_with_all = ["wordlist", "combine", "brute", "markov", "mask", "hybrid"])]
+    walk: bool,
+
+    /// The keyboard to walk across with --walk [default: the one used where the system locale is
+    /// set]
+    #[arg(long, value_enum, requires = "walk")]
+    layout: Option<crack::Layout>,
+
+    /// Try every password that fits a hashcat style mask e.g. ?u?l?l?l?d?d?d?d, where ?l, ?u, ?d,
+    /// ?s and ?a stand for a lower case letter, upper case letter, digit, symbol or any character
+    #[arg(long, group = "keyspace", conflicts_with_all = ["wordlist", "combine", "brute"])]
+    mask: Option<crack::Mask>,
+
+    /// Leave out this many passwords from the start of a --brute or --mask search. With --limit,
+    /// this splits a search between machines e.g. --skip 0 --limit 1000000 on one and --skip
+    /// 1000000 --limit 1000000 on the next
+    #[arg(long, requires = "keyspace")]
+    skip: Option<u64>,
+
+    /// Try at most this many passwords of a --brute or --mask search
+    #[arg(long, requires = "keyspace")]
+    limit: Option<u64>,
+
+    /// Rule for making more candidates from each word of the wordlists e.g. "cL" capitalises the
+    /// word and swaps letters for look-alike symbols. Repeat to apply several rules, each to the
+    /// original word
+    #[arg(long, conflicts_with_all = ["brute", "mask"])]
+    rule: Vec<crack::Rule>,
+
+    /// Also try the lower case, Capitalised and UPPER CASE forms of every candidate, as VBA
+    /// passwords are case sensitive
+    #[arg(long, default_value_t = false, conflicts_with_all = ["brute", "mask"])]
+    cases: bool,
+
+    /// Also try every word of the wordlists with numbers, years and symbols added to its end or
+    /// start e.g. "Finance2023" or "!finance"
+    #[arg(long, default_value_t = false, conflicts_with_all = ["combine", "brute", "mask"])]
+    hybrid: bool,
+
+    /// Skip candidates shorter than this, when the password policy is known
+    #[arg(long, conflicts_with = "keyspace")]
+    min_len: Option<usize>,
+
+    /// Skip candidates longer than this, when the password policy is known
+    #[arg(long, conflicts_with = "keyspace")]
+    max_len: Option<usize>,
+
+    /// Skip candidates without a digit
+    #[arg(long, default_value_t = false, conflicts_with = "keyspace")]
+    require_digit: bool,
+
+    /// Skip candidates without an upper case letter
+    #[arg(long, default_value_t = false, conflicts_with = "keyspace")]
+    require_upper: bool,
+
+    /// Skip candidates without a lower case letter
+    #[arg(long, default_value_t = false, conflicts_with = "keyspace")]
+    require_lower: bool,
+
+    /// Skip candidates without a symbol, being anything other than a letter or digit
+    #[arg(long, default_value_t = false, conflicts_with = "keyspace")]
+    require_symbol: bool,
+
+    /// Number of threads to use. Defaults to the number of available cores
+    #[arg(short, long)]
+    threads: Option<usize>,
+
+    /// Give up after this many seconds
+    #[arg(long)]
+    time_limit: Option<u64>,
+
+    /// File of passwords cracked before, looked in before cracking and added to when a password is
+    /// found
+    #[arg(long)]
+    potfile: Option<PathBuf>,
+
+    /// Start a search that is expected to take over an hour without asking first
+    #[arg(short, long, default_value_t = false)]
+    yes: bool,
+
+    /// Walk any directories given, cracking every Excel file with VBA found within them
+    #[arg(short, long, default_value_t = false)]
+    recursive: bool,
+
+    /// Excel files, or directories with --recursive, to crack. Many files are cracked together,
+    /// going through the candidates once for all of them
+    #[arg(required = true)]
+    filenames: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+struct HashArgs {
+    /// Format to print the salt and hash in
+    #[arg(short, long, value_enum, default_value_t = hash::Format::Hex)]
+    format: hash::Format,
+
+    /// Excel file to read
+    filename: PathBuf,
+}
+
+#[derive(Args)]
+struct TuiArgs {
+    /// Excel file to open
+    filename: PathBuf,
+
+    /// Settings for cracking the password, taken from the config file
+    #[arg(skip)]
+    crack: crack::Options,
+}
+
+#[derive(Args)]
+struct StripArgs {
+    /// Modify the file in-place, if not selected a new file will be generated and saved alongside
+    /// the original
+    #[arg(short, long, default_value_t = false)]
+    inplace: bool,
+
+    /// Save the stripped file to this path instead. If a directory is given, the file keeps its
+    /// name within that directory
+    #[arg(short, long, conflicts_with = "inplace")]
+    output: Option<PathBuf>,
+
+    /// Save the stripped file with an xlsx extension, marked as a workbook without macros. Only
+    /// works for xlsm files
+    #[arg(long, default_value_t = false, conflicts_with = "inplace")]
+    xlsx: bool,
+
+    /// Excel file to strip
+    filename: PathBuf,
+}
+
+#[derive(Args)]
+struct ConvertArgs {
+    /// Save the xlsx file to this path, rather than alongside the original. If a directory is
+    /// given, the file keeps its name within that directory
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Excel file to convert
+    filename: PathBuf,
+}
+
+#[derive(Args)]
+struct DiffArgs {
+    /// Excel file to compare from
+    first: PathBuf,
+
+    /// Excel file to compare to
+    second: PathBuf,
+}
+
+#[derive(Args)]
+struct ScanArgs {
+    /// Walk any directories given, scanning every Excel file with VBA found within them
+    #[arg(short, long, default_value_t = false)]
+    recursive: bool,
+
+    /// Excel files, or directories with --recursive, to scan
+    #[arg(required = true)]
+    filenames: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+struct VerifyArgs {
+    /// Excel file that was unlocked
+    source: PathBuf,
+
+    /// Unlocked Excel file to check
+    output: PathBuf,
+}
+
+#[derive(Args)]
+struct ValidateArgs {
+    /// Excel file to check
+    filename: PathBuf,
+}
+
+#[derive(Args)]
+struct ServeArgs {
+    /// Address to listen on
+    #[arg(short, long, default_value = "127.0.0.1:8080")]
+    address: String,
+}
+
+#[derive(Args)]
+struct FetchWordlistArgs {
+    /// Name of the wordlist to download, one of openwall, rockyou or seclists. With --url, the
+    /// name to keep the list under
+    #[arg(required_unless_present = "list")]
+    name: Option<String>,
+
+    /// Download the list from this URL, in place of where the named list is known to be
+    #[arg(long, requires = "name")]
+    url: Option<String>,
+
+    /// SHA-1 checksum the list must have, as hex. The list is not kept if it does not match
+    #[arg(long, requires = "name")]
+    sha1: Option<String>,
+
+    /// List the wordlists that can be downloaded by name, and which are in the cache already
+    #[arg(short, long, default_value_t = false, conflicts_with = "name")]
+    list: bool,
+}
+
+#[derive(Args)]
+struct BenchArgs {
+    /// Number of threads to measure alongside a single thread. Defaults to the number of
+    /// available cores
+    #[arg(short, long)]
+    threads: Option<usize>,
+
+    /// How many seconds to hash for with each number of threads
+    #[arg(short, long, default_value_t = 3)]
+    seconds: u64,
+}
+
+/// The filename that stands for standard in
+const STDIN: &str = "-";
+
+/// The exit code for a command line that could not be acted on
+const USAGE: u8 = 2;
+
+/// Searches expected to take longer than this are only started once they have been agreed to
+const LONG_SEARCH: Duration = Duration::from_secs(60 * 60);
+
+/// Searches with fewer candidates than this are soon over, so how long they take is not estimated
+const ESTIMATE_FROM: u64 = 50_000_000;
+
 enum XlType {
     Old,
     New,
 }
 
-fn main() -> UnlockResult<()> {
-    let cli = Cli::parse();
-    let (filename, version) = get_file(&cli)?;
-    match (&cli.command, version) {
-        (Commands::Read(args), XlType::Old) => read::print_xl_97(filename, args.decode)?,
-        (Commands::Read(args), XlType::New) => read::print_xl(filename, args.decode)?,
-        (Commands::Remove(args), XlType::Old) => remove::xl_97(filename, args.inplace)?,
-        (Commands::Remove(args), XlType::New) => remove::xl(filename, args.inplace)?,
+fn main() -> ExitCode {
+    let mut cli = Cli::parse();
+    style::init(cli.no_color);
+    init_logging(cli.verbose);
+    let leniency = lenient::Options {
+        force: cli.force,
+        strict: cli.strict,
+    };
+    // The interactive view and the server have no terminal line of their own to draw on
+    progress::set(!cli.quiet && !matches!(cli.command, Commands::Tui(_) | Commands::Serve(_)));
+    match Config::load() {
+        Ok(config) => cli.command.apply(&config),
+        Err(e) => {
+            print_error(&e);
+            return ExitCode::from(e.exit_code());
+        }
+    }
+    if let Commands::Remove(RemoveArgs {
+        watch: Some(directory),
+        ..
+    }) = &cli.command
+    {
+        return watch_directory(&cli.command, directory, cli.quiet, &leniency);
     }
 
-    Ok(())
+    if let Commands::FetchWordlist(args) = &cli.command {
+        return match fetch_wordlist(args, cli.quiet) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                print_error(&e);
+                ExitCode::from(e.exit_code())
+            }
+        };
+    }
+
+    if let Commands::Bench(args) = &cli.command {
+        bench(args, cli.quiet);
+        return ExitCode::SUCCESS;
+    }
+
+    if let Commands::Serve(args) = &cli.command {
+        return match serve::serve(&args.address, &leniency) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                print_error(&e);
+                ExitCode::from(e.exit_code())
+            }
+        };
+    }
+
+    let (inputs, recursive) = cli.command.inputs();
+    let filenames = files::expand(&inputs, recursive);
+    if let Commands::Remove(RemoveArgs {
+        output: Some(output),
+        ..
+    }) = &cli.command
+    {
+        if filenames.len() > 1 && !output.is_dir() {
+            print_error("the output must be a directory when unlocking more than one file");
+            return ExitCode::from(USAGE);
+        }
+    }
+
+    if cli.json_lines {
+        return run_json_lines(&cli.command, &filenames, &leniency);
+    }
+
+    if let Commands::Crack(args) = &cli.command {
+        if filenames.len() > 1 {
+            return crack_many(args, &filenames, cli.quiet, &leniency);
+        }
+    }
+
+    // A single file behaves as it always has, with no status lines
+    if let [filename] = &filenames[..] {
+        return match run(&cli.command, filename, cli.quiet, &leniency) {
+            Ok(locked) => ExitCode::from(u8::from(locked)),
+            Err(e) => {
+                print_error(&e);
+                ExitCode::from(e.exit_code())
+            }
+        };
+    }
+
+    // With many files, the exit code is the most severe of the individual outcomes
+    let mut failures = 0;
+    let mut code = 0;
+    for filename in &filenames {
+        match run(&cli.command, filename, cli.quiet, &leniency) {
+            Ok(locked) => {
+                if !cli.quiet {
+                    println!("{}", style::success(&format!("✅ {}", filename.display())));
+                }
+                code = code.max(u8::from(locked));
+            }
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    style::error(&format!("❌ {}: {e}", filename.display()))
+                );
+                failures += 1;
+                code = code.max(e.exit_code());
+            }
+        }
+    }
+    if failures > 0 {
+        eprintln!("{failures} of {} files failed", filenames.len());
+    }
+    ExitCode::from(code)
+}
+
+/// Run the command against every file, printing a line of JSON for each as soon as it is done.
+/// The exit code is worked out as for any other batch
+fn run_json_lines(
+    command: &Commands,
+    filenames: &[PathBuf],
+    leniency: &lenient::Options,
+) -> ExitCode {
+    let mut code = 0;
+    for filename in filenames {
+        let mut record = jsonl::Record::new(filename);
+        let result = match command {
+            // read parses the project anyway, so the record is filled in from that rather than
+            // parsing it a second time
+            Commands::Read(args)
+                if filename != Path::new(STDIN)
+                    && !filename.to_str().is_some_and(fetch::is_url) =>
+            {
+                get_file(filename).and_then(|(filename, version)| {
+                    let project = read_file(args, filename, &version, true, leniency)?;
+                    record.set_project(&project);
+                    Ok(project.is_locked())
+                })
+            }
+            _ => {
+                record.read_project(filename, leniency);
+                run(command, filename, true, leniency)
+            }
+        };
+        match result {
+            Ok(locked) => {
+                record.action = Some(command.name());
+                code = code.max(u8::from(locked));
+            }
+            Err(e) => {
+                record.error = Some(e.to_string());
+                code = code.max(e.exit_code());
+            }
+        }
+        println!("{record}");
+    }
+    ExitCode::from(code)
 }
 
-fn get_file(cli: &Cli) -> UnlockResult<(&Path, XlType)> {
-    let filename = match &cli.command {
-        Commands::Read(a) => a.filename.as_str(),
-        Commands::Remove(a) => a.filename.as_str(),
+/// Run the command against every locked file that appears in the directory, until stopped
+fn watch_directory(
+    command: &Commands,
+    directory: &Path,
+    quiet: bool,
+    leniency: &lenient::Options,
+) -> ExitCode {
+    let result = watch::watch(directory, |filename| {
+        // Files that are already unlocked, including the ones written here, are left alone
+        let unlocked = is_locked(filename, leniency).and_then(|locked| {
+            if locked {
+                run(command, filename, quiet, leniency).map(|_| true)
+            } else {
+                Ok(false)
+            }
+        });
+        match unlocked {
+            Ok(true) if !quiet => {
+                println!("{}", style::success(&format!("✅ {}", filename.display())));
+            }
+            Ok(_) => (),
+            Err(e) => eprintln!(
+                "{}",
+                style::error(&format!("❌ {}: {e}", filename.display()))
+            ),
+        }
+    });
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            print_error(&e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+fn is_locked(filename: &Path, leniency: &lenient::Options) -> UnlockResult<bool> {
+    let (project, _) = match get_file(filename)? {
+        (filename, XlType::Old) => read::xl_97_project(filename, false, leniency)?,
+        (filename, XlType::New) => read::xl_project(filename, false, leniency)?,
+    };
+    Ok(project.is_locked())
+}
+
+/// Run the command against a single file, returning whether the file was found to be locked or,
+/// for diff, whether the files differ and, for scan, verify or validate, whether anything was found
+fn run(
+    command: &Commands,
+    filename: &Path,
+    quiet: bool,
+    leniency: &lenient::Options,
+) -> UnlockResult<bool> {
+    if filename == Path::new(STDIN) {
+        return run_stdin(command, quiet, leniency);
+    }
+    if let Some(url) = filename.to_str().filter(|f| fetch::is_url(f)) {
+        return run_url(command, url, quiet, leniency);
+    }
+    let (filename, version) = get_file(filename)?;
+    match (command, version) {
+        (Commands::Read(args), version) => {
+            return read_file(args, filename, &version, quiet, leniency).map(|p| p.is_locked());
+        }
+        (Commands::Remove(args), version) => remove_file(args, filename, &version, leniency)?,
+        (Commands::UnprotectSheets(args), version) => {
+            unprotect_sheets_file(args, filename, &version)?;
+        }
+        (Commands::Unprotect(args), version) => unprotect_file(args, filename, &version, leniency)?,
+        (Commands::SetPassword(args), XlType::Old) => {
+            set_password::xl_97(filename, &args.password, args.inplace, leniency)?;
+        }
+        (Commands::SetPassword(args), XlType::New) => {
+            set_password::xl(filename, &args.password, args.inplace, leniency)?;
+        }
+        (Commands::Lock(args), XlType::Old) => lock::xl_97(filename, args.inplace, leniency)?,
+        (Commands::Lock(args), XlType::New) => lock::xl(filename, args.inplace, leniency)?,
+        (Commands::ListModules(_), version) => modules_file(filename, &version, quiet, leniency)?,
+        (Commands::ListReferences(_), version) => {
+            references_file(filename, &version, quiet, leniency)?;
+        }
+        (Commands::Streams(_), version) => streams_file(filename, &version, quiet)?,
+        (Commands::DumpStream(args), version) => {
+            dump_stream_file(filename, &args.stream, &version, quiet)?;
+        }
+        (Commands::Export(args), XlType::Old) => {
+            export::xl_97(filename, &args.directory, leniency)?;
+        }
+        (Commands::Export(args), XlType::New) => export::xl(filename, &args.directory, leniency)?,
+        (Commands::Pcode(_), version) => pcode_file(filename, &version, quiet, leniency)?,
+        (Commands::ExtractBin(args), version) => extract_bin_file(args, filename, &version)?,
+        (Commands::InjectBin(args), version) => {
+            inject_bin_file(args, filename, &version, leniency)?;
+        }
+        (Commands::Info(_), XlType::Old) if quiet => {
+            read::xl_97_project(filename, false, leniency)?;
+        }
+        (Commands::Info(_), XlType::Old) => info::print_xl_97(filename, leniency)?,
+        (Commands::Info(_), XlType::New) if quiet => {
+            read::xl_project(filename, false, leniency)?;
+        }
+        (Commands::Info(_), XlType::New) => info::print_xl(filename, leniency)?,
+        (Commands::Crack(args), version) => crack_file(args, filename, &version, quiet, leniency)?,
+        (Commands::Hash(_), XlType::Old) if quiet => {
+            hash::xl_97(filename, leniency)?;
+        }
+        (Commands::Hash(args), XlType::Old) => hash::print_xl_97(filename, args.format, leniency)?,
+        (Commands::Hash(_), XlType::New) if quiet => {
+            hash::xl(filename, leniency)?;
+        }
+        (Commands::Hash(args), XlType::New) => hash::print_xl(filename, args.format, leniency)?,
+        (Commands::Tui(args), XlType::Old) => tui::xl_97(filename, &args.crack, leniency)?,
+        (Commands::Tui(args), XlType::New) => tui::xl(filename, &args.crack, leniency)?,
+        (Commands::Strip(args), version) => strip_file(args, filename, &version)?,
+        (Commands::Convert(args), version) => {
+            strip_to_xlsx(filename, args.output.as_deref(), &version)?;
+        }
+        (Commands::Diff(args), _) => {
+            return diff_files(filename, &args.second, quiet, leniency);
+        }
+        (Commands::Scan(_), version) => return scan_file(filename, &version, quiet, leniency),
+        (Commands::Verify(args), version) => {
+            return verify_file(filename, &args.output, &version, quiet, leniency);
+        }
+        (Commands::Validate(_), version) => return validate_file(filename, &version, quiet),
+        (Commands::Serve(_), _) => unreachable!("serve does not take a file"),
+        (Commands::FetchWordlist(_), _) => unreachable!("fetch-wordlist does not take a file"),
+        (Commands::Bench(_), _) => unreachable!("bench does not take a file"),
+    }
+
+    Ok(false)
+}
+
+/// Read the VBA project of a file and print what was found, returning the project
+fn read_file(
+    args: &ReadArgs,
+    filename: &Path,
+    version: &XlType,
+    quiet: bool,
+    leniency: &lenient::Options,
+) -> UnlockResult<read::Project> {
+    let (project, decoded) = match version {
+        XlType::Old => read::xl_97_project(filename, args.builtin_decode(), leniency),
+        XlType::New => read::xl_project(filename, args.builtin_decode(), leniency),
+    }
+    .inspect_err(|e| read_failed(args, e, quiet))?;
+    let decoded = if args.custom_decode() {
+        crack::decode(project.password(), &args.crack_options())?
+    } else {
+        decoded
     };
-    let filename = std::path::Path::new(filename);
+    let strength = if args.strength {
+        crack::strength(project.password(), args.crack_options().threads)?
+    } else {
+        None
+    };
+    if !quiet {
+        read::print_info(
+            &project,
+            args.decode,
+            decoded,
+            strength,
+            args.format.unwrap_or_default(),
+        );
+    }
+    Ok(project)
+}
+
+/// Compare the VBA projects of two files, returning whether they differ
+fn diff_files(
+    first: &Path,
+    second: &Path,
+    quiet: bool,
+    leniency: &lenient::Options,
+) -> UnlockResult<bool> {
+    let differences = diff::differences(&snapshot(first, leniency)?, &snapshot(second, leniency)?);
+    if !quiet {
+        for line in &differences {
+            println!("{line}");
+        }
+    }
+    Ok(!differences.is_empty())
+}
+
+fn scan_file(
+    filename: &Path,
+    version: &XlType,
+    quiet: bool,
+    leniency: &lenient::Options,
+) -> UnlockResult<bool> {
+    let findings = match version {
+        XlType::Old => scan::xl_97(filename, leniency)?,
+        XlType::New => scan::xl(filename, leniency)?,
+    };
+    if !quiet {
+        for finding in &findings {
+            println!("{}:{finding}", filename.display());
+        }
+    }
+    Ok(!findings.is_empty())
+}
+
+fn verify_file(
+    source: &Path,
+    output: &Path,
+    version: &XlType,
+    quiet: bool,
+    leniency: &lenient::Options,
+) -> UnlockResult<bool> {
+    let problems = match version {
+        XlType::Old => verify::xl_97(source, output, leniency)?,
+        XlType::New => verify::xl(source, output, leniency)?,
+    };
+    if !quiet {
+        for line in &problems {
+            println!("{}", style::failure(&format!("❌ {line}")));
+        }
+        if problems.is_empty() {
+            println!(
+                "{}",
+                style::success(&format!("✅ {} is sound", output.display()))
+            );
+        }
+    }
+    Ok(!problems.is_empty())
+}
+
+/// Check a file against the specification, returning whether any problems were found
+fn validate_file(filename: &Path, version: &XlType, quiet: bool) -> UnlockResult<bool> {
+    let problems = match version {
+        XlType::Old => validate::xl_97(filename)?,
+        XlType::New => validate::xl(filename)?,
+    };
+    if !quiet {
+        for line in &problems {
+            println!("{}", style::failure(&format!("❌ {line}")));
+        }
+        if problems.is_empty() {
+            println!(
+                "{}",
+                style::success(&format!(
+                    "✅ {} follows the specification",
+                    filename.display()
+                ))
+            );
+        }
+    }
+    Ok(!problems.is_empty())
+}
+
+fn snapshot(filename: &Path, leniency: &lenient::Options) -> UnlockResult<diff::Snapshot> {
+    match get_file(filename)? {
+        (filename, XlType::Old) => diff::xl_97(filename, leniency),
+        (filename, XlType::New) => diff::xl(filename, leniency),
+    }
+}
+
+/// Run the command against an Excel file piped in on standard input. The updated file, if there
+/// is one, is written to standard out unless an output path has been given
+fn run_stdin(command: &Commands, quiet: bool, leniency: &lenient::Options) -> UnlockResult<bool> {
+    let mut data = Vec::new();
+    std::io::stdin().read_to_end(&mut data)?;
+    match command {
+        Commands::Read(args) => read_data(args, &data, quiet, leniency),
+        Commands::Remove(args) => {
+            let unlocked = remove_bytes(args, &data, leniency)?;
+            match &args.output {
+                Some(output) => std::fs::write(output, unlocked)?,
+                None => std::io::stdout().write_all(&unlocked)?,
+            }
+            Ok(false)
+        }
+        _ => Err(UnlockError::Stdin),
+    }
+}
+
+/// Run the command against an Excel file downloaded from the URL. The unlocked file is saved in
+/// the current directory, under the name from the URL, unless an output path has been given
+fn run_url(
+    command: &Commands,
+    url: &str,
+    quiet: bool,
+    leniency: &lenient::Options,
+) -> UnlockResult<bool> {
+    if !matches!(command, Commands::Read(_) | Commands::Remove(_)) {
+        return Err(UnlockError::Url);
+    }
+    let data = fetch::download(url)?;
+    match command {
+        Commands::Read(args) => read_data(args, &data, quiet, leniency),
+        Commands::Remove(args) => {
+            let unlocked = remove_bytes(args, &data, leniency)?;
+            let name = Path::new(fetch::file_name(url));
+            let output = match &args.output {
+                Some(output) => output_path(output, name),
+                None => remove::unlocked_filename(name)?,
+            };
+            std::fs::write(output, unlocked)?;
+            Ok(false)
+        }
+        _ => Err(UnlockError::Url),
+    }
+}
+
+/// Print what could be read of a project that failed to parse, unless asked to be quiet
+fn read_failed(args: &ReadArgs, e: &UnlockError, quiet: bool) {
+    if !quiet {
+        read::print_partial(e, args.format.unwrap_or_default());
+    }
+}
+
+fn read_data(
+    args: &ReadArgs,
+    data: &[u8],
+    quiet: bool,
+    leniency: &lenient::Options,
+) -> UnlockResult<bool> {
+    let (project, decoded) = read::bytes_project(data, args.builtin_decode(), leniency)
+        .inspect_err(|e| read_failed(args, e, quiet))?;
+    let decoded = if args.custom_decode() {
+        crack::decode(project.password(), &args.crack_options())?
+    } else {
+        decoded
+    };
+    let strength = if args.strength {
+        crack::strength(project.password(), args.crack_options().threads)?
+    } else {
+        None
+    };
+    if !quiet {
+        read::print_info(
+            &project,
+            args.decode,
+            decoded,
+            strength,
+            args.format.unwrap_or_default(),
+        );
+    }
+    Ok(project.is_locked())
+}
+
+impl ReadArgs {
+    /// Whether to decode with the built-in list and default options as the file is read
+    fn builtin_decode(&self) -> bool {
+        self.decode && !self.custom_decode()
+    }
+
+    /// Whether any wordlists, rules, thread count or potfile were given for decoding, in which case
+    /// the password is searched for after the file is read. The same goes for a build without the
+    /// built-in list, so that decoding with no wordlist fails rather than finding nothing
+    fn custom_decode(&self) -> bool {
+        self.decode
+            && (!cfg!(feature = "wordlist")
+                || !self.wordlist.is_empty()
+                || !self.rule.is_empty()
+                || self.threads.is_some()
+                || self.potfile.is_some())
+    }
+
+    fn crack_options(&self) -> crack::Options {
+        crack::Options {
+            wordlists: self.wordlist.clone(),
+            rules: self.rule.clone(),
+            potfile: self.potfile.clone(),
+            threads: self
+                .threads
+                .unwrap_or_else(|| crack::Options::default().threads),
+            ..crack::Options::default()
+        }
+    }
+}
+
+impl Commands {
+    /// Fill in any options not given on the command line from the config file
+    fn apply(&mut self, config: &Config) {
+        match self {
+            Self::Read(a) => {
+                a.format = a.format.or(config.format);
+                if a.decode {
+                    a.potfile = a.potfile.take().or_else(|| config.potfile.clone());
+                }
+            }
+            Self::Remove(a) => a.inplace |= config.inplace && a.output.is_none(),
+            Self::UnprotectSheets(a) => a.inplace |= config.inplace && a.output.is_none(),
+            Self::Unprotect(a) => a.inplace |= config.inplace && a.output.is_none(),
+            Self::SetPassword(a) => a.inplace |= config.inplace,
+            Self::Lock(a) => a.inplace |= config.inplace,
+            Self::InjectBin(a) => a.inplace |= config.inplace && a.output.is_none(),
+            Self::Strip(a) => a.inplace |= config.inplace && a.output.is_none() && !a.xlsx,
+            Self::Crack(a) => {
+                if a.wordlist.is_empty() {
+                    a.wordlist.extend(config.wordlist.clone());
+                }
+                a.threads = a.threads.or(config.threads);
+                a.time_limit = a.time_limit.or(config.time_limit);
+                a.potfile = a.potfile.take().or_else(|| config.potfile.clone());
+            }
+            Self::Tui(a) => {
+                a.crack.wordlists = config.wordlist.clone().into_iter().collect();
+                a.crack.threads = config.threads.unwrap_or(a.crack.threads);
+                a.crack.time_limit = config.time_limit.map(Duration::from_secs);
+                a.crack.potfile.clone_from(&config.potfile);
+            }
+            Self::ListModules(_)
+            | Self::ListReferences(_)
+            | Self::Streams(_)
+            | Self::DumpStream(_)
+            | Self::Export(_)
+            | Self::Pcode(_)
+            | Self::ExtractBin(_)
+            | Self::Info(_)
+            | Self::Hash(_)
+            | Self::Convert(_)
+            | Self::Diff(_)
+            | Self::Scan(_)
+            | Self::Verify(_)
+            | Self::Validate(_)
+            | Self::Serve(_)
+            | Self::FetchWordlist(_) => (),
+            Self::Bench(a) => a.threads = a.threads.or(config.threads),
+        }
+    }
+
+    /// The name of the command, as typed on the command line
+    const fn name(&self) -> &'static str {
+        match self {
+            Self::Read(_) => "read",
+            Self::Remove(_) => "remove",
+            Self::UnprotectSheets(_) => "unprotect-sheets",
+            Self::Unprotect(_) => "unprotect",
+            Self::SetPassword(_) => "set-password",
+            Self::Lock(_) => "lock",
+            Self::ListModules(_) => "list-modules",
+            Self::ListReferences(_) => "list-references",
+            Self::Streams(_) => "streams",
+            Self::DumpStream(_) => "dump-stream",
+            Self::Export(_) => "export",
+            Self::Pcode(_) => "pcode",
+            Self::ExtractBin(_) => "extract-bin",
+            Self::InjectBin(_) => "inject-bin",
+            Self::Info(_) => "info",
+            Self::Crack(_) => "crack",
+            Self::Hash(_) => "hash",
+            Self::Tui(_) => "tui",
+            Self::Strip(_) => "strip",
+            Self::Convert(_) => "convert",
+            Self::Diff(_) => "diff",
+            Self::Scan(_) => "scan",
+            Self::Verify(_) => "verify",
+            Self::Validate(_) => "validate",
+            Self::Serve(_) => "serve",
+            Self::FetchWordlist(_) => "fetch-wordlist",
+            Self::Bench(_) => "bench",
+        }
+    }
+
+    /// The files or directories to operate on, and whether directories should be walked
+    fn inputs(&self) -> (Vec<&Path>, bool) {
+        match self {
+            Self::Read(a) => (
+                a.filenames.iter().map(PathBuf::as_path).collect(),
+                a.recursive,
+            ),
+            Self::Remove(a) => (
+                a.filenames.iter().map(PathBuf::as_path).collect(),
+                a.recursive,
+            ),
+            Self::UnprotectSheets(a) => (vec![a.filename.as_path()], false),
+            Self::Unprotect(a) => (vec![a.filename.as_path()], false),
+            Self::SetPassword(a) => (vec![a.filename.as_path()], false),
+            Self::Lock(a) => (vec![a.filename.as_path()], false),
+            Self::ListModules(a) => (vec![a.filename.as_path()], false),
+            Self::ListReferences(a) => (vec![a.filename.as_path()], false),
+            Self::Streams(a) => (vec![a.filename.as_path()], false),
+            Self::DumpStream(a) => (vec![a.filename.as_path()], false),
+            Self::Export(a) => (vec![a.filename.as_path()], false),
+            Self::Pcode(a) => (vec![a.filename.as_path()], false),
+            Self::ExtractBin(a) => (vec![a.filename.as_path()], false),
+            Self::InjectBin(a) => (vec![a.filename.as_path()], false),
+            Self::Info(a) => (vec![a.filename.as_path()], false),
+            Self::Crack(a) => (
+                a.filenames.iter().map(PathBuf::as_path).collect(),
+                a.recursive,
+            ),
+            Self::Hash(a) => (vec![a.filename.as_path()], false),
+            Self::Tui(a) => (vec![a.filename.as_path()], false),
+            Self::Strip(a) => (vec![a.filename.as_path()], false),
+            Self::Convert(a) => (vec![a.filename.as_path()], false),
+            Self::Diff(a) => (vec![a.first.as_path()], false),
+            Self::Scan(a) => (
+                a.filenames.iter().map(PathBuf::as_path).collect(),
+                a.recursive,
+            ),
+            Self::Verify(a) => (vec![a.source.as_path()], false),
+            Self::Validate(a) => (vec![a.filename.as_path()], false),
+            Self::Serve(_) | Self::FetchWordlist(_) | Self::Bench(_) => (Vec::new(), false),
+        }
+    }
+}
+
+/// Work out which kind of Excel file this is, from its contents if they can be read and from its
+/// extension if not
+fn get_file(filename: &Path) -> UnlockResult<(&Path, XlType)> {
+    match files::detect(filename) {
+        Some(Container::Cfb) => return Ok((filename, XlType::Old)),
+        Some(Container::Zip) => return Ok((filename, XlType::New)),
+        None => (),
+    }
+
     let extension = filename
         .extension()
         .and_then(|s| s.to_str())
@@ -76,9 +1352,549 @@ fn get_file(cli: &Cli) -> UnlockResult<(&Path, XlType)> {
     match extension.as_deref() {
         Some("xls") => Ok((filename, XlType::Old)),
         Some("xlsm" | "xlsb") => Ok((filename, XlType::New)),
-        Some("xlsx") => Err(UnlockError::XlsX(filename.to_string_lossy().to_string())),
-        _ => Err(UnlockError::NotExcel(
-            filename.to_string_lossy().to_string(),
-        )),
+        Some("xlsx") => Err(UnlockError::XlsX(filename.to_path_buf())),
+        _ => Err(UnlockError::NotExcel(filename.to_path_buf())),
+    }
+}
+
+fn print_error(message: impl std::fmt::Display) {
+    eprintln!("{} {message}", style::error("Error:"));
+}
+
+fn init_logging(verbose: u8) {
+    let level = match verbose {
+        0 => tracing::Level::WARN,
+        1 => tracing::Level::INFO,
+        2 => tracing::Level::DEBUG,
+        _ => tracing::Level::TRACE,
+    };
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .with_ansi(style::stderr_enabled())
+        .without_time()
+        .init();
+}
+
+/// The path to save an updated file to. If the output is a directory then the file keeps its
+/// original name within it
+fn output_path(output: &Path, filename: &Path) -> PathBuf {
+    match filename.file_name() {
+        Some(name) if output.is_dir() => output.join(name),
+        _ => output.to_path_buf(),
+    }
+}
+
+fn remove_file(
+    args: &RemoveArgs,
+    filename: &Path,
+    version: &XlType,
+    leniency: &lenient::Options,
+) -> UnlockResult<()> {
+    if args.workbook {
+        // The copy keeps the name it would have had from remove alone
+        let layers = remove_layers(args);
+        let output = match &args.output {
+            Some(output) => output_path(output, filename),
+            None if args.inplace => filename.to_path_buf(),
+            None => remove::unlocked_filename(filename)?,
+        };
+        return match version {
+            XlType::Old => unprotect::xl_97_to(filename, layers, &output, leniency),
+            XlType::New => unprotect::xl_to(filename, layers, &output, leniency),
+        };
+    }
+    let output = args.output.as_ref().map(|o| output_path(o, filename));
+    match (version, output) {
+        (XlType::Old, Some(output)) => remove::xl_97_to(filename, &output, leniency),
+        (XlType::Old, None) => remove::xl_97(filename, args.inplace, leniency),
+        (XlType::New, Some(output)) => remove::xl_to(filename, &output, leniency),
+        (XlType::New, None) => remove::xl(filename, args.inplace, leniency),
+    }
+}
+
+/// Remove the VBA project protection from a file held in memory, along with any other protection
+/// asked for
+fn remove_bytes(
+    args: &RemoveArgs,
+    data: &[u8],
+    leniency: &lenient::Options,
+) -> UnlockResult<Vec<u8>> {
+    if args.workbook {
+        unprotect::bytes(data, remove_layers(args), leniency)
+    } else {
+        remove::bytes(data, leniency)
+    }
+}
+
+const fn remove_layers(args: &RemoveArgs) -> unprotect::Layers<'static> {
+    unprotect::Layers {
+        vba: true,
+        workbook: args.workbook,
+        sheets: false,
+        sheet_names: &[],
+        very_hidden: false,
+        file_sharing: false,
+        shared: false,
+    }
+}
+
+fn unprotect_sheets_file(
+    args: &UnprotectSheetsArgs,
+    filename: &Path,
+    version: &XlType,
+) -> UnlockResult<()> {
+    let output = args.output.as_ref().map(|o| output_path(o, filename));
+    match (version, output) {
+        (XlType::Old, Some(output)) => {
+            unprotect_sheets::xl_97_to(filename, &args.sheet_names, &output)
+        }
+        (XlType::Old, None) => unprotect_sheets::xl_97(filename, &args.sheet_names, args.inplace),
+        (XlType::New, Some(output)) => {
+            unprotect_sheets::xl_to(filename, &args.sheet_names, &output)
+        }
+        (XlType::New, None) => unprotect_sheets::xl(filename, &args.sheet_names, args.inplace),
+    }
+}
+
+fn unprotect_file(
+    args: &UnprotectArgs,
+    filename: &Path,
+    version: &XlType,
+    leniency: &lenient::Options,
+) -> UnlockResult<()> {
+    let layers = unprotect::Layers {
+        vba: args.vba || (args.all && files::is_excel_with_vba(filename)),
+        workbook: args.workbook || args.all,
+        sheets: args.sheets || args.all || !args.sheet_names.is_empty(),
+        sheet_names: &args.sheet_names,
+        very_hidden: args.very_hidden || args.all,
+        file_sharing: args.file_sharing || args.all,
+        shared: args.shared || args.all,
+    };
+    let output = args.output.as_ref().map(|o| output_path(o, filename));
+    match (version, output) {
+        (XlType::Old, Some(output)) => unprotect::xl_97_to(filename, layers, &output, leniency),
+        (XlType::Old, None) => unprotect::xl_97(filename, layers, args.inplace, leniency),
+        (XlType::New, Some(output)) => unprotect::xl_to(filename, layers, &output, leniency),
+        (XlType::New, None) => unprotect::xl(filename, layers, args.inplace, leniency),
+    }
+}
+
+fn modules_file(
+    filename: &Path,
+    version: &XlType,
+    quiet: bool,
+    leniency: &lenient::Options,
+) -> UnlockResult<()> {
+    match version {
+        XlType::Old if quiet => list_modules::xl_97_modules(filename, leniency).map(|_| ()),
+        XlType::Old => list_modules::print_xl_97(filename, leniency),
+        XlType::New if quiet => list_modules::xl_modules(filename, leniency).map(|_| ()),
+        XlType::New => list_modules::print_xl(filename, leniency),
+    }
+}
+
+fn references_file(
+    filename: &Path,
+    version: &XlType,
+    quiet: bool,
+    leniency: &lenient::Options,
+) -> UnlockResult<()> {
+    match version {
+        XlType::Old if quiet => list_references::xl_97_references(filename, leniency).map(|_| ()),
+        XlType::Old => list_references::print_xl_97(filename, leniency),
+        XlType::New if quiet => list_references::xl_references(filename, leniency).map(|_| ()),
+        XlType::New => list_references::print_xl(filename, leniency),
+    }
+}
+
+fn pcode_file(
+    filename: &Path,
+    version: &XlType,
+    quiet: bool,
+    leniency: &lenient::Options,
+) -> UnlockResult<()> {
+    match version {
+        XlType::Old if quiet => pcode::xl_97_pcode(filename, leniency).map(|_| ()),
+        XlType::Old => pcode::print_xl_97(filename, leniency),
+        XlType::New if quiet => pcode::xl_pcode(filename, leniency).map(|_| ()),
+        XlType::New => pcode::print_xl(filename, leniency),
+    }
+}
+
+fn streams_file(filename: &Path, version: &XlType, quiet: bool) -> UnlockResult<()> {
+    match version {
+        XlType::Old if quiet => streams::xl_97(filename).map(|_| ()),
+        XlType::Old => streams::print_xl_97(filename),
+        XlType::New if quiet => streams::xl(filename).map(|_| ()),
+        XlType::New => streams::print_xl(filename),
+    }
+}
+
+fn dump_stream_file(
+    filename: &Path,
+    stream: &str,
+    version: &XlType,
+    quiet: bool,
+) -> UnlockResult<()> {
+    match version {
+        XlType::Old if quiet => dump_stream::xl_97(filename, stream).map(|_| ()),
+        XlType::Old => dump_stream::print_xl_97(filename, stream),
+        XlType::New if quiet => dump_stream::xl(filename, stream).map(|_| ()),
+        XlType::New => dump_stream::print_xl(filename, stream),
+    }
+}
+
+fn strip_file(args: &StripArgs, filename: &Path, version: &XlType) -> UnlockResult<()> {
+    if args.xlsx {
+        return strip_to_xlsx(filename, args.output.as_deref(), version);
+    }
+    let output = args.output.as_ref().map(|o| output_path(o, filename));
+    match (version, output) {
+        (XlType::Old, Some(output)) => strip::xl_97_to(filename, &output),
+        (XlType::Old, None) => strip::xl_97(filename, args.inplace),
+        (XlType::New, Some(output)) => strip::xl_to(filename, &output),
+        (XlType::New, None) => strip::xl(filename, args.inplace),
+    }
+}
+
+/// Strip the file, saving it as an xlsx file. This is alongside the original unless an output
+/// path is given
+fn strip_to_xlsx(filename: &Path, output: Option<&Path>, version: &XlType) -> UnlockResult<()> {
+    let output = output
+        .map_or_else(|| filename.to_path_buf(), |o| output_path(o, filename))
+        .with_extension("xlsx");
+    match version {
+        XlType::Old => strip::xl_97_to(filename, &output),
+        XlType::New => strip::xl_to(filename, &output),
+    }
+}
+
+fn extract_bin_file(args: &ExtractBinArgs, filename: &Path, version: &XlType) -> UnlockResult<()> {
+    let default = extract_bin::default_output(filename);
+    let output = match &args.output {
+        Some(o) if o.is_dir() => o.join(default.file_name().unwrap_or_default()),
+        Some(o) => o.clone(),
+        None => default,
+    };
+    match version {
+        XlType::Old => extract_bin::xl_97(filename, &output),
+        XlType::New => extract_bin::xl(filename, &output),
+    }
+}
+
+fn inject_bin_file(
+    args: &InjectBinArgs,
+    filename: &Path,
+    version: &XlType,
+    leniency: &lenient::Options,
+) -> UnlockResult<()> {
+    let output = args.output.as_ref().map(|o| output_path(o, filename));
+    match (version, output) {
+        (XlType::Old, Some(output)) => inject_bin::xl_97_to(filename, &args.bin, &output, leniency),
+        (XlType::Old, None) => inject_bin::xl_97(filename, &args.bin, args.inplace, leniency),
+        (XlType::New, Some(output)) => inject_bin::xl_to(filename, &args.bin, &output, leniency),
+        (XlType::New, None) => inject_bin::xl(filename, &args.bin, args.inplace, leniency),
+    }
+}
+
+fn crack_file(
+    args: &CrackArgs,
+    filename: &Path,
+    version: &XlType,
+    quiet: bool,
+    leniency: &lenient::Options,
+) -> UnlockResult<()> {
+    let mut options = crack_options(args);
+    if args.context {
+        options.context = match version {
+            XlType::Old => context::xl_97(filename, leniency)?,
+            XlType::New => context::xl(filename, leniency)?,
+        };
+    }
+    confirm_search(args, &options, 1, quiet)?;
+    let password = match version {
+        XlType::Old => crack::xl_97(filename, &options, leniency)?,
+        XlType::New => crack::xl(filename, &options, leniency)?,
+    };
+    if !quiet {
+        println!("{password}");
+    }
+    Ok(())
+}
+
+/// Download the named wordlist into the cache, or list the wordlists that can be downloaded
+fn fetch_wordlist(args: &FetchWordlistArgs, quiet: bool) -> UnlockResult<()> {
+    let Some(name) = &args.name else {
+        if !quiet {
+            for known in wordlists::KNOWN {
+                let cached = if wordlists::path(known.name)?.is_file() {
+                    format!(" {}", style::success("(cached)"))
+                } else {
+                    String::new()
+                };
+                println!("{:<10} {}{cached}", known.name, known.description);
+            }
+        }
+        return Ok(());
+    };
+    let (path, checksum) = wordlists::fetch(name, args.url.as_deref(), args.sha1.as_deref())?;
+    if !quiet {
+        let line = format!("✅ Downloaded {name} to {}", path.display());
+        println!("{}", style::success(&line));
+        println!("SHA-1: {checksum}");
+    }
+    Ok(())
+}
+
+/// Measure the hash rate with a single thread and with many, and print how long some common
+/// searches would take at the faster rate
+// The estimates do not need every digit of the number of candidates
+#[allow(clippy::cast_precision_loss)]
+fn bench(args: &BenchArgs, quiet: bool) {
+    let duration = Duration::from_secs(args.seconds.max(1));
+    let threads = args
+        .threads
+        .unwrap_or_else(|| crack::Options::default().threads);
+    let single = crack::benchmark(1, duration);
+    let mut rate = single;
+    if !quiet {
+        println!("{:<12} {}", "1 thread:", hashes(single));
+    }
+    if threads > 1 {
+        rate = crack::benchmark(threads, duration);
+        if !quiet {
+            println!(
+                "{:<12} {} ({:.1}x)",
+                format!("{threads} threads:"),
+                hashes(rate),
+                rate / single
+            );
+        }
+    }
+    if quiet {
+        return;
+    }
+    let searches = [
+        ("The built-in wordlist", crack::Options::default()),
+        (
+            "Brute force of lowernum up to 8 characters",
+            crack::Options {
+                brute_force: Some(crack::BruteForce {
+                    charset: crack::Charset::LowerNum,
+                    min: 1,
+                    max: 8,
+                }),
+                ..crack::Options::default()
+            },
+        ),
+    ];
+    println!();
+    for (name, options) in searches {
+        // The built-in wordlist is left out of builds without it
+        if let Ok(Some(candidates)) = crack::candidates(&options) {
+            let seconds = candidates as f64 / rate;
+            let expected = Duration::try_from_secs_f64(seconds).unwrap_or(Duration::MAX);
+            if expected < Duration::from_secs(1) {
+                println!("{name}: under a second");
+            } else {
+                println!("{name}: about {}", indicatif::HumanDuration(expected));
+            }
+        }
+    }
+}
+
+/// A hash rate, with its digits grouped
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn hashes(rate: f64) -> String {
+    format!("{} hashes/s", indicatif::HumanCount(rate as u64))
+}
+
+/// Crack every file in one go, so the candidates are only gone through once. Each file gets a
+/// status line and the exit code is worked out as for any other batch
+fn crack_many(
+    args: &CrackArgs,
+    filenames: &[PathBuf],
+    quiet: bool,
+    leniency: &lenient::Options,
+) -> ExitCode {
+    let projects: Vec<_> = filenames
+        .iter()
+        .map(|filename| {
+            if filename == Path::new(STDIN) {
+                return Err(UnlockError::Stdin);
+            }
+            if filename.to_str().is_some_and(fetch::is_url) {
+                return Err(UnlockError::Url);
+            }
+            let ((project, _), words) = match get_file(filename)? {
+                (filename, XlType::Old) => (
+                    read::xl_97_project(filename, false, leniency)?,
+                    harvest(args, || context::xl_97(filename, leniency))?,
+                ),
+                (filename, XlType::New) => (
+                    read::xl_project(filename, false, leniency)?,
+                    harvest(args, || context::xl(filename, leniency))?,
+                ),
+            };
+            Ok((project, words))
+        })
+        .collect();
+    let mut options = crack_options(args);
+    let mut passwords = Vec::new();
+    for (project, words) in projects.iter().flatten() {
+        passwords.push(project.password());
+        for word in words {
+            if !options.context.contains(word) {
+                options.context.push(word.clone());
+            }
+        }
+    }
+    let cracked = confirm_search(args, &options, passwords.len(), quiet)
+        .and_then(|()| crack::many(&passwords, &options));
+    let mut cracked = match cracked {
+        Ok(cracked) => cracked.into_iter(),
+        Err(e) => {
+            print_error(&e);
+            return ExitCode::from(e.exit_code());
+        }
+    };
+
+    let mut failures = 0;
+    let mut code = 0;
+    for (filename, project) in filenames.iter().zip(projects) {
+        let password =
+            project.and_then(|_| cracked.next().unwrap_or(Err(UnlockError::PasswordNotFound)));
+        match password {
+            Ok(password) => {
+                if !quiet {
+                    let line = format!("✅ {}: {password}", filename.display());
+                    println!("{}", style::success(&line));
+                }
+            }
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    style::error(&format!("❌ {}: {e}", filename.display()))
+                );
+                failures += 1;
+                code = code.max(e.exit_code());
+            }
+        }
+    }
+    if failures > 0 {
+        eprintln!("{failures} of {} files failed", filenames.len());
+    }
+    ExitCode::from(code)
+}
+
+/// The context words of a file, if they are wanted
+fn harvest(
+    args: &CrackArgs,
+    words: impl FnOnce() -> UnlockResult<Vec<String>>,
+) -> UnlockResult<Vec<String>> {
+    if args.context {
+        words()
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+/// Print how many candidates the search will try, and about how long it will take to try them
+/// against that many passwords. A search expected to take longer than an hour needs --yes, or to
+/// be agreed to at the terminal, before it starts
+// The estimate does not need every digit of the number of candidates
+#[allow(clippy::cast_precision_loss)]
+fn confirm_search(
+    args: &CrackArgs,
+    options: &crack::Options,
+    passwords: usize,
+    quiet: bool,
+) -> UnlockResult<()> {
+    let Some(candidates) = crack::candidates(options)? else {
+        return Ok(());
+    };
+    if candidates < ESTIMATE_FROM {
+        return Ok(());
+    }
+    let seconds = candidates as f64 * passwords as f64 / crack::hash_rate(options.threads);
+    let mut expected = Duration::try_from_secs_f64(seconds).unwrap_or(Duration::MAX);
+    if let Some(limit) = options.time_limit {
+        expected = expected.min(limit);
+    }
+    if !quiet {
+        eprintln!(
+            "Trying {} candidates should take about {}",
+            indicatif::HumanCount(candidates),
+            indicatif::HumanDuration(expected)
+        );
+    }
+    if expected <= LONG_SEARCH || args.yes {
+        return Ok(());
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err(UnlockError::NotConfirmed);
+    }
+    eprint!("This could take over an hour. Carry on? [y/N] ");
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    if matches!(answer.trim(), "y" | "Y" | "yes" | "Yes") {
+        Ok(())
+    } else {
+        Err(UnlockError::NotConfirmed)
+    }
+}
+
+fn crack_options(args: &CrackArgs) -> crack::Options {
+    let defaults = crack::Options::default();
+    crack::Options {
+        wordlists: args.wordlist.clone(),
+        context: Vec::new(),
+        combinator: match &args.combine[..] {
+            [left, right] => Some(crack::Combinator {
+                left: left.clone(),
+                right: right.clone(),
+                separator: args.separator.clone().unwrap_or_default(),
+            }),
+            _ => None,
+        },
+        brute_force: args.brute.then(|| crack::BruteForce {
+            charset: args.charset.unwrap_or_default(),
+            min: args.min.unwrap_or(1),
+            max: args.max.unwrap_or(8),
+        }),
+        mask: args.mask.clone(),
+        rules: args.rule.clone(),
+        cases: args.cases,
+        hybrid: args.hybrid,
+        markov: args.markov.then(|| crack::Markov {
+            corpus: args.corpus.clone(),
+            min: args.min.unwrap_or(1),
+            max: args.max.unwrap_or(8),
+        }),
+        walk: args.walk.then(|| crack::Walk {
+            layout: args.layout.unwrap_or_else(crack::Layout::from_locale),
+            min: args.min.unwrap_or(1),
+            max: args.max.unwrap_or(12),
+        }),
+        policy: crack::Policy {
+            min_length: args.min_len,
+            max_length: args.max_len,
+            required: [
+                (args.require_digit, crack::Class::Digit),
+                (args.require_upper, crack::Class::Upper),
+                (args.require_lower, crack::Class::Lower),
+                (args.require_symbol, crack::Class::Symbol),
+            ]
+            .into_iter()
+            .filter_map(|(required, class)| required.then_some(class))
+            .collect(),
+        },
+        skip: args.skip.unwrap_or_default(),
+        limit: args.limit,
+        potfile: args.potfile.clone(),
+        threads: args.threads.unwrap_or(defaults.threads),
+        time_limit: args.time_limit.map(Duration::from_secs),
     }
 }
@@ -1,2 +1,3 @@
+pub mod compression;
 pub mod data_encryption;
 pub mod password_hash;
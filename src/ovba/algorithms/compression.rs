@@ -0,0 +1,419 @@
+//! VBA compression algorithm
+//!
+//! The dir stream and the source code of each module are stored in a compressed container.
+//! Specification can be found [here](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/d2f1b5d5-1a86-48e6-b3e1-bbb6ab25ddeb)
+use crate::error;
+use tracing::trace;
+
+const CHUNK_SIZE: usize = 4096;
+
+/// Decompress a compressed container into the original bytes
+///
+/// # Reference
+/// Specification can be found [here](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/492124e0-2fcd-4a04-a9d5-14a5d0ba6b0b)
+///
+/// # Error
+/// Will generate an error if:
+/// - the container does not start with the signature byte 0x01
+/// - a chunk header does not have the signature bits 0b011
+/// - a copy token points to data before the start of the current chunk
+/// - the data runs out part way through a chunk header or copy token
+pub fn decompress<D: AsRef<[u8]>>(container: D) -> Result<Vec<u8>, error::Compression> {
+    let container = container.as_ref();
+    match container.first() {
+        Some(0x01) => (),
+        Some(&b) => return Err(error::Compression::Signature(b)),
+        None => return Err(error::Compression::Truncated),
+    }
+
+    let mut decompressed = Vec::with_capacity(container.len() * 2);
+    let mut position = 1;
+    while position < container.len() {
+        let Some(header) = container.get(position..position + 2) else {
+            return Err(error::Compression::Truncated);
+        };
+        let header = u16::from_le_bytes([header[0], header[1]]);
+        let size = usize::from(header & 0x0fff) + 3;
+        if (header >> 12) & 0x07 != 0b011 {
+            return Err(error::Compression::ChunkSignature(header));
+        }
+        let compressed = header & 0x8000 == 0x8000;
+        trace!(position, size, compressed, "decompressing chunk");
+
+        // The final chunk is allowed to be shorter than its header says, so don't read past the
+        // end of the data
+        let end = (position + size).min(container.len());
+        let chunk = &container[position + 2..end];
+        if compressed {
+            decompress_chunk(chunk, &mut decompressed)?;
+        } else {
+            decompressed.extend_from_slice(&chunk[..chunk.len().min(CHUNK_SIZE)]);
+        }
+        position = end;
+    }
+
+    Ok(decompressed)
+}
+
+/// Decompress the token sequences of a single compressed chunk, appending to the output buffer
+fn decompress_chunk(chunk: &[u8], decompressed: &mut Vec<u8>) -> Result<(), error::Compression> {
+    let chunk_start = decompressed.len();
+    let mut position = 0;
+    while position < chunk.len() {
+        let flags = chunk[position];
+        position += 1;
+        for bit in 0..8 {
+            if position >= chunk.len() {
+                break;
+            }
+            if flags & (1 << bit) == 0 {
+                // Literal token
+                decompressed.push(chunk[position]);
+                position += 1;
+            } else {
+                // Copy token
+                let Some(token) = chunk.get(position..position + 2) else {
+                    return Err(error::Compression::Truncated);
+                };
+                let token = u16::from_le_bytes([token[0], token[1]]);
+                position += 2;
+
+                let (offset, length) = unpack_copy_token(token, decompressed.len() - chunk_start);
+                if offset > decompressed.len() - chunk_start {
+                    return Err(error::Compression::CopyTokenOffset(
+                        offset,
+                        decompressed.len() - chunk_start,
+                    ));
+                }
+                // Copy byte by byte as the source and destination can overlap
+                let source = decompressed.len() - offset;
+                for i in 0..length {
+                    decompressed.push(decompressed[source + i]);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Compress bytes into a compressed container
+///
+/// Each chunk of 4096 bytes is searched for runs of bytes that repeat earlier ones, which are
+/// replaced by copy tokens. A chunk that would not fit once compressed is stored as it is instead.
+/// The spec requires a chunk stored as it is to hold 4096 bytes, so if that is the last, short
+/// chunk it is padded out with zeros. [`decompress`] then gives back the bytes followed by that
+/// padding, and otherwise gives back exactly the bytes
+///
+/// Nothing in the crate writes a dir or module stream yet, so only the tests call this for now
+///
+/// # Reference
+/// The algorithm is given in section 2.4.1.3 of the specification, next to the one for
+/// decompressing
+#[allow(dead_code)]
+#[must_use]
+pub fn compress<D: AsRef<[u8]>>(data: D) -> Vec<u8> {
+    let data = data.as_ref();
+    let mut container = Vec::with_capacity(data.len() + data.len() / 8 + 3);
+    container.push(0x01);
+    for chunk in data.chunks(CHUNK_SIZE) {
+        let start = container.len();
+        container.extend([0, 0]);
+        compress_chunk(chunk, &mut container);
+        let size = container.len() - start;
+        let header = if size > CHUNK_SIZE + 2 {
+            container.truncate(start + 2);
+            container.extend_from_slice(chunk);
+            container.resize(start + 2 + CHUNK_SIZE, 0);
+            0x3fff
+        } else {
+            // Truncation Ok as a compressed chunk is never more than 4098 bytes long
+            #[allow(clippy::cast_possible_truncation)]
+            let size = (size - 3) as u16;
+            0xb000 | size
+        };
+        trace!(start, size, raw = header == 0x3fff, "compressed chunk");
+        container[start..start + 2].copy_from_slice(&header.to_le_bytes());
+    }
+    container
+}
+
+/// Compress a single chunk into token sequences, appending to the output buffer. Stops early once
+/// the chunk has grown too big to be worth compressing
+fn compress_chunk(chunk: &[u8], compressed: &mut Vec<u8>) {
+    let limit = compressed.len() + CHUNK_SIZE;
+    let mut position = 0;
+    while position < chunk.len() && compressed.len() <= limit {
+        let flags_position = compressed.len();
+        compressed.push(0);
+        for bit in 0..8 {
+            if position >= chunk.len() {
+                break;
+            }
+            if let Some((offset, length)) = longest_match(chunk, position) {
+                let bit_count = copy_token_bit_count(position);
+                // Truncation Ok as the offset and length have been limited to fit in the token
+                #[allow(clippy::cast_possible_truncation)]
+                let token = (((offset - 1) << (16 - bit_count)) | (length - 3)) as u16;
+                compressed.extend(token.to_le_bytes());
+                compressed[flags_position] |= 1 << bit;
+                position += length;
+            } else {
+                compressed.push(chunk[position]);
+                position += 1;
+            }
+        }
+    }
+}
+
+/// The offset and length of the longest run of earlier bytes in the chunk that match those at the
+/// position, if there is one long enough to be worth a copy token
+fn longest_match(chunk: &[u8], position: usize) -> Option<(usize, usize)> {
+    let maximum = (0xffff >> copy_token_bit_count(position)) + 3;
+    let mut best: Option<(usize, usize)> = None;
+    for candidate in (0..position).rev() {
+        let length = chunk[position..]
+            .iter()
+            .zip(&chunk[candidate..])
+            .take(maximum)
+            .take_while(|(a, b)| a == b)
+            .count();
+        if length >= 3 && best.is_none_or(|(_, l)| length > l) {
+            best = Some((position - candidate, length));
+            if length == maximum {
+                break;
+            }
+        }
+    }
+    best
+}
+
+/// Split a copy token into its offset and length. The number of bits used for each depends on how
+/// far through the current chunk we are
+fn unpack_copy_token(token: u16, difference: usize) -> (usize, usize) {
+    let bit_count = copy_token_bit_count(difference);
+    let length_mask = 0xffff >> bit_count;
+    let offset_mask = !length_mask;
+    let length = usize::from(token & length_mask) + 3;
+    let offset = usize::from((token & offset_mask) >> (16 - bit_count)) + 1;
+    (offset, length)
+}
+
+/// The number of bits of a copy token used for the offset: the smallest number of bits that can
+/// hold the difference, but never less than 4
+fn copy_token_bit_count(difference: usize) -> u32 {
+    let bits = usize::BITS - difference.saturating_sub(1).leading_zeros();
+    bits.max(4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bad_signature() {
+        assert_eq!(
+            Err(error::Compression::Signature(0x02)),
+            decompress([0x02, 0x19, 0xb0, 0x00])
+        );
+        assert_eq!(Err(error::Compression::Truncated), decompress([]));
+    }
+
+    #[test]
+    fn bad_chunk_signature() {
+        assert_eq!(
+            Err(error::Compression::ChunkSignature(0x8019)),
+            decompress([0x01, 0x19, 0x80, 0x00, 0x61])
+        );
+    }
+
+    #[test]
+    fn literals_only() {
+        // Example from section 3.2.1 of the spec
+        let compressed = [
+            0x01, 0x19, 0xb0, 0x00, 0x61, 0x62, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x00, 0x69,
+            0x6a, 0x6b, 0x6c, 0x6d, 0x6e, 0x6f, 0x70, 0x00, 0x71, 0x72, 0x73, 0x74, 0x75, 0x76,
+            0x2e,
+        ];
+        assert_eq!(
+            b"abcdefghijklmnopqrstuv.".to_vec(),
+            decompress(compressed).unwrap()
+        );
+    }
+
+    #[test]
+    fn with_copy_tokens() {
+        // Example from section 3.2.2 of the spec
+        let compressed = [
+            0x01, 0x2f, 0xb0, 0x00, 0x23, 0x61, 0x61, 0x61, 0x62, 0x63, 0x64, 0x65, 0x82, 0x66,
+            0x00, 0x70, 0x61, 0x67, 0x68, 0x69, 0x6a, 0x01, 0x38, 0x08, 0x61, 0x6b, 0x6c, 0x00,
+            0x30, 0x6d, 0x6e, 0x6f, 0x70, 0x06, 0x71, 0x02, 0x70, 0x04, 0x10, 0x72, 0x73, 0x74,
+            0x75, 0x76, 0x10, 0x77, 0x78, 0x79, 0x7a, 0x00, 0x3c,
+        ];
+        assert_eq!(
+            b"#aaabcdefaaaaghijaaaaaklaaamnopqaaaaaaaaaaaarstuvwxyzaaa".to_vec(),
+            decompress(compressed).unwrap()
+        );
+    }
+
+    #[test]
+    fn bad_copy_token() {
+        // A copy token as the very first token has nothing to copy from
+        assert_eq!(
+            Err(error::Compression::CopyTokenOffset(1, 0)),
+            decompress([0x01, 0x02, 0xb0, 0x01, 0x00, 0x00])
+        );
+    }
+
+    #[test]
+    fn overlapping_copy() {
+        // A copy token can reach back less far than it copies, repeating the bytes it has just
+        // written: one literal "a" followed by a token copying 9 bytes from 1 byte back
+        assert_eq!(
+            b"aaaaaaaaaa".to_vec(),
+            decompress([0x01, 0x03, 0xb0, 0x02, 0x61, 0x06, 0x00]).unwrap()
+        );
+    }
+
+    #[test]
+    fn raw_then_compressed_chunks() {
+        // A chunk stored as it is always holds 4096 bytes, and is followed by a compressed chunk
+        let mut compressed = vec![0x01, 0xff, 0x3f];
+        compressed.extend((0..CHUNK_SIZE).map(|i| u8::try_from(i % 251).unwrap()));
+        compressed.extend([0x03, 0xb0, 0x02, 0x61, 0x06, 0x00]);
+        let decompressed = decompress(&compressed).unwrap();
+        assert_eq!(CHUNK_SIZE + 10, decompressed.len());
+        assert_eq!(&compressed[3..CHUNK_SIZE + 3], &decompressed[..CHUNK_SIZE]);
+        assert_eq!(b"aaaaaaaaaa", &decompressed[CHUNK_SIZE..]);
+    }
+
+    #[test]
+    fn copy_across_chunks() {
+        // Copy tokens cannot reach back into the chunk before
+        let mut compressed = vec![0x01, 0xff, 0x3f];
+        compressed.extend([0x61; CHUNK_SIZE]);
+        compressed.extend([0x02, 0xb0, 0x01, 0x00, 0x00]);
+        assert_eq!(
+            Err(error::Compression::CopyTokenOffset(1, 0)),
+            decompress(compressed)
+        );
+    }
+
+    #[test]
+    fn truncated() {
+        // Part way through a chunk header
+        assert_eq!(Err(error::Compression::Truncated), decompress([0x01, 0x03]));
+        // Part way through a copy token
+        assert_eq!(
+            Err(error::Compression::Truncated),
+            decompress([0x01, 0x03, 0xb0, 0x02, 0x61, 0x06])
+        );
+    }
+
+    #[test]
+    fn dir_stream() {
+        // The dir stream of a real file starts with a PROJECTSYSKIND record
+        use std::io::Read;
+        let mut file = cfb::open("tests/data/xls/Unlocked_with_macro.xls").unwrap();
+        let mut compressed = Vec::new();
+        file.open_stream(crate::consts::CFB_DIR_PATH)
+            .unwrap()
+            .read_to_end(&mut compressed)
+            .unwrap();
+        let dir = decompress(&compressed).unwrap();
+        assert!(dir.len() > compressed.len());
+        assert_eq!([0x01, 0x00, 0x04, 0x00, 0x00, 0x00], dir[..6]);
+    }
+
+    #[test]
+    fn copy_tokens() {
+        // Near the start of a chunk, 4 bits are for the offset and 12 for the length
+        assert_eq!((1, 9), unpack_copy_token(0x0006, 1));
+        assert_eq!((16, 4098), unpack_copy_token(0xffff, 16));
+        // Further in, more bits are needed for the offset
+        assert_eq!((17, 3), unpack_copy_token(0x8000, 17));
+        assert_eq!((4096, 3), unpack_copy_token(0xfff0, 4096));
+    }
+
+    #[test]
+    fn compress_spec_examples() {
+        // Examples from sections 3.2.1 and 3.2.2 of the spec
+        let compressed = [
+            0x01, 0x19, 0xb0, 0x00, 0x61, 0x62, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x00, 0x69,
+            0x6a, 0x6b, 0x6c, 0x6d, 0x6e, 0x6f, 0x70, 0x00, 0x71, 0x72, 0x73, 0x74, 0x75, 0x76,
+            0x2e,
+        ];
+        assert_eq!(compressed.to_vec(), compress(b"abcdefghijklmnopqrstuv."));
+        let compressed = [
+            0x01, 0x2f, 0xb0, 0x00, 0x23, 0x61, 0x61, 0x61, 0x62, 0x63, 0x64, 0x65, 0x82, 0x66,
+            0x00, 0x70, 0x61, 0x67, 0x68, 0x69, 0x6a, 0x01, 0x38, 0x08, 0x61, 0x6b, 0x6c, 0x00,
+            0x30, 0x6d, 0x6e, 0x6f, 0x70, 0x06, 0x71, 0x02, 0x70, 0x04, 0x10, 0x72, 0x73, 0x74,
+            0x75, 0x76, 0x10, 0x77, 0x78, 0x79, 0x7a, 0x00, 0x3c,
+        ];
+        // Office picks a different one of the equally long matches for some copy tokens, which
+        // decompresses just the same
+        let data = b"#aaabcdefaaaaghijaaaaaklaaamnopqaaaaaaaaaaaarstuvwxyzaaa";
+        assert_eq!(compressed.len(), compress(data).len());
+        assert_eq!(data.to_vec(), decompress(compress(data)).unwrap());
+        assert_eq!(vec![0x01], compress([]));
+    }
+
+    #[test]
+    fn compress_round_trip() {
+        // Runs long enough to need several chunks and the longest copy tokens
+        let mut data = b"Attribute VB_Name = \"Module1\"\r\n".repeat(300);
+        data.extend([b' '; 5000]);
+        let compressed = compress(&data);
+        assert!(compressed.len() < data.len() / 10);
+        assert_eq!(data, decompress(compressed).unwrap());
+    }
+
+    #[test]
+    fn compress_raw_chunks() {
+        // Bytes that never repeat are stored as they are, and a short last chunk is padded out
+        // with zeros that come back when decompressed
+        let mut state = 1_u32;
+        let data: Vec<u8> = (0..CHUNK_SIZE + 4000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                state.to_be_bytes()[1]
+            })
+            .collect();
+        let compressed = compress(&data);
+        assert_eq!(1 + 2 * (CHUNK_SIZE + 2), compressed.len());
+        assert_eq!([0xff, 0x3f], compressed[1..3]);
+        assert_eq!([0xff, 0x3f], compressed[CHUNK_SIZE + 3..CHUNK_SIZE + 5]);
+        let decompressed = decompress(compressed).unwrap();
+        assert_eq!(2 * CHUNK_SIZE, decompressed.len());
+        assert_eq!(data, decompressed[..data.len()]);
+        assert!(decompressed[data.len()..].iter().all(|&b| b == 0));
+
+        // A short last chunk that still fits once compressed is not padded
+        let short = &data[..CHUNK_SIZE + 1000];
+        let compressed = compress(short);
+        assert_eq!([0xff, 0x3f], compressed[1..3]);
+        assert_ne!([0xff, 0x3f], compressed[CHUNK_SIZE + 3..CHUNK_SIZE + 5]);
+        assert_eq!(short.to_vec(), decompress(compressed).unwrap());
+    }
+
+    #[test]
+    fn compress_dir_stream() {
+        // A real dir stream survives being compressed again
+        use std::io::Read;
+        let mut file = cfb::open("tests/data/xls/Unlocked_with_macro.xls").unwrap();
+        let mut compressed = Vec::new();
+        file.open_stream(crate::consts::CFB_DIR_PATH)
+            .unwrap()
+            .read_to_end(&mut compressed)
+            .unwrap();
+        let dir = decompress(&compressed).unwrap();
+        assert_eq!(dir, decompress(compress(&dir)).unwrap());
+    }
+
+    #[test]
+    fn bit_count() {
+        assert_eq!(4, copy_token_bit_count(1));
+        assert_eq!(4, copy_token_bit_count(16));
+        assert_eq!(5, copy_token_bit_count(17));
+        assert_eq!(12, copy_token_bit_count(4096));
+    }
+}
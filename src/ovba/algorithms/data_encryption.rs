@@ -72,7 +72,6 @@ pub fn decode<D: AsRef<[u8]>>(encrypted_data: D) -> Result<Vec<u8>, error::DataE
     Ok(data)
 }
 
-#[allow(dead_code)]
 /// Apply VBA encryption algorithm to a slice of bytes of data
 ///
 /// # Reference
@@ -8,9 +8,8 @@ use crate::error;
 
 pub type Salt = [u8; 4];
 pub type Hash = [u8; 20];
-// TODO: Should actually take an array of bytes that are MBCS characters encoded using the
-// code page specified by PROJECTCODEPAGE
-type Password<'a> = &'a str;
+/// The password as MBCS characters, encoded in the code page specified by PROJECTCODEPAGE
+type Password<'a> = &'a [u8];
 
 /// Retrieve the hash and salt from the VBA format for storing hashed passwords
 ///
@@ -83,8 +82,7 @@ pub fn decode<D: AsRef<[u8]>>(data: D) -> Result<(Salt, Hash), error::PasswordHa
 ///
 /// Will error if:
 /// - The salt is not 4 bytes long
-#[allow(dead_code)]
-fn encode<S: AsRef<[u8]>>(salt: S, hash: Hash) -> Result<Vec<u8>, error::PasswordHashEncode> {
+pub fn encode<S: AsRef<[u8]>>(salt: S, hash: Hash) -> Result<Vec<u8>, error::PasswordHashEncode> {
     if salt.as_ref().len() != 4 {
         return Err(error::PasswordHashEncode::SaltLength(salt.as_ref().len()));
     }
@@ -143,59 +141,23 @@ fn encode<S: AsRef<[u8]>>(salt: S, hash: Hash) -> Result<Vec<u8>, error::Passwor
     Ok(output)
 }
 
-/// Generate an SHA1 hash of the given password, expressed in bytes, plus the 4 random bytes of the
-/// salt appended to it.
+/// Generate an SHA1 hash of the bytes of the given password, plus the 4 random bytes of the salt
+/// appended to it.
 ///
 /// Outputs a fixed 20 byte array
-#[allow(dead_code)]
 fn generate_hash<S: AsRef<[u8]>>(password: Password, salt: S) -> Hash {
     let mut hasher = Sha1::new();
-    let mut salted: Vec<u8> = password.as_bytes().to_owned();
+    let mut salted: Vec<u8> = password.to_owned();
     salted.extend_from_slice(salt.as_ref());
     hasher.update(salted);
     hasher.finalize().into()
 }
 
-/// Hashes the supplied password with the salt & then encodes it for storage in the VBA project
-///
-/// A separate function from `encode_password` to allow encoding from a deterministic salt value
-#[allow(dead_code)]
-fn encode_password_with_salt<S: AsRef<[u8]>>(
-    password: Password,
-    salt: S,
-) -> Result<Vec<u8>, error::PasswordHashEncode> {
-    let hash = generate_hash(password, &salt);
-    encode(salt, hash)
-}
-
-/// Hashes the password with a random salt, and then encodes for storing in the VBA file
-#[allow(dead_code)]
-pub fn encode_password(password: Password) -> Vec<u8> {
+/// Hashes the password with a random salt, returning the salt & hash ready to be encoded
+pub fn hash_password(password: Password) -> (Salt, Hash) {
     let mut rng = rand::thread_rng();
     let salt = [rng.gen(), rng.gen(), rng.gen(), rng.gen()];
-    encode_password_with_salt(password, salt).expect("the salt is 4 bytes long")
-}
-
-/// Determine if a password matches a salt + hash combination
-///
-/// Separate to `password_match` as it saves the step of decoding, which is useful where this has
-/// already taken place or where it is intended to run this multiple times. In the latter case we
-/// will want to decode once and the cache the salt and hash
-#[allow(dead_code)]
-pub fn password_match_hash(test: Password, salt: Salt, hash: Hash) -> bool {
-    generate_hash(test, salt) == hash
-}
-
-/// Determine if a password matches the encoded password
-///
-/// Returns an error if the encoded password cannot be decoded, see `decode`
-#[allow(dead_code)]
-pub fn password_match<D: AsRef<[u8]>>(
-    test: Password,
-    encoded_password: D,
-) -> Result<bool, error::PasswordHash> {
-    let (salt, hash) = decode(encoded_password)?;
-    Ok(password_match_hash(test, salt, hash))
+    (salt, generate_hash(password, salt))
 }
 
 #[cfg(test)]
@@ -398,23 +360,22 @@ mod tests {
     }
 
     #[test]
-    fn encoded_password_matches() {
-        let password = "CorrectHorseBatteryStaple";
-        let enc = encode_password(password);
-        assert!(password_match(password, enc).unwrap());
-        let password = "P@ssw0rd";
-        let enc = encode_password(password);
-        assert!(password_match(password, enc).unwrap());
+    fn hashed_password_matches() {
+        for password in [&b"CorrectHorseBatteryStaple"[..], b"P@ssw0rd"] {
+            let (salt, hash) = hash_password(password);
+            let (ds, dh) = decode(encode(salt, hash).unwrap()).unwrap();
+            assert_eq!(salt, ds);
+            assert_eq!(generate_hash(password, ds), dh);
+        }
     }
 
     #[test]
-    fn encoded_password_matches_no_random() {
+    fn hash_no_random() {
         let salt = [0x4a, 0x4d, 0x2a, 0x15];
-        let password = "CorrectHorseBatteryStaple";
-        let enc = encode_password_with_salt(password, salt).unwrap();
-        assert!(password_match(password, enc).unwrap());
-        let password = "P@ssw0rd";
-        let enc = encode_password_with_salt(password, salt).unwrap();
-        assert!(password_match(password, enc).unwrap());
+        let hash = [
+            0x99, 0xd3, 0x6d, 0x12, 0xd3, 0x6b, 0x1d, 0x01, 0x00, 0x38, 0x4d, 0x89, 0x2a, 0xa0,
+            0x1c, 0x4b, 0x43, 0x43, 0xd4, 0x21,
+        ];
+        assert_eq!(hash, generate_hash(b"P@ssw0rd", salt));
     }
 }
@@ -1 +1,6 @@
+pub mod designer;
+pub mod dir;
+pub mod module;
 pub mod project;
+pub mod project_wm;
+pub mod vba_project;
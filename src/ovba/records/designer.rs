@@ -0,0 +1,223 @@
+#![allow(clippy::doc_markdown)]
+//! A Struct to hold what is read of the designer storage of a user form
+//!
+//! Each designer module, such as a user form, has a storage of its own beside the PROJECT stream,
+//! named after the stream of the module. The storage holds:
+//! - The \x03VBFrame stream: the properties of the form itself as text, in the code page of the
+//!   project. This is the header the VBE writes at the top of an exported '.frm' file
+//! - The f stream: the FormControl, which lists the sites holding the controls of the form
+//! - The o stream: the properties of each of the controls
+//!
+//! Only enough of the f stream is read to count the controls. After a four byte header, giving
+//! the size of the data blocks, come any fonts and pictures of the form, then the class table
+//! unless the form was saved without one, and then the number of sites.
+//!
+//! The f stream is given in section 2.2.10 of the MS-OFORMS specification
+
+use crate::ovba::types::{mbcs, number::Integer};
+
+/// The GUID of a StdFont, as it is written in the stream
+const STD_FONT: [u8; 16] = [
+    0x03, 0x52, 0xe3, 0x0b, 0x91, 0x8f, 0xce, 0x11, 0x9d, 0xe3, 0x00, 0xaa, 0x00, 0x4b, 0xb8, 0x51,
+];
+
+/// The GUID of a TextProps, as it is written in the stream
+const TEXT_PROPS: [u8; 16] = [
+    0x20, 0x09, 0xc2, 0xaf, 0x4e, 0xda, 0xce, 0x11, 0xb9, 0x43, 0x00, 0xaa, 0x00, 0x68, 0x87, 0xb4,
+];
+
+// The bits of the property mask of the form that say which properties have been saved
+const BACK_COLOR: u32 = 1 << 1;
+const FORE_COLOR: u32 = 1 << 2;
+const NEXT_AVAILABLE_ID: u32 = 1 << 3;
+const BOOLEAN_PROPERTIES: u32 = 1 << 6;
+const MOUSE_ICON: u32 = 1 << 15;
+const FONT: u32 = 1 << 20;
+const PICTURE: u32 = 1 << 21;
+
+/// The flag of the boolean properties that is set when the form is saved without a class table
+const DONT_SAVE_CLASS_TABLE: u32 = 1 << 15;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Designer {
+    frame: Vec<u8>,
+    caption: Option<String>,
+    controls: Option<u32>,
+}
+
+impl Designer {
+    /// Read the designer from its \x03VBFrame stream, and its f stream if there is one. The text of
+    /// the frame is decoded in the code page given
+    pub fn from_bytes(frame: &[u8], form: Option<&[u8]>, code_page: u16) -> Self {
+        Self {
+            frame: frame.to_vec(),
+            caption: caption(&mbcs::decode(frame, code_page)),
+            controls: form.and_then(site_count),
+        }
+    }
+
+    /// The properties of the form, as text in the code page of the project
+    pub fn frame(&self) -> &[u8] {
+        &self.frame
+    }
+
+    /// The caption shown in the title bar of the form, if it has been set
+    pub fn caption(&self) -> Option<&str> {
+        self.caption.as_deref()
+    }
+
+    /// The number of controls on the form, if the f stream could be read
+    pub const fn controls(&self) -> Option<u32> {
+        self.controls
+    }
+}
+
+/// The caption of the form, from the Caption line of its properties e.g. `Caption = "Hello"`
+fn caption(frame: &str) -> Option<String> {
+    frame.lines().find_map(|line| {
+        let (name, value) = line.split_once('=')?;
+        if name.trim() != "Caption" {
+            return None;
+        }
+        let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+        Some(value.replace("\"\"", "\""))
+    })
+}
+
+/// The number of sites, i.e. controls, recorded in the f stream of a form
+fn site_count(form: &[u8]) -> Option<u32> {
+    let mut reader = Reader { buf: form, pos: 0 };
+    // Minor version 0, major version 4
+    if reader.take(2)? != [0x00, 0x04] {
+        return None;
+    }
+    let data_size = usize::from(reader.u16()?);
+    let data_end = reader.pos + data_size;
+    let mask = reader.u32()?;
+
+    // The boolean properties come straight after any colours and the next ID
+    let skipped = [BACK_COLOR, FORE_COLOR, NEXT_AVAILABLE_ID]
+        .iter()
+        .filter(|&&bit| mask & bit != 0)
+        .count();
+    reader.take(skipped * 4)?;
+    let class_table = mask & BOOLEAN_PROPERTIES == 0 || reader.u32()? & DONT_SAVE_CLASS_TABLE == 0;
+    reader.pos = data_end;
+
+    if mask & MOUSE_ICON != 0 {
+        reader.picture()?;
+    }
+    if mask & FONT != 0 {
+        reader.font()?;
+    }
+    if mask & PICTURE != 0 {
+        reader.picture()?;
+    }
+    if class_table {
+        for _ in 0..reader.u16()? {
+            // Version, then the size of the class information
+            reader.take(2)?;
+            let size = usize::from(reader.u16()?);
+            reader.take(size)?;
+        }
+    }
+    reader.u32()
+}
+
+/// Reads the little endian values of the f stream in turn
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.buf.get(self.pos..self.pos + len)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(u16::from_le_slice)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4).map(u32::from_le_slice)
+    }
+
+    /// Skip a GuidAndPicture: the GUID, a preamble, then the size of the picture and the picture
+    fn picture(&mut self) -> Option<()> {
+        self.take(20)?;
+        let size = usize::try_from(self.u32()?).ok()?;
+        self.take(size).map(|_| ())
+    }
+
+    /// Skip a GuidAndFont, which is either a StdFont or a TextProps
+    fn font(&mut self) -> Option<()> {
+        match self.take(16)? {
+            guid if guid == STD_FONT => {
+                // Version, character set, flags, weight and height, then the name of the face
+                self.take(10)?;
+                let face = usize::from(*self.take(1)?.first()?);
+                self.take(face).map(|_| ())
+            }
+            guid if guid == TEXT_PROPS => {
+                self.take(2)?;
+                let size = usize::from(self.u16()?);
+                self.take(size).map(|_| ())
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: &[u8] = b"VERSION 5.00\r\nBegin {C62A69F0-16DC-11CE-9E98-00AA00574A4F} UserForm1 \r\n   Caption         =   \"Say \"\"Hi\"\"\"\r\n   ClientHeight    =   3015\r\nEnd\r\n";
+
+    /// An f stream with a font, a class table of one class and the number of sites given
+    fn form(sites: u32) -> Vec<u8> {
+        let mut form = vec![0x00, 0x04, 0x08, 0x00];
+        form.extend((BOOLEAN_PROPERTIES | FONT).to_le_bytes());
+        form.extend(0_u32.to_le_bytes());
+        form.extend(STD_FONT);
+        form.extend([
+            0x01, 0x00, 0x00, 0x00, 0x90, 0x01, 0x00, 0x00, 0x00, 0x00, 0x06,
+        ]);
+        form.extend(b"Tahoma");
+        form.extend([0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0xaa, 0xbb]);
+        form.extend(sites.to_le_bytes());
+        form.extend(0_u32.to_le_bytes());
+        form
+    }
+
+    #[test]
+    fn designer() {
+        let form = form(3);
+        let designer = Designer::from_bytes(FRAME, Some(&form), 1252);
+        assert_eq!(Some("Say \"Hi\""), designer.caption());
+        assert_eq!(Some(3), designer.controls());
+        assert_eq!(FRAME, designer.frame());
+    }
+
+    #[test]
+    fn no_class_table() {
+        let mut form = vec![0x00, 0x04, 0x08, 0x00];
+        form.extend(BOOLEAN_PROPERTIES.to_le_bytes());
+        form.extend(DONT_SAVE_CLASS_TABLE.to_le_bytes());
+        form.extend(5_u32.to_le_bytes());
+        assert_eq!(Some(5), site_count(&form));
+    }
+
+    #[test]
+    fn unreadable_form() {
+        let form = form(3);
+        assert_eq!(None, site_count(&form[..20]));
+        assert_eq!(None, site_count(&[0x00, 0x03, 0x00, 0x00]));
+        let designer = Designer::from_bytes(b"VERSION 5.00\r\n", None, 1252);
+        assert_eq!(None, designer.caption());
+        assert_eq!(None, designer.controls());
+    }
+}
@@ -0,0 +1,1276 @@
+// Every record of the stream is kept, but not every one of them is read by the crate yet
+#![allow(clippy::doc_markdown, dead_code)]
+//! A Struct to hold the contents of the dir stream
+//!
+//! The dir stream specifies the information, references and modules of the VBA project. It is
+//! stored in a compressed container and, once decompressed, is an array of binary records.
+//!
+//! The PROJECT stream lists the modules too, along with the name of the project, so the two can
+//! be checked against each other with [`Dir::mismatches`]
+//!
+//! The parser reads past sizes and reserved values that it does not need. Those are only checked,
+//! along with the other rules the specification says each record MUST follow, by
+//! [`Dir::violations`]
+//!
+//! A record holding a single integer that is not the size it should be is read from its first
+//! bytes, with a warning kept, unless the options are strict
+//!
+//! dir = InformationRecord
+//!       ReferencesRecord
+//!       ModulesRecord
+//!       Terminator
+//!
+//! Specification can be found [here](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+
+use crate::{
+    error,
+    lenient::{Options, Warnings},
+    ovba::{
+        algorithms::compression,
+        records::project::{ModuleType, Project},
+        types::{guid, libid, locale::Locale, mbcs, number::Integer},
+    },
+};
+use cfb::Stream;
+use nom::Finish;
+use std::io::Read;
+use tracing::debug;
+
+#[derive(Debug)]
+pub struct Dir {
+    information: Information,
+    references: Vec<Reference>,
+    cookie: u16,
+    modules: Vec<Module>,
+    warnings: Vec<String>,
+}
+
+// String Types
+// An array of bytes, encoded in the code page of the project
+type MbcsString = Vec<u8>;
+// An array of bytes, UTF-16 encoded
+type UnicodeString = String;
+
+#[derive(Debug)]
+pub struct Information {
+    sys_kind: SysKind,
+    compat_version: Option<u32>,
+    locale: Locale,
+    lcid_invoke: u32,
+    name: MbcsString,
+    doc_string: MbcsString,
+    doc_string_unicode: UnicodeString,
+    help_file_1: MbcsString,
+    help_file_2: MbcsString,
+    help_context: u32,
+    lib_flags: u32,
+    version_major: u32,
+    version_minor: u16,
+    constants: MbcsString,
+    constants_unicode: UnicodeString,
+}
+
+/// The platform the VBA project was last saved on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SysKind {
+    Win16,
+    Win32,
+    Mac,
+    Win64,
+}
+
+/// The kinds of reference a VBA project can have to another library or project
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceKind {
+    /// A type library registered on the machine, such as "stdole"
+    Registered,
+    /// Another VBA project, such as an add-in
+    Project,
+    /// The type library of an ActiveX control, extended for the project
+    Control,
+}
+
+#[derive(Debug)]
+pub struct Reference {
+    name: Option<ReferenceName>,
+    record: ReferenceRecord,
+    code_page: u16,
+}
+
+#[derive(Debug)]
+struct ReferenceName {
+    name: MbcsString,
+    name_unicode: UnicodeString,
+}
+
+#[derive(Debug)]
+enum ReferenceRecord {
+    Control(ReferenceControl),
+    Registered {
+        libid: MbcsString,
+    },
+    Project {
+        libid_absolute: MbcsString,
+        libid_relative: MbcsString,
+        major_version: u32,
+        minor_version: u16,
+    },
+}
+
+#[derive(Debug)]
+struct ReferenceControl {
+    libid_original: Option<MbcsString>,
+    libid_twiddled: MbcsString,
+    name_extended: Option<ReferenceName>,
+    libid_extended: MbcsString,
+    original_type_lib: guid::Guid,
+    cookie: u32,
+}
+
+#[derive(Debug)]
+pub struct Module {
+    name: MbcsString,
+    name_unicode: Option<UnicodeString>,
+    stream_name: MbcsString,
+    stream_name_unicode: UnicodeString,
+    doc_string: MbcsString,
+    doc_string_unicode: UnicodeString,
+    text_offset: u32,
+    help_context: u32,
+    cookie: u16,
+    procedural: bool,
+    read_only: bool,
+    private: bool,
+    code_page: u16,
+}
+
+impl Dir {
+    pub fn from_stream<T: std::io::Read + std::io::Seek>(
+        mut stream: Stream<T>,
+        options: &Options,
+    ) -> Result<Self, error::DirStructure> {
+        let mut buf = Vec::new();
+        let _ = stream.read_to_end(&mut buf);
+        Self::from_bytes(&buf, options)
+    }
+
+    pub fn from_bytes(compressed: &[u8], options: &Options) -> Result<Self, error::DirStructure> {
+        let buf = compression::decompress(compressed)?;
+        debug!(
+            "decompressed {} bytes of the dir stream into {} bytes",
+            compressed.len(),
+            buf.len()
+        );
+
+        let warnings = Warnings::new(options);
+        let (_res, mut d) = nom_parse::dir(&warnings)(&buf).finish().map_err(|e| {
+            let offset = buf.len() - e.input.len();
+            debug!(
+                offset,
+                parser = ?e.code,
+                "failed to parse the dir stream at record 0x{:04x}",
+                e.input.get(..2).map_or(0, u16::from_le_slice)
+            );
+            error::DirStructure::NomParseError(offset, buf.len())
+        })?;
+        debug!("parsed {} modules from the dir stream", d.modules.len());
+        d.warnings = warnings.into_messages();
+
+        Ok(d)
+    }
+
+    /// Every way in which the records of a dir stream break the rules of the specification: the
+    /// records being out of order, records of the wrong size, or reserved values that are not
+    /// what they MUST be. The records are not checked beyond the first one out of place
+    pub fn violations(compressed: &[u8]) -> Vec<String> {
+        match compression::decompress(compressed) {
+            Ok(buf) => rules::check(&buf),
+            Err(e) => vec![e.to_string()],
+        }
+    }
+
+    pub const fn information(&self) -> &Information {
+        &self.information
+    }
+
+    pub fn references(&self) -> &[Reference] {
+        &self.references
+    }
+
+    pub fn modules(&self) -> &[Module] {
+        &self.modules
+    }
+
+    /// The minor deviations from the specification that were read past
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// The ways in which the dir stream does not agree with the PROJECT stream: a different
+    /// project name, a module listed in one and not the other, or a module the PROJECT stream
+    /// says is procedural that the dir stream does not, or the other way round
+    pub fn mismatches(&self, project: &Project) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        let name = self.information.name();
+        if name != project.name() {
+            mismatches.push(format!(
+                "the project is named {name} in the dir stream but {} in the PROJECT stream",
+                project.name()
+            ));
+        }
+        for module in &self.modules {
+            let name = module.name();
+            match project.module_type(&name) {
+                None => mismatches.push(format!(
+                    "module {name} is in the dir stream but not the PROJECT stream"
+                )),
+                Some(module_type) if (module_type == ModuleType::Standard) != module.procedural => {
+                    mismatches.push(format!(
+                        "module {name} is a {module_type:?} module in the PROJECT stream but {} \
+                         procedural in the dir stream",
+                        if module.procedural { "is" } else { "is not" }
+                    ));
+                }
+                Some(_) => {}
+            }
+        }
+        for (name, _) in project.modules() {
+            if !self.modules.iter().any(|m| m.name() == name) {
+                mismatches.push(format!(
+                    "module {name} is in the PROJECT stream but not the dir stream"
+                ));
+            }
+        }
+        mismatches
+    }
+}
+
+impl Information {
+    pub const fn sys_kind(&self) -> SysKind {
+        self.sys_kind
+    }
+
+    /// The version of VBA that last saved the project, if it was recorded
+    pub const fn compat_version(&self) -> Option<u32> {
+        self.compat_version
+    }
+
+    /// The language and code page of the project
+    pub const fn locale(&self) -> Locale {
+        self.locale
+    }
+
+    pub const fn lcid(&self) -> u32 {
+        self.locale.lcid()
+    }
+
+    /// The code page that the MBCS strings of the project are encoded in
+    pub const fn code_page(&self) -> u16 {
+        self.locale.code_page()
+    }
+
+    pub fn name(&self) -> String {
+        mbcs::decode(&self.name, self.code_page())
+    }
+
+    /// The description of the project, preferring the Unicode version where it has been recorded
+    pub fn doc_string(&self) -> String {
+        prefer_unicode(&self.doc_string_unicode, &self.doc_string, self.code_page())
+    }
+
+    pub fn help_file(&self) -> String {
+        mbcs::decode(&self.help_file_1, self.code_page())
+    }
+
+    pub const fn help_context(&self) -> u32 {
+        self.help_context
+    }
+
+    /// The major and minor version of the project
+    pub const fn version(&self) -> (u32, u16) {
+        (self.version_major, self.version_minor)
+    }
+
+    /// The conditional compilation arguments, preferring the Unicode version where it has been
+    /// recorded
+    pub fn constants(&self) -> String {
+        prefer_unicode(&self.constants_unicode, &self.constants, self.code_page())
+    }
+
+    /// The conditional compilation arguments, split into the name and value of each
+    pub fn constant_values(&self) -> Vec<(String, i16)> {
+        parse_constants(&self.constants())
+    }
+}
+
+/// Split conditional compilation arguments, e.g. `DEBUG = 1 : WIN = -1`, into the name and value
+/// of each. Any argument that is not a name and a whole number is left out
+fn parse_constants(text: &str) -> Vec<(String, i16)> {
+    text.split(':')
+        .filter_map(|constant| {
+            let (name, value) = constant.split_once('=')?;
+            let name = name.trim();
+            let value = value.trim().parse().ok()?;
+            (!name.is_empty()).then(|| (name.to_string(), value))
+        })
+        .collect()
+}
+
+impl std::fmt::Display for SysKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Win16 => "16-bit Windows",
+            Self::Win32 => "32-bit Windows",
+            Self::Mac => "Macintosh",
+            Self::Win64 => "64-bit Windows",
+        })
+    }
+}
+
+impl Reference {
+    /// The name of the reference, preferring the Unicode version where it has been recorded
+    pub fn name(&self) -> Option<String> {
+        self.name
+            .as_ref()
+            .map(|n| prefer_unicode(&n.name_unicode, &n.name, self.code_page))
+    }
+
+    pub const fn kind(&self) -> ReferenceKind {
+        match self.record {
+            ReferenceRecord::Control(_) => ReferenceKind::Control,
+            ReferenceRecord::Registered { .. } => ReferenceKind::Registered,
+            ReferenceRecord::Project { .. } => ReferenceKind::Project,
+        }
+    }
+
+    /// The identifier of the library referred to. For a control, this is the identifier of its
+    /// extended type library, and for a project, the absolute path to it
+    pub fn libid(&self) -> String {
+        let libid = match &self.record {
+            ReferenceRecord::Control(control) => &control.libid_extended,
+            ReferenceRecord::Registered { libid } => libid,
+            ReferenceRecord::Project { libid_absolute, .. } => libid_absolute,
+        };
+        mbcs::decode(libid, self.code_page)
+    }
+
+    /// The identifier of the library referred to, split into its parts
+    pub fn library(&self) -> libid::Libid {
+        libid::parse(&self.libid())
+    }
+}
+
+impl Module {
+    /// The name of the module, preferring the Unicode version where it has been recorded
+    pub fn name(&self) -> String {
+        self.name_unicode
+            .clone()
+            .unwrap_or_else(|| mbcs::decode(&self.name, self.code_page))
+    }
+
+    /// The name of the stream, within the VBA storage, that holds the module
+    pub fn stream_name(&self) -> String {
+        if self.stream_name_unicode.is_empty() {
+            mbcs::decode(&self.stream_name, self.code_page)
+        } else {
+            self.stream_name_unicode.clone()
+        }
+    }
+
+    /// The offset into the module stream at which the compressed source code starts
+    pub const fn text_offset(&self) -> usize {
+        self.text_offset as usize
+    }
+
+    /// Whether the module is a procedural (standard) module, rather than a document, class or
+    /// designer module
+    pub const fn is_procedural(&self) -> bool {
+        self.procedural
+    }
+
+    /// The description of the module, preferring the Unicode version where it has been recorded
+    pub fn doc_string(&self) -> String {
+        prefer_unicode(&self.doc_string_unicode, &self.doc_string, self.code_page)
+    }
+
+    /// Whether the module is read-only, from the MODULEREADONLY record
+    pub const fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Whether the module is only visible to the project it is in, from the MODULEPRIVATE record
+    pub const fn is_private(&self) -> bool {
+        self.private
+    }
+}
+
+/// The Unicode version of a string where it has been recorded, or the MBCS version if not
+fn prefer_unicode(unicode: &str, text: &[u8], code_page: u16) -> String {
+    if unicode.is_empty() {
+        mbcs::decode(text, code_page)
+    } else {
+        unicode.to_string()
+    }
+}
+
+mod nom_parse {
+    use super::{
+        Dir, Information, Locale, Module, Reference, ReferenceControl, ReferenceName,
+        ReferenceRecord, SysKind, UnicodeString,
+    };
+    use crate::lenient::Warnings;
+    use crate::ovba::types::{
+        guid,
+        number::{le_u16, le_u32, Integer},
+    };
+    use nom::{
+        branch::alt,
+        combinator::{map, map_opt, opt, value, verify},
+        error::{Error, ErrorKind},
+        multi::{count, length_data, many0},
+        sequence::{pair, preceded, terminated, tuple},
+        IResult,
+    };
+
+    pub(super) fn dir(warnings: &Warnings) -> impl Fn(&[u8]) -> IResult<&[u8], Dir> + '_ {
+        move |input: &[u8]| {
+            // The strings of the references and modules are decoded in the code page of the
+            // project
+            let (input, information) = information(warnings)(input)?;
+            let code_page = information.locale.code_page();
+            let (input, (references, (cookie, modules), _)) = tuple((
+                many0(reference(code_page)),
+                modules(warnings, code_page),
+                pair(id(0x0010), le_u32),
+            ))(input)?;
+            Ok((
+                input,
+                Dir {
+                    information,
+                    references,
+                    cookie,
+                    modules,
+                    warnings: Vec::new(),
+                },
+            ))
+        }
+    }
+
+    fn information(warnings: &Warnings) -> impl Fn(&[u8]) -> IResult<&[u8], Information> + '_ {
+        move |input: &[u8]| {
+            map(
+                tuple((
+                    sys_kind(warnings),
+                    opt(int_record(warnings, 0x004a)),
+                    locale(warnings),
+                    bytes_record(0x0004),
+                    pair(bytes_record(0x0005), unicode_record(0x0040)),
+                    pair(bytes_record(0x0006), bytes_record(0x003d)),
+                    int_record(warnings, 0x0007),
+                    int_record(warnings, 0x0008),
+                    version,
+                    pair(bytes_record(0x000c), unicode_record(0x003c)),
+                )),
+                |(
+                    sys_kind,
+                    compat_version,
+                    (locale, lcid_invoke),
+                    name,
+                    (doc_string, doc_string_unicode),
+                    (help_file_1, help_file_2),
+                    help_context,
+                    lib_flags,
+                    (version_major, version_minor),
+                    (constants, constants_unicode),
+                )| Information {
+                    sys_kind,
+                    compat_version,
+                    locale,
+                    lcid_invoke,
+                    name,
+                    doc_string,
+                    doc_string_unicode,
+                    help_file_1,
+                    help_file_2,
+                    help_context,
+                    lib_flags,
+                    version_major,
+                    version_minor,
+                    constants,
+                    constants_unicode,
+                },
+            )(input)
+        }
+    }
+
+    /// The PROJECTLCID, PROJECTLCIDINVOKE and PROJECTCODEPAGE records, which come one after the
+    /// other
+    fn locale(warnings: &Warnings) -> impl Fn(&[u8]) -> IResult<&[u8], (Locale, u32)> + '_ {
+        move |input: &[u8]| {
+            map(
+                tuple((
+                    int_record(warnings, 0x0002),
+                    int_record(warnings, 0x0014),
+                    int_record(warnings, 0x0003),
+                )),
+                |(lcid, lcid_invoke, code_page)| (Locale::new(lcid, code_page), lcid_invoke),
+            )(input)
+        }
+    }
+
+    fn sys_kind(warnings: &Warnings) -> impl Fn(&[u8]) -> IResult<&[u8], SysKind> + '_ {
+        move |input: &[u8]| {
+            map_opt(int_record(warnings, 0x0001), |kind: u32| match kind {
+                0 => Some(SysKind::Win16),
+                1 => Some(SysKind::Win32),
+                2 => Some(SysKind::Mac),
+                3 => Some(SysKind::Win64),
+                _ => None,
+            })(input)
+        }
+    }
+
+    fn version(input: &[u8]) -> IResult<&[u8], (u32, u16)> {
+        preceded(pair(id(0x0009), le_u32), pair(le_u32, le_u16))(input)
+    }
+
+    fn reference(code_page: u16) -> impl Fn(&[u8]) -> IResult<&[u8], Reference> {
+        move |input: &[u8]| {
+            map(
+                pair(
+                    opt(reference_name),
+                    alt((reference_control, reference_registered, reference_project)),
+                ),
+                |(name, record)| Reference {
+                    name,
+                    record,
+                    code_page,
+                },
+            )(input)
+        }
+    }
+
+    fn reference_name(input: &[u8]) -> IResult<&[u8], ReferenceName> {
+        map(
+            pair(bytes_record(0x0016), unicode_record(0x003e)),
+            |(name, name_unicode)| ReferenceName { name, name_unicode },
+        )(input)
+    }
+
+    fn reference_control(input: &[u8]) -> IResult<&[u8], ReferenceRecord> {
+        map(
+            tuple((
+                opt(bytes_record(0x0033)),
+                preceded(pair(id(0x002f), le_u32), length_data(le_u32)),
+                pair(le_u32, le_u16),
+                opt(reference_name),
+                preceded(pair(id(0x0030), le_u32), length_data(le_u32)),
+                pair(le_u32, le_u16),
+                guid::parse_binary,
+                le_u32,
+            )),
+            |(
+                libid_original,
+                libid_twiddled,
+                _,
+                name_extended,
+                libid_extended,
+                _,
+                original_type_lib,
+                cookie,
+            )| {
+                ReferenceRecord::Control(ReferenceControl {
+                    libid_original,
+                    libid_twiddled: libid_twiddled.to_vec(),
+                    name_extended,
+                    libid_extended: libid_extended.to_vec(),
+                    original_type_lib,
+                    cookie,
+                })
+            },
+        )(input)
+    }
+
+    fn reference_registered(input: &[u8]) -> IResult<&[u8], ReferenceRecord> {
+        map(
+            terminated(
+                preceded(pair(id(0x000d), le_u32), length_data(le_u32)),
+                pair(le_u32, le_u16),
+            ),
+            |libid: &[u8]| ReferenceRecord::Registered {
+                libid: libid.to_vec(),
+            },
+        )(input)
+    }
+
+    fn reference_project(input: &[u8]) -> IResult<&[u8], ReferenceRecord> {
+        map(
+            preceded(
+                pair(id(0x000e), le_u32),
+                tuple((length_data(le_u32), length_data(le_u32), le_u32, le_u16)),
+            ),
+            |(libid_absolute, libid_relative, major_version, minor_version)| {
+                ReferenceRecord::Project {
+                    libid_absolute: libid_absolute.to_vec(),
+                    libid_relative: libid_relative.to_vec(),
+                    major_version,
+                    minor_version,
+                }
+            },
+        )(input)
+    }
+
+    fn modules(
+        warnings: &Warnings,
+        code_page: u16,
+    ) -> impl Fn(&[u8]) -> IResult<&[u8], (u16, Vec<Module>)> + '_ {
+        move |input: &[u8]| {
+            let (input, module_count) = int_record::<u16>(warnings, 0x000f)(input)?;
+            let (input, cookie) = int_record(warnings, 0x0013)(input)?;
+            let (input, modules) =
+                count(module(warnings, code_page), usize::from(module_count))(input)?;
+            Ok((input, (cookie, modules)))
+        }
+    }
+
+    fn module(
+        warnings: &Warnings,
+        code_page: u16,
+    ) -> impl Fn(&[u8]) -> IResult<&[u8], Module> + '_ {
+        move |input: &[u8]| module_records(input, warnings, code_page)
+    }
+
+    fn module_records<'a>(
+        input: &'a [u8],
+        warnings: &Warnings,
+        code_page: u16,
+    ) -> IResult<&'a [u8], Module> {
+        map(
+            tuple((
+                bytes_record(0x0019),
+                opt(unicode_record(0x0047)),
+                pair(bytes_record(0x001a), unicode_record(0x0032)),
+                pair(bytes_record(0x001c), unicode_record(0x0048)),
+                int_record(warnings, 0x0031),
+                int_record(warnings, 0x001e),
+                int_record(warnings, 0x002c),
+                terminated(
+                    alt((value(true, id(0x0021)), value(false, id(0x0022)))),
+                    le_u32,
+                ),
+                opt(pair(id(0x0025), le_u32)),
+                opt(pair(id(0x0028), le_u32)),
+                pair(id(0x002b), le_u32),
+            )),
+            |(
+                name,
+                name_unicode,
+                (stream_name, stream_name_unicode),
+                (doc_string, doc_string_unicode),
+                text_offset,
+                help_context,
+                cookie,
+                procedural,
+                read_only,
+                private,
+                _,
+            )| Module {
+                name,
+                name_unicode,
+                stream_name,
+                stream_name_unicode,
+                doc_string,
+                doc_string_unicode,
+                text_offset,
+                help_context,
+                cookie,
+                procedural,
+                read_only: read_only.is_some(),
+                private: private.is_some(),
+                code_page,
+            },
+        )(input)
+    }
+
+    /// The two byte identifier that starts every record
+    fn id(id: u16) -> impl Fn(&[u8]) -> IResult<&[u8], u16> {
+        move |input: &[u8]| verify(le_u16, |v| *v == id)(input)
+    }
+
+    /// A record holding a single integer, whose size MUST be that of the integer. A record of
+    /// another size is read from as many of its first bytes as the integer takes, with a warning,
+    /// unless the options are strict
+    fn int_record<T: Integer>(
+        warnings: &Warnings,
+        record_id: u16,
+    ) -> impl Fn(&[u8]) -> IResult<&[u8], T> + '_ {
+        move |input: &[u8]| {
+            let (rest, data) = preceded(id(record_id), length_data(le_u32))(input)?;
+            let value = if data.len() == T::BYTES {
+                Ok(T::from_le_slice(data))
+            } else {
+                Err(format!(
+                    "record 0x{record_id:04x} of the dir stream has a size of {}, but MUST be {}",
+                    data.len(),
+                    T::BYTES
+                ))
+            };
+            let value = warnings
+                .recover(value, || {
+                    Some(T::from_le_slice(&data[..data.len().min(T::BYTES)]))
+                })
+                .map_err(|_| nom::Err::Error(Error::new(input, ErrorKind::Verify)))?;
+            Ok((rest, value))
+        }
+    }
+
+    /// A record holding an array of bytes, prefixed with its length
+    fn bytes_record(record_id: u16) -> impl Fn(&[u8]) -> IResult<&[u8], Vec<u8>> {
+        move |input: &[u8]| map(preceded(id(record_id), length_data(le_u32)), <[u8]>::to_vec)(input)
+    }
+
+    /// A record holding a UTF-16 string, prefixed with its length in bytes
+    fn unicode_record(record_id: u16) -> impl Fn(&[u8]) -> IResult<&[u8], UnicodeString> {
+        move |input: &[u8]| {
+            map(
+                preceded(id(record_id), length_data(le_u32)),
+                |s: &[u8]| {
+                    let chars: Vec<u16> = s.chunks_exact(2).map(u16::from_le_slice).collect();
+                    String::from_utf16_lossy(&chars)
+                },
+            )(input)
+        }
+    }
+}
+
+/// The rules of section 2.3.4.2 of the MS-OVBA specification, checked against the decompressed
+/// records of a dir stream
+mod rules {
+    use crate::ovba::types::number::Integer;
+
+    /// The records that can start a reference
+    const REFERENCE_IDS: [u16; 5] = [0x0016, 0x0033, 0x002f, 0x000d, 0x000e];
+
+    /// The LCID that PROJECTLCID and PROJECTLCIDINVOKE MUST hold, for US English
+    const US_ENGLISH: u32 = 0x0409;
+
+    pub(super) fn check(buf: &[u8]) -> Vec<String> {
+        let mut records = Records::new(buf);
+        if records.dir().is_some() && records.pos < buf.len() {
+            records.problems.push(format!(
+                "there are 0x{:x} bytes after the dir stream Terminator",
+                buf.len() - records.pos
+            ));
+        }
+        records.problems
+    }
+
+    /// Walks the records of the stream in turn, noting every rule broken along the way. Each step
+    /// returns None once the records can no longer be followed
+    struct Records<'a> {
+        buf: &'a [u8],
+        pos: usize,
+        problems: Vec<String>,
+    }
+
+    impl<'a> Records<'a> {
+        fn dir(&mut self) -> Option<()> {
+            self.information()?;
+            while self.peek().is_some_and(|id| REFERENCE_IDS.contains(&id)) {
+                self.reference()?;
+            }
+            self.modules()?;
+            self.reserved("Terminator", 0x0010)
+        }
+
+        fn information(&mut self) -> Option<()> {
+            let sys_kind = self.u32_record("PROJECTSYSKIND", 0x0001)?;
+            if sys_kind > 3 {
+                self.problem(format!(
+                    "PROJECTSYSKIND is 0x{sys_kind:x}, but MUST be 0 to 3"
+                ));
+            }
+            if self.peek() == Some(0x004a) {
+                self.u32_record("PROJECTCOMPATVERSION", 0x004a)?;
+            }
+            for (name, id) in [("PROJECTLCID", 0x0002), ("PROJECTLCIDINVOKE", 0x0014)] {
+                let lcid = self.u32_record(name, id)?;
+                if lcid != US_ENGLISH {
+                    self.problem(format!("{name} is 0x{lcid:x}, but MUST be 0x409"));
+                }
+            }
+            self.u16_record("PROJECTCODEPAGE", 0x0003)?;
+            let name = self.record("PROJECTNAME", 0x0004)?;
+            if !(1..=128).contains(&name.len()) {
+                self.problem(format!(
+                    "PROJECTNAME is {} bytes long, but MUST be 1 to 128",
+                    name.len()
+                ));
+            }
+            self.text_records("PROJECTDOCSTRING", 0x0005, 0x0040, 2000)?;
+            let help_file_1 = self.limited_record("PROJECTHELPFILEPATH", 0x0006, 260)?;
+            let help_file_2 = self.record("PROJECTHELPFILEPATH", 0x003d)?;
+            if help_file_1 != help_file_2 {
+                self.problem("the two paths of PROJECTHELPFILEPATH MUST be the same".to_string());
+            }
+            self.u32_record("PROJECTHELPCONTEXT", 0x0007)?;
+            let lib_flags = self.u32_record("PROJECTLIBFLAGS", 0x0008)?;
+            if lib_flags != 0 {
+                self.problem(format!("PROJECTLIBFLAGS is 0x{lib_flags:x}, but MUST be 0"));
+            }
+            // PROJECTVERSION is the one record whose size does not give the length of its data
+            self.id("PROJECTVERSION", 0x0009)?;
+            let reserved = self.u32()?;
+            if reserved != 4 {
+                self.problem(format!(
+                    "the Reserved field of PROJECTVERSION is 0x{reserved:x}, but MUST be 4"
+                ));
+            }
+            self.take(6)?;
+            self.text_records("PROJECTCONSTANTS", 0x000c, 0x003c, 1015)
+        }
+
+        fn reference(&mut self) -> Option<()> {
+            if self.peek() == Some(0x0016) {
+                self.text_records("REFERENCENAME", 0x0016, 0x003e, usize::MAX)?;
+            }
+            match self.peek()? {
+                0x0033 => {
+                    self.record("REFERENCEORIGINAL", 0x0033)?;
+                    self.reference_control()
+                }
+                0x002f => self.reference_control(),
+                0x000d => {
+                    let data = self.record("REFERENCEREGISTERED", 0x000d)?;
+                    self.fields("REFERENCEREGISTERED", data, |fields| {
+                        fields.length_data()?;
+                        fields.reserved_pair("REFERENCEREGISTERED")
+                    });
+                    Some(())
+                }
+                _ => {
+                    let data = self.record("REFERENCEPROJECT", 0x000e)?;
+                    self.fields("REFERENCEPROJECT", data, |fields| {
+                        fields.length_data()?;
+                        fields.length_data()?;
+                        // The major and minor versions
+                        fields.take(6).map(|_| ())
+                    });
+                    Some(())
+                }
+            }
+        }
+
+        fn reference_control(&mut self) -> Option<()> {
+            let data = self.record("REFERENCECONTROL", 0x002f)?;
+            self.fields("REFERENCECONTROL", data, |fields| {
+                fields.length_data()?;
+                fields.reserved_pair("REFERENCECONTROL")
+            });
+            if self.peek() == Some(0x0016) {
+                self.text_records("REFERENCENAME", 0x0016, 0x003e, usize::MAX)?;
+            }
+            let data = self.record("REFERENCECONTROL", 0x0030)?;
+            self.fields("REFERENCECONTROL", data, |fields| {
+                fields.length_data()?;
+                fields.reserved_pair("REFERENCECONTROL")?;
+                // The original type library and the cookie
+                fields.take(20).map(|_| ())
+            });
+            Some(())
+        }
+
+        fn modules(&mut self) -> Option<()> {
+            let count = self.u16_record("PROJECTMODULES", 0x000f)?;
+            self.u16_record("PROJECTCOOKIE", 0x0013)?;
+            for _ in 0..count {
+                self.module()?;
+            }
+            Some(())
+        }
+
+        fn module(&mut self) -> Option<()> {
+            self.record("MODULENAME", 0x0019)?;
+            if self.peek() == Some(0x0047) {
+                self.unicode_record("MODULENAMEUNICODE", 0x0047)?;
+            }
+            self.text_records("MODULESTREAMNAME", 0x001a, 0x0032, usize::MAX)?;
+            self.text_records("MODULEDOCSTRING", 0x001c, 0x0048, usize::MAX)?;
+            self.u32_record("MODULEOFFSET", 0x0031)?;
+            self.u32_record("MODULEHELPCONTEXT", 0x001e)?;
+            self.u16_record("MODULECOOKIE", 0x002c)?;
+            if self.peek() == Some(0x0022) {
+                self.reserved("MODULETYPE", 0x0022)?;
+            } else {
+                self.reserved("MODULETYPE", 0x0021)?;
+            }
+            if self.peek() == Some(0x0025) {
+                self.reserved("MODULEREADONLY", 0x0025)?;
+            }
+            if self.peek() == Some(0x0028) {
+                self.reserved("MODULEPRIVATE", 0x0028)?;
+            }
+            self.reserved("MODULE Terminator", 0x002b)
+        }
+
+        const fn new(buf: &'a [u8]) -> Self {
+            Self {
+                buf,
+                pos: 0,
+                problems: Vec::new(),
+            }
+        }
+
+        fn problem(&mut self, problem: String) {
+            self.problems.push(problem);
+        }
+
+        fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+            let Some(bytes) = self.buf.get(self.pos..self.pos.checked_add(len)?) else {
+                self.problem(format!(
+                    "the dir stream ends at byte 0x{:x}, part way through a record",
+                    self.buf.len()
+                ));
+                return None;
+            };
+            self.pos += len;
+            Some(bytes)
+        }
+
+        fn u16(&mut self) -> Option<u16> {
+            self.take(2).map(u16::from_le_slice)
+        }
+
+        fn u32(&mut self) -> Option<u32> {
+            self.take(4).map(u32::from_le_slice)
+        }
+
+        /// The data of a field, after the 4 byte length of it
+        fn length_data(&mut self) -> Option<&'a [u8]> {
+            let len = usize::try_from(self.u32()?).ok()?;
+            self.take(len)
+        }
+
+        fn peek(&self) -> Option<u16> {
+            self.buf.get(self.pos..self.pos + 2).map(u16::from_le_slice)
+        }
+
+        /// The identifier of the next record, which MUST be the one expected
+        fn id(&mut self, name: &str, id: u16) -> Option<()> {
+            let pos = self.pos;
+            let found = self.u16()?;
+            if found == id {
+                return Some(());
+            }
+            self.problem(format!(
+                "expected {name} (0x{id:04x}) at byte 0x{pos:x}, but found record 0x{found:04x}"
+            ));
+            None
+        }
+
+        /// The data of a record, after its identifier and size
+        fn record(&mut self, name: &str, id: u16) -> Option<&'a [u8]> {
+            self.id(name, id)?;
+            self.length_data()
+        }
+
+        fn sized_record(&mut self, name: &str, id: u16, size: usize) -> Option<&'a [u8]> {
+            let data = self.record(name, id)?;
+            if data.len() != size {
+                self.problem(format!(
+                    "{name} has a size of {}, but MUST have a size of {size}",
+                    data.len()
+                ));
+            }
+            Some(data)
+        }
+
+        fn u16_record(&mut self, name: &str, id: u16) -> Option<u16> {
+            let data = self.sized_record(name, id, 2)?;
+            Some(data.get(..2).map_or(0, u16::from_le_slice))
+        }
+
+        fn u32_record(&mut self, name: &str, id: u16) -> Option<u32> {
+            let data = self.sized_record(name, id, 4)?;
+            Some(data.get(..4).map_or(0, u32::from_le_slice))
+        }
+
+        /// A record with no data, whose size is a reserved field that MUST be 0
+        fn reserved(&mut self, name: &str, id: u16) -> Option<()> {
+            self.id(name, id)?;
+            let reserved = self.u32()?;
+            if reserved != 0 {
+                self.problem(format!(
+                    "the Reserved field of {name} is 0x{reserved:x}, but MUST be 0"
+                ));
+            }
+            Some(())
+        }
+
+        fn limited_record(&mut self, name: &str, id: u16, max: usize) -> Option<&'a [u8]> {
+            let data = self.record(name, id)?;
+            if data.len() > max {
+                self.problem(format!(
+                    "{name} is {} bytes long, but MUST be no more than {max}",
+                    data.len()
+                ));
+            }
+            Some(data)
+        }
+
+        fn unicode_record(&mut self, name: &str, id: u16) -> Option<&'a [u8]> {
+            let data = self.record(name, id)?;
+            if data.len() % 2 != 0 {
+                self.problem(format!(
+                    "the Unicode text of {name} is {} bytes long, but MUST be an even number",
+                    data.len()
+                ));
+            }
+            Some(data)
+        }
+
+        /// A record of text in the code page of the project, followed by a record of the same
+        /// text in UTF-16
+        fn text_records(&mut self, name: &str, id: u16, unicode_id: u16, max: usize) -> Option<()> {
+            self.limited_record(name, id, max)?;
+            self.unicode_record(name, unicode_id).map(|_| ())
+        }
+
+        /// The two reserved fields that follow the libid of a reference, which MUST be 0
+        fn reserved_pair(&mut self, name: &str) -> Option<()> {
+            let (reserved_1, reserved_2) = (self.u32()?, self.u16()?);
+            if reserved_1 != 0 || reserved_2 != 0 {
+                self.problem(format!("the Reserved fields of {name} MUST be 0"));
+            }
+            Some(())
+        }
+
+        /// Read the fields held in the data of a record, whose size MUST cover them exactly
+        fn fields(
+            &mut self,
+            name: &str,
+            data: &'a [u8],
+            read: impl FnOnce(&mut Self) -> Option<()>,
+        ) {
+            let mut fields = Self::new(data);
+            if read(&mut fields).is_none() {
+                self.problem(format!(
+                    "{name} has a size of {}, which is too small for its fields",
+                    data.len()
+                ));
+                return;
+            }
+            self.problems.append(&mut fields.problems);
+            if fields.pos != data.len() {
+                self.problem(format!(
+                    "{name} has a size of {}, but its fields take up {} bytes",
+                    data.len(),
+                    fields.pos
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts;
+    use std::io::Read;
+
+    /// The dir stream and the bytes of the PROJECT stream of a test file
+    fn streams() -> (Dir, String) {
+        let mut file = cfb::open("tests/data/xls/Unlocked_with_macro.xls").unwrap();
+        let dir = Dir::from_stream(
+            file.open_stream(consts::CFB_DIR_PATH).unwrap(),
+            &Options::default(),
+        )
+        .unwrap_or_else(|e| panic!("{e}"));
+        let mut project = Vec::new();
+        file.open_stream(consts::CFB_VBA_PATH)
+            .unwrap()
+            .read_to_end(&mut project)
+            .unwrap();
+        (dir, String::from_utf8(project).unwrap())
+    }
+
+    #[test]
+    fn matching_streams() {
+        let (dir, project) = streams();
+        let project = Project::from_bytes(project.as_bytes(), 1252, &Options::default())
+            .unwrap_or_else(|e| panic!("{e}"));
+        assert!(dir.mismatches(&project).is_empty());
+        assert_eq!("VBAProject", dir.information().name());
+        assert_eq!(3, dir.references().len());
+    }
+
+    #[test]
+    fn mismatched_streams() {
+        let (dir, project) = streams();
+        let project = project
+            .replace("Name=\"VBAProject\"", "Name=\"Renamed\"")
+            .replace("Module=Module1", "Class=Module1")
+            .replace("Document=Sheet1/&H00000000\r\n", "");
+        let project = Project::from_bytes(project.as_bytes(), 1252, &Options::default())
+            .unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(
+            vec![
+                "the project is named VBAProject in the dir stream but Renamed in the PROJECT stream",
+                "module Sheet1 is in the dir stream but not the PROJECT stream",
+                "module Module1 is a Class module in the PROJECT stream but is procedural in the dir stream",
+            ],
+            dir.mismatches(&project)
+        );
+    }
+    /// The decompressed records of the dir stream of a test file
+    fn records() -> Vec<u8> {
+        let mut file = cfb::open("tests/data/xls/Unlocked_with_macro.xls").unwrap();
+        let mut compressed = Vec::new();
+        file.open_stream(consts::CFB_DIR_PATH)
+            .unwrap()
+            .read_to_end(&mut compressed)
+            .unwrap();
+        assert!(Dir::violations(&compressed).is_empty());
+        compression::decompress(&compressed).unwrap()
+    }
+
+    /// Replace the first occurrence of a run of bytes
+    fn patch(buf: &mut [u8], from: &[u8], to: &[u8]) {
+        let pos = buf.windows(from.len()).position(|w| w == from).unwrap();
+        buf[pos..pos + to.len()].copy_from_slice(to);
+    }
+
+    #[test]
+    fn violations() {
+        let mut buf = records();
+        assert!(rules::check(&buf).is_empty());
+        // PROJECTLCID in German, and a MODULETYPE with its reserved field set
+        patch(
+            &mut buf,
+            &[0x02, 0x00, 4, 0, 0, 0, 0x09, 0x04],
+            &[0x02, 0x00, 4, 0, 0, 0, 0x07],
+        );
+        patch(&mut buf, &[0x21, 0x00, 0, 0, 0, 0], &[0x21, 0x00, 1]);
+        buf.extend([0, 0]);
+        assert_eq!(
+            vec![
+                "PROJECTLCID is 0x407, but MUST be 0x409",
+                "the Reserved field of MODULETYPE is 0x1, but MUST be 0",
+                "there are 0x2 bytes after the dir stream Terminator",
+            ],
+            rules::check(&buf)
+        );
+    }
+
+    #[test]
+    fn records_out_of_order() {
+        let mut buf = records();
+        // Swap PROJECTCODEPAGE for a record that does not belong there
+        patch(&mut buf, &[0x03, 0x00, 2, 0, 0, 0], &[0x07, 0x00]);
+        let violations = rules::check(&buf);
+        assert_eq!(1, violations.len());
+        assert!(violations[0].starts_with("expected PROJECTCODEPAGE (0x0003) at byte 0x"));
+        assert!(violations[0].ends_with("but found record 0x0007"));
+
+        let buf = records();
+        assert_eq!(
+            vec!["the dir stream ends at byte 0x20, part way through a record"],
+            rules::check(&buf[..0x20])
+        );
+    }
+
+    #[test]
+    fn module_metadata() {
+        let mut file = cfb::open("tests/data/xls/Unlocked_with_macro.xls").unwrap();
+        let (dir, _) = streams();
+        let module = &dir.modules()[2];
+        assert_eq!("Module1", module.stream_name());
+        assert!(module.is_procedural());
+        assert!(!module.is_read_only());
+        assert!(!module.is_private());
+
+        // The source of the module starts at the MODULEOFFSET, with the signature byte of a
+        // compressed container
+        let mut stream = Vec::new();
+        file.open_stream(format!("{}/Module1", consts::CFB_VBA_STORAGE_PATH))
+            .unwrap()
+            .read_to_end(&mut stream)
+            .unwrap();
+        assert!(module.text_offset() > 0);
+        assert_eq!(Some(&0x01), stream.get(module.text_offset()));
+
+        // Mark the standard module as read-only and private
+        let buf = records();
+        let module_type = [0x21, 0x00, 0, 0, 0, 0];
+        let pos = buf.windows(6).position(|w| w == module_type).unwrap() + 6;
+        let mut flagged = buf[..pos].to_vec();
+        flagged.extend([0x25, 0x00, 0, 0, 0, 0, 0x28, 0x00, 0, 0, 0, 0]);
+        flagged.extend(&buf[pos..]);
+        assert!(rules::check(&flagged).is_empty());
+        let (_, dir) = nom_parse::dir(&Warnings::new(&Options::default()))(&flagged).unwrap();
+        let module = &dir.modules()[2];
+        assert!(module.is_read_only());
+        assert!(module.is_private());
+        assert!(!dir.modules()[0].is_read_only());
+    }
+
+    #[test]
+    fn odd_record_size() {
+        // PROJECTLCID with two bytes more than it should have
+        let buf = records();
+        let lcid = [0x02, 0x00, 4, 0, 0, 0, 0x09, 0x04, 0, 0];
+        let pos = buf.windows(lcid.len()).position(|w| w == lcid).unwrap();
+        let mut odd = buf[..pos].to_vec();
+        odd.extend([0x02, 0x00, 6, 0, 0, 0, 0x09, 0x04, 0, 0, 0, 0]);
+        odd.extend(&buf[pos + lcid.len()..]);
+
+        let warnings = Warnings::new(&Options::default());
+        let (_, dir) = nom_parse::dir(&warnings)(&odd).unwrap();
+        assert_eq!(0x409, dir.information().lcid());
+        assert_eq!(
+            vec![
+                "record 0x0002 of the dir stream has a size of 6, but MUST be 4. Carrying on with a \
+                 best guess"
+            ],
+            warnings.into_messages()
+        );
+
+        let strict = Warnings::new(&Options {
+            strict: true,
+            ..Options::default()
+        });
+        assert!(nom_parse::dir(&strict)(&odd).is_err());
+    }
+
+    #[test]
+    fn constants() {
+        assert_eq!(
+            vec![("DEBUG".to_string(), 1), ("Win".to_string(), -1)],
+            parse_constants("DEBUG = 1 : Win = -1")
+        );
+        assert_eq!(vec![("A".to_string(), 0)], parse_constants("A=0"));
+        assert!(parse_constants("").is_empty());
+        assert_eq!(
+            vec![("B".to_string(), 2)],
+            parse_constants("A = x : = 1 : B = 2 : C = 99999")
+        );
+    }
+
+    #[test]
+    fn code_page_streams() {
+        let (dir, project) = streams();
+        // "Проект" in code page 1251
+        let name = [0xcf, 0xf0, 0xee, 0xe5, 0xea, 0xf2];
+        let mut bytes = Vec::new();
+        let (before, after) = project.split_once("VBAProject").unwrap();
+        bytes.extend(before.as_bytes());
+        bytes.extend(name);
+        bytes.extend(after.as_bytes());
+        let project = Project::from_bytes(&bytes, 1251, &Options::default())
+            .unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(
+            vec!["the project is named VBAProject in the dir stream but Проект in the PROJECT stream"],
+            dir.mismatches(&project)
+        );
+    }
+}
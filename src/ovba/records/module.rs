@@ -0,0 +1,196 @@
+#![allow(clippy::doc_markdown)]
+//! A Struct to hold the contents of a module stream
+//!
+//! Each module stream starts with the PerformanceCache, which holds the compiled p-code of the
+//! module in a format that is not documented and changes between versions of VBA. The source code
+//! follows, in a compressed container starting at the MODULEOFFSET given in the dir stream.
+//!
+//! Office runs the p-code rather than the source whenever the version of VBA matches, so a module
+//! can be "stomped" by swapping the source for something harmless while leaving malicious p-code
+//! in place. The string constants of the p-code are read back, so they can be checked against
+//! the source with [`ModuleStream::stomped`]
+//!
+//! Only enough of the PerformanceCache of VBA 6 and later is read to split the p-code into lines:
+//! after the 0xCAFE magic and a reserved word comes the number of lines, a table with a 12 byte
+//! entry for each line, 10 more bytes, then the p-code itself. Each entry gives the length of the
+//! p-code of its line at byte 4, and where it starts, relative to the start of the p-code, at
+//! byte 8
+//!
+//! The layout is given in section 2.3.4.3 of the MS-OVBA specification
+
+use crate::{error, ovba::algorithms::compression};
+
+/// The two bytes that mark the start of the p-code of a module, within the PerformanceCache
+const PCODE_MAGIC: [u8; 2] = [0xfe, 0xca];
+
+/// The size of the entry for each line in the line table of the p-code
+const LINE_ENTRY_SIZE: usize = 12;
+
+/// The opcode that loads a string constant, which is followed by the length of the string and
+/// then the string itself, padded to an even length
+const LIT_STR: u16 = 0xb9;
+
+#[derive(Debug)]
+pub struct ModuleStream {
+    performance_cache: Vec<u8>,
+    source: Vec<u8>,
+}
+
+impl ModuleStream {
+    pub fn from_bytes(buf: &[u8], text_offset: usize) -> Result<Self, error::Compression> {
+        let (performance_cache, compressed) = buf.split_at(text_offset.min(buf.len()));
+        Ok(Self {
+            performance_cache: performance_cache.to_vec(),
+            source: compression::decompress(compressed)?,
+        })
+    }
+
+    /// The source code of the module, in the code page of the project
+    pub fn into_source(self) -> Vec<u8> {
+        self.source
+    }
+
+    /// The p-code of each line of the source code, not counting the hidden Attribute lines. A line
+    /// with nothing to run, such as a blank line or a comment, has no p-code. There are no lines
+    /// at all when the module has not been compiled, or the line table cannot be read
+    pub fn pcode_lines(&self) -> Vec<&[u8]> {
+        self.line_table().unwrap_or_default()
+    }
+
+    fn line_table(&self) -> Option<Vec<&[u8]>> {
+        let cache = &self.performance_cache;
+        let start = cache.windows(2).position(|w| w == PCODE_MAGIC)?;
+        let count = cache.get(start + 4..start + 6)?;
+        let count = usize::from(u16::from_le_bytes([count[0], count[1]]));
+        let table = start + 6;
+        let pcode = table + count * LINE_ENTRY_SIZE + 10;
+        cache
+            .get(table..pcode)?
+            .chunks_exact(LINE_ENTRY_SIZE)
+            .map(|entry| {
+                let length = usize::from(u16::from_le_bytes([entry[4], entry[5]]));
+                let offset = u32::from_le_bytes([entry[8], entry[9], entry[10], entry[11]]);
+                if length == 0 || offset == u32::MAX {
+                    return Some(&[][..]);
+                }
+                let offset = pcode + usize::try_from(offset).ok()?;
+                cache.get(offset..offset + length)
+            })
+            .collect()
+    }
+
+    /// The string constants loaded by the p-code, in the order they are found. There are none when
+    /// the module has not been compiled
+    pub fn string_constants(&self) -> Vec<String> {
+        self.pcode_lines()
+            .into_iter()
+            .flat_map(line_constants)
+            .collect()
+    }
+
+    /// The first string constant of the p-code that cannot be found in the source code, if there
+    /// is one. The two should always agree, so any such constant shows the source has been
+    /// swapped out from under the p-code
+    pub fn stomped(&self) -> Option<String> {
+        let source = String::from_utf8_lossy(&self.source);
+        self.string_constants().into_iter().find(|constant| {
+            // Quotes are doubled up within a string in the source
+            !source.contains(&constant.replace('"', "\"\""))
+        })
+    }
+}
+
+/// The string constants loaded by the p-code of a single line
+fn line_constants(line: &[u8]) -> Vec<String> {
+    // The operands of every other opcode are not known, so look for string constants at every
+    // opcode boundary there could be. Opcodes are always 2 byte aligned within the line
+    let mut constants = Vec::new();
+    let mut position = 0;
+    while let Some(&[op_lo, op_hi, len_lo, len_hi]) = line.get(position..position + 4) {
+        // The top bits of an opcode word hold flags, not the opcode itself
+        let opcode = u16::from_le_bytes([op_lo, op_hi]) & 0x03ff;
+        let len = usize::from(u16::from_le_bytes([len_lo, len_hi]));
+        match line.get(position + 4..position + 4 + len) {
+            Some(text) if opcode == LIT_STR && len > 0 && !text.iter().any(|&b| b < 0x20) => {
+                constants.push(String::from_utf8_lossy(text).to_string());
+                position += 4 + len + len % 2;
+            }
+            _ => position += 2,
+        }
+    }
+    constants
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn module1() -> (Vec<u8>, usize) {
+        let mut file = cfb::open("tests/data/xls/Unlocked_with_macro.xls").unwrap();
+        let mut dir = Vec::new();
+        file.open_stream(crate::consts::CFB_DIR_PATH)
+            .unwrap()
+            .read_to_end(&mut dir)
+            .unwrap();
+        let dir =
+            crate::ovba::records::dir::Dir::from_bytes(&dir, &crate::lenient::Options::default())
+                .unwrap_or_else(|e| panic!("{e}"));
+        let module = dir
+            .modules()
+            .iter()
+            .find(|m| m.name() == "Module1")
+            .unwrap();
+        let mut stream = Vec::new();
+        file.open_stream(format!(
+            "{}/{}",
+            crate::consts::CFB_VBA_STORAGE_PATH,
+            module.stream_name()
+        ))
+        .unwrap()
+        .read_to_end(&mut stream)
+        .unwrap();
+        (stream, module.text_offset())
+    }
+
+    #[test]
+    fn string_constants() {
+        let (stream, text_offset) = module1();
+        let module = ModuleStream::from_bytes(&stream, text_offset).unwrap();
+        assert_eq!(
+            vec!["You'll never figure out how I did this!".to_string()],
+            module.string_constants()
+        );
+        // Sub, blank, MsgBox, blank, End Sub
+        let lines = module.pcode_lines();
+        assert_eq!(
+            vec![6, 0, 50, 0, 2],
+            lines.iter().map(|l| l.len()).collect::<Vec<_>>()
+        );
+        assert_eq!([0xb9, 0x00, 0x27, 0x00], lines[2][..4]);
+        assert!(String::from_utf8_lossy(&module.source).starts_with("Attribute VB_Name"));
+        assert_eq!(None, module.stomped());
+    }
+
+    #[test]
+    fn stomped() {
+        let (stream, text_offset) = module1();
+        let mut stomped = stream[..text_offset].to_vec();
+        stomped.extend(compression::compress(
+            "Attribute VB_Name = \"Module1\"\r\nSub Amazing_macro()\r\nEnd Sub\r\n",
+        ));
+        let module = ModuleStream::from_bytes(&stomped, text_offset).unwrap();
+        assert_eq!(
+            Some("You'll never figure out how I did this!".to_string()),
+            module.stomped()
+        );
+    }
+
+    #[test]
+    fn not_compiled() {
+        let module = ModuleStream::from_bytes(&compression::compress("Sub A()"), 0).unwrap();
+        assert!(module.pcode_lines().is_empty());
+        assert!(module.string_constants().is_empty());
+        assert_eq!(None, module.stomped());
+    }
+}
@@ -17,18 +17,40 @@
 //!                  NWLN HostExtenders
 //!                  [NWLN ProjectWorkspace]
 //!
+//! Some older files carry the password in a `DPx=` property rather than `DPB=`. This is not in
+//! the specification: it is what is left by the old trick of renaming the property so that Excel
+//! no longer finds the password. The data is the same, so it is read as the password all the same
+//!
+//! Excel ends each line with CRLF, but some other tools write LFCR or a bare LF. Any of these are
+//! read, and the stream is written back out with the line ending of its first line
+//!
+//! Properties that are not in the specification, such as those written by other hosts, are kept
+//! as they are and written back out after the properties that are
+//!
+//! Anything after the last line that can be parsed, such as blank lines or bytes appended by other
+//! tools, is kept as it is and written back out unchanged
+//!
+//! Some files written by other tools leave out the `GC=` property. The project is then taken to be
+//! visible, and the property is put back when the stream is written out
+//!
 //! Specification can be found [here](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/d88cb9d8-a475-423d-b370-cc0caaf78628)
 
 use crate::{
     error,
+    lenient::{Options, Warnings},
     ovba::{
-        algorithms::password_hash,
-        types::{guid, hex_int_32, int_32, module_identifier, path},
+        algorithms::{data_encryption, password_hash},
+        records::project_wm::NameMap,
+        types::{
+            guid, hex_int_32, int_32, locale::Locale, mbcs, module_identifier, new_line, path,
+        },
     },
 };
 use cfb::Stream;
 use nom::Finish;
+use rand::Rng;
 use std::io::Read;
+use tracing::debug;
 
 #[derive(Debug)]
 pub struct Project {
@@ -39,11 +61,31 @@ pub struct Project {
     name: ProjectIdentifier,
     help_id: int_32::Int32,
     description: Option<DescriptionText>,
+    version_compatible: Option<String>,
     protection_state: ProtectionState,
     password: Password,
+    legacy_password: bool,
     visibility_state: Visibility,
     host_extenders: Vec<HostExtenderRef>,
     workspace: Option<Vec<WindowRecord>>,
+    name_map: NameMap,
+    locale: Option<Locale>,
+    new_line: new_line::NwLn,
+    trailing: Vec<u8>,
+    unknown_properties: Vec<Vec<u8>>,
+    warnings: Vec<String>,
+}
+
+/// What could be read of a PROJECT stream that failed to parse. Each property line is read on its
+/// own, so those that are well formed are found even when another line is not
+#[derive(Debug, Default)]
+pub struct PartialProject {
+    id: Option<guid::Guid>,
+    name: Option<ProjectIdentifier>,
+    modules: Vec<(String, ModuleType)>,
+    protection_state: Option<ProtectionState>,
+    password: Option<Password>,
+    visibility_state: Option<Visibility>,
 }
 
 // String Types
@@ -51,7 +93,8 @@ pub struct Project {
 type ProjectIdentifier = String;
 // Up to 2,000 characters, must be surrounded by quotes, characters are quotedchar type
 type DescriptionText = String;
-// Any number of bytes above 0x20 (space), that don't include 0x3b (;)
+// Any number of bytes above 0x20 (space), that don't include 0x3b (;), in the code page of the
+// project
 type LibName = String;
 
 #[derive(Debug)]
@@ -68,8 +111,21 @@ enum Module {
     Designer(module_identifier::ModuleIdentifier),
 }
 
-#[derive(Debug)]
-struct ProtectionState {
+/// The kind of a VBA module, as recorded in the PROJECT stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleType {
+    /// A module attached to a workbook or worksheet
+    Document,
+    /// A standard, or procedural, module
+    Standard,
+    /// A class module
+    Class,
+    /// A module attached to a designer, such as a user form
+    Designer,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ProtectionState {
     user: bool,
     host: bool,
     vbe: bool,
@@ -82,14 +138,14 @@ pub enum Password {
     Plain(String),
 }
 
-#[derive(Debug)]
-enum Visibility {
+#[derive(Debug, Clone, Copy)]
+pub enum Visibility {
     NotVisible,
     Visible,
 }
 
 #[derive(Debug)]
-struct HostExtenderRef {
+pub struct HostExtenderRef {
     index: hex_int_32::HexInt32,
     guid: guid::Guid,
     lib: LibName,
@@ -97,14 +153,14 @@ struct HostExtenderRef {
 }
 
 #[derive(Debug)]
-struct WindowRecord {
+pub struct WindowRecord {
     module: module_identifier::ModuleIdentifier,
     code: Window,
     designer: Option<Window>,
 }
 
 #[derive(Debug)]
-struct Window {
+pub struct Window {
     left: int_32::Int32,
     top: int_32::Int32,
     right: int_32::Int32,
@@ -113,46 +169,760 @@ struct Window {
 }
 
 #[derive(Debug)]
-enum WindowState {
+pub enum WindowState {
+    /// No state is recorded, and the window is shown as normal
+    Normal,
     Closed,
     Zoomed,
     Minimized,
 }
 
 impl Project {
+    /// Parse the PROJECT stream of a VBA project
+    ///
+    /// # Errors
+    /// Will return an error if the stream cannot be parsed, in the same situations as
+    /// [`Project::from_bytes`]
     pub fn from_stream<T: std::io::Read + std::io::Seek>(
         mut stream: Stream<T>,
+        code_page: u16,
+        options: &Options,
     ) -> Result<Self, error::ProjectStructure> {
         let mut buf = Vec::new();
         let _ = stream.read_to_end(&mut buf);
+        Self::from_bytes(&buf, code_page, options)
+    }
+
+    /// Parse the bytes of the PROJECT stream of a VBA project, decoding the text in the code page
+    /// of the project, which is recorded in the dir stream
+    ///
+    /// Any property that is not in the specification, e.g. `Key="Value"`, is kept aside to be
+    /// written back out
+    ///
+    /// Protection properties that are only slightly off are read as best they can be, with a
+    /// warning kept for each, unless the options are strict
+    ///
+    /// # Errors
+    /// Will return an error if the bytes do not follow the layout of the specification, or the
+    /// protection properties cannot be decrypted. With the force option, other lines that are not
+    /// in the specification are skipped
+    pub fn from_bytes(
+        buf: &[u8],
+        code_page: u16,
+        options: &Options,
+    ) -> Result<Self, error::ProjectStructure> {
+        debug!("parsing {} bytes of the PROJECT stream", buf.len());
+        let warnings = Warnings::new(options);
+        let mut p = Self::parse(buf, code_page, &warnings)?;
+        p.warnings = warnings.into_messages();
+
+        Ok(p)
+    }
 
-        let (_res, p) = nom_parse::project(&buf)
+    fn parse(
+        buf: &[u8],
+        code_page: u16,
+        warnings: &Warnings,
+    ) -> Result<Self, error::ProjectStructure> {
+        let split = split_unknown(buf, warnings);
+        let known = split.known.as_slice();
+        let (_, mut p) = nom_parse::project(warnings, code_page)(known)
             .finish()
-            .map_err(|e| error::ProjectStructure::NomParseError(e.input.to_vec(), buf.clone()))?;
+            .map_err(|e| {
+                let offset = known.len() - e.input.len();
+                debug!(
+                    offset,
+                    parser = ?e.code,
+                    "failed to parse the PROJECT stream at {:?}",
+                    String::from_utf8_lossy(&e.input[..e.input.len().min(40)])
+                );
+                let (record, context) = failed_record(known, offset);
+                error::ProjectStructure::NomParseError(
+                    split.original_offset(offset),
+                    record,
+                    context,
+                )
+            })?;
+        p.unknown_properties = split.unknown;
 
         Ok(p)
     }
 
+    /// Parse the bytes of the PROJECT stream as [`Project::from_bytes`] does, but return what could
+    /// be read of the stream along with any error
+    ///
+    /// # Errors
+    /// Will return an error in the same situations as [`Project::from_bytes`]
+    pub fn from_bytes_partial(
+        buf: &[u8],
+        code_page: u16,
+        options: &Options,
+    ) -> Result<Self, (Box<PartialProject>, error::ProjectStructure)> {
+        Self::from_bytes(buf, code_page, options).map_err(|e| {
+            (
+                Box::new(nom_parse::partial(buf, &Warnings::new(options), code_page)),
+                e,
+            )
+        })
+    }
+
+    /// Write the PROJECT stream back out, with the text in the code page given
+    ///
+    /// Every property is written in the order that Excel uses, with the line ending of the stream
+    /// that was read. Properties that are not in the specification come after the rest, before
+    /// the first section. The protection properties are encrypted afresh with a random seed, so
+    /// they will not match the bytes that were read, but they decrypt to the same values. Any
+    /// lines that were skipped with the force option are not written. A password read from the
+    /// `DPx=` property is written back there
+    #[must_use]
+    pub fn to_bytes(&self, code_page: u16) -> Vec<u8> {
+        let nl = self.new_line.as_bytes();
+        let mut output = Vec::new();
+        let id = self.id.to_string();
+        output.extend(format!("ID=\"{id}\"").into_bytes());
+        output.extend(nl);
+        for item in &self.items {
+            let line = match item {
+                Item::Module(Module::Doc(name, version)) => {
+                    format!("Document={name}/&H{version:08X}")
+                }
+                Item::Module(Module::Std(name)) => format!("Module={name}"),
+                Item::Module(Module::Class(name)) => format!("Class={name}"),
+                Item::Module(Module::Designer(name)) => format!("BaseClass={name}"),
+                Item::Package(guid) => format!("Package={guid}"),
+            };
+            output.extend(mbcs::encode(&line, code_page));
+            output.extend(nl);
+        }
+        if let Some(help_file) = &self.help_file {
+            output.extend(quoted_property("HelpFile", help_file, code_page, nl));
+        }
+        if let Some(exe_name) = &self.exe_name {
+            output.extend(quoted_property("ExeName32", exe_name, code_page, nl));
+        }
+        output.extend(quoted_property("Name", &self.name, code_page, nl));
+        output.extend(format!("HelpContextID=\"{}\"", self.help_id).into_bytes());
+        output.extend(nl);
+        if let Some(description) = &self.description {
+            output.extend(quoted_property("Description", description, code_page, nl));
+        }
+        if let Some(version_compatible) = &self.version_compatible {
+            output.extend(quoted_property(
+                "VersionCompatible32",
+                version_compatible,
+                code_page,
+                nl,
+            ));
+        }
+        let key = project_key(&id);
+        output.extend(encrypted_property(
+            "CMG",
+            key,
+            &self.protection_state.data(),
+        ));
+        output.extend(nl);
+        let password_name = if self.legacy_password { "DPx" } else { "DPB" };
+        output.extend(encrypted_property(
+            password_name,
+            key,
+            &self.password.data(code_page),
+        ));
+        output.extend(nl);
+        output.extend(encrypted_property("GC", key, &self.visibility_state.data()));
+        output.extend(nl);
+        for property in &self.unknown_properties {
+            output.extend(property);
+            output.extend(nl);
+        }
+        output.extend(nl);
+        output.extend(b"[Host Extender Info]");
+        output.extend(nl);
+        for host_extender in &self.host_extenders {
+            output.extend(mbcs::encode(&host_extender.to_string(), code_page));
+            output.extend(nl);
+        }
+        if let Some(workspace) = &self.workspace {
+            output.extend(nl);
+            output.extend(b"[Workspace]");
+            output.extend(nl);
+            for window in workspace {
+                output.extend(mbcs::encode(&window.to_string(), code_page));
+                output.extend(nl);
+            }
+        }
+        output.extend(&self.trailing);
+        output
+    }
+
+    /// Remove all protection from the project: the project is no longer locked for viewing, has no
+    /// password and is visible. Excel only records the real ID of a project that is not locked, so
+    /// the project is given the ID passed in
+    pub fn unlock(&mut self, id: guid::Guid) {
+        self.id = id;
+        self.protection_state = ProtectionState {
+            user: false,
+            host: false,
+            vbe: false,
+        };
+        self.password = Password::None;
+        self.legacy_password = false;
+        self.visibility_state = Visibility::Visible;
+    }
+
+    /// Lock the project for viewing, as Excel does: the VBE protection flag is set and the project
+    /// is not visible. Any password is kept, but one read from the `DPx=` property is moved to
+    /// `DPB=` so that Excel finds it. Excel records the null ID for a locked project, so the
+    /// project is given the ID passed in
+    pub fn lock(&mut self, id: guid::Guid) {
+        self.id = id;
+        self.protection_state = self.protection_state.locked();
+        self.legacy_password = false;
+        self.visibility_state = Visibility::NotVisible;
+    }
+
+    /// Set the password of the project, without changing whether it is locked
+    pub fn set_password(&mut self, password: Password) {
+        self.password = password;
+        self.legacy_password = false;
+    }
+
+    /// The minor deviations from the specification that were found, and read past, when parsing
+    #[must_use]
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// The lines of any properties that are not in the specification, without their line endings
+    #[must_use]
+    pub fn unknown_properties(&self) -> &[Vec<u8>] {
+        &self.unknown_properties
+    }
+
+    /// Any bytes after the last line that could be parsed, which are written back out unchanged
+    #[must_use]
+    pub fn trailing_bytes(&self) -> &[u8] {
+        &self.trailing
+    }
+
+    /// The value of the VersionCompatible32 property, if there is one. Excel writes `393222000`
+    #[must_use]
+    pub fn version_compatible(&self) -> Option<&str> {
+        self.version_compatible.as_deref()
+    }
+
+    /// How the lines of the stream end. The specification allows CRLF or LFCR, but not a bare LF
+    #[must_use]
+    pub const fn new_line(&self) -> new_line::NwLn {
+        self.new_line
+    }
+
+    /// Whether the password was read from the `DPx=` property, which Excel does not look at, rather
+    /// than `DPB=`
+    #[must_use]
+    pub const fn legacy_password(&self) -> bool {
+        self.legacy_password
+    }
+
+    /// Whether the project is locked for viewing in the VBE
+    #[must_use]
     pub const fn is_locked(&self) -> bool {
         self.protection_state.vbe
     }
 
+    /// The password of the project, which is only ever held as plain text in very old files
+    #[must_use]
     pub const fn password(&self) -> &Password {
         &self.password
     }
+
+    /// Which of the user, host and VBE protections are set on the project
+    #[must_use]
+    pub const fn protection_state(&self) -> &ProtectionState {
+        &self.protection_state
+    }
+
+    /// The ID of the project, which Excel blanks to all zeros when the project is locked
+    #[must_use]
+    pub const fn id(&self) -> guid::Guid {
+        self.id
+    }
+
+    /// The name of the project, as shown in the VBE
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The description of the project, if one has been given
+    #[must_use]
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// The path to the help file of the project, if one has been given
+    #[must_use]
+    pub fn help_file(&self) -> Option<&str> {
+        self.help_file.as_deref()
+    }
+
+    /// The topic of the help file that is shown for the project
+    #[must_use]
+    pub const fn help_id(&self) -> int_32::Int32 {
+        self.help_id
+    }
+
+    /// The path to the compiled project, if one has been given
+    #[must_use]
+    pub fn exe_name(&self) -> Option<&str> {
+        self.exe_name.as_deref()
+    }
+
+    /// Whether the project is shown in the VBE
+    #[must_use]
+    pub const fn visibility(&self) -> Visibility {
+        self.visibility_state
+    }
+
+    /// The host extenders listed in the `[Host Extender Info]` section
+    #[must_use]
+    pub fn host_extenders(&self) -> &[HostExtenderRef] {
+        &self.host_extenders
+    }
+
+    /// The positions of the windows of the VBE, from the optional `[Workspace]` section
+    #[must_use]
+    pub fn workspace(&self) -> Option<&[WindowRecord]> {
+        self.workspace.as_deref()
+    }
+
+    /// Add the Unicode names of the modules, from the PROJECTwm stream that sits beside the
+    /// PROJECT stream
+    pub fn set_name_map(&mut self, name_map: NameMap) {
+        self.name_map = name_map;
+    }
+
+    /// Add the language and code page of the project, from the dir stream
+    pub fn set_locale(&mut self, locale: Locale) {
+        self.locale = Some(locale);
+    }
+
+    /// The identifier of the language of the project, if the dir stream has been read
+    pub fn lcid(&self) -> Option<u32> {
+        self.locale.map(Locale::lcid)
+    }
+
+    /// The code page that the text of the project is encoded in, if the dir stream has been read
+    pub fn code_page(&self) -> Option<u16> {
+        self.locale.map(Locale::code_page)
+    }
+
+    /// The Unicode name of a module listed in the PROJECT stream, or the name as it is if the
+    /// PROJECTwm stream does not map it
+    #[must_use]
+    pub fn unicode_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.name_map.unicode_name(name).unwrap_or(name)
+    }
+
+    /// The name and type of every module listed in the PROJECT stream, in the order listed
+    #[must_use]
+    pub fn modules(&self) -> Vec<(&str, ModuleType)> {
+        self.items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Module(m) => Some(m.name_and_type()),
+                Item::Package(_) => None,
+            })
+            .collect()
+    }
+
+    /// The GUIDs of the ActiveX controls used by the designer modules
+    #[must_use]
+    pub fn packages(&self) -> Vec<guid::Guid> {
+        self.items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Package(g) => Some(*g),
+                Item::Module(_) => None,
+            })
+            .collect()
+    }
+
+    /// The type of the named module, if it is listed in the PROJECT stream. The name can be
+    /// either the one in the PROJECT stream or its Unicode name
+    #[must_use]
+    pub fn module_type(&self, name: &str) -> Option<ModuleType> {
+        self.modules()
+            .into_iter()
+            .find(|&(n, _)| n == name || self.unicode_name(n) == name)
+            .map(|(_, module_type)| module_type)
+    }
+}
+
+impl Module {
+    fn name_and_type(&self) -> (&str, ModuleType) {
+        match self {
+            Self::Doc(n, _) => (n, ModuleType::Document),
+            Self::Std(n) => (n, ModuleType::Standard),
+            Self::Class(n) => (n, ModuleType::Class),
+            Self::Designer(n) => (n, ModuleType::Designer),
+        }
+    }
+}
+
+impl PartialProject {
+    /// The ID of the project, if it could be read
+    #[must_use]
+    pub const fn id(&self) -> Option<guid::Guid> {
+        self.id
+    }
+
+    /// The name of the project, if it could be read
+    #[must_use]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The name and type of every module that could be read, in the order listed
+    #[must_use]
+    pub fn modules(&self) -> &[(String, ModuleType)] {
+        &self.modules
+    }
+
+    /// The protection state of the project, if it could be read
+    #[must_use]
+    pub const fn protection_state(&self) -> Option<ProtectionState> {
+        self.protection_state
+    }
+
+    /// The password of the project, if it could be read
+    #[must_use]
+    pub const fn password(&self) -> Option<&Password> {
+        self.password.as_ref()
+    }
+
+    /// Whether the project is visible, if it could be read
+    #[must_use]
+    pub const fn visibility(&self) -> Option<Visibility> {
+        self.visibility_state
+    }
+}
+
+impl ProtectionState {
+    /// Whether the user is prevented from adding, removing or changing references to the project
+    #[must_use]
+    pub const fn user(self) -> bool {
+        self.user
+    }
+
+    /// Whether the host application is prevented from making changes to the project
+    #[must_use]
+    pub const fn host(self) -> bool {
+        self.host
+    }
+
+    /// Whether the project is locked for viewing in the VBE
+    #[must_use]
+    pub const fn vbe(self) -> bool {
+        self.vbe
+    }
+
+    /// The same protection state, but with the project locked for viewing in the VBE
+    #[must_use]
+    pub const fn locked(self) -> Self {
+        Self { vbe: true, ..self }
+    }
+
+    /// The unencrypted data of the CMG property
+    #[must_use]
+    pub fn data(self) -> [u8; 4] {
+        let flags = u8::from(self.user) | u8::from(self.host) << 1 | u8::from(self.vbe) << 2;
+        [flags, 0x00, 0x00, 0x00]
+    }
+}
+
+impl Password {
+    /// Hash the password with a random salt, ready for storing in the project. Excel hashes the
+    /// password as it is encoded in the code page of the project
+    #[must_use]
+    pub fn hashed(password: &str, code_page: u16) -> Self {
+        let (salt, hash) = password_hash::hash_password(&mbcs::encode(password, code_page));
+        Self::Hash(salt, hash)
+    }
+
+    /// The unencrypted data of the DPB property, with a plain text password in the code page given
+    ///
+    /// # Panics
+    /// Will not panic, as a hashed password always has a salt of the right length
+    #[must_use]
+    pub fn data(&self, code_page: u16) -> Vec<u8> {
+        match self {
+            Self::None => vec![0x00],
+            Self::Hash(salt, hash) => {
+                password_hash::encode(salt, *hash).expect("the salt is always 4 bytes long")
+            }
+            Self::Plain(text) => {
+                let mut data = mbcs::encode(text, code_page);
+                data.push(0x00);
+                data
+            }
+        }
+    }
+}
+
+impl Visibility {
+    /// Whether the project is shown in the VBE
+    #[must_use]
+    pub const fn is_visible(self) -> bool {
+        matches!(self, Self::Visible)
+    }
+
+    /// The unencrypted data of the GC property
+    #[must_use]
+    pub const fn data(self) -> [u8; 1] {
+        match self {
+            Self::NotVisible => [0x00],
+            Self::Visible => [0xff],
+        }
+    }
+}
+
+impl HostExtenderRef {
+    /// The index of the host extender, as referred to by the host application
+    #[must_use]
+    pub const fn index(&self) -> hex_int_32::HexInt32 {
+        self.index
+    }
+
+    /// The GUID of the host extender
+    #[must_use]
+    pub const fn guid(&self) -> guid::Guid {
+        self.guid
+    }
+
+    /// The name of the library of the host extender e.g. `VBE` or `Excel8.0`
+    #[must_use]
+    pub fn lib_name(&self) -> &str {
+        &self.lib
+    }
+
+    /// The flags of the host extender, which are reserved and always zero
+    #[must_use]
+    pub const fn creation_flags(&self) -> hex_int_32::HexInt32 {
+        self.creation_flags
+    }
+}
+
+impl std::fmt::Display for HostExtenderRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "&H{:08X}={};{};&H{:08X}",
+            self.index, self.guid, self.lib, self.creation_flags
+        )
+    }
+}
+
+impl std::fmt::Display for WindowRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.module, self.code)?;
+        if let Some(designer) = &self.designer {
+            write!(f, ", {designer}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Window {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = match self.state {
+            WindowState::Normal => "",
+            WindowState::Closed => "C",
+            WindowState::Zoomed => "Z",
+            WindowState::Minimized => "I",
+        };
+        write!(
+            f,
+            "{}, {}, {}, {}, {state}",
+            self.left, self.top, self.right, self.bottom
+        )
+    }
+}
+
+/// The starts of the lines that can come before the first section of the PROJECT stream
+const PROPERTIES: [&[u8]; 16] = [
+    b"ID=",
+    b"Document=",
+    b"Module=",
+    b"Class=",
+    b"BaseClass=",
+    b"Package=",
+    b"HelpFile=",
+    b"ExeName32=",
+    b"Name=",
+    b"HelpContextID=",
+    b"Description=",
+    b"VersionCompatible32=",
+    b"CMG=",
+    b"DPB=",
+    b"DPx=",
+    b"GC=",
+];
+
+/// Split the lines that are not in the specification out of the PROJECT stream, so that the rest
+/// can be parsed. Those that look like a property, `Key=Value`, are returned without their line
+/// endings to be written back out. Any other line is left in to fail the parse, unless with the
+/// force option where it is dropped with a warning. Everything from the first section, e.g.
+/// `[Host Extender Info]`, is kept
+fn split_unknown(buf: &[u8], warnings: &Warnings) -> Split {
+    let mut output = Vec::with_capacity(buf.len());
+    let mut unknown = Vec::new();
+    let mut removed = Vec::new();
+    let mut sections = false;
+    let mut position = 0;
+    for line in buf.split_inclusive(|&b| b == b'\n') {
+        let start = position;
+        position += line.len();
+        // With LFCR line endings, the CR is left at the start of the next line
+        let text = line.strip_prefix(b"\r").unwrap_or(line);
+        sections |= text.starts_with(b"[");
+        let known = sections
+            || line.trim_ascii().is_empty()
+            || PROPERTIES.iter().any(|p| text.starts_with(p));
+        if known {
+            output.extend_from_slice(line);
+        } else if is_property(text) {
+            debug!(
+                "keeping the PROJECT property {:?}, which is not in the specification",
+                String::from_utf8_lossy(text).trim_end()
+            );
+            let text = text.strip_suffix(b"\n").unwrap_or(text);
+            unknown.push(text.strip_suffix(b"\r").unwrap_or(text).to_vec());
+            removed.push((start, line.len()));
+        } else if warnings.force() {
+            warnings.push(format!(
+                "Ignoring the PROJECT line {:?}, which is not in the specification",
+                String::from_utf8_lossy(line).trim_end()
+            ));
+            removed.push((start, line.len()));
+        } else {
+            output.extend_from_slice(line);
+        }
+    }
+    Split {
+        known: output,
+        unknown,
+        removed,
+    }
+}
+
+/// The PROJECT stream split into the lines that can be parsed and the properties that cannot
+struct Split {
+    known: Vec<u8>,
+    unknown: Vec<Vec<u8>>,
+    /// The offset in the stream, and length, of each line that was taken out
+    removed: Vec<(usize, usize)>,
+}
+
+impl Split {
+    /// The offset in the original stream of a byte of the lines that are parsed
+    fn original_offset(&self, offset: usize) -> usize {
+        self.removed.iter().fold(offset, |offset, &(start, len)| {
+            if start <= offset {
+                offset + len
+            } else {
+                offset
+            }
+        })
+    }
+}
+
+/// Describe the record of the PROJECT stream that holds the byte at the offset, along with the
+/// line it is on, for reporting where parsing failed
+fn failed_record(buf: &[u8], offset: usize) -> (String, String) {
+    let line_start = buf[..offset]
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(0, |p| p + 1);
+    let line_end = buf[offset..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(buf.len(), |p| offset + p);
+    let line = buf[line_start..line_end].trim_ascii();
+    let section = buf[..line_start]
+        .split(|&b| b == b'\n')
+        .map(<[u8]>::trim_ascii)
+        .rfind(|l| l.starts_with(b"["));
+    let record = if line.is_empty() {
+        if offset == buf.len() {
+            "end of the stream".to_string()
+        } else {
+            "blank line".to_string()
+        }
+    } else if line.starts_with(b"[") {
+        format!("{} section", String::from_utf8_lossy(line))
+    } else if section == Some(b"[Host Extender Info]") {
+        "host extender".to_string()
+    } else if section == Some(b"[Workspace]") {
+        "workspace window".to_string()
+    } else {
+        let name = line.split(|&b| b == b'=').next().unwrap_or_default();
+        format!("{} record", String::from_utf8_lossy(name))
+    };
+    let context: String = String::from_utf8_lossy(line).chars().take(60).collect();
+    (record, context)
+}
+
+/// Whether the line is a property, i.e. a name of letters, digits and underscores followed by `=`
+fn is_property(line: &[u8]) -> bool {
+    line.iter().position(|&b| b == b'=').is_some_and(|end| {
+        end > 0
+            && line[..end]
+                .iter()
+                .all(|&b| b.is_ascii_alphanumeric() || b == b'_')
+    })
+}
+
+/// The project key used to encrypt the protection properties. This is the sum of the bytes of the
+/// project ID, including the braces
+///
+/// Specification can be found [here](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/a02dfe4e-3c9f-45a4-8f14-f2f2d44fa063)
+pub fn project_key(id: &str) -> u8 {
+    id.bytes().fold(0, u8::wrapping_add)
+}
+
+/// Format a line of the PROJECT stream with the text in quotes, doubling up any quotes within it
+/// e.g. `Name="VBAProject"`
+fn quoted_property(name: &str, text: &str, code_page: u16, new_line: &[u8]) -> Vec<u8> {
+    let mut line = format!("{name}=\"").into_bytes();
+    line.extend(mbcs::encode(&text.replace('"', "\"\""), code_page));
+    line.push(b'"');
+    line.extend(new_line);
+    line
+}
+
+/// Encrypt the data with a random seed and format as a line of the PROJECT stream, without the
+/// line ending e.g. `CMG="..."`
+pub fn encrypted_property(name: &str, project_key: u8, data: &[u8]) -> Vec<u8> {
+    let seed = rand::thread_rng().gen();
+    let encrypted = data_encryption::encode(seed, project_key, data)
+        .iter()
+        .fold(String::new(), |s, b| format!("{s}{b:02X}"));
+    format!("{name}=\"{encrypted}\"").into_bytes()
 }
 
 mod nom_parse {
     use super::{
-        DescriptionText, HostExtenderRef, Item, LibName, Module, Password, Project,
-        ProjectIdentifier, ProtectionState, Visibility, Window, WindowRecord, WindowState,
+        debug, DescriptionText, HostExtenderRef, Item, LibName, Module, NameMap, PartialProject,
+        Password, Project, ProjectIdentifier, ProtectionState, Visibility, Window, WindowRecord,
+        WindowState,
     };
     use crate::{
         error,
+        lenient::Warnings,
         ovba::{
             algorithms::{data_encryption, password_hash},
             types::{
-                guid, hex_int_32, hexdigits, int_32, module_identifier, new_line, path,
+                guid, hex_int_32, hexdigits, int_32, mbcs, module_identifier, new_line, path,
                 quoted_characters,
             },
         },
@@ -161,129 +931,218 @@ mod nom_parse {
         branch::alt,
         bytes::complete::{tag, take_while},
         character::complete::one_of,
-        combinator::{map, map_res, opt},
+        combinator::{map, map_res, opt, rest},
         multi::{many0, separated_list0},
         sequence::{delimited, pair, preceded, terminated, tuple},
         IResult,
     };
 
-    pub(super) fn project(input: &[u8]) -> IResult<&[u8], Project> {
-        map(
-            tuple((
-                id,
-                items,
-                opt(help_file),
-                opt(exe_name_32),
-                name,
-                help_id,
-                opt(description),
-                opt(version_compat_32),
-                protection_state,
-                password,
-                visibility_state,
-                host_extenders,
-                opt(workspace),
-            )),
-            |(
-                id,
-                items,
-                help_file,
-                exe_name,
-                name,
-                help_id,
-                description,
-                _,
-                protection_state,
-                password,
-                visibility_state,
-                host_extenders,
-                workspace,
-            )| {
-                Project {
+    pub(super) fn project(
+        warnings: &Warnings,
+        code_page: u16,
+    ) -> impl Fn(&[u8]) -> IResult<&[u8], Project> + '_ {
+        move |input: &[u8]| {
+            map(
+                tuple((
                     id,
+                    items(code_page),
+                    opt(help_file(code_page)),
+                    opt(exe_name_32(code_page)),
+                    name(code_page),
+                    help_id,
+                    opt(description(code_page)),
+                    opt(version_compat_32(code_page)),
+                    protection_state(warnings),
+                    password(warnings, code_page),
+                    opt(visibility_state(warnings)),
+                    host_extenders(code_page),
+                    opt(workspace(code_page)),
+                    rest,
+                )),
+                |(
+                    (id, new_line),
                     items,
                     help_file,
                     exe_name,
                     name,
                     help_id,
                     description,
+                    version_compatible,
                     protection_state,
-                    password,
+                    (legacy_password, password),
                     visibility_state,
                     host_extenders,
                     workspace,
-                }
-            },
-        )(input)
+                    trailing,
+                )| {
+                    Project {
+                        id,
+                        items,
+                        help_file,
+                        exe_name,
+                        name,
+                        help_id,
+                        description,
+                        version_compatible,
+                        protection_state,
+                        password,
+                        legacy_password,
+                        visibility_state: visibility_state.unwrap_or_else(|| {
+                            debug!("no GC property in the PROJECT stream, taking it to be visible");
+                            Visibility::Visible
+                        }),
+                        host_extenders,
+                        workspace,
+                        name_map: NameMap::default(),
+                        locale: None,
+                        new_line,
+                        trailing: trailing_bytes(trailing),
+                        unknown_properties: Vec::new(),
+                        warnings: Vec::new(),
+                    }
+                },
+            )(input)
+        }
     }
 
-    fn id(input: &[u8]) -> IResult<&[u8], guid::Guid> {
-        delimited(tag("ID=\""), guid::parse, pair(tag("\""), new_line::parse))(input)
+    /// Read each property line before the first section on its own, keeping those that parse
+    pub(super) fn partial(input: &[u8], warnings: &Warnings, code_page: u16) -> PartialProject {
+        let mut partial = PartialProject::default();
+        for line in input.split_inclusive(|&b| b == b'\n') {
+            // With LFCR line endings, the CR is left at the start of the next line
+            let line = line.strip_prefix(b"\r").unwrap_or(line);
+            if line.starts_with(b"[") {
+                break;
+            }
+            if let Ok((_, (id, _))) = id(line) {
+                partial.id = Some(id);
+            } else if let Ok((_, Item::Module(m))) =
+                terminated(module(code_page), new_line::parse)(line)
+            {
+                let (name, module_type) = m.name_and_type();
+                partial.modules.push((name.to_string(), module_type));
+            } else if let Ok((_, name)) = name(code_page)(line) {
+                partial.name = Some(name);
+            } else if let Ok((_, state)) = protection_state(warnings)(line) {
+                partial.protection_state = Some(state);
+            } else if let Ok((_, (_, password))) = password(warnings, code_page)(line) {
+                partial.password = Some(password);
+            } else if let Ok((_, visibility)) = visibility_state(warnings)(line) {
+                partial.visibility_state = Some(visibility);
+            }
+        }
+        debug!(
+            "read {} modules from the lines of the PROJECT stream that could be parsed",
+            partial.modules.len()
+        );
+        partial
     }
 
-    fn document_module(input: &[u8]) -> IResult<&[u8], Module> {
-        map(
-            pair(
-                preceded(tag("Document="), module_identifier::parse),
-                preceded(tag("/"), hex_int_32::parse),
-            ),
-            |(module, doc_tlib_ver)| Module::Doc(module, doc_tlib_ver),
-        )(input)
+    /// Keep whatever is left at the end of the stream, so that it can be written back out
+    fn trailing_bytes(trailing: &[u8]) -> Vec<u8> {
+        if !trailing.is_empty() {
+            debug!(
+                "{} bytes left unparsed at the end of the PROJECT stream",
+                trailing.len()
+            );
+        }
+        trailing.to_vec()
     }
 
-    fn std_module(input: &[u8]) -> IResult<&[u8], Module> {
-        map(
-            preceded(tag("Module="), module_identifier::parse),
-            Module::Std,
+    /// The ID of the project, along with the line ending it is written with
+    fn id(input: &[u8]) -> IResult<&[u8], (guid::Guid, new_line::NwLn)> {
+        pair(
+            preceded(tag("ID=\""), guid::parse),
+            preceded(tag("\""), new_line::parse),
         )(input)
     }
 
-    fn class_module(input: &[u8]) -> IResult<&[u8], Module> {
-        map(
-            preceded(tag("Class="), module_identifier::parse),
-            Module::Class,
-        )(input)
+    fn document_module(code_page: u16) -> impl Fn(&[u8]) -> IResult<&[u8], Module> {
+        move |input: &[u8]| {
+            map(
+                pair(
+                    preceded(tag("Document="), module_identifier::parse(code_page)),
+                    preceded(tag("/"), hex_int_32::parse),
+                ),
+                |(module, doc_tlib_ver)| Module::Doc(module, doc_tlib_ver),
+            )(input)
+        }
     }
 
-    fn designer_module(input: &[u8]) -> IResult<&[u8], Module> {
-        map(
-            preceded(tag("BaseClass="), module_identifier::parse),
-            Module::Designer,
-        )(input)
+    fn std_module(code_page: u16) -> impl Fn(&[u8]) -> IResult<&[u8], Module> {
+        move |input: &[u8]| {
+            map(
+                preceded(tag("Module="), module_identifier::parse(code_page)),
+                Module::Std,
+            )(input)
+        }
     }
 
-    fn module(input: &[u8]) -> IResult<&[u8], Item> {
-        map(
-            alt((document_module, std_module, class_module, designer_module)),
-            Item::Module,
-        )(input)
+    fn class_module(code_page: u16) -> impl Fn(&[u8]) -> IResult<&[u8], Module> {
+        move |input: &[u8]| {
+            map(
+                preceded(tag("Class="), module_identifier::parse(code_page)),
+                Module::Class,
+            )(input)
+        }
+    }
+
+    fn designer_module(code_page: u16) -> impl Fn(&[u8]) -> IResult<&[u8], Module> {
+        move |input: &[u8]| {
+            map(
+                preceded(tag("BaseClass="), module_identifier::parse(code_page)),
+                Module::Designer,
+            )(input)
+        }
+    }
+
+    fn module(code_page: u16) -> impl Fn(&[u8]) -> IResult<&[u8], Item> {
+        move |input: &[u8]| {
+            map(
+                alt((
+                    document_module(code_page),
+                    std_module(code_page),
+                    class_module(code_page),
+                    designer_module(code_page),
+                )),
+                Item::Module,
+            )(input)
+        }
     }
 
     fn package(input: &[u8]) -> IResult<&[u8], Item> {
         map(preceded(tag("Package="), guid::parse), Item::Package)(input)
     }
 
-    fn items(input: &[u8]) -> IResult<&[u8], Vec<Item>> {
-        terminated(
-            separated_list0(new_line::parse, alt((module, package))),
-            new_line::parse,
-        )(input)
+    fn items(code_page: u16) -> impl Fn(&[u8]) -> IResult<&[u8], Vec<Item>> {
+        move |input: &[u8]| {
+            terminated(
+                separated_list0(new_line::parse, alt((module(code_page), package))),
+                new_line::parse,
+            )(input)
+        }
     }
 
-    fn help_file(input: &[u8]) -> IResult<&[u8], path::Path> {
-        delimited(tag("HelpFile="), path::parse, new_line::parse)(input)
+    fn help_file(code_page: u16) -> impl Fn(&[u8]) -> IResult<&[u8], path::Path> {
+        move |input: &[u8]| {
+            delimited(tag("HelpFile="), path::parse(code_page), new_line::parse)(input)
+        }
     }
 
-    fn exe_name_32(input: &[u8]) -> IResult<&[u8], path::Path> {
-        delimited(tag("ExeName32="), path::parse, new_line::parse)(input)
+    fn exe_name_32(code_page: u16) -> impl Fn(&[u8]) -> IResult<&[u8], path::Path> {
+        move |input: &[u8]| {
+            delimited(tag("ExeName32="), path::parse(code_page), new_line::parse)(input)
+        }
     }
 
-    fn name(input: &[u8]) -> IResult<&[u8], ProjectIdentifier> {
-        delimited(
-            tag("Name="),
-            quoted_characters::parse(1, 128),
-            new_line::parse,
-        )(input)
+    fn name(code_page: u16) -> impl Fn(&[u8]) -> IResult<&[u8], ProjectIdentifier> {
+        move |input: &[u8]| {
+            delimited(
+                tag("Name="),
+                quoted_characters::parse(1, 128, code_page),
+                new_line::parse,
+            )(input)
+        }
     }
 
     fn help_id(input: &[u8]) -> IResult<&[u8], int_32::Int32> {
@@ -294,159 +1153,233 @@ mod nom_parse {
         )(input)
     }
 
-    fn description(input: &[u8]) -> IResult<&[u8], DescriptionText> {
-        delimited(
-            tag("Description="),
-            quoted_characters::parse(0, 2000),
-            new_line::parse,
-        )(input)
+    fn description(code_page: u16) -> impl Fn(&[u8]) -> IResult<&[u8], DescriptionText> {
+        move |input: &[u8]| {
+            delimited(
+                tag("Description="),
+                quoted_characters::parse(0, 2000, code_page),
+                new_line::parse,
+            )(input)
+        }
+    }
+
+    /// Excel always writes `393222000`, but other hosts write their own values
+    fn version_compat_32(code_page: u16) -> impl Fn(&[u8]) -> IResult<&[u8], String> {
+        move |input: &[u8]| {
+            delimited(
+                tag("VersionCompatible32="),
+                quoted_characters::parse(0, 2000, code_page),
+                new_line::parse,
+            )(input)
+        }
     }
 
-    fn version_compat_32(input: &[u8]) -> IResult<&[u8], &[u8]> {
-        terminated(tag("VersionCompatible32=\"393222000\""), new_line::parse)(input)
+    fn protection_state(
+        warnings: &Warnings,
+    ) -> impl Fn(&[u8]) -> IResult<&[u8], ProtectionState> + '_ {
+        move |input: &[u8]| {
+            map_res(
+                delimited(
+                    tag("CMG=\""),
+                    hexdigits::parse(22, 28),
+                    pair(tag("\""), new_line::parse),
+                ),
+                |encrypted: Vec<u8>| {
+                    let data = data_encryption::decode(encrypted)?;
+                    // The flags are in the lowest three bits, whatever else has been set
+                    let flags = warnings
+                        .recover(protection_flags(&data), || data.first().map(|b| b & 7))?;
+                    Ok::<_, error::ProtectionState>(ProtectionState {
+                        user: flags & 1 == 1,
+                        host: flags & 2 == 2,
+                        vbe: flags & 4 == 4,
+                    })
+                },
+            )(input)
+        }
     }
 
-    fn protection_state(input: &[u8]) -> IResult<&[u8], ProtectionState> {
-        map_res(
-            delimited(
-                tag("CMG=\""),
-                hexdigits::parse(22, 28),
-                pair(tag("\""), new_line::parse),
-            ),
-            |encrypted: Vec<u8>| {
-                let data = data_encryption::decode(encrypted)?;
-                if data.len() != 4 {
-                    return Err(error::ProtectionState::DataLength(data.len()));
-                }
-                if data[0] > 7 || data[1] != 0 || data[2] != 0 || data[3] != 0 {
-                    return Err(error::ProtectionState::ReservedBits([
-                        data[0], data[1], data[2], data[3],
-                    ]));
-                }
-                Ok(ProtectionState {
-                    user: data[0] & 1 == 1,
-                    host: data[0] & 2 == 2,
-                    vbe: data[0] & 4 == 4,
-                })
-            },
-        )(input)
+    fn protection_flags(data: &[u8]) -> Result<u8, error::ProtectionState> {
+        if data.len() != 4 {
+            return Err(error::ProtectionState::DataLength(data.len()));
+        }
+        if data[0] > 7 || data[1] != 0 || data[2] != 0 || data[3] != 0 {
+            return Err(error::ProtectionState::ReservedBits([
+                data[0], data[1], data[2], data[3],
+            ]));
+        }
+        Ok(data[0])
     }
 
-    fn password(input: &[u8]) -> IResult<&[u8], Password> {
-        map_res(
-            delimited(
-                tag("DPB=\""),
-                hexdigits::parse(16, 2000),
-                pair(tag("\""), new_line::parse),
-            ),
-            |encrypted: Vec<u8>| {
-                let data = data_encryption::decode(encrypted)?;
-                Ok(match data.len() {
-                    0 => return Err(error::Password::NoData),
-                    1 => {
-                        if data.first() != Some(0x00).as_ref() {
-                            return Err(error::PasswordNone::NotNull(data[0]).into());
-                        }
-                        Password::None
-                    }
-                    29 => {
-                        let (salt, hash) = password_hash::decode(data)?;
-                        Password::Hash(salt, hash)
-                    }
-                    _ => {
-                        if data.last() != Some(0x00).as_ref() {
-                            return Err(error::PasswordPlain::Terminator(*data.last().expect(
-                                "Cannot construct a plain password with zero length data",
-                            ))
-                            .into());
-                        }
-                        let password =
-                            String::from_utf8_lossy(&data[0..(data.len() - 1)]).to_string();
-                        Password::Plain(password)
-                    }
-                })
-            },
-        )(input)
+    /// The password, along with whether it was found in the legacy `DPx=` property
+    fn password(
+        warnings: &Warnings,
+        code_page: u16,
+    ) -> impl Fn(&[u8]) -> IResult<&[u8], (bool, Password)> + '_ {
+        move |input: &[u8]| {
+            pair(
+                alt((map(tag("DPB=\""), |_| false), map(tag("DPx=\""), |_| true))),
+                password_value(warnings, code_page),
+            )(input)
+        }
     }
 
-    fn visibility_state(input: &[u8]) -> IResult<&[u8], Visibility> {
-        map_res(
-            delimited(
-                tag("GC=\""),
-                hexdigits::parse(16, 22),
-                pair(tag("\""), new_line::parse),
-            ),
-            |encrypted: Vec<u8>| {
-                let data = data_encryption::decode(encrypted)?;
-                if data.len() != 1 {
-                    return Err(error::Visibility::DataLength(data.len()));
+    fn password_value(
+        warnings: &Warnings,
+        code_page: u16,
+    ) -> impl Fn(&[u8]) -> IResult<&[u8], Password> + '_ {
+        move |input: &[u8]| {
+            map_res(
+                terminated(hexdigits::parse(16, 2000), pair(tag("\""), new_line::parse)),
+                |encrypted: Vec<u8>| {
+                    let data = data_encryption::decode(encrypted)?;
+                    // A hash cannot be guessed at, but anything else can be read as best it can
+                    warnings.recover(password_data(&data, code_page), || match data.len() {
+                        0 | 1 => Some(Password::None),
+                        29 => None,
+                        _ => Some(Password::Plain(mbcs::decode(
+                            data.strip_suffix(&[0x00]).unwrap_or(&data),
+                            code_page,
+                        ))),
+                    })
+                },
+            )(input)
+        }
+    }
+
+    fn password_data(data: &[u8], code_page: u16) -> Result<Password, error::Password> {
+        Ok(match data.len() {
+            0 => return Err(error::Password::NoData),
+            1 => {
+                if data.first() != Some(0x00).as_ref() {
+                    return Err(error::PasswordNone::NotNull(data[0]).into());
                 }
-                match data.first() {
-                    Some(0x00) => Ok(Visibility::NotVisible),
-                    Some(0xff) => Ok(Visibility::Visible),
-                    Some(x) => Err(error::Visibility::InvalidState(*x)),
-                    None => unreachable!(),
+                Password::None
+            }
+            29 => {
+                let (salt, hash) = password_hash::decode(data)?;
+                Password::Hash(salt, hash)
+            }
+            _ => {
+                if data.last() != Some(0x00).as_ref() {
+                    return Err(error::PasswordPlain::Terminator(
+                        *data
+                            .last()
+                            .expect("Cannot construct a plain password with zero length data"),
+                    )
+                    .into());
                 }
-            },
-        )(input)
+                let password = mbcs::decode(&data[0..(data.len() - 1)], code_page);
+                Password::Plain(password)
+            }
+        })
     }
 
-    fn host_extenders(input: &[u8]) -> IResult<&[u8], Vec<HostExtenderRef>> {
-        preceded(
-            tuple((
-                new_line::parse,
-                tag("[Host Extender Info]"),
-                new_line::parse,
-            )),
-            many0(host_extender_ref),
-        )(input)
+    fn visibility_state(warnings: &Warnings) -> impl Fn(&[u8]) -> IResult<&[u8], Visibility> + '_ {
+        move |input: &[u8]| {
+            map_res(
+                delimited(
+                    tag("GC=\""),
+                    hexdigits::parse(16, 22),
+                    pair(tag("\""), new_line::parse),
+                ),
+                |encrypted: Vec<u8>| {
+                    let data = data_encryption::decode(encrypted)?;
+                    // Anything but zero in the first byte is taken to mean visible
+                    warnings.recover(visibility_data(&data), || {
+                        data.first().map(|&b| {
+                            if b == 0x00 {
+                                Visibility::NotVisible
+                            } else {
+                                Visibility::Visible
+                            }
+                        })
+                    })
+                },
+            )(input)
+        }
     }
 
-    fn host_extender_ref(input: &[u8]) -> IResult<&[u8], HostExtenderRef> {
-        map(
-            tuple((
-                terminated(hex_int_32::parse, tag("=")),
-                terminated(guid::parse, tag(";")),
-                terminated(lib_name, tag(";")),
-                terminated(hex_int_32::parse, new_line::parse),
-            )),
-            |(index, guid, lib, creation_flags)| HostExtenderRef {
-                index,
-                guid,
-                lib,
-                creation_flags,
-            },
-        )(input)
+    fn visibility_data(data: &[u8]) -> Result<Visibility, error::Visibility> {
+        if data.len() != 1 {
+            return Err(error::Visibility::DataLength(data.len()));
+        }
+        match data.first() {
+            Some(0x00) => Ok(Visibility::NotVisible),
+            Some(0xff) => Ok(Visibility::Visible),
+            Some(x) => Err(error::Visibility::InvalidState(*x)),
+            None => unreachable!(),
+        }
     }
 
-    // TODO: This probably needs to take account of the MBCS requirment
-    fn lib_name(input: &[u8]) -> IResult<&[u8], LibName> {
-        map_res(take_while(|c| c > 0x20 && c != 0x3b), |s: &[u8]| {
-            String::from_utf8(s.to_vec())
-        })(input)
+    fn host_extenders(code_page: u16) -> impl Fn(&[u8]) -> IResult<&[u8], Vec<HostExtenderRef>> {
+        move |input: &[u8]| {
+            preceded(
+                tuple((
+                    new_line::parse,
+                    tag("[Host Extender Info]"),
+                    new_line::parse,
+                )),
+                many0(host_extender_ref(code_page)),
+            )(input)
+        }
     }
 
-    fn workspace(input: &[u8]) -> IResult<&[u8], Vec<WindowRecord>> {
-        preceded(
-            tuple((new_line::parse, tag("[Workspace]"), new_line::parse)),
-            many0(window_record),
-        )(input)
+    fn host_extender_ref(code_page: u16) -> impl Fn(&[u8]) -> IResult<&[u8], HostExtenderRef> {
+        move |input: &[u8]| {
+            map(
+                tuple((
+                    terminated(hex_int_32::parse, tag("=")),
+                    terminated(guid::parse, tag(";")),
+                    terminated(lib_name(code_page), tag(";")),
+                    terminated(hex_int_32::parse, new_line::parse),
+                )),
+                |(index, guid, lib, creation_flags)| HostExtenderRef {
+                    index,
+                    guid,
+                    lib,
+                    creation_flags,
+                },
+            )(input)
+        }
     }
 
-    fn window_record(input: &[u8]) -> IResult<&[u8], WindowRecord> {
-        map(
-            tuple((
-                terminated(module_identifier::parse, tag("=")),
-                project_window,
-                opt(preceded(tag(", "), project_window)),
-                new_line::parse,
-            )),
-            |(module, code, designer, _)| WindowRecord {
-                module,
-                code,
-                designer,
-            },
-        )(input)
+    /// Neither the lead nor the trail byte of a character in a double byte code page, such as
+    /// Shift-JIS or GBK, can be a space or semicolon, so the end of the name is found before it is
+    /// decoded
+    fn lib_name(code_page: u16) -> impl Fn(&[u8]) -> IResult<&[u8], LibName> {
+        move |input: &[u8]| {
+            map(take_while(|c| c > 0x20 && c != 0x3b), |name| {
+                mbcs::decode(name, code_page)
+            })(input)
+        }
+    }
+
+    fn workspace(code_page: u16) -> impl Fn(&[u8]) -> IResult<&[u8], Vec<WindowRecord>> {
+        move |input: &[u8]| {
+            preceded(
+                tuple((new_line::parse, tag("[Workspace]"), new_line::parse)),
+                many0(window_record(code_page)),
+            )(input)
+        }
+    }
+
+    fn window_record(code_page: u16) -> impl Fn(&[u8]) -> IResult<&[u8], WindowRecord> {
+        move |input: &[u8]| {
+            map(
+                tuple((
+                    terminated(module_identifier::parse(code_page), tag("=")),
+                    project_window,
+                    opt(preceded(tag(", "), project_window)),
+                    new_line::parse,
+                )),
+                |(module, code, designer, _)| WindowRecord {
+                    module,
+                    code,
+                    designer,
+                },
+            )(input)
+        }
     }
 
     fn project_window(input: &[u8]) -> IResult<&[u8], Window> {
@@ -467,11 +1400,341 @@ mod nom_parse {
     }
 
     fn window_state(input: &[u8]) -> IResult<&[u8], WindowState> {
-        map(one_of("CZI"), |c| match c {
-            'C' => WindowState::Closed,
-            'Z' => WindowState::Zoomed,
-            'I' => WindowState::Minimized,
+        map(opt(one_of("CZI")), |c| match c {
+            None => WindowState::Normal,
+            Some('C') => WindowState::Closed,
+            Some('Z') => WindowState::Zoomed,
+            Some('I') => WindowState::Minimized,
             _ => unreachable!(),
         })(input)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts;
+    use sha1::{Digest, Sha1};
+
+    fn project_stream(filename: &str) -> Vec<u8> {
+        let mut file = cfb::open(filename).unwrap();
+        let mut project = Vec::new();
+        file.open_stream(consts::CFB_VBA_PATH)
+            .unwrap()
+            .read_to_end(&mut project)
+            .unwrap();
+        project
+    }
+
+    /// The lines of the PROJECT stream, leaving out the protection properties as they are
+    /// encrypted with a random seed
+    fn plain_lines(project: &[u8]) -> Vec<&[u8]> {
+        project
+            .split_inclusive(|&b| b == b'\n')
+            .filter(|line| {
+                !["CMG=", "DPB=", "DPx=", "GC="]
+                    .iter()
+                    .any(|p| line.starts_with(p.as_bytes()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        for filename in [
+            "tests/data/xls/Unlocked_with_macro.xls",
+            "tests/data/xls/Locked_with_macro.xls",
+        ] {
+            let original = project_stream(filename);
+            let project = Project::from_bytes(&original, 1252, &Options::default())
+                .unwrap_or_else(|e| panic!("{e}"));
+            let written = project.to_bytes(1252);
+            assert_eq!(plain_lines(&original), plain_lines(&written));
+
+            let reread = Project::from_bytes(&written, 1252, &Options::default())
+                .unwrap_or_else(|e| panic!("{e}"));
+            assert_eq!(project.is_locked(), reread.is_locked());
+            assert_eq!(project.password().data(1252), reread.password().data(1252));
+            assert_eq!(project.visibility().data(), reread.visibility().data());
+        }
+    }
+
+    #[test]
+    fn round_trip_quotes_and_code_page() {
+        let original = project_stream("tests/data/xls/Unlocked_with_macro.xls");
+        let mut project = Project::from_bytes(&original, 1252, &Options::default())
+            .unwrap_or_else(|e| panic!("{e}"));
+        project.name = "Проект".to_string();
+        project.description = Some("A \"quoted\" description".to_string());
+        let written = project.to_bytes(1251);
+        let text = mbcs::decode(&written, 1251);
+        assert!(text.contains("Name=\"Проект\"\r\n"));
+        assert!(text.contains("Description=\"A \"\"quoted\"\" description\"\r\n"));
+
+        let reread = Project::from_bytes(&written, 1251, &Options::default())
+            .unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!("Проект", reread.name());
+        assert_eq!(Some("A \"quoted\" description"), reread.description());
+    }
+
+    #[test]
+    fn unlock() {
+        let original = project_stream("tests/data/xls/Locked_with_macro.xls");
+        let mut project = Project::from_bytes(&original, 1252, &Options::default())
+            .unwrap_or_else(|e| panic!("{e}"));
+        assert!(project.is_locked());
+        project.unlock(consts::UNLOCKED_ID);
+        let written = project.to_bytes(1252);
+        assert!(written.starts_with(b"ID=\"{3C6F1B8B-BDBE-4F1B-AA02-BCA23D695691}\"\r\n"));
+
+        let reread = Project::from_bytes(&written, 1252, &Options::default())
+            .unwrap_or_else(|e| panic!("{e}"));
+        assert!(!reread.is_locked());
+        assert!(matches!(reread.password(), Password::None));
+        assert!(matches!(reread.visibility(), Visibility::Visible));
+    }
+
+    #[test]
+    fn hashed_in_code_page() {
+        let Password::Hash(salt, hash) = Password::hashed("Пароль", 1251) else {
+            panic!("the password is not hashed");
+        };
+        let mut salted = mbcs::encode("Пароль", 1251);
+        assert_eq!(6, salted.len());
+        salted.extend(salt);
+        assert_eq!(<[u8; 20]>::from(Sha1::digest(salted)), hash);
+    }
+
+    #[test]
+    fn legacy_password() {
+        let original = project_stream("tests/data/xls/Locked_with_macro.xls");
+        let legacy = String::from_utf8_lossy(&original)
+            .replace("DPB=", "DPx=")
+            .into_bytes();
+        let mut project = Project::from_bytes(&legacy, 1252, &Options::default())
+            .unwrap_or_else(|e| panic!("{e}"));
+        assert!(project.legacy_password());
+        assert!(matches!(project.password(), Password::Hash(_, _)));
+        assert!(project.to_bytes(1252).windows(4).any(|w| w == b"DPx="));
+
+        project.unlock(consts::UNLOCKED_ID);
+        let written = project.to_bytes(1252);
+        assert!(written.windows(4).any(|w| w == b"DPB="));
+        assert!(!written.windows(4).any(|w| w == b"DPx="));
+    }
+
+    #[test]
+    fn missing_visibility() {
+        let original = project_stream("tests/data/xls/Unlocked_with_macro.xls");
+        let missing: Vec<u8> = original
+            .split_inclusive(|&b| b == b'\n')
+            .filter(|line| !line.starts_with(b"GC="))
+            .flatten()
+            .copied()
+            .collect();
+        assert_ne!(original, missing);
+        let project = Project::from_bytes(&missing, 1252, &Options::default())
+            .unwrap_or_else(|e| panic!("{e}"));
+        assert!(project.visibility().is_visible());
+
+        let written = project.to_bytes(1252);
+        assert!(written.windows(4).any(|w| w == b"\nGC="));
+        assert_eq!(plain_lines(&original), plain_lines(&written));
+    }
+
+    #[test]
+    fn line_endings() {
+        let original = project_stream("tests/data/xls/Locked_with_macro.xls");
+        for new_line in ["\n\r", "\n"] {
+            let changed = String::from_utf8_lossy(&original)
+                .replace("\r\n", new_line)
+                .into_bytes();
+            let project = Project::from_bytes(&changed, 1252, &Options::default())
+                .unwrap_or_else(|e| panic!("{e}"));
+            assert!(project.is_locked());
+            let written = String::from_utf8_lossy(&project.to_bytes(1252)).into_owned();
+            assert!(new_line == "\n\r" || !written.contains('\r'));
+            let restored = written.replace(new_line, "\r\n").into_bytes();
+            assert_eq!(plain_lines(&original), plain_lines(&restored));
+        }
+    }
+
+    #[test]
+    fn trailing_bytes() {
+        let mut original = project_stream("tests/data/xls/Unlocked_with_macro.xls");
+        original.extend(b"\r\n\r\nExtra=1\r\n\x00\x01");
+        let project = Project::from_bytes(&original, 1252, &Options::default())
+            .unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(b"\r\n\r\nExtra=1\r\n\x00\x01", project.trailing_bytes());
+        assert_eq!(plain_lines(&original), plain_lines(&project.to_bytes(1252)));
+    }
+
+    #[test]
+    fn unknown_properties() {
+        let original = project_stream("tests/data/xls/Locked_with_macro.xls");
+        let id_end = original
+            .iter()
+            .position(|&b| b == b'\n')
+            .unwrap_or_default()
+            + 1;
+        let mut added = original[..id_end].to_vec();
+        added.extend(b"Vendor_Key=\"1\"\r\n");
+        added.extend(&original[id_end..]);
+
+        let mut project = Project::from_bytes(&added, 1252, &Options::default())
+            .unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!([b"Vendor_Key=\"1\"".to_vec()], project.unknown_properties());
+        project.unlock(consts::UNLOCKED_ID);
+        let written = project.to_bytes(1252);
+        assert!(written
+            .windows(18)
+            .any(|w| w == b"Vendor_Key=\"1\"\r\n\r\n"));
+    }
+
+    #[test]
+    fn not_a_property() {
+        let original = project_stream("tests/data/xls/Locked_with_macro.xls");
+        let mut added = b"Not a property\r\n".to_vec();
+        added.extend(&original);
+        assert!(Project::from_bytes(&added, 1252, &Options::default()).is_err());
+    }
+
+    #[test]
+    fn lib_name_code_pages() {
+        let original = project_stream("tests/data/xls/Unlocked_with_macro.xls");
+        for (code_page, name) in [(932, "ライブラリ"), (936, "对象库")] {
+            let encoded = mbcs::encode(name, code_page);
+            assert!(encoded.iter().all(|&b| b > 0x20 && b != 0x3b));
+            let mut lib = b";".to_vec();
+            lib.extend(&encoded);
+            lib.push(b';');
+            let position = original
+                .windows(5)
+                .position(|w| w == b";VBE;")
+                .unwrap_or_else(|| panic!("no VBE host extender"));
+            let mut changed = original[..position].to_vec();
+            changed.extend(&lib);
+            changed.extend(&original[position + 5..]);
+
+            let project = Project::from_bytes(&changed, code_page, &Options::default())
+                .unwrap_or_else(|e| panic!("{e}"));
+            assert_eq!(name, project.host_extenders()[0].lib_name());
+            let written = project.to_bytes(code_page);
+            assert!(written.windows(lib.len()).any(|w| w == lib));
+        }
+    }
+
+    #[test]
+    fn version_compatible() {
+        let original = project_stream("tests/data/xls/Unlocked_with_macro.xls");
+        let project = Project::from_bytes(&original, 1252, &Options::default())
+            .unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(Some("393222000"), project.version_compatible());
+
+        let changed = String::from_utf8_lossy(&original)
+            .replace(
+                "VersionCompatible32=\"393222000\"",
+                "VersionCompatible32=\"12\"",
+            )
+            .into_bytes();
+        let project = Project::from_bytes(&changed, 1252, &Options::default())
+            .unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(Some("12"), project.version_compatible());
+        assert_eq!(plain_lines(&changed), plain_lines(&project.to_bytes(1252)));
+    }
+
+    #[test]
+    fn parse_error_location() {
+        let original = project_stream("tests/data/xls/Locked_with_macro.xls");
+        let cmg = original
+            .windows(5)
+            .position(|w| w == b"CMG=\"")
+            .unwrap_or_else(|| panic!("no CMG property"));
+        let mut broken = original;
+        broken[cmg + 5] = b'X';
+        let Err(error::ProjectStructure::NomParseError(offset, record, context)) =
+            Project::from_bytes(&broken, 1252, &Options::default())
+        else {
+            panic!("a broken CMG property should not parse")
+        };
+        assert_eq!(cmg + 5, offset);
+        assert_eq!("CMG record", record);
+        assert!(context.starts_with("CMG=\"X"));
+
+        // Lines that are taken out before parsing still count towards the offset
+        let mut added = b"ID=\"{00000000-0000-0000-0000-000000000000}\"\r\n".to_vec();
+        let id_end = added.len();
+        added.extend(b"Vendor=\"1\"\r\n");
+        added.extend(&broken[id_end..]);
+        let Err(error::ProjectStructure::NomParseError(offset, _, _)) =
+            Project::from_bytes(&added, 1252, &Options::default())
+        else {
+            panic!("a broken CMG property should not parse")
+        };
+        assert_eq!(cmg + 5 + 12, offset);
+    }
+
+    #[test]
+    fn partial() {
+        let original = project_stream("tests/data/xls/Locked_with_macro.xls");
+        let cmg = original
+            .windows(5)
+            .position(|w| w == b"CMG=\"")
+            .unwrap_or_else(|| panic!("no CMG property"));
+        let mut broken = original;
+        broken[cmg + 5] = b'X';
+        let Err((partial, _)) = Project::from_bytes_partial(&broken, 1252, &Options::default())
+        else {
+            panic!("a broken CMG property should not parse")
+        };
+        assert_eq!(Some(guid::Guid::NIL), partial.id());
+        assert_eq!(Some("VBAProject"), partial.name());
+        assert_eq!(
+            [
+                ("ThisWorkbook".to_string(), ModuleType::Document),
+                ("Sheet1".to_string(), ModuleType::Document),
+                ("Module1".to_string(), ModuleType::Standard),
+            ],
+            partial.modules()
+        );
+        assert!(partial.protection_state().is_none());
+        assert!(matches!(partial.password(), Some(Password::Hash(_, _))));
+        assert!(partial.visibility().is_some_and(|v| !v.is_visible()));
+    }
+
+    #[test]
+    fn warnings() {
+        let original = project_stream("tests/data/xls/Unlocked_with_macro.xls");
+        let project = Project::from_bytes(&original, 1252, &Options::default())
+            .unwrap_or_else(|e| panic!("{e}"));
+        assert!(project.warnings().is_empty());
+
+        // Set a reserved bit of the protection state
+        let key = project_key(&project.id().to_string());
+        let cmg = encrypted_property("CMG", key, &[0x0c, 0x00, 0x00, 0x00]);
+        let changed: Vec<u8> = original
+            .split_inclusive(|&b| b == b'\n')
+            .flat_map(|line| {
+                if line.starts_with(b"CMG=") {
+                    [cmg.as_slice(), b"\r\n"].concat()
+                } else {
+                    line.to_vec()
+                }
+            })
+            .collect();
+        let project = Project::from_bytes(&changed, 1252, &Options::default())
+            .unwrap_or_else(|e| panic!("{e}"));
+        assert!(project.is_locked());
+        assert_eq!(1, project.warnings().len());
+
+        let strict = Project::from_bytes(
+            &changed,
+            1252,
+            &Options {
+                strict: true,
+                ..Options::default()
+            },
+        );
+        assert!(strict.is_err());
+    }
+}
@@ -0,0 +1,124 @@
+#![allow(clippy::doc_markdown)]
+//! A Struct to hold the contents of the PROJECTwm stream
+//!
+//! The PROJECTwm stream maps the name of each module, as written in the code page of the project
+//! in the PROJECT stream, to the same name in UTF-16. The names of modules in workbooks made in
+//! other languages can only be shown properly by going through this map.
+//!
+//! PROJECTwm = *NameMap
+//!             Terminator
+//!
+//! NameMap = ModuleName (in the code page of the project, ending in 0x00)
+//!           ModuleNameUnicode (UTF-16, ending in 0x0000)
+//!
+//! The layout is given in section 2.3.3 of the MS-OVBA specification
+
+use crate::{error, ovba::types::mbcs};
+use cfb::Stream;
+use std::io::Read;
+use tracing::debug;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct NameMap {
+    names: Vec<(String, String)>,
+}
+
+impl NameMap {
+    pub fn from_stream<T: std::io::Read + std::io::Seek>(
+        mut stream: Stream<T>,
+        code_page: u16,
+    ) -> Result<Self, error::ProjectStructure> {
+        let mut buf = Vec::new();
+        let _ = stream.read_to_end(&mut buf);
+        Self::from_bytes(&buf, code_page)
+    }
+
+    /// Parse the bytes of the PROJECTwm stream, decoding the names that are not in UTF-16 in the
+    /// code page given
+    pub fn from_bytes(buf: &[u8], code_page: u16) -> Result<Self, error::ProjectStructure> {
+        let mut names = Vec::new();
+        let mut position = 0;
+        loop {
+            // The map ends with a two byte terminator, where the next name would have started
+            match buf.get(position..position + 2) {
+                Some([0x00, 0x00]) => break,
+                Some(_) => (),
+                None => return Err(error::ProjectStructure::NameMap(position)),
+            }
+            let end = buf[position..]
+                .iter()
+                .position(|&b| b == 0x00)
+                .map(|len| position + len)
+                .ok_or(error::ProjectStructure::NameMap(position))?;
+            let name = mbcs::decode(&buf[position..end], code_page);
+            position = end + 1;
+
+            let mut unicode = Vec::new();
+            loop {
+                let Some(&[lo, hi]) = buf.get(position..position + 2) else {
+                    return Err(error::ProjectStructure::NameMap(position));
+                };
+                position += 2;
+                match u16::from_le_bytes([lo, hi]) {
+                    0x0000 => break,
+                    c => unicode.push(c),
+                }
+            }
+            names.push((name, String::from_utf16_lossy(&unicode)));
+        }
+        debug!("parsed {} names from the PROJECTwm stream", names.len());
+
+        Ok(Self { names })
+    }
+
+    /// The name of each module as written in the PROJECT stream, along with its Unicode name
+    pub fn names(&self) -> &[(String, String)] {
+        &self.names
+    }
+
+    /// The Unicode name of the module with the name given in the PROJECT stream, if it is mapped
+    pub fn unicode_name(&self, name: &str) -> Option<&str> {
+        self.names
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, unicode)| unicode.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_map() {
+        let buf = [
+            0x41, 0x00, 0x41, 0x00, 0x00, 0x00, 0x3f, 0x00, 0x1f, 0x04, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let map = NameMap::from_bytes(&buf, 1252).unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(
+            [
+                ("A".to_string(), "A".to_string()),
+                ("?".to_string(), "\u{41f}".to_string())
+            ],
+            map.names()
+        );
+        assert_eq!(Some("\u{41f}"), map.unicode_name("?"));
+        assert_eq!(None, map.unicode_name("B"));
+    }
+
+    #[test]
+    fn empty_name_map() {
+        let map = NameMap::from_bytes(&[0x00, 0x00], 1252).unwrap_or_else(|e| panic!("{e}"));
+        assert!(map.names().is_empty());
+    }
+
+    #[test]
+    fn truncated_name_map() {
+        for buf in [&[][..], &[0x41, 0x00, 0x41], &[0x41, 0x42]] {
+            assert!(matches!(
+                NameMap::from_bytes(buf, 1252),
+                Err(error::ProjectStructure::NameMap(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,80 @@
+#![allow(clippy::doc_markdown)]
+//! A Struct to hold the contents of the _VBA_PROJECT stream
+//!
+//! The _VBA_PROJECT stream holds the PerformanceCache of the project as a whole. All that can be
+//! read of it is the header:
+//!
+//! _VBA_PROJECT = Reserved1 (0x61CC)
+//!                Version (2 bytes)
+//!                Reserved2 (0x00)
+//!                Reserved3 (2 bytes)
+//!                PerformanceCache
+//!
+//! The version is that of the VBA that compiled the PerformanceCache, which is not documented. It
+//! changes between releases of Office, and between their 32-bit and 64-bit builds. Office only
+//! runs the compiled p-code of the modules when the version matches its own, and compiles the
+//! source afresh otherwise.
+//!
+//! The layout is given in section 2.3.4.1 of the MS-OVBA specification
+
+use crate::error;
+use cfb::Stream;
+use std::io::Read;
+
+/// The two bytes that every _VBA_PROJECT stream starts with
+const RESERVED_1: [u8; 2] = [0xcc, 0x61];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VbaProject {
+    version: u16,
+}
+
+impl VbaProject {
+    pub fn from_stream<T: std::io::Read + std::io::Seek>(
+        mut stream: Stream<T>,
+    ) -> Result<Self, error::ProjectStructure> {
+        let mut buf = Vec::new();
+        let _ = stream.read_to_end(&mut buf);
+        Self::from_bytes(&buf)
+    }
+
+    pub fn from_bytes(buf: &[u8]) -> Result<Self, error::ProjectStructure> {
+        match buf.get(0..4) {
+            Some(&[reserved_1, reserved_2, lo, hi]) if [reserved_1, reserved_2] == RESERVED_1 => {
+                Ok(Self {
+                    version: u16::from_le_bytes([lo, hi]),
+                })
+            }
+            _ => Err(error::ProjectStructure::VbaProject(
+                buf.iter().take(4).copied().collect(),
+            )),
+        }
+    }
+
+    /// The version of the VBA that compiled the project
+    pub const fn version(self) -> u16 {
+        self.version
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version() {
+        let project = VbaProject::from_bytes(&[0xcc, 0x61, 0xb5, 0x00, 0x00, 0x03, 0x00])
+            .unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(0xb5, project.version());
+    }
+
+    #[test]
+    fn not_vba_project() {
+        for buf in [&[][..], &[0xcc, 0x61, 0xb5], &[0x61, 0xcc, 0xb5, 0x00]] {
+            assert!(matches!(
+                VbaProject::from_bytes(buf),
+                Err(error::ProjectStructure::VbaProject(_))
+            ));
+        }
+    }
+}
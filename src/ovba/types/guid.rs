@@ -1,46 +1,98 @@
 use nom::{
-    bytes::complete::{tag, take},
+    bytes::complete::tag,
+    combinator::map,
+    number::complete::be_u64,
+    sequence::{preceded, terminated, tuple},
     IResult,
 };
 
-use super::u128_from_hex_bytes;
+use super::number;
+use crate::error;
+use std::{fmt::Display, str::FromStr};
 
-pub type Guid = u128;
+/// A globally unique identifier, held in the four fields it is made up of. The first three are
+/// numbers, while the last is 8 bytes kept in the order they are written
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Guid {
+    data1: u32,
+    data2: u16,
+    data3: u16,
+    data4: [u8; 8],
+}
+
+impl Guid {
+    /// The GUID of all zeros
+    pub const NIL: Self = Self::from_u128(0);
+
+    /// The GUID whose digits, as written between the braces, are those of the number
+    #[must_use]
+    pub const fn from_u128(value: u128) -> Self {
+        let b = value.to_be_bytes();
+        Self {
+            data1: u32::from_be_bytes([b[0], b[1], b[2], b[3]]),
+            data2: u16::from_be_bytes([b[4], b[5]]),
+            data3: u16::from_be_bytes([b[6], b[7]]),
+            data4: [b[8], b[9], b[10], b[11], b[12], b[13], b[14], b[15]],
+        }
+    }
+}
+
+/// Written in the same way as in the PROJECT stream e.g. `{00000000-0000-0000-0000-000000000000}`
+impl Display for Guid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let d = self.data4;
+        write!(
+            f,
+            "{{{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}}}",
+            self.data1, self.data2, self.data3, d[0], d[1], d[2], d[3], d[4], d[5], d[6], d[7]
+        )
+    }
+}
+
+impl FromStr for Guid {
+    type Err = error::InvalidGuid;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse(s.as_bytes()) {
+            Ok(([], guid)) => Ok(guid),
+            _ => Err(s.to_string().into()),
+        }
+    }
+}
 
 pub fn parse(input: &[u8]) -> IResult<&[u8], Guid> {
-    let (input, _) = tag(b"{")(input)?;
-
-    let (input, num) = take(8_usize)(input)?;
-    let num = u128_from_hex_bytes(num)?;
-    let mut output = num;
-
-    let (input, _) = tag(b"-")(input)?;
-    let (input, num) = take(4_usize)(input)?;
-    let num = u128_from_hex_bytes(num)?;
-    output <<= 16;
-    output |= num;
-
-    let (input, _) = tag(b"-")(input)?;
-    let (input, num) = take(4_usize)(input)?;
-    let num = u128_from_hex_bytes(num)?;
-    output <<= 16;
-    output |= num;
-
-    let (input, _) = tag(b"-")(input)?;
-    let (input, num) = take(4_usize)(input)?;
-    let num = u128_from_hex_bytes(num)?;
-    output <<= 16;
-    output |= num;
-
-    let (input, _) = tag(b"-")(input)?;
-    let (input, num) = take(12_usize)(input)?;
-    let num = u128_from_hex_bytes(num)?;
-    output <<= 48;
-    output |= num;
-
-    let (input, _) = tag(b"}")(input)?;
-
-    Ok((input, output))
+    map(
+        tuple((
+            preceded(tag(b"{"), number::hex::<u32>(8)),
+            preceded(tag(b"-"), number::hex::<u16>(4)),
+            preceded(tag(b"-"), number::hex::<u16>(4)),
+            preceded(tag(b"-"), number::hex::<u16>(4)),
+            preceded(tag(b"-"), terminated(number::hex::<u64>(12), tag(b"}"))),
+        )),
+        |(data1, data2, data3, clock, node)| {
+            let (c, n) = (clock.to_be_bytes(), node.to_be_bytes());
+            Guid {
+                data1,
+                data2,
+                data3,
+                data4: [c[0], c[1], n[2], n[3], n[4], n[5], n[6], n[7]],
+            }
+        },
+    )(input)
+}
+
+/// Parse a GUID stored as 16 bytes of binary data, rather than as text. The first three parts are
+/// stored little-endian and the final 8 bytes in order
+pub fn parse_binary(input: &[u8]) -> IResult<&[u8], Guid> {
+    map(
+        tuple((number::le(4), number::le_u16, number::le_u16, be_u64)),
+        |(data1, data2, data3, data4)| Guid {
+            data1,
+            data2,
+            data3,
+            data4: data4.to_be_bytes(),
+        },
+    )(input)
 }
 
 #[cfg(test)]
@@ -55,15 +107,78 @@ mod tests {
     fn well_formed() {
         assert_eq!(
             parse(b"{00000000-0000-0000-0000-000000000000}"),
-            Ok((&b""[..], 0))
+            Ok((&b""[..], Guid::NIL))
         );
         assert_eq!(
             parse(b"{3832D640-CF90-11CF-8E43-00A0C911005A}"),
             Ok((
                 &b""[..],
-                u128::from_str_radix("3832d640cf9011cf8e4300a0c911005a", 16).unwrap()
+                Guid::from_u128(0x3832_d640_cf90_11cf_8e43_00a0_c911_005a)
+            ))
+        );
+    }
+
+    #[test]
+    fn binary() {
+        assert_eq!(
+            parse_binary(&[
+                0xe1, 0x2e, 0x45, 0x0d, 0x8f, 0xe0, 0x1a, 0x10, 0x85, 0x2e, 0x02, 0x60, 0x8c, 0x4d,
+                0x0b, 0xb4, 0x02
+            ]),
+            Ok((
+                &[0x02][..],
+                Guid::from_u128(0x0d45_2ee1_e08f_101a_852e_0260_8c4d_0bb4)
             ))
         );
+        assert_eq!(
+            parse_binary(&[0xe1, 0x2e, 0x45, 0x0d]),
+            Err(Err::Error(Error::new(&[][..], ErrorKind::Eof)))
+        );
+    }
+
+    #[test]
+    fn formatted() {
+        assert_eq!(
+            Guid::from_u128(0x0d45_2ee1_e08f_101a_852e_0260_8c4d_0bb4).to_string(),
+            "{0D452EE1-E08F-101A-852E-02608C4D0BB4}"
+        );
+        assert_eq!(
+            parse(b"{00000000-0000-0000-0000-000000000000}")
+                .unwrap()
+                .1
+                .to_string(),
+            "{00000000-0000-0000-0000-000000000000}"
+        );
+    }
+
+    #[test]
+    fn from_str() {
+        let text = "{3832D640-CF90-11CF-8E43-00A0C911005A}";
+        let guid: Guid = text.parse().unwrap();
+        assert_eq!(text, guid.to_string());
+        assert_eq!(
+            guid,
+            "{3832d640-cf90-11cf-8e43-00a0c911005a}".parse().unwrap()
+        );
+        for bad in [
+            "",
+            "3832D640-CF90-11CF-8E43-00A0C911005A",
+            &format!("{text} "),
+        ] {
+            assert!(bad.parse::<Guid>().is_err());
+        }
+    }
+
+    #[test]
+    fn binary_matches_text() {
+        let binary = [
+            0x40, 0xd6, 0x32, 0x38, 0x90, 0xcf, 0xcf, 0x11, 0x8e, 0x43, 0x00, 0xa0, 0xc9, 0x11,
+            0x00, 0x5a,
+        ];
+        assert_eq!(
+            parse_binary(&binary).unwrap().1,
+            parse(b"{3832D640-CF90-11CF-8E43-00A0C911005A}").unwrap().1
+        );
     }
 
     #[test]
@@ -72,7 +187,7 @@ mod tests {
             parse(b"{3832D640-CF90-11CF-8E43-00A0C911005A}{00000000-0000-0000-0000-000000000000}"),
             Ok((
                 &b"{00000000-0000-0000-0000-000000000000}"[..],
-                u128::from_str_radix("3832d640cf9011cf8e4300a0c911005a", 16).unwrap()
+                Guid::from_u128(0x3832_d640_cf90_11cf_8e43_00a0_c911_005a)
             ))
         );
     }
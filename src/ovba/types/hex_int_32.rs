@@ -1,17 +1,11 @@
-use nom::{
-    bytes::complete::{tag, take},
-    IResult,
-};
+use nom::{bytes::complete::tag, sequence::preceded, IResult};
 
-use super::i32_from_hex_bytes;
+use super::number;
 
 pub type HexInt32 = i32;
 
 pub fn parse(input: &[u8]) -> IResult<&[u8], HexInt32> {
-    let (input, _) = tag(b"&H")(input)?;
-    let (input, num) = take(8_usize)(input)?;
-    let num = i32_from_hex_bytes(num)?;
-    Ok((input, num))
+    preceded(tag(b"&H"), number::hex(8))(input)
 }
 
 #[cfg(test)]
@@ -0,0 +1,96 @@
+#![allow(clippy::doc_markdown)]
+//! The identifier of a library referred to by a VBA project
+//!
+//! A registered type library is identified by a LibidReference, of the form
+//! `*\G{GUID}#Version#LCID#Path#Description`. The GUID and version are those the library is
+//! registered under, while the path is where it was found on the machine that last saved the
+//! project. A reference to another VBA project is identified by its path alone, after `*\` and a
+//! letter giving the kind of path.
+//!
+//! The layout is given in section 2.1.1.8 of the MS-OVBA specification
+
+use super::guid;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Libid {
+    /// The GUID the library is registered under, if it is a registered library
+    pub guid: Option<guid::Guid>,
+    /// The version of the library, e.g. `2.0`
+    pub version: Option<String>,
+    /// The path to the library, or to the project referred to
+    pub path: String,
+    /// The description of the library, e.g. `OLE Automation`
+    pub description: Option<String>,
+}
+
+/// Split a library identifier into its parts. An identifier that does not follow the
+/// specification is taken to be a path
+pub fn parse(libid: &str) -> Libid {
+    let Some(rest) = libid.strip_prefix("*\\") else {
+        return path_only(libid);
+    };
+    let Some(registered) = rest.strip_prefix('G').or_else(|| rest.strip_prefix('H')) else {
+        // Any other kind is a reference to a project, which is known by its path alone
+        return path_only(rest.get(1..).unwrap_or_default());
+    };
+    let mut parts = registered.splitn(5, '#');
+    let (Some(id), Some(version), Some(_lcid), Some(path), Some(description)) = (
+        parts.next(),
+        parts.next(),
+        parts.next(),
+        parts.next(),
+        parts.next(),
+    ) else {
+        return path_only(libid);
+    };
+    Libid {
+        guid: id.parse().ok(),
+        version: Some(version.to_string()),
+        path: path.to_string(),
+        description: Some(description.to_string()),
+    }
+}
+
+fn path_only(path: &str) -> Libid {
+    Libid {
+        guid: None,
+        version: None,
+        path: path.to_string(),
+        description: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registered_library() {
+        assert_eq!(
+            Libid {
+                guid: Some(guid::Guid::from_u128(
+                    0x0002_0430_0000_0000_C000_0000_0000_0046
+                )),
+                version: Some("2.0".to_string()),
+                path: "C:\\Windows\\System32\\stdole2.tlb".to_string(),
+                description: Some("OLE Automation".to_string()),
+            },
+            parse("*\\G{00020430-0000-0000-C000-000000000046}#2.0#0#C:\\Windows\\System32\\stdole2.tlb#OLE Automation")
+        );
+    }
+
+    #[test]
+    fn project() {
+        assert_eq!(
+            path_only("C:\\Users\\me\\Book1.xlsm"),
+            parse("*\\CC:\\Users\\me\\Book1.xlsm")
+        );
+    }
+
+    #[test]
+    fn not_in_specification() {
+        assert_eq!(path_only("stdole2.tlb"), parse("stdole2.tlb"));
+        assert_eq!(path_only("*\\G{bad}#2.0"), parse("*\\G{bad}#2.0"));
+        assert_eq!(None, parse("*\\G{bad}#2.0#0#a.tlb#A").guid);
+    }
+}
@@ -0,0 +1,32 @@
+#![allow(clippy::doc_markdown)]
+//! The language and code page of a VBA project
+//!
+//! Both are recorded in the dir stream, by the PROJECTLCID and PROJECTCODEPAGE records. The code
+//! page is the one every MBCS string of the project is encoded in, including the whole of the
+//! PROJECT stream, so it is needed before any of their text can be decoded. The LCID is that of
+//! the language the project was written in.
+//!
+//! The records are given in sections 2.3.4.2.1.3 and 2.3.4.2.1.5 of the MS-OVBA specification
+
+/// The language and code page of a VBA project, as recorded in its dir stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Locale {
+    lcid: u32,
+    code_page: u16,
+}
+
+impl Locale {
+    pub const fn new(lcid: u32, code_page: u16) -> Self {
+        Self { lcid, code_page }
+    }
+
+    /// The identifier of the language of the project e.g. 1033 for US English
+    pub const fn lcid(self) -> u32 {
+        self.lcid
+    }
+
+    /// The code page that the MBCS strings of the project are encoded in
+    pub const fn code_page(self) -> u16 {
+        self.code_page
+    }
+}
@@ -0,0 +1,62 @@
+//! Text in the code page of the VBA project
+//!
+//! The PROJECT stream, the `PROJECTwm` stream and the MBCS records of the dir stream hold their text
+//! in the code page given by the PROJECTCODEPAGE record of the dir stream e.g. 1252 for Western
+//! European, 1251 for Cyrillic or 932 for Japanese. The dir stream carries its own code page, and
+//! the other streams are parsed in the code page read from it, falling back on 1252 where it is not
+//! known
+
+/// The code page Excel uses for Western European languages, taken when no other is known
+pub const DEFAULT_CODE_PAGE: u16 = 1252;
+
+/// Decode text in the code page given. A code page that is not known is read as 1252, and any
+/// bytes that are not valid in the code page are replaced
+pub fn decode(bytes: &[u8], code_page: u16) -> String {
+    let encoding = codepage::to_encoding(code_page).unwrap_or(encoding_rs::WINDOWS_1252);
+    encoding.decode_without_bom_handling(bytes).0.into_owned()
+}
+
+/// Encode text in the code page given, the reverse of [`decode`]. Any characters that cannot be
+/// written in the code page are replaced
+pub fn encode(text: &str, code_page: u16) -> Vec<u8> {
+    let encoding = codepage::to_encoding(code_page).unwrap_or(encoding_rs::WINDOWS_1252);
+    encoding.encode(text).0.into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_pages() {
+        assert_eq!("café", decode(b"caf\xe9", 1252));
+        assert_eq!(
+            "Проект",
+            decode(&[0xcf, 0xf0, 0xee, 0xe5, 0xea, 0xf2], 1251)
+        );
+        assert_eq!(
+            "モジュール",
+            decode(
+                &[0x83, 0x82, 0x83, 0x57, 0x83, 0x85, 0x81, 0x5b, 0x83, 0x8b],
+                932
+            )
+        );
+        // A code page that does not exist is read as 1252
+        assert_eq!("café", decode(b"caf\xe9", 1));
+    }
+
+    #[test]
+    fn round_trip() {
+        for code_page in [1252, 1251, 932] {
+            let bytes: Vec<u8> = (0x20..=0x7e).collect();
+            assert_eq!(bytes, encode(&decode(&bytes, code_page), code_page));
+        }
+        // Every byte has a meaning in 1252, so nothing is lost in going through it
+        let bytes: Vec<u8> = (0x00..=0xff).collect();
+        assert_eq!(bytes, encode(&decode(&bytes, 1252), 1252));
+        assert_eq!(
+            vec![0x83, 0x82, 0x83, 0x57, 0x83, 0x85, 0x81, 0x5b, 0x83, 0x8b],
+            encode("モジュール", 932)
+        );
+    }
+}
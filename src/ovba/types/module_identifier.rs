@@ -1,24 +1,54 @@
+use super::mbcs;
 use nom::{
-    bytes::complete::take_while_m_n,
+    branch::alt,
+    bytes::complete::{take_while1, take_while_m_n},
     character::{is_alphabetic, is_alphanumeric},
-    combinator::{map, recognize},
+    combinator::{map, map_opt, recognize},
     sequence::pair,
     IResult,
 };
 
 pub type ModuleIdentifier = String;
 
-pub fn parse(input: &[u8]) -> IResult<&[u8], ModuleIdentifier> {
-    map(
-        recognize(pair(
-            take_while_m_n(1, 1, is_alphabetic),
-            take_while_m_n(0, 30, |b| is_alphanumeric(b) || b == b'_'),
-        )),
-        |s: &[u8]| {
-            String::from_utf8(s.to_vec())
-                .expect("alphanumeric bytes and _ converting into a String")
-        },
-    )(input)
+/// The name of a module, which can have letters outside ASCII in the code page of the project
+pub fn parse(code_page: u16) -> impl Fn(&[u8]) -> IResult<&[u8], ModuleIdentifier> {
+    move |input: &[u8]| {
+        alt((
+            mbcs_identifier(code_page),
+            map(
+                recognize(pair(
+                    take_while_m_n(1, 1, is_alphabetic),
+                    take_while_m_n(0, 30, |b| is_alphanumeric(b) || b == b'_'),
+                )),
+                |s: &[u8]| {
+                    String::from_utf8(s.to_vec())
+                        .expect("alphanumeric bytes and _ converting into a String")
+                },
+            ),
+        ))(input)
+    }
+}
+
+/// A name with letters outside ASCII, in the code page of the project. A byte of a double byte
+/// character can look like punctuation, so the whole name is taken up to the next delimiter and
+/// checked once decoded
+fn mbcs_identifier(code_page: u16) -> impl Fn(&[u8]) -> IResult<&[u8], ModuleIdentifier> {
+    move |input: &[u8]| {
+        map_opt(
+            take_while1(|b| b > 0x20 && b != b'=' && b != b'/'),
+            |s: &[u8]| {
+                if s.is_ascii() {
+                    return None;
+                }
+                let name = mbcs::decode(s, code_page);
+                let mut chars = name.chars();
+                let valid = chars.next().is_some_and(char::is_alphabetic)
+                    && chars.all(|c| c.is_alphanumeric() || c == '_')
+                    && name.chars().count() <= 31;
+                valid.then_some(name)
+            },
+        )(input)
+    }
 }
 
 #[cfg(test)]
@@ -32,21 +62,21 @@ mod tests {
     #[test]
     fn bad_leading_char() {
         assert_eq!(
-            parse(b"01234"),
+            parse(1252)(b"01234"),
             Err(Err::Error(Error::new(
                 &b"01234"[..],
                 ErrorKind::TakeWhileMN
             )))
         );
         assert_eq!(
-            parse(b" 1234"),
+            parse(1252)(b" 1234"),
             Err(Err::Error(Error::new(
                 &b" 1234"[..],
                 ErrorKind::TakeWhileMN
             )))
         );
         assert_eq!(
-            parse(b"*1234"),
+            parse(1252)(b"*1234"),
             Err(Err::Error(Error::new(
                 &b"*1234"[..],
                 ErrorKind::TakeWhileMN
@@ -57,7 +87,7 @@ mod tests {
     #[test]
     fn long_input() {
         assert_eq!(
-            parse(b"A_really_really_long_string_that_is_more_than_31_characters"),
+            parse(1252)(b"A_really_really_long_string_that_is_more_than_31_characters"),
             Ok((
                 &b"t_is_more_than_31_characters"[..],
                 String::from("A_really_really_long_string_tha")
@@ -67,26 +97,47 @@ mod tests {
 
     #[test]
     fn terminating_char() {
-        assert_eq!(parse(b"A01234\n"), Ok((&b"\n"[..], String::from("A01234"))));
         assert_eq!(
-            parse(b"A01234\r\n"),
+            parse(1252)(b"A01234\n"),
+            Ok((&b"\n"[..], String::from("A01234")))
+        );
+        assert_eq!(
+            parse(1252)(b"A01234\r\n"),
             Ok((&b"\r\n"[..], String::from("A01234")))
         );
         assert_eq!(
-            parse(b"A01234\n\r"),
+            parse(1252)(b"A01234\n\r"),
             Ok((&b"\n\r"[..], String::from("A01234")))
         );
         assert_eq!(
-            parse(b"A01234&another_thing"),
+            parse(1252)(b"A01234&another_thing"),
             Ok((&b"&another_thing"[..], String::from("A01234")))
         );
     }
 
+    #[test]
+    fn mbcs_input() {
+        let sheet = [0x83, 0x56, 0x81, 0x5b, 0x83, 0x67, 0x31];
+        let mut input = sheet.to_vec();
+        input.extend(b"/&H00000000");
+        assert_eq!(
+            parse(932)(&input),
+            Ok((&b"/&H00000000"[..], String::from("シート1")))
+        );
+        assert_eq!(
+            parse(1251)(b"\xcc\xee\xe4\xf3\xeb\xfc1\r\n"),
+            Ok((&b"\r\n"[..], String::from("Модуль1")))
+        );
+    }
+
     #[test]
     fn short_input() {
-        assert_eq!(parse(b"A_module"), Ok((&b""[..], String::from("A_module"))));
         assert_eq!(
-            parse(b"A_really_really_long_module_xxx"),
+            parse(1252)(b"A_module"),
+            Ok((&b""[..], String::from("A_module")))
+        );
+        assert_eq!(
+            parse(1252)(b"A_really_really_long_module_xxx"),
             Ok((&b""[..], String::from("A_really_really_long_module_xxx")))
         );
     }
@@ -1,9 +1,32 @@
 use nom::{branch::alt, bytes::complete::tag, combinator::value, IResult};
 
-pub type NwLn = u8;
+/// The end of a line of the PROJECT stream. The specification allows CRLF or LFCR, which is all
+/// Excel writes, but some other tools write a bare LF
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum NwLn {
+    #[default]
+    CrLf,
+    LfCr,
+    Lf,
+}
+
+impl NwLn {
+    /// The bytes that end the line
+    pub const fn as_bytes(self) -> &'static [u8] {
+        match self {
+            Self::CrLf => b"\r\n",
+            Self::LfCr => b"\n\r",
+            Self::Lf => b"\n",
+        }
+    }
+}
 
 pub fn parse(input: &[u8]) -> IResult<&[u8], NwLn> {
-    value(0x0a, alt((tag("\r\n"), tag("\n\r"))))(input)
+    alt((
+        value(NwLn::CrLf, tag("\r\n")),
+        value(NwLn::LfCr, tag("\n\r")),
+        value(NwLn::Lf, tag("\n")),
+    ))(input)
 }
 
 #[cfg(test)]
@@ -16,28 +39,33 @@ mod tests {
 
     #[test]
     fn well_formed() {
-        assert_eq!(parse(b"\r\n"), Ok((&b""[..], b'\n')));
-        assert_eq!(parse(b"\n\r"), Ok((&b""[..], b'\n')));
+        assert_eq!(parse(b"\r\n"), Ok((&b""[..], NwLn::CrLf)));
+        assert_eq!(parse(b"\n\r"), Ok((&b""[..], NwLn::LfCr)));
+        assert_eq!(parse(b"\n"), Ok((&b""[..], NwLn::Lf)));
     }
 
     #[test]
     fn further_data() {
         assert_eq!(
             parse(b"\r\nsomething else"),
-            Ok((&b"something else"[..], b'\n'))
+            Ok((&b"something else"[..], NwLn::CrLf))
+        );
+        assert_eq!(
+            parse(b"\nsomething else"),
+            Ok((&b"something else"[..], NwLn::Lf))
         );
     }
 
     #[test]
-    fn just_one() {
+    fn just_carriage_return() {
         assert_eq!(
-            parse(b"\n"),
-            Err(Err::Error(Error::new(&b"\n"[..], ErrorKind::Tag)))
+            parse(b"\r"),
+            Err(Err::Error(Error::new(&b"\r"[..], ErrorKind::Tag)))
         );
         assert_eq!(
-            parse(b"\nsomething else"),
+            parse(b"\rsomething else"),
             Err(Err::Error(Error::new(
-                &b"\nsomething else"[..],
+                &b"\rsomething else"[..],
                 ErrorKind::Tag
             )))
         );
@@ -53,4 +81,11 @@ mod tests {
             )))
         );
     }
+
+    #[test]
+    fn round_trip() {
+        for new_line in [NwLn::CrLf, NwLn::LfCr, NwLn::Lf] {
+            assert_eq!(parse(new_line.as_bytes()), Ok((&b""[..], new_line)));
+        }
+    }
 }
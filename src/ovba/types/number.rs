@@ -0,0 +1,143 @@
+//! Integers of a fixed width, either written out as hex digits, as in the PROJECT stream, or stored
+//! as little-endian bytes, as in the dir stream and BIFF records
+//!
+//! Each parser takes exactly the number of digits, or bytes, it is given. A little-endian integer
+//! can take up fewer bytes than the type it is read into, such as the 48-bit integers some records
+//! hold, in which case the missing high bytes are zero
+
+use nom::{bytes::complete::take, combinator::map, error::ErrorKind, IResult};
+
+/// An integer type that can be parsed from hex digits or little-endian bytes
+pub trait Integer: Sized {
+    /// The number of bytes the integer takes up
+    const BYTES: usize;
+
+    /// Read the integer from hex digits, if they all are hex digits and it does not overflow
+    fn from_hex(digits: &[u8]) -> Option<Self>;
+
+    /// Read the integer from no more than [`Self::BYTES`] little-endian bytes
+    fn from_le_slice(bytes: &[u8]) -> Self;
+}
+
+macro_rules! integer {
+    ($($t:ty),*) => {
+        $(
+            impl Integer for $t {
+                const BYTES: usize = std::mem::size_of::<$t>();
+
+                fn from_hex(digits: &[u8]) -> Option<Self> {
+                    if digits.is_empty() || !digits.iter().all(u8::is_ascii_hexdigit) {
+                        return None;
+                    }
+                    let digits = std::str::from_utf8(digits).ok()?;
+                    <$t>::from_str_radix(digits, 16).ok()
+                }
+
+                fn from_le_slice(bytes: &[u8]) -> Self {
+                    let mut buf = [0; std::mem::size_of::<$t>()];
+                    buf[..bytes.len()].copy_from_slice(bytes);
+                    <$t>::from_le_bytes(buf)
+                }
+            }
+        )*
+    };
+}
+
+integer!(u8, u16, u32, u64, u128, i16, i32, i64);
+
+/// An integer written as exactly the number of hex digits given e.g. `7A12CF0A`
+pub fn hex<T: Integer>(digits: usize) -> impl Fn(&[u8]) -> IResult<&[u8], T> {
+    move |input: &[u8]| {
+        let (input, num) = take(digits)(input)?;
+        let num = T::from_hex(num)
+            .ok_or_else(|| nom::Err::Error(nom::error::Error::new(num, ErrorKind::HexDigit)))?;
+        Ok((input, num))
+    }
+}
+
+/// An integer stored as the number of little-endian bytes given, which must be no more than the
+/// integer takes up
+pub fn le<T: Integer>(bytes: usize) -> impl Fn(&[u8]) -> IResult<&[u8], T> {
+    assert!(bytes <= T::BYTES, "too many bytes for the integer");
+    move |input: &[u8]| map(take(bytes), T::from_le_slice)(input)
+}
+
+/// A 16-bit little-endian integer
+pub fn le_u16(input: &[u8]) -> IResult<&[u8], u16> {
+    le(2)(input)
+}
+
+/// A 32-bit little-endian integer
+pub fn le_u32(input: &[u8]) -> IResult<&[u8], u32> {
+    le(4)(input)
+}
+
+/// A 48-bit little-endian integer
+#[allow(dead_code)]
+pub fn le_u48(input: &[u8]) -> IResult<&[u8], u64> {
+    le(6)(input)
+}
+
+/// A 64-bit little-endian integer
+#[allow(dead_code)]
+pub fn le_u64(input: &[u8]) -> IResult<&[u8], u64> {
+    le(8)(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::{error::Error, Err};
+
+    #[test]
+    fn hex_widths() {
+        assert_eq!(hex::<u16>(4)(b"CF90-"), Ok((&b"-"[..], 0xcf90)));
+        assert_eq!(
+            hex::<u64>(12)(b"00A0C911005A}"),
+            Ok((&b"}"[..], 0x00a0_c911_005a))
+        );
+        assert_eq!(
+            hex::<u64>(16)(b"fedcba9876543210"),
+            Ok((&b""[..], 0xfedc_ba98_7654_3210))
+        );
+        assert_eq!(
+            hex::<i32>(8)(b"FFFFFFFF"),
+            Err(Err::Error(Error::new(
+                &b"FFFFFFFF"[..],
+                ErrorKind::HexDigit
+            )))
+        );
+    }
+
+    #[test]
+    fn not_hex() {
+        for digits in [&b"CF9-"[..], b"+CF9", b"cf 9"] {
+            assert_eq!(
+                hex::<u16>(4)(digits),
+                Err(Err::Error(Error::new(digits, ErrorKind::HexDigit)))
+            );
+        }
+        assert_eq!(
+            hex::<u16>(4)(b"CF9"),
+            Err(Err::Error(Error::new(&b"CF9"[..], ErrorKind::Eof)))
+        );
+    }
+
+    #[test]
+    fn little_endian() {
+        let bytes = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09];
+        assert_eq!(le_u16(&bytes), Ok((&bytes[2..], 0x0201)));
+        assert_eq!(le_u32(&bytes), Ok((&bytes[4..], 0x0403_0201)));
+        assert_eq!(le_u48(&bytes), Ok((&bytes[6..], 0x0605_0403_0201)));
+        assert_eq!(le_u64(&bytes), Ok((&bytes[8..], 0x0807_0605_0403_0201)));
+        assert_eq!(le::<i16>(2)(&[0xff, 0xff]), Ok((&[][..], -1)));
+        assert_eq!(
+            le_u32(&bytes[..3]),
+            Err(Err::Error(Error::new(&bytes[..3], ErrorKind::Eof)))
+        );
+        assert_eq!(
+            le_u48(&bytes[..5]),
+            Err(Err::Error(Error::new(&bytes[..5], ErrorKind::Eof)))
+        );
+    }
+}
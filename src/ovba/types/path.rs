@@ -4,8 +4,8 @@ use super::quoted_characters;
 
 pub type Path = String;
 
-pub fn parse(input: &[u8]) -> IResult<&[u8], Path> {
-    quoted_characters::parse(0, 259)(input)
+pub fn parse(code_page: u16) -> impl Fn(&[u8]) -> IResult<&[u8], Path> {
+    quoted_characters::parse(0, 259, code_page)
 }
 
 #[cfg(test)]
@@ -19,7 +19,7 @@ mod tests {
     #[test]
     fn find_a_path() {
         assert_eq!(
-            parse(&b"\"C:\\Program Files\\Microsoft Office\\root\\Office16\""[..]),
+            parse(1252)(&b"\"C:\\Program Files\\Microsoft Office\\root\\Office16\""[..]),
             Ok((
                 &b""[..],
                 String::from("C:\\Program Files\\Microsoft Office\\root\\Office16")
@@ -30,7 +30,7 @@ mod tests {
     #[test]
     fn escaped_dquote() {
         assert_eq!(
-            parse(&b"\"C:\\Program Files\\Microsoft Office\\\"\"root\"\"\\Office16\""[..]),
+            parse(1252)(&b"\"C:\\Program Files\\Microsoft Office\\\"\"root\"\"\\Office16\""[..]),
             Ok((
                 &b""[..],
                 String::from("C:\\Program Files\\Microsoft Office\\\"root\"\\Office16")
@@ -41,14 +41,14 @@ mod tests {
     #[test]
     fn missing_start_or_end_dquotes() {
         assert_eq!(
-            parse(&b"C:\\Program Files\\Microsoft Office\\root\\Office16\""[..]),
+            parse(1252)(&b"C:\\Program Files\\Microsoft Office\\root\\Office16\""[..]),
             Err(Err::Error(Error::new(
                 &b"C:\\Program Files\\Microsoft Office\\root\\Office16\""[..],
                 ErrorKind::Tag
             )))
         );
         assert_eq!(
-            parse(&b"\"C:\\Program Files\\Microsoft Office\\root\\Office16"[..]),
+            parse(1252)(&b"\"C:\\Program Files\\Microsoft Office\\root\\Office16"[..]),
             Err(Err::Error(Error::new(&b""[..], ErrorKind::Tag)))
         );
     }
@@ -56,7 +56,7 @@ mod tests {
     #[test]
     fn further_data() {
         assert_eq!(
-            parse(&b"\"C:\\Program Files\\Microsoft Office\\root\\Office16\" and now for something completely different"[..]),
+            parse(1252)(&b"\"C:\\Program Files\\Microsoft Office\\root\\Office16\" and now for something completely different"[..]),
             Ok((
                 &b" and now for something completely different"[..],
                 String::from("C:\\Program Files\\Microsoft Office\\root\\Office16")
@@ -67,14 +67,14 @@ mod tests {
     #[test]
     fn invalid_character() {
         assert_eq!(
-            parse(&b"\"C:\\Program Files\\Microsoft Office\\ro\not\\Office16\""[..]),
+            parse(1252)(&b"\"C:\\Program Files\\Microsoft Office\\ro\not\\Office16\""[..]),
             Err(Err::Error(Error::new(
                 &b"\not\\Office16\""[..],
                 ErrorKind::Tag
             )))
         );
         assert_eq!(
-            parse(&b"\"C:\\Program Files\\Microsoft Office\\ro\0ot\\Office16\""[..]),
+            parse(1252)(&b"\"C:\\Program Files\\Microsoft Office\\ro\0ot\\Office16\""[..]),
             Err(Err::Error(Error::new(
                 &b"\0ot\\Office16\""[..],
                 ErrorKind::Tag
@@ -85,13 +85,13 @@ mod tests {
     #[test]
     fn too_long() {
         assert_eq!(
-            parse(&b"\"C:\\Program Files\\Microsoft Office\\root\\Office16ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff\""[..]),
+            parse(1252)(&b"\"C:\\Program Files\\Microsoft Office\\root\\Office16ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff\""[..]),
             Ok(( &b""[..],
                 String::from("C:\\Program Files\\Microsoft Office\\root\\Office16ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"),
             ))
         );
         assert_eq!(
-            parse(&b"\"C:\\Program Files\\Microsoft Office\\root\\Office16fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff\""[..]),
+            parse(1252)(&b"\"C:\\Program Files\\Microsoft Office\\root\\Office16fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff\""[..]),
             Err(Err::Error(Error::new(
                 &b"f\""[..],
                 ErrorKind::Tag
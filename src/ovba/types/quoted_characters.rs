@@ -1,7 +1,9 @@
-use super::quoted_character;
+use super::{mbcs, quoted_character};
 use nom::{bytes::complete::tag, combinator::map, multi::many_m_n, sequence::delimited, IResult};
 
-pub fn parse(min: usize, max: usize) -> impl Fn(&[u8]) -> IResult<&[u8], String> {
+/// Between `min` and `max` characters in double quotes, with any double quote in them doubled up,
+/// decoded in the code page of the project
+pub fn parse(min: usize, max: usize, code_page: u16) -> impl Fn(&[u8]) -> IResult<&[u8], String> {
     move |input: &[u8]| {
         map(
             delimited(
@@ -9,10 +11,7 @@ pub fn parse(min: usize, max: usize) -> impl Fn(&[u8]) -> IResult<&[u8], String>
                 many_m_n(min, max, quoted_character::parse),
                 tag("\""),
             ),
-            // TODO: Meant to support MBCS characters
-            // This is pretending we only ever have ASCII here
-            // Worse, it will panic if non-ASCII is ever passed
-            |p: Vec<u8>| String::from_utf8(p).unwrap(),
+            |p: Vec<u8>| mbcs::decode(&p, code_page),
         )(input)
     }
 }
@@ -28,7 +27,7 @@ mod tests {
     #[test]
     fn well_formed() {
         assert_eq!(
-            parse(1, 20)(&b"\"A quoted string\""[..]),
+            parse(1, 20, 1252)(&b"\"A quoted string\""[..]),
             Ok((&b""[..], String::from("A quoted string")))
         );
     }
@@ -36,7 +35,7 @@ mod tests {
     #[test]
     fn quoted_dquote() {
         assert_eq!(
-            parse(1, 20)(&b"\"A \"\"quoted\"\" string\""[..]),
+            parse(1, 20, 1252)(&b"\"A \"\"quoted\"\" string\""[..]),
             Ok((&b""[..], String::from("A \"quoted\" string")))
         );
     }
@@ -44,7 +43,7 @@ mod tests {
     #[test]
     fn too_short() {
         assert_eq!(
-            parse(16, 20)(&b"\"A quoted string\""[..]),
+            parse(16, 20, 1252)(&b"\"A quoted string\""[..]),
             Err(Err::Error(Error::new(&b"\""[..], ErrorKind::TakeWhileMN)))
         );
     }
@@ -52,7 +51,7 @@ mod tests {
     #[test]
     fn too_long() {
         assert_eq!(
-            parse(1, 13)(&b"\"A quoted string\""[..]),
+            parse(1, 13, 1252)(&b"\"A quoted string\""[..]),
             Err(Err::Error(Error::new(&b"ng\""[..], ErrorKind::Tag)))
         );
     }
@@ -60,22 +59,34 @@ mod tests {
     #[test]
     fn invalid_character() {
         assert_eq!(
-            parse(1, 20)(&b"\"A quo\nted string\""[..]),
+            parse(1, 20, 1252)(&b"\"A quo\nted string\""[..]),
             Err(Err::Error(Error::new(
                 &b"\nted string\""[..],
                 ErrorKind::Tag
             )))
         );
         assert_eq!(
-            parse(1, 20)(&b"\"A quoted str\0ing\""[..]),
+            parse(1, 20, 1252)(&b"\"A quoted str\0ing\""[..]),
             Err(Err::Error(Error::new(&b"\0ing\""[..], ErrorKind::Tag)))
         );
     }
 
+    #[test]
+    fn mbcs_characters() {
+        assert_eq!(
+            parse(1, 20, 1252)(&b"\"Caf\xe9\""[..]),
+            Ok((&b""[..], String::from("Café")))
+        );
+        assert_eq!(
+            parse(1, 20, 1251)(&b"\"\xcf\xf0\""[..]),
+            Ok((&b""[..], String::from("Пр")))
+        );
+    }
+
     #[test]
     fn further_data() {
         assert_eq!(
-            parse(1, 20)(&b"\"A quoted string\" plus a bit more"[..]),
+            parse(1, 20, 1252)(&b"\"A quoted string\" plus a bit more"[..]),
             Ok((&b" plus a bit more"[..], String::from("A quoted string")))
         );
     }
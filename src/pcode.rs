@@ -0,0 +1,140 @@
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use crate::consts;
+use crate::error::{UnlockError, UnlockResult};
+use crate::export::read_module;
+use crate::lenient::Options;
+use crate::ovba::records::dir::Dir;
+use crate::read::xl_vba;
+use cfb::CompoundFile;
+
+/// The compiled p-code of a single module of a VBA project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModulePcode {
+    /// The name of the module, as shown in the VBE
+    pub name: String,
+    /// The p-code of each line of the source code, not counting the hidden Attribute lines. A line
+    /// with nothing to run has no p-code, and a module that has not been compiled has no lines
+    pub lines: Vec<Vec<u8>>,
+    /// The string constants loaded by the p-code, in the order they are found
+    pub strings: Vec<String>,
+}
+
+/// Print the p-code of every module of the VBA project to standard out, as hex, along with the
+/// string constants found in it.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - There is no VBA file within the zip archive, found at "/xl/vbaProject.bin"
+/// - The VBA file within the archive cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+/// - A module stream cannot be found, or its source code cannot be decompressed
+pub fn print_xl(filename: &Path, options: &Options) -> UnlockResult<()> {
+    print_pcode(&xl_pcode(filename, options)?);
+    Ok(())
+}
+
+/// Read the p-code of every module of the VBA project.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// Office keeps running the p-code even once the source code has been "stomped" away, so this is
+/// the best record there is of what such a module does. Only the p-code of VBA 6 and later, as
+/// used by Office 2000 onwards, can be split into lines
+///
+/// # Errors
+/// Will return an error in the same situations as [`print_xl`]
+pub fn xl_pcode(filename: &Path, options: &Options) -> UnlockResult<Vec<ModulePcode>> {
+    let mut vba_cfb = xl_vba(filename)?;
+    pcode(
+        &mut vba_cfb,
+        consts::DIR_PATH,
+        consts::VBA_STORAGE_PATH,
+        options,
+    )
+}
+
+/// Print the p-code of every module of the VBA project to standard out, as hex, along with the
+/// string constants found in it.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+/// - A module stream cannot be found, or its source code cannot be decompressed
+pub fn print_xl_97(filename: &Path, options: &Options) -> UnlockResult<()> {
+    print_pcode(&xl_97_pcode(filename, options)?);
+    Ok(())
+}
+
+/// Read the p-code of every module of the VBA project.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// Office keeps running the p-code even once the source code has been "stomped" away, so this is
+/// the best record there is of what such a module does. Only the p-code of VBA 6 and later, as
+/// used by Office 2000 onwards, can be split into lines
+///
+/// # Errors
+/// Will return an error in the same situations as [`print_xl_97`]
+pub fn xl_97_pcode(filename: &Path, options: &Options) -> UnlockResult<Vec<ModulePcode>> {
+    let mut file = cfb::open(filename).map_err(UnlockError::CFBOpen)?;
+    pcode(
+        &mut file,
+        consts::CFB_DIR_PATH,
+        consts::CFB_VBA_STORAGE_PATH,
+        options,
+    )
+}
+
+fn pcode<F: Read + Seek>(
+    cfb: &mut CompoundFile<F>,
+    dir_path: &str,
+    storage_path: &str,
+    options: &Options,
+) -> UnlockResult<Vec<ModulePcode>> {
+    let dir = Dir::from_stream(cfb.open_stream(dir_path)?, options)?;
+    dir.modules()
+        .iter()
+        .map(|module| {
+            let stream = read_module(cfb, storage_path, module)?;
+            Ok(ModulePcode {
+                name: module.name(),
+                lines: stream
+                    .pcode_lines()
+                    .into_iter()
+                    .map(<[u8]>::to_vec)
+                    .collect(),
+                strings: stream.string_constants(),
+            })
+        })
+        .collect()
+}
+
+fn print_pcode(modules: &[ModulePcode]) {
+    for (i, module) in modules.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{}", module.name);
+        if module.lines.is_empty() {
+            println!("  No p-code");
+            continue;
+        }
+        let width = module.lines.len().to_string().len();
+        for (line, pcode) in module.lines.iter().enumerate() {
+            if !pcode.is_empty() {
+                let hex: Vec<String> = pcode.iter().map(|b| format!("{b:02x}")).collect();
+                println!("  {:>width$}  {}", line + 1, hex.join(" "));
+            }
+        }
+        for string in &module.strings {
+            println!("  String: \"{string}\"");
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! A potfile of the passwords that have already been cracked.
+//!
+//! Each line holds the hash and salt of a password, as hex, followed by the password itself i.e.
+//! `HASH:SALT:PASSWORD`. The same passwords turn up again and again across many files, so the
+//! potfile is checked before any cracking is done and added to whenever a password is found
+
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::UnlockResult;
+use crate::hash::PasswordHash;
+use crate::read::to_hex;
+
+/// A potfile, which does not need to exist until a password is added to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Potfile {
+    path: PathBuf,
+}
+
+impl Potfile {
+    /// The potfile at the given path
+    #[must_use]
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// The password with the salt and hash, if it is in the potfile. A potfile that does not exist
+    /// yet holds no passwords
+    ///
+    /// # Errors
+    /// Will return an error if the potfile exists but cannot be read
+    pub fn find(&self, password_hash: &PasswordHash) -> UnlockResult<Option<String>> {
+        let contents = match std::fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let key = key(password_hash);
+        Ok(contents
+            .split(|&b| b == b'\n')
+            .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+            .find_map(|line| {
+                let (start, password) = line.split_at_checked(key.len())?;
+                let password = password.strip_prefix(b":")?;
+                start
+                    .eq_ignore_ascii_case(key.as_bytes())
+                    .then(|| String::from_utf8_lossy(password).to_string())
+            }))
+    }
+
+    /// Add the password with the salt and hash to the end of the potfile, creating it if needed
+    ///
+    /// # Errors
+    /// Will return an error if the potfile cannot be opened or written to
+    pub fn add(&self, password_hash: &PasswordHash, password: &str) -> UnlockResult<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}:{password}", key(password_hash))?;
+        Ok(())
+    }
+}
+
+/// The start of the line for the salt and hash, in the same `HASH:SALT` form printed by the hash
+/// command
+fn key(h: &PasswordHash) -> String {
+    format!("{}:{}", to_hex(&h.hash), to_hex(&h.salt))
+}
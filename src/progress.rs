@@ -0,0 +1,32 @@
+//! Progress bars for the operations that can take a while.
+//!
+//! Bars are off unless turned on with [`set`], so nothing is drawn when the crate is used as a
+//! library. Even when on, nothing is drawn unless standard error is a terminal
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// How each bar is laid out, filled in by indicatif
+const TEMPLATE: &str =
+    "{msg} [{bar:40}] {human_pos}/{human_len} {percent}% at {per_sec}, {eta} left";
+
+/// Turn the progress bars on or off
+pub fn set(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// A progress bar counting up to the given length, or a hidden one if progress bars are off
+pub(crate) fn bar(len: u64, message: &'static str) -> ProgressBar {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return ProgressBar::hidden();
+    }
+    let style = ProgressStyle::with_template(TEMPLATE)
+        .expect("the template is valid")
+        .progress_chars("=> ");
+    ProgressBar::new(len)
+        .with_style(style)
+        .with_message(message)
+}
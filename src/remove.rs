@@ -1,12 +1,20 @@
+use crate::cancel;
 use crate::consts;
 use crate::error::UnlockError;
 use crate::error::UnlockResult;
+use crate::files::{self, Container};
+use crate::lenient::Options;
+use crate::list_modules;
+use crate::ovba::records::project::{self, Password, Project, Visibility};
+use crate::progress;
 use crate::read::zip_to_raw_vba;
-use cfb::Stream;
+use cfb::CompoundFile;
 use std::fs::File;
-use std::io::{BufRead, Write};
+use std::io::{BufRead, Cursor, Read, Seek, Write};
 use std::path::Path;
 use std::path::PathBuf;
+use tracing::{debug, info, trace, warn};
+use zip::ZipArchive;
 
 /// Remove the VBA protection from an Excel file
 /// This is the version for Excel files since 2003 i.e. xlsm and xlsb
@@ -18,6 +26,9 @@ use std::path::PathBuf;
 /// Alternatively, pass false for the inplace flag to get a copy of the source file. It will have
 /// the same name as the source file, but have '_unlocked' appended to the filename.
 ///
+/// A PROJECT stream that cannot be parsed has its protection removed line by line, unless the
+/// options are strict
+///
 /// # Errors
 /// Will return an error in the following situations:
 /// - The file cannot be opened
@@ -39,25 +50,211 @@ use std::path::PathBuf;
 /// - The updated VBA CFB file cannot be written to the new zip file
 /// - The rest of the source zip file cannot be copied across as raw to the new zip file
 /// - If being run inplace, the new zip file cannot be copied back over the original
-pub fn xl(filename: &Path, inplace: bool) -> UnlockResult<()> {
+pub fn xl(filename: &Path, inplace: bool, options: &Options) -> UnlockResult<()> {
+    rewrite_xl(
+        filename,
+        Destination::new(inplace, "_unlocked"),
+        |p, code_page| unlocked_project(p, code_page, options),
+    )
+}
+
+/// Remove the VBA protection from an Excel file, saving the unlocked copy to the output path
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// # Errors
+/// Will return an error in the same situations as [`xl`], plus if the output file cannot be
+/// created
+pub fn xl_to(filename: &Path, output: &Path, options: &Options) -> UnlockResult<()> {
+    rewrite_xl(filename, Destination::Path(output), |p, code_page| {
+        unlocked_project(p, code_page, options)
+    })
+}
+
+/// Remove the VBA protection from an Excel file
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// The inplace flag, if set to true, will overwrite the source file with a modified unlocked
+/// version. It is recommended to take a back-up of the file before doing this as the tool is
+/// relatively new and untested. It may corrupt your file.
+///
+/// Alternatively, pass false for the inplace flag to get a copy of the source file. It will have
+/// the same name as the source file, but have '_unlocked' appended to the filename.
+///
+/// A PROJECT stream that cannot be parsed has its protection removed line by line, unless the
+/// options are strict
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be copied (for not inplace only) or opened for read/write
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b).
+/// This file format stores the data of a file as a mini file system. The data of each "file"
+/// within the overall file is stored as streams. These streams are written to 512 byte sectors, or
+/// 64 byte chunks of the mini-stream. In either case, the sectors or the mini-stream, the stream
+/// is not guaranteed to be written to contiguous memory, so it is important that the file is
+/// properly opened as a CFB file in order to read the streams correctly
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593),
+/// which holds the VBA locked status, cannot be found within the overall VBA CFB file
+/// - The updated project stream cannot be written back to the CFB file
+pub fn xl_97(filename: &Path, inplace: bool, options: &Options) -> UnlockResult<()> {
+    rewrite_xl_97(
+        filename,
+        Destination::new(inplace, "_unlocked"),
+        |p, code_page| unlocked_project(p, code_page, options),
+    )
+}
+
+/// Remove the VBA protection from an Excel file, saving the unlocked copy to the output path
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// # Errors
+/// Will return an error in the same situations as [`xl_97`], plus if the file cannot be copied
+/// to the output path
+pub fn xl_97_to(filename: &Path, output: &Path, options: &Options) -> UnlockResult<()> {
+    rewrite_xl_97(filename, Destination::Path(output), |p, code_page| {
+        unlocked_project(p, code_page, options)
+    })
+}
+
+/// Remove the VBA protection from an Excel file held in memory, returning the unlocked file
+///
+/// This works for any of xlsm, xlsb or xls files, the format being worked out from the contents
+///
+/// # Errors
+/// Will return an error in the same situations as [`xl`] or [`xl_97`], or if the data is not
+/// recognised as either a zip or CFB file
+pub fn bytes(data: &[u8], options: &Options) -> UnlockResult<Vec<u8>> {
+    rewrite_bytes(data, |p, code_page| unlocked_project(p, code_page, options))
+}
+
+/// Where the rewritten Excel file gets saved to
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Destination<'a> {
+    /// Overwrite the original file
+    InPlace,
+    /// Save alongside the original, with the suffix appended to the filename
+    Suffix(&'a str),
+    /// Save to the given path
+    Path(&'a Path),
+}
+
+impl<'a> Destination<'a> {
+    pub(crate) const fn new(inplace: bool, suffix: &'a str) -> Self {
+        if inplace {
+            Self::InPlace
+        } else {
+            Self::Suffix(suffix)
+        }
+    }
+
+    /// The path to save to. An output path that points at the original file is treated as
+    /// running inplace, so that the original is not truncated while it is still being read
+    fn resolve(self, filename: &Path) -> Self {
+        match self {
+            Self::Path(p) if same_file(filename, p) => Self::InPlace,
+            _ => self,
+        }
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    matches!((a.canonicalize(), b.canonicalize()), (Ok(a), Ok(b)) if a == b)
+}
+
+/// Rewrite the PROJECT stream of an Excel file since 2003 i.e. xlsm and xlsb
+///
+/// The update function is passed the bytes of the current PROJECT stream, along with the code page
+/// of the project, and returns the bytes of the replacement stream
+pub(crate) fn rewrite_xl<F>(
+    filename: &Path,
+    destination: Destination,
+    update_project: F,
+) -> UnlockResult<()>
+where
+    F: FnOnce(&[u8], u16) -> UnlockResult<Vec<u8>>,
+{
+    rewrite_zip(filename, destination, |archive, new_file| {
+        rewrite_archive(archive, new_file, update_project)
+    })
+}
+
+/// Write a rewritten copy of a zip based Excel file to the destination
+///
+/// The rewrite function is passed the source archive and the new, empty, file to write the
+/// updated archive to
+pub(crate) fn rewrite_zip<G>(
+    filename: &Path,
+    destination: Destination,
+    rewrite: G,
+) -> UnlockResult<()>
+where
+    G: FnOnce(&mut ZipArchive<File>, File) -> UnlockResult<File>,
+{
     let zipfile = File::open(filename)?;
     let mut archive = zip::ZipArchive::new(zipfile)?;
-    let vba_raw = zip_to_raw_vba(&mut archive)?;
-
-    // Replace the VBA CFB file with an unlocked project
-    // Strip back out to a Vec of bytes as this is what's needed to write to the zip file
-    let mut vba = cfb::CompoundFile::open(vba_raw).map_err(UnlockError::CFBOpen)?;
-    let project = vba.open_stream(consts::PROJECT_PATH)?;
-    let replacement = unlocked_project(project)?;
-    let mut project = vba.create_stream(consts::PROJECT_PATH)?;
-    project.write_all(&replacement)?;
-    project.flush()?;
-    let vba_inner = vba.into_inner().into_inner();
 
     // Open a new, empty archive for writing to
-    let new_filename = replacement_filename(filename)?;
+    let destination = destination.resolve(filename);
+    let new_filename = match destination {
+        Destination::InPlace => replacement_filename(filename, "_tmp")?,
+        Destination::Suffix(suffix) => replacement_filename(filename, suffix)?,
+        Destination::Path(p) => p.to_path_buf(),
+    };
+    info!("writing {}", new_filename.display());
     let new_file = File::create(&new_filename)?;
-    let mut new_archive = zip::ZipWriter::new(new_file);
+    if let Err(e) = rewrite(&mut archive, new_file) {
+        // Don't leave a half written file behind
+        let _ = std::fs::remove_file(&new_filename);
+        return Err(e);
+    }
+    drop(archive);
+
+    // If we're doing this in place then overwrite the original with the new
+    if matches!(destination, Destination::InPlace) {
+        info!("replacing {}", filename.display());
+        std::fs::rename(new_filename, filename)?;
+    }
+
+    Ok(())
+}
+
+/// Write a copy of the zip archive to the writer, with the PROJECT stream of the VBA file updated
+fn rewrite_archive<R, W, F>(
+    archive: &mut ZipArchive<R>,
+    writer: W,
+    update_project: F,
+) -> UnlockResult<W>
+where
+    R: Read + Seek,
+    W: Write + Seek,
+    F: FnOnce(&[u8], u16) -> UnlockResult<Vec<u8>>,
+{
+    let vba_raw = zip_to_raw_vba(archive)?;
+    let vba = update_vba(vba_raw.into_inner(), update_project)?;
+    replace_vba(archive, writer, &vba)
+}
+
+/// Rewrite the PROJECT stream of a vbaProject.bin file, returning the updated file
+pub(crate) fn update_vba<F>(vba: Vec<u8>, update_project: F) -> UnlockResult<Vec<u8>>
+where
+    F: FnOnce(&[u8], u16) -> UnlockResult<Vec<u8>>,
+{
+    // Strip back out to a Vec of bytes as this is what's needed to write to the zip file
+    let mut vba = cfb::CompoundFile::open(Cursor::new(vba)).map_err(UnlockError::CFBOpen)?;
+    rewrite_stream(&mut vba, consts::PROJECT_PATH, update_project)?;
+    Ok(vba.into_inner().into_inner())
+}
+
+/// Write a copy of the zip archive to the writer, with the VBA file swapped for the given bytes
+pub(crate) fn replace_vba<R, W>(
+    archive: &mut ZipArchive<R>,
+    writer: W,
+    vba: &[u8],
+) -> UnlockResult<W>
+where
+    R: Read + Seek,
+    W: Write + Seek,
+{
+    let mut new_archive = zip::ZipWriter::new(writer);
 
     // Loop through the original archive:
     //  - Write the VBA file from our updated vec of bytes
@@ -65,105 +262,250 @@ pub fn xl(filename: &Path, inplace: bool) -> UnlockResult<()> {
     // The end effect is to have a new archive, which is a clone of the original,
     // save for the VBA file which has been rewritten
     let target: &Path = consts::ZIP_VBA_PATH.as_ref();
+    let bar = progress::bar(archive.len() as u64, "Copying the archive");
     for i in 0..archive.len() {
+        cancel::check()?;
         let file = archive.by_index_raw(i)?;
         match file.enclosed_name() {
             Some(p) if p == target => {
+                debug!("writing the updated {}", consts::ZIP_VBA_PATH);
                 new_archive.start_file(consts::ZIP_VBA_PATH, zip::write::FileOptions::default())?;
-                new_archive.write_all(&vba_inner)?;
+                new_archive.write_all(vba)?;
                 new_archive.flush()?;
             }
-            _ => new_archive.raw_copy_file(file)?,
+            _ => {
+                trace!("copying {} across unchanged", file.name());
+                new_archive.raw_copy_file(file)?;
+            }
         }
+        bar.inc(1);
     }
-    new_archive.finish()?;
-
-    drop(archive);
-    drop(new_archive);
+    bar.finish_and_clear();
+    Ok(new_archive.finish()?)
+}
 
-    // If we're doing this in place then overwrite the original with the new
-    if inplace {
-        std::fs::rename(new_filename, filename)?;
+/// Write a copy of the zip archive to the writer, passing the contents of every entry picked out
+/// by the test through the update function. Everything else is copied across unchanged
+pub(crate) fn update_entries<R, W, T, F>(
+    archive: &mut ZipArchive<R>,
+    writer: W,
+    wanted: T,
+    mut update: F,
+) -> UnlockResult<W>
+where
+    R: Read + Seek,
+    W: Write + Seek,
+    T: Fn(&str) -> bool,
+    F: FnMut(&str, &[u8]) -> UnlockResult<Vec<u8>>,
+{
+    let mut new_archive = zip::ZipWriter::new(writer);
+    for i in 0..archive.len() {
+        cancel::check()?;
+        let name = archive.by_index_raw(i)?.name().to_string();
+        if wanted(&name) {
+            let mut data = Vec::new();
+            archive.by_index(i)?.read_to_end(&mut data)?;
+            let data = update(&name, &data)?;
+            debug!("writing the updated {name}");
+            new_archive.start_file(name, zip::write::FileOptions::default())?;
+            new_archive.write_all(&data)?;
+        } else {
+            trace!("copying {name} across unchanged");
+            new_archive.raw_copy_file(archive.by_index_raw(i)?)?;
+        }
     }
+    Ok(new_archive.finish()?)
+}
 
-    Ok(())
+/// Replace the contents of the stream of a CFB file with the output of the update function. The
+/// update function is passed the code page of the project, read from the dir stream beside it
+pub(crate) fn rewrite_stream<T, F>(
+    file: &mut CompoundFile<T>,
+    path: &str,
+    update_project: F,
+) -> UnlockResult<()>
+where
+    T: Read + Write + Seek,
+    F: FnOnce(&[u8], u16) -> UnlockResult<Vec<u8>>,
+{
+    let mut project = Vec::new();
+    debug!("opening the {path} stream");
+    file.open_stream(path)?.read_to_end(&mut project)?;
+    let dir_path = format!("{}VBA/dir", path.trim_end_matches("PROJECT"));
+    let code_page = list_modules::code_page(file, &dir_path);
+    let replacement = update_project(&project, code_page)?;
+    let mut project = file.create_stream(path)?;
+    project.write_all(&replacement)?;
+    Ok(project.flush()?)
 }
 
-/// Remove the VBA protection from an Excel file
-/// This is the version for Excel files between 1997 & 2003 i.e. xls
-///
-/// The inplace flag, if set to true, will overwrite the source file with a modified unlocked
-/// version. It is recommended to take a back-up of the file before doing this as the tool is
-/// relatively new and untested. It may corrupt your file.
+/// Rewrite the PROJECT stream of an Excel file held in memory, returning the updated file. The
+/// format of the file is worked out from its contents
+pub(crate) fn rewrite_bytes<F>(data: &[u8], update_project: F) -> UnlockResult<Vec<u8>>
+where
+    F: FnOnce(&[u8], u16) -> UnlockResult<Vec<u8>>,
+{
+    match files::sniff(data) {
+        Some(Container::Zip) => {
+            let mut archive = ZipArchive::new(Cursor::new(data))?;
+            let output = rewrite_archive(&mut archive, Cursor::new(Vec::new()), update_project)?;
+            Ok(output.into_inner())
+        }
+        Some(Container::Cfb) => {
+            let mut file =
+                CompoundFile::open(Cursor::new(data.to_vec())).map_err(UnlockError::CFBOpen)?;
+            rewrite_stream(&mut file, consts::CFB_VBA_PATH, update_project)?;
+            Ok(file.into_inner().into_inner())
+        }
+        None => Err(UnlockError::NotExcel("The input".into())),
+    }
+}
+
+/// Rewrite the PROJECT stream of an Excel file between 1997 & 2003 i.e. xls
 ///
-/// Alternatively, pass false for the inplace flag to get a copy of the source file. It will have
-/// the same name as the source file, but have '_unlocked' appended to the filename.
+/// The update function is passed the bytes of the current PROJECT stream, along with the code page
+/// of the project, and returns the bytes of the replacement stream
+pub(crate) fn rewrite_xl_97<F>(
+    filename: &Path,
+    destination: Destination,
+    update_project: F,
+) -> UnlockResult<()>
+where
+    F: FnOnce(&[u8], u16) -> UnlockResult<Vec<u8>>,
+{
+    rewrite_cfb(filename, destination, |file| {
+        rewrite_stream(file, consts::CFB_VBA_PATH, update_project)
+    })
+}
+
+/// Update a copy of a CFB based Excel file at the destination, or the file itself if in place
 ///
-/// # Errors
-/// Will return an error in the following situations:
-/// - The file cannot be copied (for not inplace only) or opened for read/write
-/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b).
-/// This file format stores the data of a file as a mini file system. The data of each "file"
-/// within the overall file is stored as streams. These streams are written to 512 byte sectors, or
-/// 64 byte chunks of the mini-stream. In either case, the sectors or the mini-stream, the stream
-/// is not guaranteed to be written to contiguous memory, so it is important that the file is
-/// properly opened as a CFB file in order to read the streams correctly
-/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593),
-/// which holds the VBA locked status, cannot be found within the overall VBA CFB file
-/// - The updated project stream cannot be written back to the CFB file
-pub fn xl_97(filename: &Path, inplace: bool) -> UnlockResult<()> {
-    let mut file = if inplace {
-        cfb::open_rw(filename).map_err(UnlockError::CFBOpen)?
-    } else {
-        let new_file = replacement_filename(filename)?;
-        std::fs::copy(filename, &new_file)?;
-        cfb::open_rw(new_file).map_err(UnlockError::CFBOpen)?
+/// The rewrite function is passed the file to update, opened for reading and writing
+pub(crate) fn rewrite_cfb<G>(
+    filename: &Path,
+    destination: Destination,
+    rewrite: G,
+) -> UnlockResult<()>
+where
+    G: FnOnce(&mut CompoundFile<File>) -> UnlockResult<()>,
+{
+    let new_file = match destination.resolve(filename) {
+        Destination::InPlace => filename.to_path_buf(),
+        Destination::Suffix(suffix) => replacement_filename(filename, suffix)?,
+        Destination::Path(p) => p.to_path_buf(),
     };
-    let project = file.open_stream(consts::CFB_VBA_PATH)?;
-    let replacement = unlocked_project(project)?;
-    let mut project = file.create_stream(consts::CFB_VBA_PATH)?;
-    Ok(project.write_all(&replacement)?)
+    if new_file != filename {
+        info!("copying {} to {}", filename.display(), new_file.display());
+        std::fs::copy(filename, &new_file)?;
+    }
+    let result = cfb::open_rw(&new_file)
+        .map_err(UnlockError::CFBOpen)
+        .and_then(|mut file| rewrite(&mut file));
+    if result.is_err() && new_file != filename {
+        // Don't leave a half written copy behind
+        let _ = std::fs::remove_file(&new_file);
+    }
+    result
 }
 
-fn unlocked_project<T: std::io::Read + std::io::Seek>(
-    mut project: Stream<T>,
-) -> UnlockResult<Vec<u8>> {
+/// Rewrite the PROJECT stream line by line, swapping out any line for which the replace function
+/// returns a value. All other lines are copied across unchanged
+pub(crate) fn rewrite_project_lines<F>(mut project: &[u8], mut replace: F) -> UnlockResult<Vec<u8>>
+where
+    F: FnMut(&[u8]) -> Option<Vec<u8>>,
+{
     let mut line = Vec::new();
     let mut output = Vec::new();
 
     while project.read_until(b'\n', &mut line)? > 0 {
-        match line.get(0..5) {
-            Some(&[b'I', b'D', b'=', b'"', b'{']) => {
-                output.extend_from_slice(consts::UNLOCKED_ID.as_bytes());
+        match replace(&line) {
+            Some(replacement) => {
+                trace!(
+                    "replacing PROJECT line {:?}",
+                    String::from_utf8_lossy(&line).trim_end()
+                );
+                output.extend_from_slice(&replacement);
             }
+            None => output.extend_from_slice(&line),
+        }
+        line.clear();
+    }
+
+    Ok(output)
+}
+
+/// The PROJECT stream with all protection removed, written back out from the parsed project
+///
+/// A stream that cannot be parsed has its protection properties swapped out line by line instead,
+/// leaving the rest of it as it was, unless the options are strict
+pub(crate) fn unlocked_project(
+    project: &[u8],
+    code_page: u16,
+    options: &Options,
+) -> UnlockResult<Vec<u8>> {
+    match Project::from_bytes(project, code_page, options) {
+        Ok(mut parsed) => {
+            parsed.unlock(consts::UNLOCKED_ID);
+            Ok(parsed.to_bytes(code_page))
+        }
+        Err(e) if !options.strict => {
+            warn!("{e}. Removing the protection from the PROJECT stream line by line instead");
+            unlocked_project_lines(project, code_page)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Swap the ID and protection properties of the PROJECT stream for those of an unlocked project,
+/// copying every other line across unchanged. Each line keeps the line ending it was read with
+fn unlocked_project_lines(project: &[u8], code_page: u16) -> UnlockResult<Vec<u8>> {
+    let id = consts::UNLOCKED_ID.to_string();
+    let key = project::project_key(&id);
+    rewrite_project_lines(project, |line| {
+        // Lines are split after each LF, so an LFCR line ending leaves the CR at the start of the
+        // next line
+        let (start, text) = line
+            .strip_prefix(b"\r")
+            .map_or((&b""[..], line), |text| (&b"\r"[..], text));
+        let end = &text[text.trim_ascii_end().len()..];
+        let property = match text.get(0..5) {
+            Some(&[b'I', b'D', b'=', b'"', b'{']) => format!("ID=\"{id}\"").into_bytes(),
             Some(&[b'C', b'M', b'G', b'=', b'"']) => {
-                output.extend_from_slice(consts::UNLOCKED_CMG.as_bytes());
+                project::encrypted_property("CMG", key, &[0x00; 4])
             }
-            Some(&[b'D', b'P', b'B', b'=', b'"']) => {
-                output.extend_from_slice(consts::UNLOCKED_DPB.as_bytes());
+            // The legacy DPx property is written back as DPB, so that Excel sees there is no
+            // password
+            Some(&[b'D', b'P', b'B' | b'x', b'=', b'"']) => {
+                project::encrypted_property("DPB", key, &Password::None.data(code_page))
             }
             Some(&[b'G', b'C', b'=', b'"', _]) => {
-                output.extend_from_slice(consts::UNLOCKED_GC.as_bytes());
+                project::encrypted_property("GC", key, &Visibility::Visible.data())
             }
-            _ => output.extend_from_slice(&line),
-        }
-        line.clear();
-    }
+            _ => return None,
+        };
+        Some([start, &property, end].concat())
+    })
+}
 
-    Ok(output)
+/// The name an unlocked copy of the file is saved under, when not working in place
+///
+/// # Errors
+/// Will return an error if the filename does not have a name and extension
+pub fn unlocked_filename(filename: &Path) -> UnlockResult<PathBuf> {
+    replacement_filename(filename, "_unlocked")
 }
 
-fn replacement_filename(source: &Path) -> UnlockResult<PathBuf> {
+fn replacement_filename(source: &Path, suffix: &str) -> UnlockResult<PathBuf> {
     let mut new = PathBuf::from(source);
     let mut stem = source
         .file_stem()
-        .ok_or(UnlockError::NotExcel(source.to_string_lossy().to_string()))?
+        .ok_or(UnlockError::NotExcel(source.to_path_buf()))?
         .to_owned();
-    stem.push("_unlocked");
+    stem.push(suffix);
     new.set_file_name(stem);
     let ext = source
         .extension()
-        .ok_or(UnlockError::NotExcel(source.to_string_lossy().to_string()))?;
+        .ok_or(UnlockError::NotExcel(source.to_path_buf()))?;
     new.set_extension(ext);
     Ok(new)
 }
@@ -0,0 +1,324 @@
+use std::fmt::Display;
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use crate::consts;
+use crate::error::{UnlockError, UnlockResult};
+use crate::export::module_sources;
+use crate::lenient::Options;
+use crate::read::xl_vba;
+use cfb::CompoundFile;
+
+/// Procedures that Office runs by itself, when a document is opened, closed or changed
+const AUTO_EXEC: [&str; 14] = [
+    "auto_open",
+    "auto_close",
+    "autoopen",
+    "autoclose",
+    "autoexec",
+    "autonew",
+    "document_open",
+    "document_close",
+    "document_new",
+    "workbook_open",
+    "workbook_activate",
+    "workbook_beforeclose",
+    "workbook_deactivate",
+    "worksheet_activate",
+];
+
+/// Calls and objects often used by malicious macros to run programs, download files or reach
+/// into Windows directly. Longer names come first, so that e.g. `WScript.Shell` is not also
+/// reported as `Shell`
+const SUSPICIOUS: [&str; 17] = [
+    "urldownloadtofile",
+    "executeexcel4macro",
+    "msxml2.xmlhttp",
+    "rtlmovememory",
+    "wscript.shell",
+    "shellexecute",
+    "createobject",
+    "virtualalloc",
+    "createthread",
+    "adodb.stream",
+    "callbyname",
+    "powershell",
+    "getobject",
+    "declare",
+    "environ",
+    "shell",
+    "kill",
+];
+
+const URL_SCHEMES: [&str; 3] = ["http://", "https://", "ftp://"];
+
+/// What sort of thing a finding is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// A procedure that runs without the user asking it to
+    AutoExec,
+    /// A call that is commonly abused
+    Suspicious,
+    /// A web address within the source
+    Url,
+    /// A string constant of the compiled p-code that is missing from the source, which is how
+    /// "VBA stomping" shows up: Office runs the p-code, not the harmless source left in its place
+    Stomped,
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AutoExec => write!(f, "auto-exec"),
+            Self::Suspicious => write!(f, "suspicious"),
+            Self::Url => write!(f, "url"),
+            Self::Stomped => write!(f, "stomped"),
+        }
+    }
+}
+
+/// Something in the source code of a module worth a closer look
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// The name of the module, as shown in the VBE
+    pub module: String,
+    /// The line of the module source it is on, counting from 1. This matches the files written by
+    /// the export command, which include the hidden Attribute lines. It is 0 for a finding about
+    /// the module as a whole
+    pub line: usize,
+    /// What sort of thing was found
+    pub kind: Kind,
+    /// The name of the procedure or call, the web address, or the string constant
+    pub text: String,
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.module, self.line, self.kind, self.text
+        )
+    }
+}
+
+/// Look through the source code of every module for auto-exec entry points, suspicious calls and
+/// web addresses.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - There is no VBA file within the zip archive, found at "/xl/vbaProject.bin"
+/// - The VBA file within the archive cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   or [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+/// - A module stream cannot be found, or its source code cannot be decompressed
+pub fn xl(filename: &Path, options: &Options) -> UnlockResult<Vec<Finding>> {
+    let mut vba_cfb = xl_vba(filename)?;
+    scan(
+        &mut vba_cfb,
+        consts::PROJECT_PATH,
+        consts::DIR_PATH,
+        consts::VBA_STORAGE_PATH,
+        options,
+    )
+}
+
+/// Look through the source code of every module for auto-exec entry points, suspicious calls and
+/// web addresses.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   or [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+/// - A module stream cannot be found, or its source code cannot be decompressed
+pub fn xl_97(filename: &Path, options: &Options) -> UnlockResult<Vec<Finding>> {
+    let mut file = cfb::open(filename).map_err(UnlockError::CFBOpen)?;
+    scan(
+        &mut file,
+        consts::CFB_VBA_PATH,
+        consts::CFB_DIR_PATH,
+        consts::CFB_VBA_STORAGE_PATH,
+        options,
+    )
+}
+
+/// Whether the source code of any module has been swapped out from under its compiled p-code,
+/// as is done by "VBA stomping".
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// Only the string constants of the p-code are checked against the source, so a module stomped
+/// with source that has all the same strings is not spotted
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - There is no VBA file within the zip archive, found at "/xl/vbaProject.bin"
+/// - The VBA file within the archive cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   or [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+/// - A module stream cannot be found, or its source code cannot be decompressed
+pub fn xl_is_stomped(filename: &Path, options: &Options) -> UnlockResult<bool> {
+    let mut vba_cfb = xl_vba(filename)?;
+    is_stomped(
+        &mut vba_cfb,
+        consts::PROJECT_PATH,
+        consts::DIR_PATH,
+        consts::VBA_STORAGE_PATH,
+        options,
+    )
+}
+
+/// Whether the source code of any module has been swapped out from under its compiled p-code,
+/// as is done by "VBA stomping".
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// Only the string constants of the p-code are checked against the source, so a module stomped
+/// with source that has all the same strings is not spotted
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   or [dir stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/672e5a8f-3bd4-4e46-9b2d-3c3ba5e4fd84)
+///   cannot be found or parsed
+/// - A module stream cannot be found, or its source code cannot be decompressed
+pub fn xl_97_is_stomped(filename: &Path, options: &Options) -> UnlockResult<bool> {
+    let mut file = cfb::open(filename).map_err(UnlockError::CFBOpen)?;
+    is_stomped(
+        &mut file,
+        consts::CFB_VBA_PATH,
+        consts::CFB_DIR_PATH,
+        consts::CFB_VBA_STORAGE_PATH,
+        options,
+    )
+}
+
+fn is_stomped<F: Read + Seek>(
+    cfb: &mut CompoundFile<F>,
+    project_path: &str,
+    dir_path: &str,
+    storage_path: &str,
+    options: &Options,
+) -> UnlockResult<bool> {
+    let (_, modules) = module_sources(cfb, project_path, dir_path, storage_path, options)?;
+    Ok(modules.iter().any(|m| m.stomped.is_some()))
+}
+
+fn scan<F: Read + Seek>(
+    cfb: &mut CompoundFile<F>,
+    project_path: &str,
+    dir_path: &str,
+    storage_path: &str,
+    options: &Options,
+) -> UnlockResult<Vec<Finding>> {
+    let (_, modules) = module_sources(cfb, project_path, dir_path, storage_path, options)?;
+    let mut findings = Vec::new();
+    for module in modules {
+        if let Some(constant) = module.stomped {
+            findings.push(Finding {
+                module: module.name.clone(),
+                line: 0,
+                kind: Kind::Stomped,
+                text: constant,
+            });
+        }
+        let source = String::from_utf8_lossy(&module.source);
+        for (i, line) in source.lines().enumerate() {
+            for (kind, text) in scan_line(line) {
+                findings.push(Finding {
+                    module: module.name.clone(),
+                    line: i + 1,
+                    kind,
+                    text,
+                });
+            }
+        }
+    }
+    Ok(findings)
+}
+
+/// Everything of note on a single line of source code
+#[must_use]
+pub fn scan_line(line: &str) -> Vec<(Kind, String)> {
+    let code = line.trim_start();
+    // Only ASCII is lowered, so that positions within the lowered text match the original
+    let lower = code.to_ascii_lowercase();
+    if lower.starts_with('\'') || lower.starts_with("rem ") {
+        // Comments can't run, but a web address in one is still worth knowing about
+        return urls(code).into_iter().map(|u| (Kind::Url, u)).collect();
+    }
+
+    let mut found = Vec::new();
+    if let Some(name) = procedure_name(code) {
+        if AUTO_EXEC.contains(&name.to_lowercase().as_str()) {
+            found.push((Kind::AutoExec, name.to_string()));
+        }
+    }
+
+    // Blank out each call once found, so that it isn't picked up again by a shorter name
+    let mut masked = lower;
+    for call in SUSPICIOUS {
+        if let Some(start) = find_word(&masked, call) {
+            found.push((
+                Kind::Suspicious,
+                code[start..start + call.len()].to_string(),
+            ));
+            masked.replace_range(start..start + call.len(), &" ".repeat(call.len()));
+        }
+    }
+
+    found.extend(urls(code).into_iter().map(|u| (Kind::Url, u)));
+    found
+}
+
+/// The name of the procedure declared on the line, if there is one
+fn procedure_name(code: &str) -> Option<&str> {
+    let mut words = code.split_whitespace();
+    let mut word = words.next()?;
+    while ["public", "private", "friend", "static"].contains(&word.to_lowercase().as_str()) {
+        word = words.next()?;
+    }
+    if !["sub", "function"].contains(&word.to_lowercase().as_str()) {
+        return None;
+    }
+    let name = words.next()?;
+    Some(name.split('(').next().unwrap_or(name))
+}
+
+/// Find the word within the text, where it isn't part of a longer name
+fn find_word(text: &str, word: &str) -> Option<usize> {
+    let is_name = |c: char| c.is_alphanumeric() || c == '_';
+    text.match_indices(word).map(|(i, _)| i).find(|&i| {
+        let before = text[..i].chars().next_back();
+        let after = text[i + word.len()..].chars().next();
+        !before.is_some_and(is_name) && !after.is_some_and(is_name)
+    })
+}
+
+fn urls(code: &str) -> Vec<String> {
+    let lower = code.to_ascii_lowercase();
+    let mut starts: Vec<usize> = URL_SCHEMES
+        .iter()
+        .flat_map(|s| lower.match_indices(s).map(|(i, _)| i))
+        .collect();
+    starts.sort_unstable();
+    starts
+        .into_iter()
+        .map(|start| {
+            let rest = &code[start..];
+            let end = rest
+                .find(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | ')' | '<' | '>'))
+                .unwrap_or(rest.len());
+            rest[..end].to_string()
+        })
+        .collect()
+}
@@ -0,0 +1,94 @@
+use std::io::{Cursor, Read};
+
+use crate::error::{UnlockError, UnlockResult};
+use crate::lenient::Options;
+use crate::read;
+use crate::remove;
+use serde_json::json;
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+use tracing::{info, warn};
+
+/// The largest workbook that will be accepted, to stop a single request using up all the memory
+const MAX_UPLOAD: u64 = 100 * 1024 * 1024;
+
+type HttpResponse = Response<Cursor<Vec<u8>>>;
+
+/// Run an HTTP server on the given address, e.g. `127.0.0.1:8080`, that works on workbooks sent to
+/// it. The format of the workbook is worked out from its contents
+///
+/// - `POST /read` returns the protection status as JSON, the same as `read --format json`. Add
+///   `?decode=true` to try to decode the password
+/// - `POST /remove` returns the unlocked workbook
+///
+/// Anything that goes wrong with a workbook is returned as JSON of the form `{"error": "..."}`.
+/// Requests are handled one at a time, and the server runs until the process is stopped
+///
+/// # Errors
+/// Will return an error if the server cannot listen on the address
+pub fn serve(address: &str, options: &Options) -> UnlockResult<()> {
+    let server = Server::http(address).map_err(|e| UnlockError::Serve(e.to_string()))?;
+    info!("listening on {address}");
+    for mut request in server.incoming_requests() {
+        let response = respond(&mut request, options);
+        info!(
+            "{} {} {}",
+            request.method(),
+            request.url(),
+            response.status_code().0
+        );
+        if let Err(e) = request.respond(response) {
+            warn!("could not send the response: {e}");
+        }
+    }
+    Ok(())
+}
+
+fn respond(request: &mut Request, options: &Options) -> HttpResponse {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    if !matches!(path, "/read" | "/remove") {
+        return error(404, "Not found, POST a workbook to /read or /remove");
+    }
+    if *request.method() != Method::Post {
+        return error(405, "Only POST is supported");
+    }
+
+    let mut data = Vec::new();
+    if let Err(e) = request
+        .as_reader()
+        .take(MAX_UPLOAD + 1)
+        .read_to_end(&mut data)
+    {
+        return error(400, &format!("Could not read the request body: {e}"));
+    }
+    if data.len() as u64 > MAX_UPLOAD {
+        return error(413, "The workbook is too large");
+    }
+
+    let result = if path == "/read" {
+        let decode = query.split('&').any(|p| p == "decode=true");
+        read::bytes_project(&data, decode, options).map(|(project, decoded)| {
+            Response::from_string(read::to_json(&project, decoded, None))
+                .with_header(content_type("application/json"))
+        })
+    } else {
+        remove::bytes(&data, options).map(|unlocked| {
+            Response::from_data(unlocked).with_header(content_type("application/octet-stream"))
+        })
+    };
+    result.unwrap_or_else(|e| match e {
+        UnlockError::FileOpen(_) => error(500, &e.to_string()),
+        // The rest are all problems with the workbook that was sent
+        _ => error(422, &e.to_string()),
+    })
+}
+
+fn error(status: u16, message: &str) -> HttpResponse {
+    Response::from_string(json!({ "error": message }).to_string())
+        .with_status_code(StatusCode(status))
+        .with_header(content_type("application/json"))
+}
+
+fn content_type(value: &str) -> Header {
+    Header::from_bytes("Content-Type", value).expect("the header is plain ASCII")
+}
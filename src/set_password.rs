@@ -0,0 +1,85 @@
+use crate::consts;
+use crate::error::UnlockResult;
+use crate::lenient::Options;
+use crate::ovba::records::project::{Password, Project};
+use crate::remove::{rewrite_xl, rewrite_xl_97, Destination};
+use std::path::Path;
+
+/// Lock the VBA project of an Excel file with the supplied password
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// The project is locked for viewing in the same way Excel does it: the protection state, password
+/// and visibility records of the PROJECT stream are all rewritten, and the stream is written back
+/// out from the parsed project. The password is stored as a SHA1 hash with a random salt
+///
+/// The inplace flag, if set to true, will overwrite the source file with a modified locked
+/// version. It is recommended to take a back-up of the file before doing this as the tool is
+/// relatively new and untested. It may corrupt your file.
+///
+/// Alternatively, pass false for the inplace flag to get a copy of the source file. It will have
+/// the same name as the source file, but have '_locked' appended to the filename.
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - There is no VBA file within the zip archive, found at "/xl/vbaProject.bin"
+/// - The VBA file within the archive cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593),
+///   which holds the VBA locked status, cannot be found within the overall VBA CFB file
+/// - The updated project stream cannot be written back to the CFB file
+/// - The updated zip file cannot be written
+/// - If being run inplace, the new zip file cannot be copied back over the original
+pub fn xl(filename: &Path, password: &str, inplace: bool, options: &Options) -> UnlockResult<()> {
+    rewrite_xl(
+        filename,
+        Destination::new(inplace, "_locked"),
+        |p, code_page| locked_project(p, password, code_page, options),
+    )
+}
+
+/// Lock the VBA project of an Excel file with the supplied password
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// The project is locked for viewing in the same way Excel does it: the protection state, password
+/// and visibility records of the PROJECT stream are all rewritten, and the stream is written back
+/// out from the parsed project. The password is stored as a SHA1 hash with a random salt
+///
+/// The inplace flag, if set to true, will overwrite the source file with a modified locked
+/// version. It is recommended to take a back-up of the file before doing this as the tool is
+/// relatively new and untested. It may corrupt your file.
+///
+/// Alternatively, pass false for the inplace flag to get a copy of the source file. It will have
+/// the same name as the source file, but have '_locked' appended to the filename.
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be copied (for not inplace only) or opened for read/write
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593),
+///   which holds the VBA locked status, cannot be found within the overall VBA CFB file
+/// - The updated project stream cannot be written back to the CFB file
+pub fn xl_97(
+    filename: &Path,
+    password: &str,
+    inplace: bool,
+    options: &Options,
+) -> UnlockResult<()> {
+    rewrite_xl_97(
+        filename,
+        Destination::new(inplace, "_locked"),
+        |p, code_page| locked_project(p, password, code_page, options),
+    )
+}
+
+fn locked_project(
+    project: &[u8],
+    password: &str,
+    code_page: u16,
+    options: &Options,
+) -> UnlockResult<Vec<u8>> {
+    let mut project = Project::from_bytes(project, code_page, options)?;
+    project.lock(consts::LOCKED_ID);
+    project.set_password(Password::hashed(password, code_page));
+    Ok(project.to_bytes(code_page))
+}
@@ -0,0 +1,122 @@
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use crate::error::{UnlockError, UnlockResult};
+use crate::read::xl_vba;
+use cfb::CompoundFile;
+
+/// A single storage or stream of a Compound File Binary (CFB) file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryInfo {
+    /// The full path of the entry, from the root storage
+    pub path: String,
+    /// The name of the entry within its parent storage
+    pub name: String,
+    /// How many storages down from the root storage the entry is
+    pub depth: usize,
+    /// Whether the entry is a storage, rather than a stream
+    pub is_storage: bool,
+    /// The length of the stream in bytes. Always zero for a storage
+    pub size: u64,
+}
+
+/// Print the tree of storages and streams of the VBA file to standard out.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - There is no VBA file within the zip archive, found at "/xl/vbaProject.bin"
+/// - The VBA file within the archive cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+pub fn print_xl(filename: &Path) -> UnlockResult<()> {
+    print_entries(&xl(filename)?);
+    Ok(())
+}
+
+/// List every storage and stream of the VBA file, in the order of a walk down the tree.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - There is no VBA file within the zip archive, found at "/xl/vbaProject.bin"
+/// - The VBA file within the archive cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+pub fn xl(filename: &Path) -> UnlockResult<Vec<EntryInfo>> {
+    Ok(entries(&xl_vba(filename)?))
+}
+
+/// Print the tree of storages and streams of the whole file to standard out.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// # Errors
+/// Will return an error if the file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+pub fn print_xl_97(filename: &Path) -> UnlockResult<()> {
+    print_entries(&xl_97(filename)?);
+    Ok(())
+}
+
+/// List every storage and stream of the whole file, in the order of a walk down the tree.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// The VBA project is found beneath the `_VBA_PROJECT_CUR` storage
+///
+/// # Errors
+/// Will return an error if the file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+pub fn xl_97(filename: &Path) -> UnlockResult<Vec<EntryInfo>> {
+    let file = cfb::open(filename).map_err(UnlockError::CFBOpen)?;
+    Ok(entries(&file))
+}
+
+fn entries<F: Read + Seek>(cfb: &CompoundFile<F>) -> Vec<EntryInfo> {
+    cfb.walk()
+        .map(|e| EntryInfo {
+            path: e.path().to_string_lossy().to_string(),
+            name: e.name().to_string(),
+            depth: e.path().components().count().saturating_sub(1),
+            is_storage: e.is_storage(),
+            size: e.len(),
+        })
+        .collect()
+}
+
+fn print_entries(entries: &[EntryInfo]) {
+    let label = |e: &EntryInfo| {
+        let indent = "  ".repeat(e.depth);
+        let name = printable(&e.name);
+        if e.depth == 0 {
+            "/".to_string()
+        } else if e.is_storage {
+            format!("{indent}{name}/")
+        } else {
+            format!("{indent}{name}")
+        }
+    };
+    let width = entries
+        .iter()
+        .map(|e| label(e).chars().count())
+        .max()
+        .unwrap_or_default();
+    for e in entries {
+        if e.is_storage {
+            println!("{}", label(e));
+        } else {
+            println!("{:width$}  {:>8}", label(e), e.size);
+        }
+    }
+}
+
+/// Some stream names start with a control character, e.g. "\u{5}SummaryInformation", so show
+/// these as escapes
+fn printable(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_control() {
+                format!("\\x{:02X}", u32::from(c))
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
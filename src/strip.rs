@@ -0,0 +1,219 @@
+use std::fs::File;
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+
+use crate::cancel;
+use crate::consts;
+use crate::error::{UnlockError, UnlockResult};
+use crate::remove::{rewrite_cfb, rewrite_zip, Destination};
+use tracing::{debug, info, trace};
+use zip::ZipArchive;
+
+/// The part of the zip archive listing the content type of every other part
+const CONTENT_TYPES_PATH: &str = "[Content_Types].xml";
+
+/// The content type of the VBA project, and of its signatures, which all start the same
+const VBA_CONTENT_TYPE: &str = "application/vnd.ms-office.vbaProject";
+
+/// The type of the relationship from the workbook to the VBA project
+const VBA_RELATIONSHIP: &str = "/relationships/vbaProject\"";
+
+/// The content types of Excel 4.0 macro sheets, which an xlsx file cannot hold either
+const MACRO_SHEET_CONTENT_TYPES: [&str; 2] = [
+    "application/vnd.ms-excel.macrosheet",
+    "application/vnd.ms-excel.intlmacrosheet",
+];
+
+const MACRO_ENABLED_CONTENT_TYPE: &str = "application/vnd.ms-excel.sheet.macroEnabled.main+xml";
+const XLSX_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml";
+
+/// Remove the VBA project from an Excel file entirely, leaving a workbook with no macros
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// The VBA project, and any signatures of it, are dropped from the zip archive. The entries for
+/// them in `[Content_Types].xml` and the workbook relationships are removed so that Excel opens
+/// the file cleanly. Everything else is copied across unchanged, so a file with no VBA project is
+/// simply copied.
+///
+/// The inplace flag, if set to true, will overwrite the source file. Otherwise a copy is saved
+/// alongside the source file, with '_stripped' appended to the filename.
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - The content types or workbook relationships cannot be read
+/// - An updated zip file cannot be created
+/// - If being run inplace, the new zip file cannot be copied back over the original
+pub fn xl(filename: &Path, inplace: bool) -> UnlockResult<()> {
+    rewrite_zip(
+        filename,
+        Destination::new(inplace, "_stripped"),
+        |archive, new_file| strip_archive(archive, new_file, false),
+    )
+}
+
+/// Remove the VBA project from an Excel file entirely, saving the macro-free copy to the output
+/// path.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// If the output path has an 'xlsx' extension then the workbook is converted to a macro-free
+/// workbook, so that Excel will open it as an xlsx file. Only xlsm files can be converted, as xlsb
+/// files store their sheets in a different format. Workbooks with Excel 4.0 macro sheets cannot be
+/// converted either. These are checked for before anything is written
+///
+/// # Errors
+/// Will return an error in the same situations as [`xl`], plus if the output file cannot be
+/// created or the workbook cannot be converted to xlsx
+pub fn xl_to(filename: &Path, output: &Path) -> UnlockResult<()> {
+    let xlsx = output
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("xlsx"));
+    if xlsx {
+        check_xlsx(&mut ZipArchive::new(File::open(filename)?)?)?;
+    }
+    rewrite_zip(filename, Destination::Path(output), |archive, new_file| {
+        strip_archive(archive, new_file, xlsx)
+    })
+}
+
+/// Remove the VBA project from an Excel file entirely, leaving a workbook with no macros
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// The whole of the VBA storage is removed from the file. A file with no VBA project is left as it
+/// is.
+///
+/// The inplace flag, if set to true, will overwrite the source file. Otherwise a copy is saved
+/// alongside the source file, with '_stripped' appended to the filename.
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be copied (for not inplace only) or opened for read/write
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The VBA storage cannot be removed
+pub fn xl_97(filename: &Path, inplace: bool) -> UnlockResult<()> {
+    rewrite_cfb(filename, Destination::new(inplace, "_stripped"), strip_cfb)
+}
+
+/// Remove the VBA project from an Excel file entirely, saving the macro-free copy to the output
+/// path.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// # Errors
+/// Will return an error in the same situations as [`xl_97`], plus if the file cannot be copied
+/// to the output path or the output path has an 'xlsx' extension
+pub fn xl_97_to(filename: &Path, output: &Path) -> UnlockResult<()> {
+    if output
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("xlsx"))
+    {
+        return Err(UnlockError::NotConvertible(
+            "xls files store their sheets in a binary format",
+        ));
+    }
+    rewrite_cfb(filename, Destination::Path(output), strip_cfb)
+}
+
+fn strip_cfb(file: &mut cfb::CompoundFile<File>) -> UnlockResult<()> {
+    if !file.is_storage(consts::CFB_VBA_PROJECT_PATH) {
+        info!("there is no VBA project to remove");
+        return Ok(());
+    }
+    info!("removing the {} storage", consts::CFB_VBA_PROJECT_PATH);
+    Ok(file.remove_storage_all(consts::CFB_VBA_PROJECT_PATH)?)
+}
+
+/// Write a copy of the zip archive to the writer, without the VBA project
+fn strip_archive<R, W>(archive: &mut ZipArchive<R>, writer: W, xlsx: bool) -> UnlockResult<W>
+where
+    R: Read + Seek,
+    W: Write + Seek,
+{
+    let mut new_archive = zip::ZipWriter::new(writer);
+    for i in 0..archive.len() {
+        cancel::check()?;
+        let name = archive.by_index_raw(i)?.name().to_string();
+        if is_vba_part(&name) {
+            debug!("dropping {name}");
+        } else if name == CONTENT_TYPES_PATH || is_workbook_relationships(&name) {
+            let mut xml = String::new();
+            archive.by_index(i)?.read_to_string(&mut xml)?;
+            let xml = if name == CONTENT_TYPES_PATH {
+                strip_content_types(&xml, xlsx)
+            } else {
+                remove_elements(&xml, "Relationship", VBA_RELATIONSHIP)
+            };
+            debug!("writing the updated {name}");
+            new_archive.start_file(name, zip::write::FileOptions::default())?;
+            new_archive.write_all(xml.as_bytes())?;
+        } else {
+            trace!("copying {name} across unchanged");
+            new_archive.raw_copy_file(archive.by_index_raw(i)?)?;
+        }
+    }
+    Ok(new_archive.finish()?)
+}
+
+/// The VBA project itself, its signatures and their relationships
+fn is_vba_part(name: &str) -> bool {
+    name.starts_with("xl/vbaProject") || name == "xl/_rels/vbaProject.bin.rels"
+}
+
+fn is_workbook_relationships(name: &str) -> bool {
+    matches!(
+        name,
+        "xl/_rels/workbook.xml.rels" | "xl/_rels/workbook.bin.rels"
+    )
+}
+
+/// Check that the workbook can be converted to an xlsx file
+fn check_xlsx<R: Read + Seek>(archive: &mut ZipArchive<R>) -> UnlockResult<()> {
+    let mut xml = String::new();
+    archive
+        .by_name(CONTENT_TYPES_PATH)?
+        .read_to_string(&mut xml)?;
+    if MACRO_SHEET_CONTENT_TYPES.iter().any(|t| xml.contains(t)) {
+        return Err(UnlockError::NotConvertible(
+            "it has Excel 4.0 macro sheets, which an xlsx file cannot hold",
+        ));
+    }
+    if !xml.contains(MACRO_ENABLED_CONTENT_TYPE) {
+        return Err(UnlockError::NotConvertible(
+            "only xlsm files can be converted, xlsb files store their sheets in a binary format",
+        ));
+    }
+    Ok(())
+}
+
+fn strip_content_types(xml: &str, xlsx: bool) -> String {
+    let xml = remove_elements(xml, "Override", VBA_CONTENT_TYPE);
+    let xml = remove_elements(&xml, "Default", VBA_CONTENT_TYPE);
+    if xlsx {
+        xml.replace(MACRO_ENABLED_CONTENT_TYPE, XLSX_CONTENT_TYPE)
+    } else {
+        xml
+    }
+}
+
+/// Remove every empty element, i.e. `<tag ... />`, with the given name that contains the text
+pub(crate) fn remove_elements(xml: &str, name: &str, text: &str) -> String {
+    let open = format!("<{name} ");
+    let mut output = String::with_capacity(xml.len());
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let Some(length) = rest[start..].find("/>") else {
+            break;
+        };
+        let end = start + length + "/>".len();
+        output.push_str(&rest[..start]);
+        if rest[start..end].contains(text) {
+            trace!("removing {}", &rest[start..end]);
+        } else {
+            output.push_str(&rest[start..end]);
+        }
+        rest = &rest[end..];
+    }
+    output.push_str(rest);
+    output
+}
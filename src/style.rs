@@ -0,0 +1,82 @@
+//! Colours for the text printed to the terminal.
+//!
+//! Colour is only used when the text is going to a terminal, the
+//! [`NO_COLOR`](https://no-color.org) environment variable is not set and it has not been turned
+//! off on the command line
+
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crossterm::style::{StyledContent, Stylize};
+
+static STDOUT: AtomicBool = AtomicBool::new(false);
+static STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Work out whether to colour standard out and standard error. Passing `no_color` turns colour off
+/// for both
+pub fn init(no_color: bool) {
+    let allowed = !no_color && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty());
+    set(
+        allowed && std::io::stdout().is_terminal(),
+        allowed && std::io::stderr().is_terminal(),
+    );
+}
+
+/// Turn colour on or off for standard out and standard error directly
+pub fn set(stdout: bool, stderr: bool) {
+    STDOUT.store(stdout, Ordering::Relaxed);
+    STDERR.store(stderr, Ordering::Relaxed);
+}
+
+/// Whether text written to standard error is coloured
+#[must_use]
+pub fn stderr_enabled() -> bool {
+    STDERR.load(Ordering::Relaxed)
+}
+
+/// Text saying that something is locked
+#[must_use]
+pub fn locked(text: &str) -> String {
+    paint(text, &STDOUT, |t| t.red().bold())
+}
+
+/// Text saying that something is not locked
+#[must_use]
+pub fn unlocked(text: &str) -> String {
+    paint(text, &STDOUT, |t| t.green().bold())
+}
+
+/// Text saying that something worked
+#[must_use]
+pub fn success(text: &str) -> String {
+    paint(text, &STDOUT, |t| t.green())
+}
+
+/// Text saying that something did not work, as part of the normal output
+#[must_use]
+pub fn failure(text: &str) -> String {
+    paint(text, &STDOUT, |t| t.red())
+}
+
+/// The name of a value that follows it
+#[must_use]
+pub fn label(text: &str) -> String {
+    paint(text, &STDOUT, |t| t.bold())
+}
+
+/// An error, written to standard error
+#[must_use]
+pub fn error(text: &str) -> String {
+    paint(text, &STDERR, |t| t.red().bold())
+}
+
+fn paint<F>(text: &str, enabled: &AtomicBool, style: F) -> String
+where
+    F: FnOnce(StyledContent<&str>) -> StyledContent<&str>,
+{
+    if enabled.load(Ordering::Relaxed) {
+        style(text.stylize()).to_string()
+    } else {
+        text.to_string()
+    }
+}
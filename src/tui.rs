@@ -0,0 +1,351 @@
+use std::io::{self, Stdout};
+use std::path::{Path, PathBuf};
+
+use crate::crack;
+use crate::error::UnlockResult;
+use crate::info;
+use crate::lenient;
+use crate::list_modules::{self, ModuleInfo};
+use crate::ovba::records::project::Project;
+use crate::read;
+use crate::remove;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use crossterm::ExecutableCommand;
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, List, ListState, Paragraph, Row, Table, Tabs};
+
+/// Open an interactive view of the VBA project, from which it can be unlocked or its password
+/// cracked.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// # Errors
+/// Will return an error if the terminal cannot be set up. Problems with the file itself are shown
+/// within the view
+pub fn xl(
+    filename: &Path,
+    options: &crack::Options,
+    leniency: &lenient::Options,
+) -> UnlockResult<()> {
+    let handlers = Handlers {
+        project: read::xl_project,
+        modules: list_modules::xl_modules,
+        remove: remove::xl,
+        crack: crack::xl,
+    };
+    run(App::new(
+        filename,
+        handlers,
+        options.clone(),
+        leniency.clone(),
+    ))
+}
+
+/// Open an interactive view of the VBA project, from which it can be unlocked or its password
+/// cracked.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// # Errors
+/// Will return an error if the terminal cannot be set up. Problems with the file itself are shown
+/// within the view
+pub fn xl_97(
+    filename: &Path,
+    options: &crack::Options,
+    leniency: &lenient::Options,
+) -> UnlockResult<()> {
+    let handlers = Handlers {
+        project: read::xl_97_project,
+        modules: list_modules::xl_97_modules,
+        remove: remove::xl_97,
+        crack: crack::xl_97,
+    };
+    run(App::new(
+        filename,
+        handlers,
+        options.clone(),
+        leniency.clone(),
+    ))
+}
+
+type ProjectReader = fn(&Path, bool, &lenient::Options) -> UnlockResult<(Project, Option<String>)>;
+
+/// The functions that do the work, which differ between the old and new file formats
+struct Handlers {
+    project: ProjectReader,
+    modules: fn(&Path, &lenient::Options) -> UnlockResult<Vec<ModuleInfo>>,
+    remove: fn(&Path, bool, &lenient::Options) -> UnlockResult<()>,
+    crack: fn(&Path, &crack::Options, &lenient::Options) -> UnlockResult<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    UnlockCopy,
+    UnlockInPlace,
+    Crack,
+    Reload,
+    Quit,
+}
+
+impl Action {
+    const ALL: [Self; 5] = [
+        Self::UnlockCopy,
+        Self::UnlockInPlace,
+        Self::Crack,
+        Self::Reload,
+        Self::Quit,
+    ];
+
+    const fn label(self) -> &'static str {
+        match self {
+            Self::UnlockCopy => "Unlock to a copy",
+            Self::UnlockInPlace => "Unlock in place",
+            Self::Crack => "Crack password",
+            Self::Reload => "Reload file",
+            Self::Quit => "Quit",
+        }
+    }
+}
+
+struct App {
+    filename: PathBuf,
+    handlers: Handlers,
+    options: crack::Options,
+    leniency: lenient::Options,
+    locked: Option<bool>,
+    properties: Vec<String>,
+    modules: Result<Vec<ModuleInfo>, String>,
+    tab: usize,
+    scroll: u16,
+    actions: ListState,
+    status: String,
+    quit: bool,
+}
+
+impl App {
+    fn new(
+        filename: &Path,
+        handlers: Handlers,
+        options: crack::Options,
+        leniency: lenient::Options,
+    ) -> Self {
+        let mut app = Self {
+            filename: filename.to_path_buf(),
+            handlers,
+            options,
+            leniency,
+            locked: None,
+            properties: Vec::new(),
+            modules: Ok(Vec::new()),
+            tab: 0,
+            scroll: 0,
+            actions: ListState::default().with_selected(Some(0)),
+            status: String::new(),
+            quit: false,
+        };
+        app.load();
+        app
+    }
+
+    /// Read the file again, to pick up any changes
+    fn load(&mut self) {
+        match (self.handlers.project)(&self.filename, false, &self.leniency) {
+            Ok((project, _)) => {
+                self.locked = Some(project.is_locked());
+                self.properties = info::report(&project);
+            }
+            Err(e) => {
+                self.locked = None;
+                self.properties = vec![format!("Error: {e}")];
+            }
+        }
+        self.modules =
+            (self.handlers.modules)(&self.filename, &self.leniency).map_err(|e| e.to_string());
+    }
+
+    fn selected(&self) -> Action {
+        Action::ALL[self.actions.selected().unwrap_or_default()]
+    }
+
+    fn key(&mut self, code: KeyCode) {
+        let last = Action::ALL.len() - 1;
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Up => {
+                let i = self.actions.selected().unwrap_or_default();
+                self.actions.select(Some(i.saturating_sub(1)));
+            }
+            KeyCode::Down => {
+                let i = self.actions.selected().unwrap_or_default();
+                self.actions.select(Some((i + 1).min(last)));
+            }
+            KeyCode::Tab | KeyCode::Left | KeyCode::Right => {
+                self.tab = 1 - self.tab;
+                self.scroll = 0;
+            }
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_add(10),
+            _ => (),
+        }
+    }
+
+    /// Carry out the selected action, once any message about it has been drawn
+    fn act(&mut self, action: Action) {
+        match action {
+            Action::UnlockCopy => {
+                self.status = match (self.handlers.remove)(&self.filename, false, &self.leniency) {
+                    Ok(()) => "Saved an unlocked copy alongside the file".to_string(),
+                    Err(e) => format!("Error: {e}"),
+                };
+            }
+            Action::UnlockInPlace => {
+                self.status = match (self.handlers.remove)(&self.filename, true, &self.leniency) {
+                    Ok(()) => "The file has been unlocked".to_string(),
+                    Err(e) => format!("Error: {e}"),
+                };
+                self.load();
+            }
+            Action::Crack => {
+                self.status =
+                    match (self.handlers.crack)(&self.filename, &self.options, &self.leniency) {
+                        Ok(password) => format!("The password is \"{password}\""),
+                        Err(e) => format!("Error: {e}"),
+                    };
+            }
+            Action::Reload => {
+                self.load();
+                self.status = "Reloaded the file".to_string();
+            }
+            Action::Quit => self.quit = true,
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [title, body, status, help] = split(
+            &Layout::vertical([
+                Constraint::Length(1),
+                Constraint::Min(0),
+                Constraint::Length(1),
+                Constraint::Length(1),
+            ]),
+            frame.size(),
+        );
+        let [main, menu] = split(
+            &Layout::horizontal([Constraint::Min(0), Constraint::Length(24)]),
+            body,
+        );
+        let [tabs, details] = split(
+            &Layout::vertical([Constraint::Length(1), Constraint::Min(0)]),
+            main,
+        );
+
+        let lock = match self.locked {
+            Some(true) => "🔒 locked",
+            Some(false) => "🔓 not locked",
+            None => "❓ unreadable",
+        };
+        frame.render_widget(
+            Paragraph::new(format!("{}  {lock}", self.filename.display())).bold(),
+            title,
+        );
+        frame.render_widget(
+            Tabs::new(vec!["Properties", "Modules"]).select(self.tab),
+            tabs,
+        );
+
+        let block = Block::default().borders(Borders::ALL);
+        if self.tab == 0 {
+            let lines: Vec<Line> = self
+                .properties
+                .iter()
+                .map(|l| Line::raw(l.as_str()))
+                .collect();
+            frame.render_widget(
+                Paragraph::new(lines).block(block).scroll((self.scroll, 0)),
+                details,
+            );
+        } else {
+            match &self.modules {
+                Ok(modules) => {
+                    let rows = modules.iter().skip(usize::from(self.scroll)).map(|m| {
+                        Row::new(vec![
+                            m.name.clone(),
+                            format!("{:?}", m.module_type),
+                            m.stream_name.clone(),
+                        ])
+                    });
+                    let widths = [
+                        Constraint::Percentage(40),
+                        Constraint::Length(10),
+                        Constraint::Percentage(40),
+                    ];
+                    frame.render_widget(
+                        Table::new(rows, widths)
+                            .header(Row::new(vec!["Name", "Type", "Stream"]).bold())
+                            .block(block),
+                        details,
+                    );
+                }
+                Err(e) => {
+                    frame
+                        .render_widget(Paragraph::new(format!("Error: {e}")).block(block), details);
+                }
+            }
+        }
+
+        let actions = List::new(Action::ALL.map(Action::label))
+            .block(Block::default().borders(Borders::ALL).title("Actions"))
+            .highlight_symbol("> ")
+            .highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(actions, menu, &mut self.actions);
+
+        frame.render_widget(Paragraph::new(self.status.as_str()), status);
+        frame.render_widget(
+            Paragraph::new("↑/↓ choose  Enter run  Tab switch view  PgUp/PgDn scroll  q quit")
+                .dim(),
+            help,
+        );
+    }
+}
+
+fn split<const N: usize>(layout: &Layout, area: Rect) -> [Rect; N] {
+    let areas = layout.split(area);
+    std::array::from_fn(|i| areas[i])
+}
+
+/// Take over the terminal for the life of the view, handing it back however the view ends
+fn run(app: App) -> UnlockResult<()> {
+    enable_raw_mode()?;
+    io::stdout().execute(EnterAlternateScreen)?;
+    let result = event_loop(app);
+    disable_raw_mode()?;
+    io::stdout().execute(LeaveAlternateScreen)?;
+    result
+}
+
+fn event_loop(mut app: App) -> UnlockResult<()> {
+    let mut terminal: Terminal<CrosstermBackend<Stdout>> =
+        Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    while !app.quit {
+        terminal.draw(|frame| app.draw(frame))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        if key.code == KeyCode::Enter {
+            let action = app.selected();
+            if action == Action::Crack {
+                // Cracking can take a while, so say so before starting
+                app.status = "Searching for the password...".to_string();
+                terminal.draw(|frame| app.draw(frame))?;
+            }
+            app.act(action);
+        } else {
+            app.key(key.code);
+        }
+    }
+    Ok(())
+}
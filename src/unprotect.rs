@@ -0,0 +1,439 @@
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, Write};
+use std::path::Path;
+
+use crate::biff::{self, Substream};
+use crate::consts;
+use crate::error::{UnlockError, UnlockResult};
+use crate::files::{self, Container};
+use crate::lenient::Options;
+use crate::remove::{
+    rewrite_cfb, rewrite_stream, rewrite_zip, unlocked_project, update_entries, update_vba,
+    Destination,
+};
+use crate::strip::remove_elements;
+use crate::unprotect_sheets;
+use cfb::CompoundFile;
+use tracing::{info, trace};
+use zip::ZipArchive;
+
+/// The parts of the zip archive that hold the workbook settings, including its protection, for
+/// xlsm and xlsb files respectively
+const WORKBOOK_XML: &str = "xl/workbook.xml";
+const WORKBOOK_BIN: &str = "xl/workbook.bin";
+
+/// The attribute of a sheet within the workbook xml that makes it very hidden
+const VERY_HIDDEN_XML: &str = " state=\"veryHidden\"";
+
+/// The start of the names of the attributes of the workbook protection in the workbook xml that
+/// protect a shared workbook: `lockRevision` and the password in `revisionsPassword`, or in
+/// `revisionsHashValue` and its siblings
+const SHARED_ATTRIBUTES: [&str; 2] = ["lockRevision", "revisions"];
+
+/// The kinds of protection to remove from an Excel file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Layers<'a> {
+    /// The lock on viewing the VBA project
+    pub vba: bool,
+    /// The protection of the workbook structure and windows
+    pub workbook: bool,
+    /// The protection of each sheet
+    pub sheets: bool,
+    /// Only remove the protection from the sheets with these names. Every sheet is unprotected if
+    /// none are given
+    pub sheet_names: &'a [String],
+    /// The very hidden state of any sheet, which can otherwise only be undone from VBA
+    pub very_hidden: bool,
+    /// The password to write to the workbook, and the recommendation to open it read-only
+    pub file_sharing: bool,
+    /// The protection of the change history of a shared workbook, which stops it being unshared
+    pub shared: bool,
+}
+
+impl Layers<'_> {
+    /// Whether any of the layers are held in the workbook part, or the globals of an xls file
+    const fn in_workbook(self) -> bool {
+        self.workbook || self.very_hidden || self.file_sharing || self.shared
+    }
+}
+
+/// Remove the chosen kinds of protection from an Excel file, in a single pass over the file
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// The inplace flag, if set to true, will overwrite the source file. Otherwise a copy is saved
+/// alongside the source file, with '_unprotected' appended to the filename.
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - For the VBA layer, there is no VBA file within the zip archive, or its PROJECT stream cannot
+///   be updated, as for [`crate::remove::xl`]
+/// - For the layers other than VBA, the records of an xlsb file cannot be parsed
+/// - An updated zip file cannot be created
+/// - If being run inplace, the new zip file cannot be copied back over the original
+pub fn xl(filename: &Path, layers: Layers, inplace: bool, options: &Options) -> UnlockResult<()> {
+    unprotect_zip(
+        filename,
+        layers,
+        Destination::new(inplace, "_unprotected"),
+        options,
+    )
+}
+
+/// Remove the chosen kinds of protection from an Excel file, saving the unprotected copy to the
+/// output path.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// # Errors
+/// Will return an error in the same situations as [`xl`], plus if the output file cannot be
+/// created
+pub fn xl_to(
+    filename: &Path,
+    layers: Layers,
+    output: &Path,
+    options: &Options,
+) -> UnlockResult<()> {
+    unprotect_zip(filename, layers, Destination::Path(output), options)
+}
+
+/// Remove the chosen kinds of protection from an Excel file
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// The inplace flag, if set to true, will overwrite the source file. Otherwise a copy is saved
+/// alongside the source file, with '_unprotected' appended to the filename.
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be copied (for not inplace only) or opened for read/write
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - For the VBA layer, the PROJECT stream cannot be updated, as for [`crate::remove::xl_97`]
+/// - For the layers other than VBA, the workbook stream cannot be found or its records parsed, or
+///   the workbook is encrypted
+pub fn xl_97(
+    filename: &Path,
+    layers: Layers,
+    inplace: bool,
+    options: &Options,
+) -> UnlockResult<()> {
+    rewrite_cfb(
+        filename,
+        Destination::new(inplace, "_unprotected"),
+        |file| unprotect_cfb(file, layers, options),
+    )
+}
+
+/// Remove the chosen kinds of protection from an Excel file, saving the unprotected copy to the
+/// output path.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// # Errors
+/// Will return an error in the same situations as [`xl_97`], plus if the file cannot be copied
+/// to the output path
+pub fn xl_97_to(
+    filename: &Path,
+    layers: Layers,
+    output: &Path,
+    options: &Options,
+) -> UnlockResult<()> {
+    rewrite_cfb(filename, Destination::Path(output), |file| {
+        unprotect_cfb(file, layers, options)
+    })
+}
+
+/// Remove the chosen kinds of protection from an Excel file held in memory, returning the
+/// unprotected file
+///
+/// This works for any of xlsm, xlsb or xls files, the format being worked out from the contents
+///
+/// # Errors
+/// Will return an error in the same situations as [`xl`] or [`xl_97`], or if the data is not
+/// recognised as either a zip or CFB file
+pub fn bytes(data: &[u8], layers: Layers, options: &Options) -> UnlockResult<Vec<u8>> {
+    match files::sniff(data) {
+        Some(Container::Zip) => {
+            let mut archive = ZipArchive::new(Cursor::new(data))?;
+            let output = unprotect_archive(&mut archive, Cursor::new(Vec::new()), layers, options)?;
+            Ok(output.into_inner())
+        }
+        Some(Container::Cfb) => {
+            let mut file =
+                CompoundFile::open(Cursor::new(data.to_vec())).map_err(UnlockError::CFBOpen)?;
+            unprotect_cfb(&mut file, layers, options)?;
+            Ok(file.into_inner().into_inner())
+        }
+        None => Err(UnlockError::NotExcel("The input".into())),
+    }
+}
+
+/// Whether the workbook has shared workbook protection, which stops it being unshared.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - There is no workbook part within the zip archive, or it cannot be read
+/// - The records of the workbook part of an xlsb file cannot be parsed
+pub fn xl_shared(filename: &Path) -> UnlockResult<bool> {
+    let mut archive = ZipArchive::new(File::open(filename)?)?;
+    if let Ok(mut part) = archive.by_name(WORKBOOK_BIN) {
+        let mut data = Vec::new();
+        part.read_to_end(&mut data)?;
+        let (_, found) = biff::update_records_12(&data, WORKBOOK_BIN, clear_revision_lock_12)?;
+        return Ok(found > 0);
+    }
+    let mut xml = String::new();
+    archive.by_name(WORKBOOK_XML)?.read_to_string(&mut xml)?;
+    Ok(remove_attributes(&xml, "workbookProtection", &SHARED_ATTRIBUTES).len() != xml.len())
+}
+
+/// Whether the workbook has shared workbook protection, which stops it being unshared.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The workbook stream cannot be found, or its records cannot be parsed
+/// - The workbook is encrypted
+pub fn xl_97_shared(filename: &Path) -> UnlockResult<bool> {
+    let mut file = cfb::open(filename).map_err(UnlockError::CFBOpen)?;
+    let (_, mut stream) = biff::read_workbook_stream(&mut file)?;
+    let found = biff::update_records_8(&mut stream, |substream, record, data| {
+        is_shared_record(substream, *record) && data.iter().any(|&b| b != 0)
+    })?;
+    Ok(found > 0)
+}
+
+fn unprotect_zip(
+    filename: &Path,
+    layers: Layers,
+    destination: Destination,
+    options: &Options,
+) -> UnlockResult<()> {
+    rewrite_zip(filename, destination, |archive, new_file| {
+        unprotect_archive(archive, new_file, layers, options)
+    })
+}
+
+fn unprotect_archive<R, W>(
+    archive: &mut ZipArchive<R>,
+    writer: W,
+    layers: Layers,
+    options: &Options,
+) -> UnlockResult<W>
+where
+    R: Read + Seek,
+    W: Write + Seek,
+{
+    if layers.vba && archive.by_name(consts::ZIP_VBA_PATH).is_err() {
+        return Err(UnlockError::NoVBAFile);
+    }
+    let sheet_parts = if layers.sheets {
+        unprotect_sheets::named_parts(archive, layers.sheet_names)?
+    } else {
+        None
+    };
+    let wanted = |name: &str| {
+        (layers.vba && name == consts::ZIP_VBA_PATH)
+            || (layers.in_workbook() && (name == WORKBOOK_XML || name == WORKBOOK_BIN))
+            || (layers.sheets
+                && unprotect_sheets::is_sheet(name)
+                && sheet_parts
+                    .as_ref()
+                    .is_none_or(|parts| parts.iter().any(|p| p == name)))
+    };
+    update_entries(archive, writer, wanted, |name, data| {
+        if name == consts::ZIP_VBA_PATH {
+            info!("removing the VBA project protection");
+            update_vba(data.to_vec(), |p, code_page| {
+                unlocked_project(p, code_page, options)
+            })
+        } else if name == WORKBOOK_XML || name == WORKBOOK_BIN {
+            unprotect_workbook(name, data, layers)
+        } else {
+            unprotect_sheets::unprotect_sheet(name, data)
+        }
+    })
+}
+
+/// Take the structure, window and shared workbook protection, and the write reservation, out of
+/// the workbook part, and show any very hidden sheets, as asked for. The part is either xml or,
+/// for xlsb, binary records
+fn unprotect_workbook(name: &str, data: &[u8], layers: Layers) -> UnlockResult<Vec<u8>> {
+    if name == WORKBOOK_BIN {
+        let mut types = Vec::new();
+        if layers.workbook {
+            types.extend(biff::BRT_BOOK_PROTECTION);
+        }
+        if layers.file_sharing {
+            types.extend(biff::BRT_FILE_SHARING);
+        }
+        let (mut data, removed) = biff::remove_records_12(data, name, &types)?;
+        if removed > 0 {
+            info!("removed {removed} protection records from the workbook");
+        }
+        if layers.shared && !layers.workbook {
+            let cleared;
+            (data, cleared) = biff::update_records_12(&data, name, clear_revision_lock_12)?;
+            if cleared > 0 {
+                info!("removed the shared workbook protection");
+            }
+        }
+        if layers.very_hidden {
+            let shown;
+            (data, shown) = biff::update_records_12(&data, name, |record, data| {
+                record == biff::BRT_BUNDLE_SH && show_sheet(data, 0)
+            })?;
+            info!("made {shown} very hidden sheets visible");
+        }
+        return Ok(data);
+    }
+    let mut xml = String::from_utf8_lossy(data).to_string();
+    if layers.workbook {
+        let updated = remove_elements(&xml, "workbookProtection", "");
+        if updated.len() != xml.len() {
+            info!("removed the workbook protection");
+        }
+        xml = updated;
+    }
+    if layers.shared {
+        let updated = remove_attributes(&xml, "workbookProtection", &SHARED_ATTRIBUTES);
+        if updated.len() != xml.len() {
+            info!("removed the shared workbook protection");
+        }
+        xml = updated;
+    }
+    if layers.file_sharing {
+        let updated = remove_elements(&xml, "fileSharing", "");
+        if updated.len() != xml.len() {
+            info!("removed the write reservation");
+        }
+        xml = updated;
+    }
+    if layers.very_hidden {
+        // A sheet with no state is visible
+        let shown = xml.matches(VERY_HIDDEN_XML).count();
+        xml = xml.replace(VERY_HIDDEN_XML, "");
+        info!("made {shown} very hidden sheets visible");
+    }
+    Ok(xml.into_bytes())
+}
+
+/// Remove every attribute whose name starts with one of the prefixes from each `<name ...>`
+/// element. Attributes are expected to be written as ` name="value"`, as Excel does
+fn remove_attributes(xml: &str, name: &str, prefixes: &[&str]) -> String {
+    let open = format!("<{name}");
+    let mut output = String::with_capacity(xml.len());
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let Some(length) = rest[start..].find('>') else {
+            break;
+        };
+        let end = start + length;
+        output.push_str(&rest[..start + open.len()]);
+        let mut attributes = &rest[start + open.len()..end];
+        while let Some(equals) = attributes.find("=\"") {
+            let close = attributes[equals + 2..]
+                .find('"')
+                .map_or(attributes.len(), |i| equals + 3 + i);
+            let attribute = &attributes[..close];
+            if prefixes
+                .iter()
+                .any(|p| attribute.trim_start().starts_with(p))
+            {
+                trace!("removing{attribute} from {name}");
+            } else {
+                output.push_str(attribute);
+            }
+            attributes = &attributes[close..];
+        }
+        output.push_str(attributes);
+        rest = &rest[end..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Clear the password and flag of the shared workbook protection from a workbook protection
+/// record of an xlsb file. Returns whether anything changed
+fn clear_revision_lock_12(record: u16, data: &mut [u8]) -> bool {
+    let Some(i) = biff::BRT_BOOK_PROTECTION.iter().position(|&r| r == record) else {
+        return false;
+    };
+    let mut changed = false;
+    for &offset in biff::BRT_BOOK_PROTECTION_REVISION[i] {
+        if let Some(bytes) = data.get_mut(offset..offset + 2) {
+            changed |= bytes.iter().any(|&b| b != 0);
+            bytes.fill(0);
+        }
+    }
+    changed
+}
+
+/// Set the state of a sheet back to visible, if it is very hidden. The state is held in the low
+/// bits of the byte at the offset. Returns whether anything changed
+fn show_sheet(data: &mut [u8], offset: usize) -> bool {
+    match data.get_mut(offset) {
+        Some(state) if *state & 0x03 == biff::VERY_HIDDEN => {
+            *state &= !0x03;
+            true
+        }
+        _ => false,
+    }
+}
+
+fn unprotect_cfb<T: Read + Write + Seek>(
+    file: &mut CompoundFile<T>,
+    layers: Layers,
+    options: &Options,
+) -> UnlockResult<()> {
+    if layers.vba {
+        info!("removing the VBA project protection");
+        rewrite_stream(file, consts::CFB_VBA_PATH, |p, code_page| {
+            unlocked_project(p, code_page, options)
+        })?;
+    }
+    if layers.in_workbook() || layers.sheets {
+        let offsets = if layers.sheets {
+            unprotect_sheets::named_offsets(file, layers.sheet_names)?
+        } else {
+            None
+        };
+        biff::update_workbook_stream(file, |substream, record, data| {
+            if (layers.workbook && is_workbook_record(substream, *record))
+                || (layers.sheets
+                    && unprotect_sheets::is_sheet_record(substream, *record, offsets.as_deref()))
+                || (layers.file_sharing && *record == biff::FILESHARING)
+                || (layers.shared && is_shared_record(substream, *record))
+            {
+                data.fill(0);
+                return true;
+            }
+            if layers.file_sharing && *record == biff::WRITEPROT {
+                // There is no data to clear, and removing the record would move the sheets
+                *record = biff::EXCEL9FILE;
+                return true;
+            }
+            // The state follows the four byte position of the sheet within the stream
+            layers.very_hidden
+                && substream == Substream::Globals
+                && *record == biff::BOUNDSHEET
+                && show_sheet(data, 4)
+        })?;
+    }
+    Ok(())
+}
+
+/// A protection record of the workbook structure or windows, within the workbook stream of an
+/// xls file
+const fn is_workbook_record(substream: Substream, record: u16) -> bool {
+    matches!(substream, Substream::Globals)
+        && matches!(record, biff::PROTECT | biff::PASSWORD | biff::WINDOWPROTECT)
+}
+
+/// A record of the shared workbook protection, within the workbook stream of an xls file
+const fn is_shared_record(substream: Substream, record: u16) -> bool {
+    matches!(substream, Substream::Globals) && matches!(record, biff::PROT4REV | biff::PROT4REVPASS)
+}
@@ -0,0 +1,290 @@
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use crate::biff::{self, Substream};
+use crate::error::{UnlockError, UnlockResult};
+use crate::lenient::Options;
+use crate::strip::remove_elements;
+use crate::unprotect::{self, Layers};
+use cfb::CompoundFile;
+use tracing::info;
+use zip::ZipArchive;
+
+/// The parts of the zip archive listing the sheets of the workbook, for xlsm and xlsb files
+/// respectively
+const WORKBOOK_XML: &str = "xl/workbook.xml";
+const WORKBOOK_BIN: &str = "xl/workbook.bin";
+
+/// The folders of the zip archive that hold the sheets of the workbook
+const SHEET_FOLDERS: [&str; 4] = [
+    "xl/worksheets/",
+    "xl/chartsheets/",
+    "xl/dialogsheets/",
+    "xl/macrosheets/",
+];
+
+/// Remove the protection from every sheet of an Excel file, or just the sheets named
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// The `<sheetProtection>` element is deleted from each sheet of an xlsm file, and the matching
+/// records from each sheet of an xlsb file. Everything else is copied across unchanged.
+///
+/// If any sheet names are given then only those sheets are unprotected. The names are matched,
+/// ignoring case as Excel does, against the sheets listed in the workbook part.
+///
+/// The inplace flag, if set to true, will overwrite the source file. Otherwise a copy is saved
+/// alongside the source file, with '_unprotected' appended to the filename.
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file is cannot be opened as a zip file
+/// - A sheet cannot be read, or the records of an xlsb sheet cannot be parsed
+/// - A sheet that is named cannot be found in the workbook
+/// - An updated zip file cannot be created
+/// - If being run inplace, the new zip file cannot be copied back over the original
+pub fn xl(filename: &Path, sheets: &[String], inplace: bool) -> UnlockResult<()> {
+    unprotect::xl(filename, layers(sheets), inplace, &Options::default())
+}
+
+/// Remove the protection from every sheet of an Excel file, or just the sheets named, saving the
+/// unprotected copy to the output path.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// # Errors
+/// Will return an error in the same situations as [`xl`], plus if the output file cannot be
+/// created
+pub fn xl_to(filename: &Path, sheets: &[String], output: &Path) -> UnlockResult<()> {
+    unprotect::xl_to(filename, layers(sheets), output, &Options::default())
+}
+
+/// Remove the protection from every sheet of an Excel file, or just the sheets named
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// The protection records of each sheet are cleared, which leaves the sheet unprotected and
+/// without a password. The records are zeroed rather than removed so that the rest of the
+/// workbook stream does not move.
+///
+/// If any sheet names are given then only those sheets are unprotected, as for [`xl`].
+///
+/// The inplace flag, if set to true, will overwrite the source file. Otherwise a copy is saved
+/// alongside the source file, with '_unprotected' appended to the filename.
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be copied (for not inplace only) or opened for read/write
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The workbook stream cannot be found, or its records cannot be parsed
+/// - The workbook is encrypted
+/// - A sheet that is named cannot be found in the workbook
+pub fn xl_97(filename: &Path, sheets: &[String], inplace: bool) -> UnlockResult<()> {
+    unprotect::xl_97(filename, layers(sheets), inplace, &Options::default())
+}
+
+/// Remove the protection from every sheet of an Excel file, or just the sheets named, saving the
+/// unprotected copy to the output path.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// # Errors
+/// Will return an error in the same situations as [`xl_97`], plus if the file cannot be copied
+/// to the output path
+pub fn xl_97_to(filename: &Path, sheets: &[String], output: &Path) -> UnlockResult<()> {
+    unprotect::xl_97_to(filename, layers(sheets), output, &Options::default())
+}
+
+/// Only the sheet layers, so the VBA project is never parsed and the options it would be read with
+/// make no difference
+fn layers(sheet_names: &[String]) -> Layers<'_> {
+    Layers {
+        sheets: true,
+        sheet_names,
+        ..Layers::default()
+    }
+}
+
+/// A sheet of the workbook, rather than anything else kept alongside them, like their
+/// relationships or the index of an xlsb sheet
+pub(crate) fn is_sheet(name: &str) -> bool {
+    SHEET_FOLDERS.iter().any(|folder| {
+        name.strip_prefix(folder).is_some_and(|file| {
+            !file.contains('/')
+                && !file.starts_with("binaryIndex")
+                && (has_extension(file, "xml") || has_extension(file, "bin"))
+        })
+    })
+}
+
+/// Take the protection out of a single sheet, which is either xml or, for xlsb, binary records
+pub(crate) fn unprotect_sheet(name: &str, data: &[u8]) -> UnlockResult<Vec<u8>> {
+    if has_extension(name, "bin") {
+        let (data, removed) = biff::remove_records_12(data, name, &biff::BRT_SHEET_PROTECTION)?;
+        if removed > 0 {
+            info!("removed the protection records from {name}");
+        }
+        return Ok(data);
+    }
+    let xml = String::from_utf8_lossy(data);
+    let updated = remove_elements(&xml, "sheetProtection", "");
+    if updated.len() != xml.len() {
+        info!("removed the protection records from {name}");
+    }
+    Ok(updated.into_bytes())
+}
+
+fn has_extension(name: &str, extension: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case(extension))
+}
+
+/// A protection record of a sheet, within the workbook stream of an xls file. Only the sheets
+/// starting at the offsets are picked out, if there are any
+pub(crate) fn is_sheet_record(
+    substream: Substream,
+    record: u16,
+    offsets: Option<&[usize]>,
+) -> bool {
+    matches!(substream, Substream::Sheet(start) if offsets.is_none_or(|o| o.contains(&start)))
+        && matches!(
+            record,
+            biff::PROTECT | biff::PASSWORD | biff::OBJPROTECT | biff::SCENPROTECT
+        )
+}
+
+/// The parts of the zip archive that hold the named sheets, found from the workbook part and its
+/// relationships. If no names are given then every sheet is wanted, which is shown by `None`
+pub(crate) fn named_parts<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    names: &[String],
+) -> UnlockResult<Option<Vec<String>>> {
+    if names.is_empty() {
+        return Ok(None);
+    }
+    let (sheets, relationships) = if archive.by_name(WORKBOOK_BIN).is_ok() {
+        let sheets = biff::sheets_12(&read_part(archive, WORKBOOK_BIN)?, WORKBOOK_BIN)?;
+        let sheets = sheets.into_iter().map(|(id, name)| (name, id)).collect();
+        (sheets, read_part(archive, "xl/_rels/workbook.bin.rels")?)
+    } else {
+        let xml = String::from_utf8_lossy(&read_part(archive, WORKBOOK_XML)?).to_string();
+        (
+            xml_sheets(&xml),
+            read_part(archive, "xl/_rels/workbook.xml.rels")?,
+        )
+    };
+    let relationships = String::from_utf8_lossy(&relationships);
+    names
+        .iter()
+        .map(|wanted| {
+            sheets
+                .iter()
+                .find(|(name, _)| same_name(name, wanted))
+                .and_then(|(_, id)| relationship_target(&relationships, id))
+                .ok_or_else(|| UnlockError::NoSheet(wanted.clone()))
+        })
+        .collect::<UnlockResult<_>>()
+        .map(Some)
+}
+
+/// The offsets within the workbook stream of an xls file of the named sheets. If no names are
+/// given then every sheet is wanted, which is shown by `None`
+pub(crate) fn named_offsets<T: Read + Seek>(
+    file: &mut CompoundFile<T>,
+    names: &[String],
+) -> UnlockResult<Option<Vec<usize>>> {
+    if names.is_empty() {
+        return Ok(None);
+    }
+    let (_, stream) = biff::read_workbook_stream(file)?;
+    let sheets = biff::sheets_8(&stream)?;
+    names
+        .iter()
+        .map(|wanted| {
+            sheets
+                .iter()
+                .find(|(name, _)| same_name(name, wanted))
+                .map(|&(_, offset)| offset)
+                .ok_or_else(|| UnlockError::NoSheet(wanted.clone()))
+        })
+        .collect::<UnlockResult<_>>()
+        .map(Some)
+}
+
+/// The name of every sheet of the workbook, in the order listed in the workbook part
+pub(crate) fn sheet_names<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+) -> UnlockResult<Vec<String>> {
+    if archive.by_name(WORKBOOK_BIN).is_ok() {
+        let sheets = biff::sheets_12(&read_part(archive, WORKBOOK_BIN)?, WORKBOOK_BIN)?;
+        Ok(sheets.into_iter().map(|(_, name)| name).collect())
+    } else {
+        let xml = String::from_utf8_lossy(&read_part(archive, WORKBOOK_XML)?).to_string();
+        Ok(xml_sheets(&xml).into_iter().map(|(name, _)| name).collect())
+    }
+}
+
+/// Excel does not allow two sheets with names that differ only by case
+fn same_name(a: &str, b: &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
+}
+
+pub(crate) fn read_part<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> UnlockResult<Vec<u8>> {
+    let mut data = Vec::new();
+    archive.by_name(name)?.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// The name and relationship id of every `<sheet>` listed in the workbook xml
+fn xml_sheets(xml: &str) -> Vec<(String, String)> {
+    elements(xml, "sheet")
+        .into_iter()
+        .filter_map(|sheet| {
+            let name = attribute(sheet, "name")?;
+            let id = attribute(sheet, "r:id")?;
+            Some((unescape(name), id.to_string()))
+        })
+        .collect()
+}
+
+/// The part that a relationship points to. Targets are relative to the workbook, unless they start
+/// from the root of the archive
+fn relationship_target(xml: &str, id: &str) -> Option<String> {
+    let target = elements(xml, "Relationship")
+        .into_iter()
+        .find(|r| attribute(r, "Id") == Some(id))
+        .and_then(|r| attribute(r, "Target"))?;
+    Some(
+        target
+            .strip_prefix('/')
+            .map_or_else(|| format!("xl/{target}"), ToString::to_string),
+    )
+}
+
+/// The opening tag of every `<name ...>` element
+fn elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let open = format!("<{name} ");
+    xml.match_indices(&open)
+        .filter_map(|(start, _)| {
+            let end = xml[start..].find('>')?;
+            Some(&xml[start..start + end])
+        })
+        .collect()
+}
+
+/// The value of an attribute, written as ` name="value"`, within an opening tag
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let key = format!(" {name}=\"");
+    let start = tag.find(&key)? + key.len();
+    let length = tag[start..].find('"')?;
+    Some(&tag[start..start + length])
+}
+
+pub(crate) fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
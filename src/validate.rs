@@ -0,0 +1,252 @@
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use crate::consts;
+use crate::error::{UnlockError, UnlockResult};
+use crate::lenient::Options;
+use crate::list_modules;
+use crate::ovba::{
+    records::{
+        dir::{self, Dir},
+        module::ModuleStream,
+        project::{ModuleType, Project},
+        vba_project::VbaProject,
+    },
+    types::{mbcs, new_line::NwLn},
+};
+use crate::read::xl_vba;
+use cfb::CompoundFile;
+use tracing::info;
+
+/// The only value of the `VersionCompatible32` property allowed by the specification
+const VERSION_COMPATIBLE: &str = "393222000";
+
+/// Check that the VBA project follows every rule the MS-OVBA specification says it MUST.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// This is the strict counterpart to the way the rest of the crate reads files, for anyone
+/// writing a vbaProject.bin with their own tooling. The records of the dir stream are checked for
+/// their order, sizes and reserved values, and the PROJECT stream for anything that would
+/// otherwise be read past. The two streams must agree with each other, every module they list
+/// must have a stream whose source can be decompressed, and the `_VBA_PROJECT` stream must be
+/// there.
+///
+/// Any problems found are returned as lines ready to print. Nothing is returned if the project
+/// follows the specification
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened
+/// - The file cannot be opened as a zip file
+/// - There is no VBA file within the zip archive, found at "/xl/vbaProject.bin"
+/// - The VBA file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+pub fn xl(filename: &Path) -> UnlockResult<Vec<String>> {
+    let mut vba = xl_vba(filename)?;
+    validate(
+        &mut vba,
+        consts::PROJECT_PATH,
+        consts::DIR_PATH,
+        consts::VBA_STORAGE_PATH,
+    )
+}
+
+/// Check that the VBA project follows every rule the MS-OVBA specification says it MUST.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// See [`xl`] for what is checked.
+///
+/// Any problems found are returned as lines ready to print. Nothing is returned if the project
+/// follows the specification
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - The file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - There is no VBA storage within the file
+pub fn xl_97(filename: &Path) -> UnlockResult<Vec<String>> {
+    let mut file = cfb::open(filename).map_err(UnlockError::CFBOpen)?;
+    if !file.is_storage(consts::CFB_VBA_PROJECT_PATH) {
+        return Err(UnlockError::NoVBAFile);
+    }
+    validate(
+        &mut file,
+        consts::CFB_VBA_PATH,
+        consts::CFB_DIR_PATH,
+        consts::CFB_VBA_STORAGE_PATH,
+    )
+}
+
+fn validate<F: Read + Seek>(
+    cfb: &mut CompoundFile<F>,
+    project_path: &str,
+    dir_path: &str,
+    storage_path: &str,
+) -> UnlockResult<Vec<String>> {
+    let mut problems = Vec::new();
+
+    info!("validating the {dir_path} stream");
+    let dir = match read_stream(cfb, dir_path)? {
+        None => {
+            problems.push(format!("there is no {dir_path} stream"));
+            None
+        }
+        Some(bytes) => {
+            problems.extend(
+                Dir::violations(&bytes)
+                    .into_iter()
+                    .map(|p| format!("dir stream: {p}")),
+            );
+            Dir::from_bytes(&bytes, &Options::default())
+                .map_err(|e| problems.push(e.to_string()))
+                .ok()
+        }
+    };
+    let code_page = dir
+        .as_ref()
+        .map_or(mbcs::DEFAULT_CODE_PAGE, |d| d.information().code_page());
+
+    info!("validating the {project_path} stream");
+    let project = match read_stream(cfb, project_path)? {
+        None => {
+            problems.push(format!("there is no {project_path} stream"));
+            None
+        }
+        Some(bytes) => Project::from_bytes(&bytes, code_page, &Options::default())
+            .inspect(|project| problems.extend(project_problems(project, &bytes)))
+            .map_err(|e| problems.push(e.to_string()))
+            .ok(),
+    };
+
+    if let (Some(dir), Some(project)) = (&dir, &project) {
+        problems.extend(dir.mismatches(project));
+        let help_file = dir.information().help_file();
+        if project.help_file().unwrap_or_default() != help_file {
+            problems.push(format!(
+                "the help file is {help_file:?} in the dir stream but {:?} in the PROJECT stream",
+                project.help_file().unwrap_or_default()
+            ));
+        }
+        let designers: Vec<_> = dir
+            .modules()
+            .iter()
+            .filter(|m| project.module_type(&m.name()) == Some(ModuleType::Designer))
+            .collect();
+        if !designers.is_empty() && project.packages().is_empty() {
+            problems.push(
+                "the PROJECT stream has designer modules, so MUST have a Package property"
+                    .to_string(),
+            );
+        }
+        for module in designers {
+            if list_modules::designer(cfb, project_path, module, code_page)?.is_none() {
+                problems.push(format!(
+                    "designer module {} has no designer storage",
+                    module.name()
+                ));
+            }
+        }
+    }
+
+    if let Some(dir) = &dir {
+        for module in dir.modules() {
+            problems.extend(module_problems(cfb, storage_path, module)?);
+        }
+    }
+
+    let vba_project_path = format!("{storage_path}/_VBA_PROJECT");
+    match read_stream(cfb, &vba_project_path)? {
+        None => problems.push(format!("there is no {vba_project_path} stream")),
+        Some(bytes) => {
+            if let Err(e) = VbaProject::from_bytes(&bytes) {
+                problems.push(e.to_string());
+            } else if bytes.get(4) != Some(&0) {
+                problems
+                    .push("the Reserved2 byte of the _VBA_PROJECT stream MUST be 0".to_string());
+            }
+        }
+    }
+
+    Ok(problems)
+}
+
+/// Everything in a PROJECT stream that was read past when it was parsed
+fn project_problems(project: &Project, bytes: &[u8]) -> Vec<String> {
+    let mut problems: Vec<_> = project
+        .warnings()
+        .iter()
+        .map(|w| format!("PROJECT stream: {w}"))
+        .collect();
+    for property in project.unknown_properties() {
+        problems.push(format!(
+            "PROJECT stream: {} is not a property in the specification",
+            String::from_utf8_lossy(property)
+        ));
+    }
+    if !project.trailing_bytes().is_empty() {
+        problems.push(format!(
+            "PROJECT stream: there are 0x{:x} bytes after the last line",
+            project.trailing_bytes().len()
+        ));
+    }
+    if project.new_line() == NwLn::Lf {
+        problems.push("PROJECT stream: lines MUST end with CRLF or LFCR, not LF".to_string());
+    }
+    if project.legacy_password() {
+        problems.push("PROJECT stream: the password MUST be given by DPB, not DPx".to_string());
+    }
+    let has_visibility = bytes
+        .split(|&b| b == b'\n')
+        .any(|line| line.strip_prefix(b"\r").unwrap_or(line).starts_with(b"GC="));
+    if !has_visibility {
+        problems.push("PROJECT stream: there MUST be a GC property".to_string());
+    }
+    if let Some(version) = project
+        .version_compatible()
+        .filter(|&v| v != VERSION_COMPATIBLE)
+    {
+        problems.push(format!(
+            "PROJECT stream: VersionCompatible32 is {version}, but MUST be {VERSION_COMPATIBLE}"
+        ));
+    }
+    problems
+}
+
+/// The stream of each module MUST be there, with source that can be decompressed from the
+/// MODULEOFFSET onwards
+fn module_problems<F: Read + Seek>(
+    cfb: &mut CompoundFile<F>,
+    storage_path: &str,
+    module: &dir::Module,
+) -> UnlockResult<Vec<String>> {
+    let path = format!("{storage_path}/{}", module.stream_name());
+    let Some(bytes) = read_stream(cfb, &path)? else {
+        return Ok(vec![format!(
+            "module {} has no stream, at {path}",
+            module.name()
+        )]);
+    };
+    if module.text_offset() > bytes.len() {
+        return Ok(vec![format!(
+            "the MODULEOFFSET of module {} is 0x{:x}, past the end of its stream",
+            module.name(),
+            module.text_offset()
+        )]);
+    }
+    Ok(ModuleStream::from_bytes(&bytes, module.text_offset())
+        .err()
+        .map(|e| format!("the source of module {} cannot be read: {e}", module.name()))
+        .into_iter()
+        .collect())
+}
+
+/// The bytes of a stream, or nothing if there is no such stream
+fn read_stream<F: Read + Seek>(
+    cfb: &mut CompoundFile<F>,
+    path: &str,
+) -> UnlockResult<Option<Vec<u8>>> {
+    if !cfb.is_stream(path) {
+        return Ok(None);
+    }
+    let mut bytes = Vec::new();
+    cfb.open_stream(path)?.read_to_end(&mut bytes)?;
+    Ok(Some(bytes))
+}
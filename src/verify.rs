@@ -0,0 +1,159 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use crate::consts;
+use crate::error::{UnlockError, UnlockResult};
+use crate::lenient::Options;
+use crate::list_modules;
+use crate::ovba::records::project::Project;
+use crate::read::zip_to_raw_vba;
+use cfb::CompoundFile;
+use tracing::{debug, info};
+use zip::ZipArchive;
+
+/// Check that the output of `remove` is sound, when compared to the file it was made from.
+/// This is the version for Excel files since 2003 i.e. xlsm and xlsb
+///
+/// The output must still open as a zip file, with a VBA file that opens as a CFB file and has a
+/// PROJECT stream that can be parsed. The VBA project must no longer be locked. All the entries
+/// of the zip file other than the VBA file, and all the streams of the VBA file other than the
+/// PROJECT stream, must be the same as in the source.
+///
+/// Any problems found are returned as lines ready to print. Nothing is returned if the output is
+/// sound
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - Either file cannot be opened
+/// - Either file is cannot be opened as a zip file
+/// - There is no VBA file within either zip archive, found at "/xl/vbaProject.bin"
+/// - Either VBA file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   of the output cannot be found or parsed
+pub fn xl(source: &Path, output: &Path, options: &Options) -> UnlockResult<Vec<String>> {
+    let mut source = ZipArchive::new(File::open(source)?)?;
+    let mut output = ZipArchive::new(File::open(output)?)?;
+
+    let mut source_vba =
+        CompoundFile::open(zip_to_raw_vba(&mut source)?).map_err(UnlockError::CFBOpen)?;
+    let mut output_vba =
+        CompoundFile::open(zip_to_raw_vba(&mut output)?).map_err(UnlockError::CFBOpen)?;
+
+    let mut problems = check_project(&mut output_vba, consts::PROJECT_PATH, options)?;
+    problems.extend(compare(
+        &zip_entries(&mut source)?,
+        &zip_entries(&mut output)?,
+        &[consts::ZIP_VBA_PATH],
+    ));
+    problems.extend(compare(
+        &cfb_streams(&mut source_vba)?,
+        &cfb_streams(&mut output_vba)?,
+        &[consts::PROJECT_PATH],
+    ));
+    Ok(problems)
+}
+
+/// Check that the output of `remove` is sound, when compared to the file it was made from.
+/// This is the version for Excel files between 1997 & 2003 i.e. xls
+///
+/// The output must still open as a CFB file, with a PROJECT stream that can be parsed. The VBA
+/// project must no longer be locked. All the streams other than the PROJECT stream must be the
+/// same as in the source.
+///
+/// Any problems found are returned as lines ready to print. Nothing is returned if the output is
+/// sound
+///
+/// # Errors
+/// Will return an error in the following situations:
+/// - Either file cannot be opened as a [Compound File Binary (CFB)](https://learn.microsoft.com/en-us/openspecs/windows_protocols/MS-CFB/53989ce4-7b05-4f8d-829b-d08d6148375b)
+/// - The [PROJECT stream](https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba/cc848a02-6f87-49a4-ad93-6edb3103f593)
+///   of the output cannot be found or parsed
+pub fn xl_97(source: &Path, output: &Path, options: &Options) -> UnlockResult<Vec<String>> {
+    let mut source = cfb::open(source).map_err(UnlockError::CFBOpen)?;
+    let mut output = cfb::open(output).map_err(UnlockError::CFBOpen)?;
+
+    let mut problems = check_project(&mut output, consts::CFB_VBA_PATH, options)?;
+    problems.extend(compare(
+        &cfb_streams(&mut source)?,
+        &cfb_streams(&mut output)?,
+        &[consts::CFB_VBA_PATH],
+    ));
+    Ok(problems)
+}
+
+/// The PROJECT stream must parse, and must not leave the VBA project locked
+fn check_project<F: Read + Seek>(
+    file: &mut CompoundFile<F>,
+    project_path: &str,
+    options: &Options,
+) -> UnlockResult<Vec<String>> {
+    info!("checking the {project_path} stream");
+    let dir_path = format!("{}VBA/dir", project_path.trim_end_matches("PROJECT"));
+    let code_page = list_modules::code_page(file, &dir_path);
+    let project = Project::from_stream(file.open_stream(project_path)?, code_page, options)?;
+    if project.is_locked() {
+        Ok(vec!["The VBA project is still locked".to_string()])
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+/// The contents of every entry of the zip archive, by name
+fn zip_entries<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+) -> UnlockResult<BTreeMap<String, Vec<u8>>> {
+    let mut entries = BTreeMap::new();
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        entries.insert(file.name().to_string(), data);
+    }
+    Ok(entries)
+}
+
+/// The contents of every stream of the CFB file, by path
+fn cfb_streams<F: Read + Seek>(
+    file: &mut CompoundFile<F>,
+) -> UnlockResult<BTreeMap<String, Vec<u8>>> {
+    let paths: Vec<_> = file
+        .walk()
+        .filter(cfb::Entry::is_stream)
+        .map(|e| e.path().to_path_buf())
+        .collect();
+    let mut streams = BTreeMap::new();
+    for path in paths {
+        let mut data = Vec::new();
+        file.open_stream(&path)?.read_to_end(&mut data)?;
+        streams.insert(path.to_string_lossy().to_string(), data);
+    }
+    Ok(streams)
+}
+
+/// Describe every part missing from, added to or changed in the output, other than the parts that
+/// are expected to change
+fn compare(
+    source: &BTreeMap<String, Vec<u8>>,
+    output: &BTreeMap<String, Vec<u8>>,
+    expected: &[&str],
+) -> Vec<String> {
+    let mut problems = Vec::new();
+    for (name, data) in source {
+        if expected.contains(&name.as_str()) {
+            continue;
+        }
+        match output.get(name) {
+            None => problems.push(format!("{name} is missing from the output")),
+            Some(d) if d != data => problems.push(format!("{name} differs from the source")),
+            Some(_) => debug!("{name} is unchanged"),
+        }
+    }
+    for name in output.keys() {
+        if !source.contains_key(name) && !expected.contains(&name.as_str()) {
+            problems.push(format!("{name} is not in the source"));
+        }
+    }
+    problems
+}
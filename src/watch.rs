@@ -0,0 +1,52 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+use crate::error::{UnlockError, UnlockResult};
+use crate::files::is_excel_with_vba;
+use notify::{EventKind, RecursiveMode, Watcher};
+use tracing::{debug, info, warn};
+
+/// How long the directory must go without any changes before new files are handed over. This
+/// stops a file being picked up while it is still being written
+const SETTLE_TIME: Duration = Duration::from_secs(1);
+
+/// Watch a directory for Excel files with VBA being created or changed, calling the supplied
+/// function with each one once the directory has settled
+///
+/// This runs until the watcher shuts down, which in practice means until the process is stopped.
+/// The function is called for every change, including any the function itself makes, so it needs
+/// to ignore files it has already dealt with
+///
+/// # Errors
+/// Will return an error if the directory cannot be watched
+pub fn watch<F: FnMut(&Path)>(directory: &Path, mut on_file: F) -> UnlockResult<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(UnlockError::Watch)?;
+    watcher
+        .watch(directory, RecursiveMode::NonRecursive)
+        .map_err(UnlockError::Watch)?;
+    info!("watching {}", directory.display());
+
+    let mut pending: BTreeSet<PathBuf> = BTreeSet::new();
+    loop {
+        match rx.recv_timeout(SETTLE_TIME) {
+            Ok(Ok(event)) => {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    debug!("{:?} {:?}", event.kind, event.paths);
+                    pending.extend(event.paths);
+                }
+            }
+            Ok(Err(e)) => warn!("problem watching {}: {e}", directory.display()),
+            Err(RecvTimeoutError::Timeout) => {
+                for path in std::mem::take(&mut pending) {
+                    if path.is_file() && is_excel_with_vba(&path) {
+                        on_file(&path);
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+}
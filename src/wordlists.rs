@@ -0,0 +1,160 @@
+//! Well-known wordlists, downloaded once and kept in a cache directory.
+//!
+//! A list in the cache can be given as a wordlist by its name, in place of a path. The SHA-1
+//! checksum of each list is worked out as it is downloaded, and checked against the one expected
+//! if there is one, so that a list that was changed or cut short on the way is never kept
+
+use std::fs::File;
+use std::io::{BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::{UnlockError, UnlockResult};
+use crate::read::to_hex;
+use crate::{fetch, progress};
+use sha1::{Digest, Sha1};
+
+/// The environment variable that can point at a cache directory in a non-standard location
+const CACHE_ENV: &str = "UNLOCK_EXCEL_CACHE";
+
+/// A wordlist that can be downloaded by name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Known {
+    /// The name the list is downloaded and given by
+    pub name: &'static str,
+    /// Where the list is downloaded from
+    pub url: &'static str,
+    /// What the list holds
+    pub description: &'static str,
+}
+
+/// The wordlists that can be downloaded by name
+pub const KNOWN: [Known; 3] = [
+    Known {
+        name: "openwall",
+        url: "https://raw.githubusercontent.com/openwall/john/bleeding-jumbo/run/password.lst",
+        description: "The common passwords that come with John the Ripper, from Openwall",
+    },
+    Known {
+        name: "rockyou",
+        url: "https://github.com/brannondorsey/naive-hashcat/releases/download/data/rockyou.txt",
+        description: "The 14 million passwords of the RockYou leak",
+    },
+    Known {
+        name: "seclists",
+        url: "https://raw.githubusercontent.com/danielmiessler/SecLists/master/Passwords/Common-Credentials/10-million-password-list-top-1000000.txt",
+        description: "The million most common passwords, from SecLists",
+    },
+];
+
+/// The directory the wordlists are downloaded into
+///
+/// # Errors
+/// Will return an error if there is no cache directory for the current user
+pub fn cache_dir() -> UnlockResult<PathBuf> {
+    let cache = std::env::var_os(CACHE_ENV)
+        .map(PathBuf::from)
+        .or_else(|| dirs::cache_dir().map(|p| p.join("unlock_excel")))
+        .ok_or_else(|| {
+            std::io::Error::new(
+                ErrorKind::NotFound,
+                "there is no cache directory to keep wordlists in",
+            )
+        })?;
+    Ok(cache.join("wordlists"))
+}
+
+/// Where the wordlist with the name is kept in the cache, whether or not it has been downloaded
+///
+/// # Errors
+/// Will return an error if there is no cache directory for the current user
+pub fn path(name: &str) -> UnlockResult<PathBuf> {
+    Ok(cache_dir()?.join(format!("{name}.txt")))
+}
+
+/// The file to read for a wordlist. A path to a file is used as it is, but a bare name that is not
+/// a file is looked for in the cache. Anything not found is left as it is, to fail when opened
+#[must_use]
+pub fn resolve(wordlist: &Path) -> PathBuf {
+    if wordlist.exists() || wordlist.components().count() != 1 {
+        return wordlist.to_path_buf();
+    }
+    wordlist
+        .to_str()
+        .and_then(|name| path(name).ok())
+        .filter(|p| p.is_file())
+        .unwrap_or_else(|| wordlist.to_path_buf())
+}
+
+/// Download a wordlist into the cache, from where it is known to be or from the URL given,
+/// returning where it was saved and its SHA-1 checksum
+///
+/// The list is only put in place once it has been downloaded in full and its checksum matches any
+/// that is given, so a failed download never leaves a broken list behind
+///
+/// # Errors
+/// Will return an error if the name is not a known wordlist and no URL is given, the name is not
+/// made of letters, digits, '-' and '_', the download fails, or the checksum does not match
+pub fn fetch(name: &str, url: Option<&str>, sha1: Option<&str>) -> UnlockResult<(PathBuf, String)> {
+    let unknown = || UnlockError::UnknownWordlist(name.to_string());
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(unknown());
+    }
+    let url = match url {
+        Some(url) => url,
+        None => {
+            KNOWN
+                .iter()
+                .find(|k| k.name == name)
+                .ok_or_else(unknown)?
+                .url
+        }
+    };
+    let fail = |e: &dyn std::fmt::Display| UnlockError::Download(url.to_string(), e.to_string());
+
+    let target = path(name)?;
+    let partial = target.with_extension("txt.part");
+    if let Some(dir) = target.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let response = fetch::get(url)?;
+    let len = response
+        .header("Content-Length")
+        .and_then(|l| l.parse().ok());
+    let bar = progress::bar(len.unwrap_or_default(), "Downloading");
+    let mut reader = bar.wrap_read(response.into_reader());
+    let mut file = BufWriter::new(File::create(&partial)?);
+    let mut hasher = Sha1::new();
+    let mut buffer = vec![0; 1 << 16];
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => {
+                let _ = std::fs::remove_file(&partial);
+                return Err(fail(&e));
+            }
+        };
+        hasher.update(&buffer[..read]);
+        file.write_all(&buffer[..read])?;
+    }
+    bar.finish_and_clear();
+    file.flush()?;
+    drop(file);
+
+    let checksum = to_hex(&hasher.finalize());
+    if let Some(expected) = sha1 {
+        if !expected.eq_ignore_ascii_case(&checksum) {
+            let _ = std::fs::remove_file(&partial);
+            return Err(fail(&format!(
+                "its SHA-1 checksum is {checksum}, not {expected}"
+            )));
+        }
+    }
+    std::fs::rename(&partial, &target)?;
+    Ok((target, checksum))
+}
@@ -0,0 +1,62 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use unlock_excel::cancel::CancellationToken;
+use unlock_excel::crack::{self, BruteForce, Charset, Options};
+use unlock_excel::error::UnlockError;
+use unlock_excel::lenient;
+use unlock_excel::remove;
+
+#[test]
+fn cancel_crack_xlsm() {
+    let file = Path::new("tests/data/xlsm/Locked_with_macro_and_complex_password.xlsm");
+    let options = Options {
+        brute_force: Some(BruteForce {
+            charset: Charset::Printable,
+            min: 1,
+            max: 8,
+        }),
+        ..Options::default()
+    };
+    let token = CancellationToken::new();
+    let canceller = token.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(200));
+        canceller.cancel();
+    });
+    let start = Instant::now();
+    assert!(matches!(
+        token.run(|| crack::xl(file, &options, &lenient::Options::default())),
+        Err(UnlockError::Cancelled)
+    ));
+    assert!(start.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn cancel_nothing_xlsm() {
+    let file = Path::new("tests/data/xlsm/Locked_with_macro.xlsm");
+    let token = CancellationToken::new();
+    let password = token.run(|| crack::xl(file, &Options::default(), &lenient::Options::default()));
+    assert_eq!("P@ssw0rd", password.unwrap());
+    assert!(!token.is_cancelled());
+}
+
+#[test]
+fn cancel_remove_xlsm() {
+    let folder = PathBuf::from("tests/data/temp_92");
+    std::fs::create_dir_all(&folder).unwrap();
+    let output = folder.join("Locked_with_macro.xlsm");
+    let token = CancellationToken::new();
+    token.cancel();
+    let file = Path::new("tests/data/xlsm/Locked_with_macro.xlsm");
+    assert!(matches!(
+        token.run(|| remove::xl_to(file, &output, &lenient::Options::default())),
+        Err(UnlockError::Cancelled)
+    ));
+    // The half written copy is not left behind
+    assert!(!output.exists());
+
+    // Operations run without the token are not affected by it
+    remove::xl_to(file, &output, &lenient::Options::default()).unwrap();
+    assert!(output.exists());
+    let _ = std::fs::remove_dir_all(folder);
+}
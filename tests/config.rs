@@ -0,0 +1,57 @@
+use std::path::PathBuf;
+use unlock_excel::config::Config;
+use unlock_excel::error::UnlockError;
+use unlock_excel::read::Format;
+
+#[test]
+fn config_full() {
+    let (temp_dir, file) = create_config(
+        61,
+        "wordlist = \"words.txt\"\nthreads = 4\ntime_limit = 60\npotfile = \"found.pot\"\nformat = \"json\"\ninplace = true\n",
+    );
+    assert_eq!(
+        Config {
+            wordlist: Some(PathBuf::from("words.txt")),
+            threads: Some(4),
+            time_limit: Some(60),
+            potfile: Some(PathBuf::from("found.pot")),
+            format: Some(Format::Json),
+            inplace: true,
+        },
+        Config::from_file(&file).unwrap()
+    );
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn config_empty() {
+    let (temp_dir, file) = create_config(62, "");
+    assert_eq!(Config::default(), Config::from_file(&file).unwrap());
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn config_unknown_key() {
+    let (temp_dir, file) = create_config(63, "colour = \"red\"\n");
+    assert!(matches!(
+        Config::from_file(&file),
+        Err(UnlockError::Config(..))
+    ));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn config_output_key() {
+    let (temp_dir, file) = create_config(64, "output = \"json\"\n");
+    assert_eq!(Some(Format::Json), Config::from_file(&file).unwrap().format);
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+fn create_config(index: usize, contents: &str) -> (PathBuf, PathBuf) {
+    let mut temp_dir = PathBuf::from("tests/data");
+    temp_dir.push(format!("temp_{index}"));
+    std::fs::create_dir_all(&temp_dir).unwrap();
+    let file = temp_dir.join("config.toml");
+    std::fs::write(&file, contents).unwrap();
+    (temp_dir, file)
+}
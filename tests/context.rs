@@ -0,0 +1,61 @@
+use std::path::{Path, PathBuf};
+use unlock_excel::context;
+use unlock_excel::crack::{self, Options};
+use unlock_excel::error::UnlockError;
+use unlock_excel::lenient;
+use unlock_excel::set_password;
+
+#[test]
+fn context_xlsm() {
+    let file = Path::new("tests/data/xlsm/Locked_with_macro.xlsm");
+    let words = context::xl(file, &lenient::Options::default()).unwrap();
+    for word in [
+        "VBAProject",
+        "Module1",
+        "Sheet1",
+        "Locked_with_macro",
+        "James MacAdie",
+    ] {
+        assert!(words.iter().any(|w| w == word), "{word} not in {words:?}");
+    }
+    // The words of each name are tried too, on their own and joined
+    for word in ["JamesMacAdie", "MacAdie", "Lockedwithmacro"] {
+        assert!(words.iter().any(|w| w == word), "{word} not in {words:?}");
+    }
+}
+
+#[test]
+fn context_xls() {
+    let file = Path::new("tests/data/xls/Locked_with_macro.xls");
+    let words = context::xl_97(file, &lenient::Options::default()).unwrap();
+    for word in ["VBAProject", "Sheet1", "James MacAdie"] {
+        assert!(words.iter().any(|w| w == word), "{word} not in {words:?}");
+    }
+}
+
+#[test]
+fn crack_context_xlsb() {
+    let temp_dir = PathBuf::from("tests/data/temp_96");
+    std::fs::create_dir_all(&temp_dir).unwrap();
+    let file = temp_dir.join("Locked_with_macro.xlsb");
+    std::fs::copy("tests/data/xlsb/Locked_with_macro.xlsb", &file).unwrap();
+    set_password::xl(&file, "MacAdie!", true, &lenient::Options::default()).unwrap();
+    let wordlist = temp_dir.join("words.txt");
+    std::fs::write(&wordlist, "letmein\n").unwrap();
+
+    let mut options = Options {
+        wordlists: vec![wordlist],
+        rules: vec!["$!".parse().unwrap()],
+        ..Options::default()
+    };
+    assert!(matches!(
+        crack::xl(&file, &options, &lenient::Options::default()),
+        Err(UnlockError::PasswordNotFound)
+    ));
+    options.context = context::xl(&file, &lenient::Options::default()).unwrap();
+    assert_eq!(
+        "MacAdie!",
+        crack::xl(&file, &options, &lenient::Options::default()).unwrap()
+    );
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
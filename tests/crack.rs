@@ -0,0 +1,861 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use unlock_excel::crack::{
+    benchmark, candidates, decode, hash_rate, strength, xl, xl_97, BruteForce, Charset, Class,
+    Combinator, Layout, Markov, Mask, Options, Policy, Rule, Strength, Walk,
+};
+use unlock_excel::error::UnlockError;
+use unlock_excel::lenient;
+use unlock_excel::{crack, hash, read, set_password};
+
+/*
+* XLSM
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn crack_locked_xlsm() {
+    let file = Path::new("tests/data/xlsm/Locked_with_macro.xlsm");
+    assert_eq!(
+        "P@ssw0rd",
+        xl(file, &Options::default(), &lenient::Options::default()).unwrap()
+    );
+}
+
+#[test]
+fn crack_unlocked_xlsm() {
+    let file = Path::new("tests/data/xlsm/Unlocked_with_macro.xlsm");
+    assert!(matches!(
+        xl(file, &Options::default(), &lenient::Options::default()),
+        Err(UnlockError::NoPassword)
+    ));
+}
+
+#[test]
+fn crack_custom_wordlist_xlsm() {
+    let file = Path::new("tests/data/xlsm/Locked_with_macro.xlsm");
+    let (temp_dir, wordlist) = create_wordlist(41, "password\r\nletmein\r\nP@ssw0rd\r\n");
+    let options = Options {
+        wordlists: vec![wordlist],
+        context: Vec::new(),
+        threads: 2,
+        time_limit: None,
+        combinator: None,
+        brute_force: None,
+        mask: None,
+        rules: Vec::new(),
+        cases: false,
+        hybrid: false,
+        markov: None,
+        walk: None,
+        policy: Policy::default(),
+        skip: 0,
+        limit: None,
+        potfile: None,
+    };
+    assert_eq!(
+        "P@ssw0rd",
+        xl(file, &options, &lenient::Options::default()).unwrap()
+    );
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn crack_last_word_many_threads_xlsm() {
+    let file = Path::new("tests/data/xlsm/Locked_with_macro.xlsm");
+    let mut words: String = (0..10_000).map(|n| format!("word{n}\n")).collect();
+    words.push_str("P@ssw0rd\n");
+    let (temp_dir, wordlist) = create_wordlist(81, &words);
+    for threads in [1, 3, 8] {
+        let options = Options {
+            wordlists: vec![wordlist.clone()],
+            threads,
+            ..Options::default()
+        };
+        assert_eq!(
+            "P@ssw0rd",
+            xl(file, &options, &lenient::Options::default()).unwrap()
+        );
+    }
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn crack_long_wordlist_xlsm() {
+    // Longer than a single batch of the wordlist, which is read in a bit at a time
+    let file = Path::new("tests/data/xlsm/Locked_with_macro.xlsm");
+    let mut words: String = (0..200_000).map(|n| format!("word{n}\r\n")).collect();
+    words.push_str("P@ssw0rd");
+    let (temp_dir, wordlist) = create_wordlist(87, &words);
+    let options = Options {
+        wordlists: vec![wordlist],
+        ..Options::default()
+    };
+    assert_eq!(
+        "P@ssw0rd",
+        xl(file, &options, &lenient::Options::default()).unwrap()
+    );
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn crack_potfile_xlsm() {
+    let file = Path::new("tests/data/xlsm/Locked_with_macro.xlsm");
+    let (temp_dir, wordlist) = create_wordlist(83, "letmein\nP@ssw0rd\n");
+    let potfile = temp_dir.join("unlock_excel.pot");
+    let mut options = Options {
+        wordlists: vec![wordlist],
+        potfile: Some(potfile.clone()),
+        ..Options::default()
+    };
+    assert_eq!(
+        "P@ssw0rd",
+        xl(file, &options, &lenient::Options::default()).unwrap()
+    );
+    let h = hash::xl(file, &lenient::Options::default()).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&potfile).unwrap(),
+        format!("{}:{}:P@ssw0rd\n", hex(&h.hash), hex(&h.salt))
+    );
+
+    // Found in the potfile, so the missing wordlist is never read
+    options.wordlists = vec![PathBuf::from("tests/data/no_such_wordlist.txt")];
+    assert_eq!(
+        "P@ssw0rd",
+        xl(file, &options, &lenient::Options::default()).unwrap()
+    );
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn crack_time_limit_xlsm() {
+    let file = Path::new("tests/data/xlsm/Locked_with_macro_and_complex_password.xlsm");
+    let options = Options {
+        time_limit: Some(Duration::ZERO),
+        ..Options::default()
+    };
+    assert!(matches!(
+        xl(file, &options, &lenient::Options::default()),
+        Err(UnlockError::PasswordNotFound)
+    ));
+}
+
+/*
+* XLSB
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn crack_locked_xlsb() {
+    let file = Path::new("tests/data/xlsb/Locked_with_macro.xlsb");
+    assert_eq!(
+        "P@ssw0rd",
+        xl(file, &Options::default(), &lenient::Options::default()).unwrap()
+    );
+}
+
+#[test]
+fn crack_several_wordlists_xlsb() {
+    let file = Path::new("tests/data/xlsb/Locked_with_macro.xlsb");
+    let (temp_dir_1, first) = create_wordlist(43, "password\nletmein\n");
+    let (temp_dir_2, second) = create_wordlist(44, "qwerty\nP@ssw0rd\n");
+    let options = Options {
+        wordlists: vec![first, second],
+        ..Options::default()
+    };
+    assert_eq!(
+        "P@ssw0rd",
+        xl(file, &options, &lenient::Options::default()).unwrap()
+    );
+    let _ = std::fs::remove_dir_all(temp_dir_1);
+    let _ = std::fs::remove_dir_all(temp_dir_2);
+}
+
+#[test]
+fn crack_combinator_xlsb() {
+    let file = Path::new("tests/data/xlsb/Locked_with_macro.xlsb");
+    let (temp_dir_1, left) = create_wordlist(46, "Finance\r\nP@ss\r\n");
+    let (temp_dir_2, right) = create_wordlist(47, "2023\r\nw0rd\r\n");
+    let options = Options {
+        combinator: Some(Combinator {
+            left,
+            right,
+            separator: String::new(),
+        }),
+        ..Options::default()
+    };
+    assert_eq!(
+        "P@ssw0rd",
+        xl(file, &options, &lenient::Options::default()).unwrap()
+    );
+    let _ = std::fs::remove_dir_all(temp_dir_1);
+    let _ = std::fs::remove_dir_all(temp_dir_2);
+}
+
+#[test]
+fn crack_brute_force_xlsm() {
+    let (temp_dir, file) = locked_copy(71, "tests/data/xlsm/Unlocked_with_macro.xlsm", "b2");
+    let options = Options {
+        threads: 3,
+        brute_force: Some(BruteForce {
+            charset: Charset::LowerNum,
+            min: 1,
+            max: 3,
+        }),
+        ..Options::default()
+    };
+    assert_eq!(
+        "b2",
+        xl(&file, &options, &lenient::Options::default()).unwrap()
+    );
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn crack_mask_xlsm() {
+    let file = Path::new("tests/data/xlsm/Locked_with_macro.xlsm");
+    let options = Options {
+        mask: Some("?u@ssw?drd".parse().unwrap()),
+        ..Options::default()
+    };
+    assert_eq!(
+        "P@ssw0rd",
+        xl(file, &options, &lenient::Options::default()).unwrap()
+    );
+}
+
+#[test]
+fn invalid_masks() {
+    assert!("".parse::<Mask>().is_err());
+    assert!("?d?d?".parse::<Mask>().is_err());
+    assert!("?x".parse::<Mask>().is_err());
+    assert!("pässword".parse::<Mask>().is_err());
+    assert!("??p?a".parse::<Mask>().is_ok());
+}
+
+#[test]
+fn candidate_counts() {
+    let brute_force = Options {
+        brute_force: Some(BruteForce {
+            charset: Charset::Digits,
+            min: 1,
+            max: 3,
+        }),
+        skip: 100,
+        ..Options::default()
+    };
+    assert_eq!(Some(1010), candidates(&brute_force).unwrap());
+    let mask = Options {
+        mask: Some("?u?d?d".parse().unwrap()),
+        limit: Some(500),
+        ..Options::default()
+    };
+    assert_eq!(Some(500), candidates(&mask).unwrap());
+
+    let (temp_dir, wordlist) = create_wordlist(89, "password\r\nletmein\r\nP@ssw0rd\r\n");
+    let rules = Options {
+        wordlists: vec![wordlist],
+        rules: vec!["u".parse().unwrap(), "$1".parse().unwrap()],
+        ..Options::default()
+    };
+    assert_eq!(Some(9), candidates(&rules).unwrap());
+    let _ = std::fs::remove_dir_all(temp_dir);
+
+    let markov = Options {
+        markov: Some(Markov {
+            corpus: None,
+            min: 1,
+            max: 8,
+        }),
+        ..Options::default()
+    };
+    assert_eq!(None, candidates(&markov).unwrap());
+
+    // Every pair of neighbouring keys, each way round
+    let walk = Options {
+        walk: Some(Walk {
+            layout: Layout::Qwerty,
+            min: 2,
+            max: 2,
+        }),
+        ..Options::default()
+    };
+    assert_eq!(Some(212), candidates(&walk).unwrap());
+    assert!(hash_rate(2) > 0.0);
+    assert!(benchmark(1, Duration::from_millis(50)) > 0.0);
+}
+
+#[test]
+fn crack_walk_xlsm() {
+    let (temp_dir, file) = locked_copy(93, "tests/data/xlsm/Locked_with_macro.xlsm", "1qaz2wsx");
+    let mut options = Options {
+        walk: Some(Walk {
+            layout: Layout::Qwerty,
+            min: 4,
+            max: 12,
+        }),
+        ..Options::default()
+    };
+    assert_eq!(
+        "1qaz2wsx",
+        xl(&file, &options, &lenient::Options::default()).unwrap()
+    );
+
+    set_password::xl(&file, "Azertyuiop", true, &lenient::Options::default()).unwrap();
+    options.rules = vec!["c".parse().unwrap()];
+    assert!(matches!(
+        xl(&file, &options, &lenient::Options::default()),
+        Err(UnlockError::PasswordNotFound)
+    ));
+    options.walk = options.walk.map(|walk| Walk {
+        layout: Layout::Azerty,
+        ..walk
+    });
+    assert_eq!(
+        "Azertyuiop",
+        xl(&file, &options, &lenient::Options::default()).unwrap()
+    );
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn crack_policy_xlsm() {
+    let file = Path::new("tests/data/xlsm/Locked_with_macro.xlsm");
+    let (temp_dir, wordlist) = create_wordlist(94, "password\nP@ss\nP@ssw0rd\n");
+    let mut options = Options {
+        wordlists: vec![wordlist],
+        policy: Policy {
+            min_length: Some(8),
+            max_length: None,
+            required: vec![Class::Digit, Class::Symbol],
+        },
+        ..Options::default()
+    };
+    assert_eq!(
+        "P@ssw0rd",
+        xl(file, &options, &lenient::Options::default()).unwrap()
+    );
+    options.policy.max_length = Some(7);
+    assert!(matches!(
+        xl(file, &options, &lenient::Options::default()),
+        Err(UnlockError::PasswordNotFound)
+    ));
+    options.policy = Policy {
+        required: vec![Class::Upper, Class::Lower],
+        ..Policy::default()
+    };
+    assert_eq!(
+        "P@ssw0rd",
+        xl(file, &options, &lenient::Options::default()).unwrap()
+    );
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn crack_cases_xlsm() {
+    let (temp_dir, file) = locked_copy(95, "tests/data/xlsm/Locked_with_macro.xlsm", "LETMEIN!");
+    let wordlist = temp_dir.join("words.txt");
+    std::fs::write(&wordlist, "letmein\nFinance\n").unwrap();
+    let mut options = Options {
+        wordlists: vec![wordlist],
+        rules: vec!["$!".parse().unwrap()],
+        ..Options::default()
+    };
+    assert!(matches!(
+        xl(&file, &options, &lenient::Options::default()),
+        Err(UnlockError::PasswordNotFound)
+    ));
+    assert_eq!(Some(4), candidates(&options).unwrap());
+    options.cases = true;
+    assert_eq!(Some(16), candidates(&options).unwrap());
+    assert_eq!(
+        "LETMEIN!",
+        xl(&file, &options, &lenient::Options::default()).unwrap()
+    );
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn crack_rules_xlsm() {
+    let file = Path::new("tests/data/xlsm/Locked_with_macro.xlsm");
+    let (temp_dir, wordlist) = create_wordlist(50, "letmein\npassword\n");
+    let mut options = Options {
+        wordlists: vec![wordlist],
+        ..Options::default()
+    };
+    assert!(matches!(
+        xl(file, &options, &lenient::Options::default()),
+        Err(UnlockError::PasswordNotFound)
+    ));
+    options.rules = vec!["r".parse().unwrap(), "c sa@ so0".parse().unwrap()];
+    assert_eq!(
+        "P@ssw0rd",
+        xl(file, &options, &lenient::Options::default()).unwrap()
+    );
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn crack_hybrid_year_xlsm() {
+    let (temp_dir_1, file) = locked_copy(
+        74,
+        "tests/data/xlsm/Unlocked_with_macro.xlsm",
+        "Finance2023!",
+    );
+    let (temp_dir_2, wordlist) = create_wordlist(75, "Sales\nFinance\n");
+    let options = Options {
+        wordlists: vec![wordlist],
+        hybrid: true,
+        ..Options::default()
+    };
+    assert_eq!(
+        "Finance2023!",
+        xl(&file, &options, &lenient::Options::default()).unwrap()
+    );
+    let _ = std::fs::remove_dir_all(temp_dir_1);
+    let _ = std::fs::remove_dir_all(temp_dir_2);
+}
+
+#[test]
+fn crack_markov_corpus_xlsm() {
+    let (temp_dir_1, file) = locked_copy(78, "tests/data/xlsm/Unlocked_with_macro.xlsm", "finales");
+    let (temp_dir_2, corpus) = create_wordlist(79, "sales\nfinance\n");
+    let options = Options {
+        markov: Some(Markov {
+            corpus: Some(corpus),
+            min: 1,
+            max: 8,
+        }),
+        ..Options::default()
+    };
+    assert_eq!(
+        "finales",
+        xl(&file, &options, &lenient::Options::default()).unwrap()
+    );
+    let _ = std::fs::remove_dir_all(temp_dir_1);
+    let _ = std::fs::remove_dir_all(temp_dir_2);
+}
+
+#[test]
+fn rule_functions() {
+    let apply = |rule: &str, word: &str| {
+        let rule: Rule = rule.parse().unwrap();
+        String::from_utf8(rule.apply(word.as_bytes())).unwrap()
+    };
+    assert_eq!(apply(":", "Word"), "Word");
+    assert_eq!(apply("l", "WoRd"), "word");
+    assert_eq!(apply("u", "WoRd"), "WORD");
+    assert_eq!(apply("c", "wORD"), "Word");
+    assert_eq!(apply("r", "word"), "drow");
+    assert_eq!(apply("d", "word"), "wordword");
+    assert_eq!(apply("cL", "password"), "P@$$w0rd");
+    assert_eq!(apply("so0", "foo"), "f00");
+    assert_eq!(apply("$2$3^!", "word"), "!word23");
+    assert!("".parse::<Rule>().is_err());
+    assert!("x".parse::<Rule>().is_err());
+    assert!("sa".parse::<Rule>().is_err());
+    assert!("$".parse::<Rule>().is_err());
+}
+
+#[test]
+fn decode_read_project_xlsb() {
+    let file = Path::new("tests/data/xlsb/Locked_with_macro.xlsb");
+    let (temp_dir, wordlist) = create_wordlist(45, "letmein\nP@ssw0rd\n");
+    let (project, decoded) = read::xl_project(file, false, &lenient::Options::default()).unwrap();
+    assert_eq!(decoded, None);
+    let options = Options {
+        wordlists: vec![wordlist],
+        ..Options::default()
+    };
+    assert_eq!(
+        decode(project.password(), &options).unwrap().as_deref(),
+        Some("P@ssw0rd")
+    );
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn crack_missing_wordlist_xlsb() {
+    let file = Path::new("tests/data/xlsb/Locked_with_macro.xlsb");
+    let options = Options {
+        wordlists: vec![PathBuf::from("tests/data/no_such_wordlist.txt")],
+        ..Options::default()
+    };
+    assert!(matches!(
+        xl(file, &options, &lenient::Options::default()),
+        Err(UnlockError::FileOpen(_))
+    ));
+}
+
+/*
+* XLS
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn crack_locked_xls() {
+    let file = Path::new("tests/data/xls/Locked_with_macro.xls");
+    assert_eq!(
+        "P@ssw0rd",
+        xl_97(file, &Options::default(), &lenient::Options::default()).unwrap()
+    );
+}
+
+#[test]
+fn crack_combinator_separator_xls() {
+    let file = Path::new("tests/data/xls/Locked_with_macro.xls");
+    let (temp_dir_1, left) = create_wordlist(48, "Q\nP\n");
+    let (temp_dir_2, right) = create_wordlist(49, "ssw0rd\nwerty\n");
+    let options = Options {
+        combinator: Some(Combinator {
+            left,
+            right,
+            separator: "@".to_string(),
+        }),
+        ..Options::default()
+    };
+    assert_eq!(
+        "P@ssw0rd",
+        xl_97(file, &options, &lenient::Options::default()).unwrap()
+    );
+    let _ = std::fs::remove_dir_all(temp_dir_1);
+    let _ = std::fs::remove_dir_all(temp_dir_2);
+}
+
+#[test]
+fn crack_brute_force_xls() {
+    let (temp_dir, file) = locked_copy(72, "tests/data/xls/Unlocked_with_macro.xls", "4071");
+    let options = Options {
+        brute_force: Some(BruteForce {
+            charset: Charset::Digits,
+            min: 2,
+            max: 4,
+        }),
+        ..Options::default()
+    };
+    assert_eq!(
+        "4071",
+        xl_97(&file, &options, &lenient::Options::default()).unwrap()
+    );
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn strength_xls() {
+    let (project, _) = read::xl_97_project(
+        Path::new("tests/data/xls/Locked_with_macro.xls"),
+        false,
+        &lenient::Options::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        Some(Strength::Common),
+        strength(project.password(), 2).unwrap()
+    );
+
+    let (temp_dir, file) = locked_copy(90, "tests/data/xls/Unlocked_with_macro.xls", "M0n3y");
+    let (project, _) = read::xl_97_project(&file, false, &lenient::Options::default()).unwrap();
+    assert_eq!(
+        Some(Strength::Rules),
+        strength(project.password(), 2).unwrap()
+    );
+    let _ = std::fs::remove_dir_all(temp_dir);
+
+    let (project, _) = read::xl_97_project(
+        Path::new("tests/data/xls/Unlocked_with_macro.xls"),
+        false,
+        &lenient::Options::default(),
+    )
+    .unwrap();
+    assert_eq!(None, strength(project.password(), 2).unwrap());
+}
+
+#[test]
+fn crack_mask_not_found_xls() {
+    let file = Path::new("tests/data/xls/Locked_with_macro.xls");
+    let options = Options {
+        mask: Some("?l@ssw?drd".parse().unwrap()),
+        ..Options::default()
+    };
+    assert!(matches!(
+        xl_97(file, &options, &lenient::Options::default()),
+        Err(UnlockError::PasswordNotFound)
+    ));
+}
+
+#[test]
+fn crack_hybrid_prefix_and_number_xls() {
+    let (temp_dir_1, file) = locked_copy(76, "tests/data/xls/Unlocked_with_macro.xls", "42finance");
+    let (temp_dir_2, wordlist) = create_wordlist(77, "finance\n");
+    let mut options = Options {
+        wordlists: vec![wordlist],
+        hybrid: true,
+        ..Options::default()
+    };
+    assert_eq!(
+        "42finance",
+        xl_97(&file, &options, &lenient::Options::default()).unwrap()
+    );
+    set_password::xl_97(&file, "finance4321", true, &lenient::Options::default()).unwrap();
+    assert_eq!(
+        "finance4321",
+        xl_97(&file, &options, &lenient::Options::default()).unwrap()
+    );
+    options.hybrid = false;
+    assert!(matches!(
+        xl_97(&file, &options, &lenient::Options::default()),
+        Err(UnlockError::PasswordNotFound)
+    ));
+    let _ = std::fs::remove_dir_all(temp_dir_1);
+    let _ = std::fs::remove_dir_all(temp_dir_2);
+}
+
+#[test]
+fn crack_markov_xls() {
+    let (temp_dir, file) = locked_copy(80, "tests/data/xls/Unlocked_with_macro.xls", "123456");
+    let options = Options {
+        markov: Some(Markov {
+            corpus: None,
+            min: 6,
+            max: 6,
+        }),
+        ..Options::default()
+    };
+    assert_eq!(
+        "123456",
+        xl_97(&file, &options, &lenient::Options::default()).unwrap()
+    );
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn crack_brute_force_slices_xls() {
+    let (temp_dir, file) = locked_copy(82, "tests/data/xls/Unlocked_with_macro.xls", "4071");
+    let mut options = Options {
+        brute_force: Some(BruteForce {
+            charset: Charset::Digits,
+            min: 3,
+            max: 4,
+        }),
+        threads: 3,
+        ..Options::default()
+    };
+    // The 1,000 three digit passwords come first, so 4071 is number 5,071
+    for (skip, limit, found) in [
+        (5000, Some(100), true),
+        (5072, None, false),
+        (0, Some(5071), false),
+    ] {
+        options.skip = skip;
+        options.limit = limit;
+        assert_eq!(
+            xl_97(&file, &options, &lenient::Options::default())
+                .ok()
+                .as_deref(),
+            found.then_some("4071")
+        );
+    }
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn crack_mask_slices_xls() {
+    let file = Path::new("tests/data/xls/Locked_with_macro.xls");
+    // P is number 15 of the upper case letters and 0 the first digit, so the password is 150th
+    let mut options = Options {
+        mask: Some("?u@ssw?drd".parse().unwrap()),
+        skip: 150,
+        limit: Some(1),
+        ..Options::default()
+    };
+    assert_eq!(
+        "P@ssw0rd",
+        xl_97(file, &options, &lenient::Options::default()).unwrap()
+    );
+    options.skip = 151;
+    options.limit = None;
+    assert!(matches!(
+        xl_97(file, &options, &lenient::Options::default()),
+        Err(UnlockError::PasswordNotFound)
+    ));
+}
+
+#[test]
+fn crack_brute_force_too_short_xls() {
+    let (temp_dir, file) = locked_copy(73, "tests/data/xls/Unlocked_with_macro.xls", "4071");
+    let options = Options {
+        brute_force: Some(BruteForce {
+            charset: Charset::Digits,
+            min: 1,
+            max: 3,
+        }),
+        ..Options::default()
+    };
+    assert!(matches!(
+        xl_97(&file, &options, &lenient::Options::default()),
+        Err(UnlockError::PasswordNotFound)
+    ));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn crack_custom_wordlist_not_found_xls() {
+    let file = Path::new("tests/data/xls/Locked_with_macro.xls");
+    let (temp_dir, wordlist) = create_wordlist(42, "password\nletmein\n");
+    let options = Options {
+        wordlists: vec![wordlist],
+        context: Vec::new(),
+        threads: 1,
+        time_limit: None,
+        combinator: None,
+        brute_force: None,
+        mask: None,
+        rules: Vec::new(),
+        cases: false,
+        hybrid: false,
+        markov: None,
+        walk: None,
+        policy: Policy::default(),
+        skip: 0,
+        limit: None,
+        potfile: None,
+    };
+    assert!(matches!(
+        xl_97(file, &options, &lenient::Options::default()),
+        Err(UnlockError::PasswordNotFound)
+    ));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+/*
+* Many files
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn crack_many_files() {
+    let (temp_dir_1, xls) = locked_copy(84, "tests/data/xls/Unlocked_with_macro.xls", "letmein");
+    let (temp_dir_2, wordlist) = create_wordlist(85, "password\nletmein\nP@ssw0rd\n");
+    let files = [
+        read::xl_project(
+            Path::new("tests/data/xlsm/Locked_with_macro.xlsm"),
+            false,
+            &lenient::Options::default(),
+        ),
+        read::xl_project(
+            Path::new("tests/data/xlsm/Unlocked_with_macro.xlsm"),
+            false,
+            &lenient::Options::default(),
+        ),
+        read::xl_97_project(&xls, false, &lenient::Options::default()),
+        read::xl_project(
+            Path::new("tests/data/xlsb/Locked_with_macro.xlsb"),
+            false,
+            &lenient::Options::default(),
+        ),
+        read::xl_97_project(
+            Path::new("tests/data/xls/Locked_with_macro.xls"),
+            false,
+            &lenient::Options::default(),
+        ),
+    ];
+    let projects: Vec<_> = files.into_iter().map(|f| f.unwrap().0).collect();
+    let passwords: Vec<_> = projects.iter().map(|p| p.password()).collect();
+    let options = Options {
+        wordlists: vec![wordlist],
+        ..Options::default()
+    };
+    let cracked = crack::many(&passwords, &options).unwrap();
+    assert_eq!(5, cracked.len());
+    assert_eq!("P@ssw0rd", cracked[0].as_ref().unwrap());
+    assert!(matches!(cracked[1], Err(UnlockError::NoPassword)));
+    assert_eq!("letmein", cracked[2].as_ref().unwrap());
+    assert_eq!("P@ssw0rd", cracked[3].as_ref().unwrap());
+    assert_eq!("P@ssw0rd", cracked[4].as_ref().unwrap());
+    let _ = std::fs::remove_dir_all(temp_dir_1);
+    let _ = std::fs::remove_dir_all(temp_dir_2);
+}
+
+#[test]
+fn crack_many_not_found() {
+    let (temp_dir, wordlist) = create_wordlist(86, "password\nletmein\n");
+    let (project, _) = read::xl_project(
+        Path::new("tests/data/xlsm/Locked_with_macro.xlsm"),
+        false,
+        &lenient::Options::default(),
+    )
+    .unwrap();
+    let options = Options {
+        wordlists: vec![wordlist],
+        ..Options::default()
+    };
+    let cracked = crack::many(&[project.password()], &options).unwrap();
+    assert!(matches!(cracked[0], Err(UnlockError::PasswordNotFound)));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn crack_many_with_progress() {
+    let words: String = (0..10_000).map(|n| format!("word{n}\n")).collect();
+    let (temp_dir, wordlist) = create_wordlist(91, &words);
+    let (project, _) = read::xl_project(
+        Path::new("tests/data/xlsm/Locked_with_macro.xlsm"),
+        false,
+        &lenient::Options::default(),
+    )
+    .unwrap();
+    let options = Options {
+        wordlists: vec![wordlist],
+        threads: 3,
+        ..Options::default()
+    };
+    let mut updates = Vec::new();
+    let cracked =
+        crack::many_with_progress(&[project.password()], &options, |p| updates.push(p)).unwrap();
+    assert!(matches!(cracked[0], Err(UnlockError::PasswordNotFound)));
+    let last = updates.last().unwrap();
+    assert_eq!((10_000, 10_000, 0), (last.tried, last.total, last.found));
+    assert!(updates.windows(2).all(|w| w[0].tried <= w[1].tried));
+
+    let options = Options::default();
+    let mut found = 0;
+    let cracked =
+        crack::many_with_progress(&[project.password()], &options, |p| found = p.found).unwrap();
+    assert_eq!("P@ssw0rd", cracked[0].as_ref().unwrap());
+    assert_eq!(1, found);
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+/*
+* Helpers
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+fn create_wordlist(index: usize, words: &str) -> (PathBuf, PathBuf) {
+    let mut temp_dir = PathBuf::from("tests/data");
+    temp_dir.push(format!("temp_{index}"));
+    std::fs::create_dir_all(&temp_dir).unwrap();
+    let wordlist = temp_dir.join("wordlist.txt");
+    std::fs::write(&wordlist, words).unwrap();
+    (temp_dir, wordlist)
+}
+
+/// A copy of the file, locked with the password
+fn locked_copy(index: usize, source: &str, password: &str) -> (PathBuf, PathBuf) {
+    let source = Path::new(source);
+    let mut temp_dir = PathBuf::from("tests/data");
+    temp_dir.push(format!("temp_{index}"));
+    std::fs::create_dir_all(&temp_dir).unwrap();
+    let file = temp_dir.join(source.file_name().unwrap());
+    std::fs::copy(source, &file).unwrap();
+    if file.extension().unwrap() == "xls" {
+        set_password::xl_97(&file, password, true, &lenient::Options::default()).unwrap();
+    } else {
+        set_password::xl(&file, password, true, &lenient::Options::default()).unwrap();
+    }
+    (temp_dir, file)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
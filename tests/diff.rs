@@ -0,0 +1,52 @@
+use std::path::Path;
+use unlock_excel::diff::{differences, xl, xl_97};
+use unlock_excel::lenient::Options;
+
+#[test]
+fn diff_same_xlsm() {
+    let a = xl(
+        Path::new("tests/data/xlsm/Locked_with_macro.xlsm"),
+        &Options::default(),
+    )
+    .unwrap();
+    assert!(differences(&a, &a).is_empty());
+}
+
+#[test]
+fn diff_locked_unlocked_xlsm() {
+    let a = xl(
+        Path::new("tests/data/xlsm/Locked_with_macro.xlsm"),
+        &Options::default(),
+    )
+    .unwrap();
+    let b = xl(
+        Path::new("tests/data/xlsm/Unlocked_with_macro.xlsm"),
+        &Options::default(),
+    )
+    .unwrap();
+    let lines = differences(&a, &b);
+    assert_eq!(lines[0], "Properties");
+    assert!(lines.contains(&"-   Locked for viewing:     yes".to_string()));
+    assert!(lines.contains(&"+   Locked for viewing:     no".to_string()));
+    assert!(!lines
+        .iter()
+        .any(|l| l == "Modules" || l.starts_with("Source of")));
+}
+
+#[test]
+fn diff_xlsm_xls_same_source() {
+    let a = xl(
+        Path::new("tests/data/xlsm/Locked_with_macro.xlsm"),
+        &Options::default(),
+    )
+    .unwrap();
+    let b = xl_97(
+        Path::new("tests/data/xls/Locked_with_macro.xls"),
+        &Options::default(),
+    )
+    .unwrap();
+    let lines = differences(&a, &b);
+    assert!(!lines
+        .iter()
+        .any(|l| l == "Modules" || l.starts_with("Source of")));
+}
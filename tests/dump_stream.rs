@@ -0,0 +1,42 @@
+use std::path::Path;
+use unlock_excel::dump_stream::{hex_dump, xl, xl_97};
+use unlock_excel::error::UnlockError;
+
+#[test]
+fn dump_stream_xlsm() {
+    let data = xl(
+        Path::new("tests/data/xlsm/Locked_with_macro.xlsm"),
+        "/PROJECT",
+    )
+    .unwrap();
+    assert!(data.starts_with(b"ID=\"{"));
+}
+
+#[test]
+fn dump_stream_missing_xlsm() {
+    let result = xl(Path::new("tests/data/xlsm/Locked_with_macro.xlsm"), "/VBA");
+    assert!(matches!(result, Err(UnlockError::NoStream(p)) if p == "/VBA"));
+}
+
+#[test]
+fn dump_stream_xls() {
+    let data = xl_97(
+        Path::new("tests/data/xls/Locked_with_macro.xls"),
+        "/_VBA_PROJECT_CUR/PROJECT",
+    )
+    .unwrap();
+    assert!(data.starts_with(b"ID=\"{"));
+}
+
+#[test]
+fn hex_dump_lines() {
+    let lines = hex_dump(b"ID=\"{00000000-0000\r\nName");
+    assert_eq!(
+        lines,
+        vec![
+            "00000000  49 44 3d 22 7b 30 30 30  30 30 30 30 30 2d 30 30  |ID=\"{00000000-00|",
+            "00000010  30 30 0d 0a 4e 61 6d 65                           |00..Name|",
+        ]
+    );
+    assert!(hex_dump(&[]).is_empty());
+}
@@ -0,0 +1,148 @@
+use std::path::{Path, PathBuf};
+use unlock_excel::error::UnlockError;
+use unlock_excel::export::{xl, xl_97, xl_97_module_source, xl_module_source};
+use unlock_excel::lenient::Options;
+
+/*
+* XLSM
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn export_unlocked_xlsm() {
+    let dir = temp_dir(31);
+    xl(
+        Path::new("tests/data/xlsm/Unlocked_with_macro.xlsm"),
+        &dir,
+        &Options::default(),
+    )
+    .unwrap();
+    check_exported(&dir);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn export_locked_xlsm() {
+    let dir = temp_dir(32);
+    xl(
+        Path::new("tests/data/xlsm/Locked_with_macro.xlsm"),
+        &dir,
+        &Options::default(),
+    )
+    .unwrap();
+    check_exported(&dir);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+/*
+* XLSB
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn export_unlocked_xlsb() {
+    let dir = temp_dir(33);
+    xl(
+        Path::new("tests/data/xlsb/Unlocked_with_macro.xlsb"),
+        &dir,
+        &Options::default(),
+    )
+    .unwrap();
+    check_exported(&dir);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn export_locked_xlsb() {
+    let dir = temp_dir(34);
+    xl(
+        Path::new("tests/data/xlsb/Locked_with_macro.xlsb"),
+        &dir,
+        &Options::default(),
+    )
+    .unwrap();
+    check_exported(&dir);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+/*
+* XLS
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn export_unlocked_xls() {
+    let dir = temp_dir(35);
+    xl_97(
+        Path::new("tests/data/xls/Unlocked_with_macro.xls"),
+        &dir,
+        &Options::default(),
+    )
+    .unwrap();
+    check_exported(&dir);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn export_locked_xls() {
+    let dir = temp_dir(36);
+    xl_97(
+        Path::new("tests/data/xls/Locked_with_macro.xls"),
+        &dir,
+        &Options::default(),
+    )
+    .unwrap();
+    check_exported(&dir);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+/*
+* Module source
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn module_source_xlsm() {
+    let file = Path::new("tests/data/xlsm/Locked_with_macro.xlsm");
+    let source = xl_module_source(file, "Module1", &Options::default()).unwrap();
+    assert!(source.starts_with("Attribute VB_Name = \"Module1\""));
+    // Module names are not case sensitive
+    assert_eq!(
+        source,
+        xl_module_source(file, "module1", &Options::default()).unwrap()
+    );
+    assert!(matches!(
+        xl_module_source(file, "Module2", &Options::default()),
+        Err(UnlockError::NoModule(_))
+    ));
+}
+
+#[test]
+fn module_source_xls() {
+    let file = Path::new("tests/data/xls/Unlocked_with_macro.xls");
+    let source = xl_97_module_source(file, "ThisWorkbook", &Options::default()).unwrap();
+    assert!(source.starts_with("Attribute VB_Name = \"ThisWorkbook\""));
+}
+
+/*
+* Helpers
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+fn check_exported(dir: &Path) {
+    for (name, extension) in [
+        ("ThisWorkbook", "cls"),
+        ("Sheet1", "cls"),
+        ("Module1", "bas"),
+    ] {
+        let source = std::fs::read(dir.join(format!("{name}.{extension}"))).unwrap();
+        let source = String::from_utf8_lossy(&source);
+        assert!(source.starts_with(&format!("Attribute VB_Name = \"{name}\"")));
+    }
+}
+
+fn temp_dir(index: usize) -> PathBuf {
+    let mut path = PathBuf::from("tests/data");
+    path.push(format!("temp_{index}"));
+    path
+}
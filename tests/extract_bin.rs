@@ -0,0 +1,71 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use unlock_excel::error::UnlockError;
+use unlock_excel::extract_bin::{default_output, xl, xl_97};
+
+#[test]
+fn extract_bin_xlsm() {
+    let file = "tests/data/xlsm/Locked_with_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 81);
+    let output = temp_dir.join("vbaProject.bin");
+    xl(Path::new(&temp_file), &output).unwrap();
+    let mut vba = cfb::open(&output).unwrap();
+    assert!(project_stream(&mut vba).starts_with("ID=\"{"));
+    assert!(vba.is_stream("/VBA/dir"));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn extract_bin_no_macro_xlsm() {
+    let file = "tests/data/xlsm/Unlocked_no_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 82);
+    let output = temp_dir.join("vbaProject.bin");
+    assert!(matches!(
+        xl(Path::new(&temp_file), &output),
+        Err(UnlockError::NoVBAFile)
+    ));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn extract_bin_xls() {
+    let file = "tests/data/xls/Locked_with_macro.xls";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 81);
+    let output = default_output(&temp_file);
+    assert_eq!(output, temp_dir.join("Locked_with_macro_vbaProject.bin"));
+    xl_97(Path::new(&temp_file), &output).unwrap();
+
+    let mut original = cfb::open(&temp_file).unwrap();
+    let mut expected = String::new();
+    original
+        .open_stream("/_VBA_PROJECT_CUR/PROJECT")
+        .unwrap()
+        .read_to_string(&mut expected)
+        .unwrap();
+    let mut vba = cfb::open(&output).unwrap();
+    assert_eq!(project_stream(&mut vba), expected);
+    assert!(vba.is_stream("/VBA/dir"));
+    assert!(vba.is_stream("/VBA/Module1"));
+    assert!(!vba.exists("/_VBA_PROJECT_CUR"));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+fn project_stream(vba: &mut cfb::CompoundFile<std::fs::File>) -> String {
+    let mut project = String::new();
+    vba.open_stream("/PROJECT")
+        .unwrap()
+        .read_to_string(&mut project)
+        .unwrap();
+    project
+}
+
+fn create_temp_dir(source: &dyn AsRef<Path>, unique_num: u8) -> (PathBuf, PathBuf) {
+    let source = source.as_ref();
+    let mut folder = source.parent().unwrap().to_path_buf();
+    folder.push(format!("temp_{unique_num}"));
+    let mut copied_file = folder.clone();
+    copied_file.push(source.file_name().unwrap());
+    std::fs::create_dir(&folder).unwrap();
+    let _ = std::fs::copy(source, &copied_file);
+    (folder, copied_file)
+}
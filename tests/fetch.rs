@@ -0,0 +1,23 @@
+use unlock_excel::fetch::{file_name, is_url};
+
+#[test]
+fn urls() {
+    assert!(is_url("https://example.com/Book1.xlsm"));
+    assert!(is_url("HTTP://example.com/Book1.xlsm"));
+    assert!(!is_url("Book1.xlsm"));
+    assert!(!is_url("ftp://example.com/Book1.xlsm"));
+}
+
+#[test]
+fn file_names() {
+    assert_eq!(
+        file_name("https://example.com/share/Book1.xlsm"),
+        "Book1.xlsm"
+    );
+    assert_eq!(
+        file_name("https://example.com/Book1.xlsm?raw=1"),
+        "Book1.xlsm"
+    );
+    assert_eq!(file_name("https://example.com/share/"), "download");
+    assert_eq!(file_name("https://example.com"), "download");
+}
@@ -0,0 +1,107 @@
+use std::path::{Path, PathBuf};
+use unlock_excel::files::{detect, expand, is_excel_with_vba, Container};
+
+#[test]
+fn expand_files_untouched() {
+    let inputs = ["b.xlsm", "a.xls", "tests/data/xlsm"];
+    assert_eq!(
+        vec![
+            PathBuf::from("b.xlsm"),
+            PathBuf::from("a.xls"),
+            PathBuf::from("tests/data/xlsm")
+        ],
+        expand(&inputs, false)
+    );
+}
+
+#[test]
+fn expand_recursive() {
+    // Copy the files somewhere the other tests are not creating temporary files
+    let temp_dir = PathBuf::from("tests/data/temp_51");
+    std::fs::create_dir_all(temp_dir.join("nested")).unwrap();
+    for file in ["xlsm/Locked_with_macro.xlsm", "xlsm/Unlocked_no_macro.xlsm"] {
+        let source = Path::new("tests/data").join(file);
+        std::fs::copy(&source, temp_dir.join(source.file_name().unwrap())).unwrap();
+    }
+    std::fs::copy(
+        "tests/data/xls/Locked_with_macro.xls",
+        temp_dir.join("nested/Locked_with_macro.xls"),
+    )
+    .unwrap();
+    assert_eq!(
+        vec![
+            temp_dir.join("Locked_with_macro.xlsm"),
+            temp_dir.join("nested/Locked_with_macro.xls"),
+        ],
+        expand(&["tests/data/temp_51"], true)
+    );
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn expand_glob() {
+    assert_eq!(
+        vec![
+            PathBuf::from("tests/data/xls/Locked_with_macro.xls"),
+            PathBuf::from("tests/data/xlsb/Locked_with_macro.xlsb"),
+            PathBuf::from("tests/data/xlsm/Locked_with_macro.xlsm"),
+        ],
+        expand(&["tests/data/xls*/Locked_with_macro.xls*"], false)
+    );
+    assert_eq!(
+        vec![PathBuf::from("tests/data/*.xlsq")],
+        expand(&["tests/data/*.xlsq"], false)
+    );
+}
+
+#[test]
+fn vba_detection() {
+    assert!(is_excel_with_vba(Path::new(
+        "tests/data/xlsm/Locked_with_macro.xlsm"
+    )));
+    assert!(is_excel_with_vba(Path::new(
+        "tests/data/xlsb/Locked_with_macro.xlsb"
+    )));
+    assert!(is_excel_with_vba(Path::new(
+        "tests/data/xls/Locked_with_macro.xls"
+    )));
+    assert!(!is_excel_with_vba(Path::new(
+        "tests/data/xlsm/Unlocked_no_macro.xlsm"
+    )));
+    assert!(!is_excel_with_vba(Path::new("tests/data/missing.xlsm")));
+}
+
+#[cfg(unix)]
+#[test]
+fn expand_non_utf8_name() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let odd = PathBuf::from(OsStr::from_bytes(b"tests/data/xlsm/Locked_\xff.xlsm"));
+    assert_eq!(expand(&[&odd], false), vec![odd.clone()]);
+}
+
+#[test]
+fn detect_renamed_files() {
+    let temp_dir = PathBuf::from("tests/data/temp_52");
+    std::fs::create_dir_all(&temp_dir).unwrap();
+    let zip = temp_dir.join("macros");
+    std::fs::copy("tests/data/xlsb/Locked_with_macro.xlsb", &zip).unwrap();
+    let cfb = temp_dir.join("macros.dat");
+    std::fs::copy("tests/data/xls/Locked_with_macro.xls", &cfb).unwrap();
+    assert_eq!(detect(&zip), Some(Container::Zip));
+    assert_eq!(detect(&cfb), Some(Container::Cfb));
+    assert!(is_excel_with_vba(&zip));
+    assert!(is_excel_with_vba(&cfb));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn detect_without_vba() {
+    assert_eq!(
+        detect(Path::new("tests/data/xlsm/Unlocked_no_macro.xlsm")),
+        None
+    );
+    assert_eq!(detect(Path::new("tests/data/missing.xlsm")), None);
+    assert_eq!(detect(Path::new("Cargo.toml")), None);
+}
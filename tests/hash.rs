@@ -0,0 +1,80 @@
+use std::path::Path;
+use unlock_excel::error::UnlockError;
+use unlock_excel::hash::{xl, xl_97, HASHCAT_MODE};
+use unlock_excel::lenient::Options;
+
+#[test]
+fn hash_locked_xlsm() {
+    let h = xl(
+        Path::new("tests/data/xlsm/Locked_with_macro.xlsm"),
+        &Options::default(),
+    )
+    .unwrap();
+    assert_eq!(hash_of("P@ssw0rd", h.salt), h.hash);
+}
+
+#[test]
+fn hash_unlocked_xlsm() {
+    assert!(matches!(
+        xl(
+            Path::new("tests/data/xlsm/Unlocked_with_macro.xlsm"),
+            &Options::default()
+        ),
+        Err(UnlockError::NoPasswordHash)
+    ));
+}
+
+#[test]
+fn hash_locked_xlsb() {
+    let h = xl(
+        Path::new("tests/data/xlsb/Locked_with_macro.xlsb"),
+        &Options::default(),
+    )
+    .unwrap();
+    assert_eq!(hash_of("P@ssw0rd", h.salt), h.hash);
+}
+
+#[test]
+fn hash_locked_xls() {
+    let h = xl_97(
+        Path::new("tests/data/xls/Locked_with_macro.xls"),
+        &Options::default(),
+    )
+    .unwrap();
+    assert_eq!(hash_of("P@ssw0rd", h.salt), h.hash);
+}
+
+#[test]
+fn hashcat_line_xls() {
+    let h = xl_97(
+        Path::new("tests/data/xls/Locked_with_macro.xls"),
+        &Options::default(),
+    )
+    .unwrap();
+    let line = h.hashcat();
+    let (hash, salt) = line.split_once(':').unwrap();
+    assert_eq!(hash.len(), 40);
+    assert_eq!(salt.len(), 8);
+    assert!(line.chars().all(|c| c == ':' || c.is_ascii_hexdigit()));
+    assert_eq!(HASHCAT_MODE, 110);
+}
+
+#[test]
+fn john_line_xlsm() {
+    let h = xl(
+        Path::new("tests/data/xlsm/Locked_with_macro.xlsm"),
+        &Options::default(),
+    )
+    .unwrap();
+    let line = h.john("C:/Finance/Model.xlsm");
+    assert!(line.starts_with("C_/Finance/Model.xlsm:$dynamic_24$"));
+    assert!(line.ends_with(&format!("$HEX${}", h.hashcat().split_once(':').unwrap().1)));
+}
+
+fn hash_of(password: &str, salt: [u8; 4]) -> [u8; 20] {
+    use sha1::{Digest, Sha1};
+    let mut hasher = Sha1::new();
+    hasher.update(password);
+    hasher.update(salt);
+    hasher.finalize().into()
+}
@@ -0,0 +1,26 @@
+use std::path::Path;
+use unlock_excel::info::{xl_97_constants, xl_constants};
+use unlock_excel::lenient::Options;
+
+#[test]
+fn constants_xlsm() {
+    let file = Path::new("tests/data/xlsm/Unlocked_with_macro.xlsm");
+    assert!(xl_constants(file, &Options::default()).unwrap().is_empty());
+}
+
+#[test]
+fn constants_xls() {
+    let file = Path::new("tests/data/xls/Locked_with_macro.xls");
+    assert!(xl_97_constants(file, &Options::default())
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn constants_not_excel() {
+    assert!(xl_constants(
+        Path::new("tests/data/xlsm/Missing.xlsm"),
+        &Options::default()
+    )
+    .is_err());
+}
@@ -0,0 +1,124 @@
+use std::path::{Path, PathBuf};
+use unlock_excel::error::UnlockError;
+use unlock_excel::inject_bin::{xl, xl_97, xl_97_to, xl_to};
+use unlock_excel::lenient::Options;
+use unlock_excel::{extract_bin, read};
+
+/*
+* XLSM
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn inject_bin_xlsm() {
+    let file = "tests/data/xlsm/Locked_with_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 91);
+    let bin = temp_dir.join("vbaProject.bin");
+    extract_bin::xl(Path::new("tests/data/xlsm/Unlocked_with_macro.xlsm"), &bin).unwrap();
+    xl(&temp_file, &bin, true, &Options::default()).unwrap();
+    assert!(!is_locked(&temp_file));
+
+    // Everything other than the VBA project is left alone
+    let mut original = zip::ZipArchive::new(std::fs::File::open(file).unwrap()).unwrap();
+    let mut injected = zip::ZipArchive::new(std::fs::File::open(&temp_file).unwrap()).unwrap();
+    assert_eq!(original.len(), injected.len());
+    for i in 0..original.len() {
+        let a = original.by_index_raw(i).unwrap();
+        let b = injected.by_name(a.name()).unwrap();
+        if a.name() != "xl/vbaProject.bin" {
+            assert_eq!(a.crc32(), b.crc32());
+        }
+    }
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn inject_bin_xlsm_to() {
+    let file = "tests/data/xlsm/Locked_with_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 92);
+    let bin = temp_dir.join("vbaProject.bin");
+    extract_bin::xl(Path::new("tests/data/xlsm/Unlocked_with_macro.xlsm"), &bin).unwrap();
+    let output = temp_dir.join("Injected.xlsm");
+    xl_to(&temp_file, &bin, &output, &Options::default()).unwrap();
+    assert!(!is_locked(&output));
+    assert!(is_locked(&temp_file));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn inject_bin_no_macro_xlsm() {
+    let file = "tests/data/xlsm/Unlocked_no_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 93);
+    let bin = temp_dir.join("vbaProject.bin");
+    extract_bin::xl(Path::new("tests/data/xlsm/Unlocked_with_macro.xlsm"), &bin).unwrap();
+    assert!(matches!(
+        xl(&temp_file, &bin, true, &Options::default()),
+        Err(UnlockError::NoVBAFile)
+    ));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn inject_bin_not_a_vba_project() {
+    let file = "tests/data/xlsm/Locked_with_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 94);
+    assert!(matches!(
+        xl(&temp_file, &temp_file, true, &Options::default()),
+        Err(UnlockError::CFBOpen(_))
+    ));
+    assert!(is_locked(&temp_file));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+/*
+* XLS
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn inject_bin_xls() {
+    let file = "tests/data/xls/Locked_with_macro.xls";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 91);
+    let bin = temp_dir.join("vbaProject.bin");
+    extract_bin::xl_97(Path::new("tests/data/xls/Unlocked_with_macro.xls"), &bin).unwrap();
+    xl_97(&temp_file, &bin, true, &Options::default()).unwrap();
+    assert!(!is_locked_97(&temp_file));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn inject_bin_xls_round_trip() {
+    let file = "tests/data/xls/Locked_with_macro.xls";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 92);
+    let bin = temp_dir.join("vbaProject.bin");
+    extract_bin::xl_97(&temp_file, &bin).unwrap();
+    let output = temp_dir.join("Injected.xls");
+    xl_97_to(&temp_file, &bin, &output, &Options::default()).unwrap();
+    assert!(is_locked_97(&output));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+fn is_locked(filename: &Path) -> bool {
+    read::xl_project(filename, false, &Options::default())
+        .unwrap()
+        .0
+        .is_locked()
+}
+
+fn is_locked_97(filename: &Path) -> bool {
+    read::xl_97_project(filename, false, &Options::default())
+        .unwrap()
+        .0
+        .is_locked()
+}
+
+fn create_temp_dir(source: &dyn AsRef<Path>, unique_num: u8) -> (PathBuf, PathBuf) {
+    let source = source.as_ref();
+    let mut folder = source.parent().unwrap().to_path_buf();
+    folder.push(format!("temp_{unique_num}"));
+    let mut copied_file = folder.clone();
+    copied_file.push(source.file_name().unwrap());
+    std::fs::create_dir(&folder).unwrap();
+    let _ = std::fs::copy(source, &copied_file);
+    (folder, copied_file)
+}
@@ -0,0 +1,97 @@
+use std::path::{Path, PathBuf};
+use unlock_excel::jsonl::Record;
+use unlock_excel::lenient::Options;
+use unlock_excel::read;
+
+#[test]
+fn record_locked_xlsm() {
+    let path = Path::new("tests/data/xlsm/Locked_with_macro.xlsm");
+    let mut record = Record::new(path);
+    record.read_project(path, &Options::default());
+    assert_eq!(record.format.as_deref(), Some("xlsm"));
+    assert_eq!(record.locked, Some(true));
+    assert_eq!(record.password, Some("hash"));
+    assert_eq!(record.visible, Some(false));
+    assert_eq!(record.action, None);
+}
+
+#[test]
+fn record_unlocked_xls() {
+    let path = Path::new("tests/data/xls/Unlocked_with_macro.xls");
+    let mut record = Record::new(path);
+    record.read_project(path, &Options::default());
+    assert_eq!(record.format.as_deref(), Some("xls"));
+    assert_eq!(record.locked, Some(false));
+    assert_eq!(record.password, Some("none"));
+    assert_eq!(record.visible, Some(true));
+}
+
+#[test]
+fn record_from_read_project() {
+    let path = Path::new("tests/data/xlsb/Locked_with_macro.xlsb");
+    let mut record = Record::new(path);
+    assert_eq!(record.format.as_deref(), Some("xlsb"));
+    assert_eq!(record.locked, None);
+    let (project, _) = read::xl_project(path, false, &Options::default()).unwrap();
+    record.set_project(&project);
+    assert_eq!(record.locked, Some(true));
+    assert_eq!(record.password, Some("hash"));
+    assert_eq!(record.visible, Some(false));
+}
+
+#[test]
+fn record_missing_file() {
+    let path = Path::new("tests/data/xlsm/Missing.xlsm");
+    let mut record = Record::new(path);
+    record.read_project(path, &Options::default());
+    assert_eq!(record.format.as_deref(), Some("xlsm"));
+    assert_eq!(record.locked, None);
+    assert_eq!(record.password, None);
+}
+
+#[test]
+fn record_renamed_xlsb() {
+    let (temp_dir, temp_file) = create_temp_dir(&"tests/data/xlsb/Locked_with_macro.xlsb", 141);
+    let renamed = temp_dir.join("Locked_with_macro");
+    std::fs::rename(&temp_file, &renamed).unwrap();
+    let mut record = Record::new(&renamed);
+    record.read_project(&renamed, &Options::default());
+    assert_eq!(record.format.as_deref(), Some("xlsb"));
+    assert_eq!(record.locked, Some(true));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn record_misnamed_xls() {
+    let (temp_dir, temp_file) = create_temp_dir(&"tests/data/xls/Locked_with_macro.xls", 142);
+    let renamed = temp_dir.join("Locked_with_macro.xlsm");
+    std::fs::rename(&temp_file, &renamed).unwrap();
+    let mut record = Record::new(&renamed);
+    record.read_project(&renamed, &Options::default());
+    assert_eq!(record.format.as_deref(), Some("xls"));
+    assert_eq!(record.locked, Some(true));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn record_is_one_line() {
+    let path = Path::new("tests/data/xlsm/Locked_with_macro.xlsm");
+    let mut record = Record::new(path);
+    record.read_project(path, &Options::default());
+    record.action = Some("read");
+    assert_eq!(
+        record.to_string(),
+        r#"{"path":"tests/data/xlsm/Locked_with_macro.xlsm","format":"xlsm","locked":true,"password":"hash","visible":false,"action":"read","error":null}"#
+    );
+}
+
+fn create_temp_dir(source: &dyn AsRef<Path>, unique_num: u8) -> (PathBuf, PathBuf) {
+    let source = source.as_ref();
+    let mut folder = source.parent().unwrap().to_path_buf();
+    folder.push(format!("temp_{unique_num}"));
+    let mut copied_file = folder.clone();
+    copied_file.push(source.file_name().unwrap());
+    std::fs::create_dir(&folder).unwrap();
+    let _ = std::fs::copy(source, &copied_file);
+    (folder, copied_file)
+}
@@ -0,0 +1,86 @@
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use unlock_excel::lenient::Options;
+use unlock_excel::{read, remove};
+
+#[test]
+fn stray_property_xls() {
+    let file = "tests/data/xls/Locked_with_macro.xls";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 131);
+    add_stray_property(&temp_file);
+
+    assert!(read::xl_97_project(&temp_file, false, &Options::default()).is_err());
+
+    let force = Options {
+        force: true,
+        ..Options::default()
+    };
+    let (p, _) = read::xl_97_project(&temp_file, false, &force).unwrap();
+    assert!(p.is_locked());
+    assert_eq!(1, p.warnings().len());
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+// A PROJECT stream that cannot be parsed still has its protection swapped out line by line, with
+// the stray line left where it was
+#[test]
+fn stray_property_remove_xls() {
+    let file = "tests/data/xls/Locked_with_macro.xls";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 132);
+    add_stray_property(&temp_file);
+
+    remove::xl_97(&temp_file, true, &Options::default()).unwrap();
+    let mut project = Vec::new();
+    cfb::open(&temp_file)
+        .unwrap()
+        .open_stream("/_VBA_PROJECT_CUR/PROJECT")
+        .unwrap()
+        .read_to_end(&mut project)
+        .unwrap();
+    assert!(project
+        .windows(b"\r\nStray line\r\n".len())
+        .any(|w| w == b"\r\nStray line\r\n"));
+
+    let force = Options {
+        force: true,
+        ..Options::default()
+    };
+    let (p, _) = read::xl_97_project(&temp_file, false, &force).unwrap();
+    assert!(!p.is_locked());
+    assert!(p.visibility().is_visible());
+    assert_eq!("{3C6F1B8B-BDBE-4F1B-AA02-BCA23D695691}", p.id().to_string());
+
+    let strict = Options {
+        strict: true,
+        ..Options::default()
+    };
+    assert!(remove::xl_97(&temp_file, true, &strict).is_err());
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+/// Add a line that is neither in the specification nor a property after the ID line of the
+/// PROJECT stream
+fn add_stray_property(filename: &Path) {
+    let mut file = cfb::open_rw(filename).unwrap();
+    let mut stream = file.open_stream("/_VBA_PROJECT_CUR/PROJECT").unwrap();
+    let mut project = Vec::new();
+    stream.read_to_end(&mut project).unwrap();
+    let id_end = project.iter().position(|&b| b == b'\n').unwrap() + 1;
+    let mut updated = project[..id_end].to_vec();
+    updated.extend_from_slice(b"Stray line\r\n");
+    updated.extend_from_slice(&project[id_end..]);
+    stream.seek(SeekFrom::Start(0)).unwrap();
+    stream.write_all(&updated).unwrap();
+    stream.flush().unwrap();
+}
+
+fn create_temp_dir(source: &dyn AsRef<Path>, unique_num: u8) -> (PathBuf, PathBuf) {
+    let source = source.as_ref();
+    let mut folder = source.parent().unwrap().to_path_buf();
+    folder.push(format!("temp_{unique_num}"));
+    let mut copied_file = folder.clone();
+    copied_file.push(source.file_name().unwrap());
+    std::fs::create_dir(&folder).unwrap();
+    let _ = std::fs::copy(source, &copied_file);
+    (folder, copied_file)
+}
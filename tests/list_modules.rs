@@ -0,0 +1,100 @@
+use std::path::Path;
+use unlock_excel::lenient::Options;
+use unlock_excel::list_modules::{
+    xl_97_forms, xl_97_modules, xl_forms, xl_modules, ModuleInfo, ModuleType,
+};
+
+fn expected() -> Vec<ModuleInfo> {
+    vec![
+        ModuleInfo {
+            name: "ThisWorkbook".to_string(),
+            module_type: ModuleType::Document,
+            stream_name: "ThisWorkbook".to_string(),
+            read_only: false,
+            private: false,
+        },
+        ModuleInfo {
+            name: "Sheet1".to_string(),
+            module_type: ModuleType::Document,
+            stream_name: "Sheet1".to_string(),
+            read_only: false,
+            private: false,
+        },
+        ModuleInfo {
+            name: "Module1".to_string(),
+            module_type: ModuleType::Standard,
+            stream_name: "Module1".to_string(),
+            read_only: false,
+            private: false,
+        },
+    ]
+}
+
+/*
+* XLSM
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn list_unlocked_xlsm() {
+    let file = Path::new("tests/data/xlsm/Unlocked_with_macro.xlsm");
+    assert_eq!(expected(), xl_modules(file, &Options::default()).unwrap());
+}
+
+#[test]
+fn list_locked_xlsm() {
+    let file = Path::new("tests/data/xlsm/Locked_with_macro.xlsm");
+    assert_eq!(expected(), xl_modules(file, &Options::default()).unwrap());
+}
+
+#[test]
+fn no_forms_xlsm() {
+    let file = Path::new("tests/data/xlsm/Locked_with_macro.xlsm");
+    assert!(xl_forms(file, &Options::default()).unwrap().is_empty());
+}
+
+/*
+* XLSB
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn list_unlocked_xlsb() {
+    let file = Path::new("tests/data/xlsb/Unlocked_with_macro.xlsb");
+    assert_eq!(expected(), xl_modules(file, &Options::default()).unwrap());
+}
+
+#[test]
+fn list_locked_xlsb() {
+    let file = Path::new("tests/data/xlsb/Locked_with_macro.xlsb");
+    assert_eq!(expected(), xl_modules(file, &Options::default()).unwrap());
+}
+
+/*
+* XLS
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn list_unlocked_xls() {
+    let file = Path::new("tests/data/xls/Unlocked_with_macro.xls");
+    assert_eq!(
+        expected(),
+        xl_97_modules(file, &Options::default()).unwrap()
+    );
+}
+
+#[test]
+fn list_locked_xls() {
+    let file = Path::new("tests/data/xls/Locked_with_macro.xls");
+    assert_eq!(
+        expected(),
+        xl_97_modules(file, &Options::default()).unwrap()
+    );
+}
+
+#[test]
+fn no_forms_xls() {
+    let file = Path::new("tests/data/xls/Locked_with_macro.xls");
+    assert!(xl_97_forms(file, &Options::default()).unwrap().is_empty());
+}
@@ -0,0 +1,71 @@
+use std::path::Path;
+use unlock_excel::lenient::Options;
+use unlock_excel::list_references::{
+    xl_97_references, xl_references, ReferenceInfo, ReferenceKind,
+};
+
+fn expected() -> Vec<(&'static str, ReferenceKind, &'static str)> {
+    vec![
+        (
+            "stdole",
+            ReferenceKind::Registered,
+            "*\\G{00020430-0000-0000-C000-000000000046}#2.0#0#C:\\Windows\\System32\\stdole2.tlb#OLE Automation",
+        ),
+        (
+            "Office",
+            ReferenceKind::Registered,
+            "*\\G{2DF8D04C-5BFA-101B-BDE5-00AA0044DE52}#2.0#0#C:\\Program Files\\Common Files\\Microsoft Shared\\OFFICE16\\MSO.DLL#Microsoft Office 16.0 Object Library",
+        ),
+        (
+            "MSForms",
+            ReferenceKind::Control,
+            "*\\G{215A6553-680A-4632-8CDB-6421E9F058C1}#2.0#0#C:\\Users\\james\\AppData\\Local\\Temp\\Excel8.0\\MSForms.exd#Microsoft Forms 2.0 Object Library",
+        ),
+    ]
+}
+
+fn check(references: Vec<ReferenceInfo>) {
+    let references: Vec<_> = references
+        .iter()
+        .map(|r| (r.name.as_deref().unwrap(), r.kind, r.libid.as_str()))
+        .collect();
+    assert_eq!(expected(), references);
+}
+
+#[test]
+fn references_xlsm() {
+    let file = Path::new("tests/data/xlsm/Locked_with_macro.xlsm");
+    check(xl_references(file, &Options::default()).unwrap());
+}
+
+#[test]
+fn references_xlsb() {
+    let file = Path::new("tests/data/xlsb/Unlocked_with_macro.xlsb");
+    check(xl_references(file, &Options::default()).unwrap());
+}
+
+#[test]
+fn reference_parts_xlsm() {
+    let file = Path::new("tests/data/xlsm/Unlocked_with_macro.xlsm");
+    let references = xl_references(file, &Options::default()).unwrap();
+    let office = &references[1];
+    assert_eq!(
+        Some("{2DF8D04C-5BFA-101B-BDE5-00AA0044DE52}".parse().unwrap()),
+        office.guid
+    );
+    assert_eq!(Some("2.0"), office.version.as_deref());
+    assert_eq!(
+        "C:\\Program Files\\Common Files\\Microsoft Shared\\OFFICE16\\MSO.DLL",
+        office.path
+    );
+    assert_eq!(
+        Some("Microsoft Office 16.0 Object Library"),
+        office.description.as_deref()
+    );
+}
+
+#[test]
+fn references_xls() {
+    let file = Path::new("tests/data/xls/Locked_with_macro.xls");
+    check(xl_97_references(file, &Options::default()).unwrap());
+}
@@ -0,0 +1,48 @@
+use std::path::Path;
+use unlock_excel::lenient::Options;
+use unlock_excel::pcode::{xl_97_pcode, xl_pcode, ModulePcode};
+
+#[test]
+fn pcode_xlsm() {
+    let modules = xl_pcode(
+        Path::new("tests/data/xlsm/Locked_with_macro.xlsm"),
+        &Options::default(),
+    )
+    .unwrap();
+    check_pcode(&modules);
+}
+
+#[test]
+fn pcode_xlsb() {
+    let modules = xl_pcode(
+        Path::new("tests/data/xlsb/Unlocked_with_macro.xlsb"),
+        &Options::default(),
+    )
+    .unwrap();
+    check_pcode(&modules);
+}
+
+#[test]
+fn pcode_xls() {
+    let modules = xl_97_pcode(
+        Path::new("tests/data/xls/Locked_with_macro.xls"),
+        &Options::default(),
+    )
+    .unwrap();
+    check_pcode(&modules);
+}
+
+fn check_pcode(modules: &[ModulePcode]) {
+    let module = modules.iter().find(|m| m.name == "Module1").unwrap();
+    // Sub, blank, MsgBox, blank, End Sub
+    assert_eq!(
+        vec![6, 0, 50, 0, 2],
+        module.lines.iter().map(Vec::len).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec!["You'll never figure out how I did this!".to_string()],
+        module.strings
+    );
+    let workbook = modules.iter().find(|m| m.name == "ThisWorkbook").unwrap();
+    assert!(workbook.lines.is_empty());
+}
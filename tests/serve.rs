@@ -0,0 +1,90 @@
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::Once;
+use std::time::Duration;
+use unlock_excel::lenient::Options;
+use unlock_excel::read::bytes_project;
+use unlock_excel::serve::serve;
+
+const ADDRESS: &str = "127.0.0.1:47811";
+
+#[test]
+fn serve_read_locked_xlsm() {
+    let (status, body) = post("/read", "tests/data/xlsm/Locked_with_macro.xlsm");
+    assert_eq!(status, 200);
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["locked"], true);
+    assert_eq!(json["protection"]["vbe"], true);
+    assert_eq!(json["protection"]["user"], false);
+    assert_eq!(json["protection"]["host"], false);
+    assert_eq!(json["visible"], false);
+    assert_eq!(json["password"]["kind"], "hash");
+}
+
+#[test]
+fn serve_read_unlocked_xls() {
+    let (status, body) = post("/read", "tests/data/xls/Unlocked_with_macro.xls");
+    assert_eq!(status, 200);
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["locked"], false);
+    assert_eq!(json["protection"]["vbe"], false);
+    assert_eq!(json["visible"], true);
+}
+
+#[test]
+fn serve_remove_locked_xlsb() {
+    let (status, body) = post("/remove", "tests/data/xlsb/Locked_with_macro.xlsb");
+    assert_eq!(status, 200);
+    let (project, _) = bytes_project(&body, false, &Options::default()).unwrap();
+    assert!(!project.is_locked());
+}
+
+#[test]
+fn serve_not_excel() {
+    let (status, body) = post("/read", "README.md");
+    assert_eq!(status, 422);
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(json["error"].is_string());
+}
+
+#[test]
+fn serve_unknown_path() {
+    let (status, _) = post("/unlock", "tests/data/xlsm/Locked_with_macro.xlsm");
+    assert_eq!(status, 404);
+}
+
+/// Send the file to the server, started on first use, returning the status code and body
+fn post(path: &str, filename: &str) -> (u16, Vec<u8>) {
+    static START: Once = Once::new();
+    START.call_once(|| {
+        std::thread::spawn(|| serve(ADDRESS, &Options::default()).unwrap());
+    });
+
+    let data = std::fs::read(filename).unwrap();
+    let mut stream = connect();
+    write!(
+        stream,
+        "POST {path} HTTP/1.1\r\nHost: {ADDRESS}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        data.len()
+    )
+    .unwrap();
+    stream.write_all(&data).unwrap();
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    let split = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+    let head = String::from_utf8_lossy(&response[..split]);
+    let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+    (status, response[split + 4..].to_vec())
+}
+
+/// Connect to the server, giving it a moment to start listening
+fn connect() -> TcpStream {
+    for _ in 0..50 {
+        if let Ok(stream) = TcpStream::connect(ADDRESS) {
+            return stream;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    panic!("the server did not start");
+}
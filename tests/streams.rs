@@ -0,0 +1,38 @@
+use std::path::Path;
+use unlock_excel::error::UnlockError;
+use unlock_excel::streams::{xl, xl_97};
+
+#[test]
+fn streams_xlsm() {
+    let entries = xl(Path::new("tests/data/xlsm/Locked_with_macro.xlsm")).unwrap();
+    assert_eq!(entries[0].path, "/");
+    assert!(entries[0].is_storage);
+    let project = entries.iter().find(|e| e.path == "/PROJECT").unwrap();
+    assert_eq!((project.depth, project.is_storage), (1, false));
+    assert!(project.size > 0);
+    let dir = entries.iter().find(|e| e.path == "/VBA/dir").unwrap();
+    assert_eq!((dir.name.as_str(), dir.depth), ("dir", 2));
+}
+
+#[test]
+fn streams_no_macro_xlsm() {
+    assert!(matches!(
+        xl(Path::new("tests/data/xlsm/Unlocked_no_macro.xlsm")),
+        Err(UnlockError::NoVBAFile)
+    ));
+}
+
+#[test]
+fn streams_xls() {
+    let entries = xl_97(Path::new("tests/data/xls/Locked_with_macro.xls")).unwrap();
+    let storage = entries
+        .iter()
+        .find(|e| e.path == "/_VBA_PROJECT_CUR")
+        .unwrap();
+    assert!(storage.is_storage);
+    assert_eq!(storage.size, 0);
+    assert!(entries
+        .iter()
+        .any(|e| e.path == "/_VBA_PROJECT_CUR/PROJECT"));
+    assert!(entries.iter().any(|e| e.path == "/Workbook"));
+}
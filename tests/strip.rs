@@ -0,0 +1,128 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use unlock_excel::error::UnlockError;
+use unlock_excel::lenient::Options;
+use unlock_excel::read;
+use unlock_excel::strip::{xl, xl_97, xl_97_to, xl_to};
+
+/*
+* XLSM
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn strip_copy_xlsm() {
+    let file = "tests/data/xlsm/Locked_with_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 71);
+    xl(Path::new(&temp_file), false).unwrap();
+    let stripped = temp_dir.join("Locked_with_macro_stripped.xlsm");
+    assert!(matches!(
+        read::xl_project(&stripped, false, &Options::default()),
+        Err(UnlockError::NoVBAFile)
+    ));
+    let content_types = zip_entry(&stripped, "[Content_Types].xml");
+    assert!(!content_types.contains("vbaProject"));
+    assert!(content_types.contains("sheet.macroEnabled.main+xml"));
+    let rels = zip_entry(&stripped, "xl/_rels/workbook.xml.rels");
+    assert!(!rels.contains("vbaProject"));
+    assert!(rels.contains("worksheets/sheet1.xml"));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn strip_xlsx_xlsm() {
+    let file = "tests/data/xlsm/Locked_with_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 72);
+    let output = temp_dir.join("Output.xlsx");
+    xl_to(Path::new(&temp_file), &output).unwrap();
+    let content_types = zip_entry(&output, "[Content_Types].xml");
+    assert!(!content_types.contains("vbaProject"));
+    assert!(!content_types.contains("macroEnabled"));
+    assert!(content_types.contains("spreadsheetml.sheet.main+xml"));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+/*
+* XLSB
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn strip_inplace_xlsb() {
+    let file = "tests/data/xlsb/Locked_with_macro.xlsb";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 71);
+    xl(Path::new(&temp_file), true).unwrap();
+    assert!(matches!(
+        read::xl_project(&temp_file, false, &Options::default()),
+        Err(UnlockError::NoVBAFile)
+    ));
+    let rels = zip_entry(&temp_file, "xl/_rels/workbook.bin.rels");
+    assert!(!rels.contains("vbaProject"));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn strip_xlsx_xlsb() {
+    let file = "tests/data/xlsb/Locked_with_macro.xlsb";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 72);
+    let output = temp_dir.join("Output.xlsx");
+    assert!(matches!(
+        xl_to(Path::new(&temp_file), &output),
+        Err(UnlockError::NotConvertible(_))
+    ));
+    assert!(!output.exists());
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+/*
+* XLS
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn strip_copy_xls() {
+    let file = "tests/data/xls/Locked_with_macro.xls";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 71);
+    xl_97(Path::new(&temp_file), false).unwrap();
+    let stripped = temp_dir.join("Locked_with_macro_stripped.xls");
+    let file = cfb::open(&stripped).unwrap();
+    assert!(!file.exists("/_VBA_PROJECT_CUR"));
+    assert!(file.is_stream("/Workbook"));
+    let (p, _) = read::xl_97_project(&temp_file, false, &Options::default()).unwrap();
+    assert!(p.is_locked());
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn strip_xlsx_xls() {
+    let file = "tests/data/xls/Locked_with_macro.xls";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 72);
+    let output = temp_dir.join("Output.xlsx");
+    assert!(matches!(
+        xl_97_to(Path::new(&temp_file), &output),
+        Err(UnlockError::NotConvertible(_))
+    ));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+fn zip_entry(filename: &Path, name: &str) -> String {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(filename).unwrap()).unwrap();
+    let mut contents = String::new();
+    archive
+        .by_name(name)
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    contents
+}
+
+fn create_temp_dir(source: &dyn AsRef<Path>, unique_num: u8) -> (PathBuf, PathBuf) {
+    let source = source.as_ref();
+    let mut folder = source.parent().unwrap().to_path_buf();
+    folder.push(format!("temp_{unique_num}"));
+    let mut copied_file = folder.clone();
+    copied_file.push(source.file_name().unwrap());
+    std::fs::create_dir(&folder).unwrap();
+    let _ = std::fs::copy(source, &copied_file);
+    (folder, copied_file)
+}
@@ -0,0 +1,18 @@
+use unlock_excel::style;
+
+// The colour settings are shared by the whole process, so they are checked in a single test
+#[test]
+fn colour_on_and_off() {
+    style::set(false, false);
+    assert_eq!(style::locked("locked"), "locked");
+    assert_eq!(style::error("Error:"), "Error:");
+
+    style::set(true, false);
+    assert!(style::locked("locked").contains("\x1b["));
+    assert!(style::locked("locked").contains("locked"));
+    assert_eq!(style::error("Error:"), "Error:");
+
+    style::set(false, true);
+    assert_eq!(style::unlocked("not locked"), "not locked");
+    assert!(style::error("Error:").contains("\x1b["));
+}
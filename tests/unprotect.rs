@@ -0,0 +1,507 @@
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use unlock_excel::error::UnlockError;
+use unlock_excel::lenient::Options;
+use unlock_excel::unprotect::{bytes, xl, xl_97, xl_97_shared, xl_97_to, xl_shared, xl_to, Layers};
+use unlock_excel::{read, verify};
+
+const PROTECTION: &str = r#"<workbookProtection workbookPassword="CC1A" lockStructure="1"/>"#;
+const SHEET_PROTECTION: &str = r#"<sheetProtection password="CC1A" sheet="1"/>"#;
+const SHEET: &str = "xl/worksheets/sheet1.xml";
+const FILE_SHARING: &str =
+    r#"<fileSharing readOnlyRecommended="1" userName="Author" reservationPassword="CC1A"/>"#;
+
+const VERY_HIDDEN: Layers = Layers {
+    vba: false,
+    workbook: false,
+    sheets: false,
+    sheet_names: &[],
+    very_hidden: true,
+    file_sharing: false,
+    shared: false,
+};
+
+const ALL: Layers = Layers {
+    vba: true,
+    workbook: true,
+    sheets: true,
+    sheet_names: &[],
+    very_hidden: true,
+    file_sharing: true,
+    shared: true,
+};
+
+/*
+* XLSM
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn unprotect_all_xlsm() {
+    let file = "tests/data/xlsm/Locked_with_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 121);
+    protect_workbook(&temp_file);
+    replace_xml(
+        &temp_file,
+        SHEET,
+        "<sheetData/>",
+        &format!("<sheetData/>{SHEET_PROTECTION}"),
+    );
+    xl(&temp_file, ALL, true, &Options::default()).unwrap();
+    let workbook = zip_entry(&temp_file, "xl/workbook.xml");
+    assert!(!workbook.contains("workbookProtection"));
+    assert!(workbook.contains("<bookViews>"));
+    assert!(!zip_entry(&temp_file, SHEET).contains("sheetProtection"));
+    assert!(!read::xl_project(&temp_file, false, &Options::default())
+        .unwrap()
+        .0
+        .is_locked());
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn unprotect_vba_only_xlsm() {
+    let file = "tests/data/xlsm/Locked_with_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 122);
+    let output = temp_dir.join("Unprotected.xlsm");
+    let layers = Layers {
+        vba: true,
+        ..Layers::default()
+    };
+    xl_to(&temp_file, layers, &output, &Options::default()).unwrap();
+    assert!(verify::xl(&temp_file, &output, &Options::default())
+        .unwrap()
+        .is_empty());
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn unprotect_workbook_only_xlsm() {
+    let file = "tests/data/xlsm/Unlocked_no_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 123);
+    protect_workbook(&temp_file);
+    let layers = Layers {
+        workbook: true,
+        ..Layers::default()
+    };
+    xl(&temp_file, layers, false, &Options::default()).unwrap();
+    let unprotected = temp_dir.join("Unlocked_no_macro_unprotected.xlsm");
+    assert!(!zip_entry(&unprotected, "xl/workbook.xml").contains("workbookProtection"));
+    assert!(matches!(
+        xl(&temp_file, ALL, false, &Options::default()),
+        Err(UnlockError::NoVBAFile)
+    ));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn unprotect_bytes_xlsm() {
+    let file = "tests/data/xlsm/Locked_with_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 124);
+    protect_workbook(&temp_file);
+    let unprotected = bytes(
+        &std::fs::read(&temp_file).unwrap(),
+        ALL,
+        &Options::default(),
+    )
+    .unwrap();
+    let output = temp_dir.join("Unprotected.xlsm");
+    std::fs::write(&output, unprotected).unwrap();
+    assert!(!zip_entry(&output, "xl/workbook.xml").contains("workbookProtection"));
+    assert!(!read::xl_project(&output, false, &Options::default())
+        .unwrap()
+        .0
+        .is_locked());
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn unprotect_very_hidden_xlsm() {
+    let file = "tests/data/xlsm/Unlocked_no_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 125);
+    replace_xml(
+        &temp_file,
+        "xl/workbook.xml",
+        r#"<sheet name="Sheet1""#,
+        r#"<sheet name="Sheet1" state="veryHidden""#,
+    );
+    assert!(zip_entry(&temp_file, "xl/workbook.xml").contains("veryHidden"));
+    xl(&temp_file, VERY_HIDDEN, true, &Options::default()).unwrap();
+    let workbook = zip_entry(&temp_file, "xl/workbook.xml");
+    assert!(!workbook.contains("veryHidden"));
+    assert!(workbook.contains(r#"<sheet name="Sheet1" sheetId="1""#));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn unprotect_file_sharing_xlsm() {
+    let file = "tests/data/xlsm/Unlocked_no_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 126);
+    replace_xml(
+        &temp_file,
+        "xl/workbook.xml",
+        "<workbookPr",
+        &format!("{FILE_SHARING}<workbookPr"),
+    );
+    let layers = Layers {
+        file_sharing: true,
+        ..Layers::default()
+    };
+    xl(&temp_file, layers, true, &Options::default()).unwrap();
+    let workbook = zip_entry(&temp_file, "xl/workbook.xml");
+    assert!(!workbook.contains("fileSharing"));
+    assert!(workbook.contains("<workbookPr"));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn unprotect_shared_xlsm() {
+    let file = "tests/data/xlsm/Unlocked_no_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 127);
+    replace_xml(
+        &temp_file,
+        "xl/workbook.xml",
+        "<bookViews>",
+        r#"<workbookProtection lockStructure="1" lockRevision="1" revisionsPassword="CC1A"/><bookViews>"#,
+    );
+    assert!(xl_shared(&temp_file).unwrap());
+    let layers = Layers {
+        shared: true,
+        ..Layers::default()
+    };
+    xl(&temp_file, layers, true, &Options::default()).unwrap();
+    assert!(!xl_shared(&temp_file).unwrap());
+    assert!(zip_entry(&temp_file, "xl/workbook.xml")
+        .contains(r#"<workbookProtection lockStructure="1"/>"#));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+/*
+* XLSB
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn unprotect_very_hidden_xlsb() {
+    let file = "tests/data/xlsb/Unlocked_no_macro.xlsb";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 121);
+    update_part(&temp_file, "xl/workbook.bin", |mut data| {
+        let state = sheet_state_12(&data);
+        data[state] = 2;
+        data
+    });
+    let output = temp_dir.join("Unprotected.xlsb");
+    xl_to(&temp_file, VERY_HIDDEN, &output, &Options::default()).unwrap();
+    let data = zip_part(&output, "xl/workbook.bin");
+    assert_eq!(data[sheet_state_12(&data)], 0);
+    assert_eq!(data.len(), zip_part(&temp_file, "xl/workbook.bin").len());
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+/*
+* XLS
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn unprotect_all_xls() {
+    let file = "tests/data/xls/Locked_with_macro.xls";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 121);
+    let offset = protect_workbook_97(&temp_file);
+    let sheet_offset = protect_sheet_97(&temp_file);
+    assert_eq!(record_97(&temp_file, offset), vec![1, 0]);
+    assert_eq!(record_97(&temp_file, sheet_offset), vec![1, 0]);
+    xl_97(&temp_file, ALL, true, &Options::default()).unwrap();
+    assert_eq!(record_97(&temp_file, offset), vec![0, 0]);
+    assert_eq!(record_97(&temp_file, sheet_offset), vec![0, 0]);
+    assert!(!read::xl_97_project(&temp_file, false, &Options::default())
+        .unwrap()
+        .0
+        .is_locked());
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn unprotect_workbook_only_xls() {
+    let file = "tests/data/xls/Locked_with_macro.xls";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 122);
+    let offset = protect_workbook_97(&temp_file);
+    let output = temp_dir.join("Unprotected.xls");
+    let layers = Layers {
+        workbook: true,
+        ..Layers::default()
+    };
+    xl_97_to(&temp_file, layers, &output, &Options::default()).unwrap();
+    assert_eq!(record_97(&output, offset), vec![0, 0]);
+    assert!(read::xl_97_project(&output, false, &Options::default())
+        .unwrap()
+        .0
+        .is_locked());
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn unprotect_very_hidden_xls() {
+    let file = "tests/data/xls/Unlocked_no_macro.xls";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 123);
+    let offset = hide_sheet_97(&temp_file);
+    xl_97(&temp_file, VERY_HIDDEN, true, &Options::default()).unwrap();
+    assert_eq!(workbook_stream_97(&temp_file)[offset + 8], 0);
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn unprotect_file_sharing_xls() {
+    let file = "tests/data/xls/Unlocked_no_macro.xls";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 124);
+    let offset = write_protect_97(&temp_file);
+    let layers = Layers {
+        file_sharing: true,
+        ..Layers::default()
+    };
+    xl_97(&temp_file, layers, true, &Options::default()).unwrap();
+    let stream = workbook_stream_97(&temp_file);
+    assert_eq!(stream[offset..offset + 4], [0xC0, 0x01, 0x00, 0x00]);
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn unprotect_shared_xls() {
+    let file = "tests/data/xls/Unlocked_no_macro.xls";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 125);
+    assert!(!xl_97_shared(&temp_file).unwrap());
+    protect_shared_97(&temp_file);
+    assert!(xl_97_shared(&temp_file).unwrap());
+    let layers = Layers {
+        shared: true,
+        ..Layers::default()
+    };
+    xl_97(&temp_file, layers, true, &Options::default()).unwrap();
+    assert!(!xl_97_shared(&temp_file).unwrap());
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+/// Add workbook protection to an xlsm file
+fn protect_workbook(filename: &Path) {
+    replace_xml(
+        filename,
+        "xl/workbook.xml",
+        "<bookViews>",
+        &format!("{PROTECTION}<bookViews>"),
+    );
+}
+
+/// Replace the first match of some xml within a part of an xlsm file
+fn replace_xml(filename: &Path, part: &str, from: &str, to: &str) {
+    update_part(filename, part, |data| {
+        String::from_utf8(data)
+            .unwrap()
+            .replacen(from, to, 1)
+            .into_bytes()
+    });
+}
+
+/// Rewrite one part of the zip archive of an Excel file
+fn update_part<F: Fn(Vec<u8>) -> Vec<u8>>(filename: &Path, part: &str, update: F) {
+    let data = std::fs::read(filename).unwrap();
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+    let mut new_archive = zip::ZipWriter::new(std::fs::File::create(filename).unwrap());
+    for i in 0..archive.len() {
+        let name = archive.by_index_raw(i).unwrap().name().to_string();
+        if name == part {
+            let mut data = Vec::new();
+            archive.by_index(i).unwrap().read_to_end(&mut data).unwrap();
+            let data = update(data);
+            new_archive
+                .start_file(name, zip::write::FileOptions::default())
+                .unwrap();
+            new_archive.write_all(&data).unwrap();
+        } else {
+            new_archive
+                .raw_copy_file(archive.by_index_raw(i).unwrap())
+                .unwrap();
+        }
+    }
+    new_archive.finish().unwrap();
+}
+
+/// Protect the structure of an xls workbook, by setting the protection record that Excel writes
+/// to the workbook globals. Returns the offset of the record within the workbook stream
+fn protect_workbook_97(filename: &Path) -> usize {
+    let mut file = cfb::open_rw(filename).unwrap();
+    let mut stream = Vec::new();
+    file.open_stream("/Workbook")
+        .unwrap()
+        .read_to_end(&mut stream)
+        .unwrap();
+    let mut pos = 0;
+    let offset = loop {
+        let record = u16::from_le_bytes([stream[pos], stream[pos + 1]]);
+        let length = usize::from(u16::from_le_bytes([stream[pos + 2], stream[pos + 3]]));
+        if record == 0x0012 {
+            break pos;
+        }
+        pos += 4 + length;
+    };
+    stream[offset + 4] = 1;
+    file.open_stream("/Workbook")
+        .unwrap()
+        .write_all(&stream)
+        .unwrap();
+    offset
+}
+
+/// Protect the first sheet of an xls file, by turning a two byte record of the sheet into a
+/// protection record. Returns the offset of the record within the workbook stream
+fn protect_sheet_97(filename: &Path) -> usize {
+    let mut file = cfb::open_rw(filename).unwrap();
+    let mut stream = Vec::new();
+    file.open_stream("/Workbook")
+        .unwrap()
+        .read_to_end(&mut stream)
+        .unwrap();
+    let (mut pos, mut globals) = (0, true);
+    let offset = loop {
+        let record = u16::from_le_bytes([stream[pos], stream[pos + 1]]);
+        let length = usize::from(u16::from_le_bytes([stream[pos + 2], stream[pos + 3]]));
+        if !globals && length == 2 && record != 0x0809 {
+            break pos;
+        }
+        globals &= record != 0x000A;
+        pos += 4 + length;
+    };
+    stream[offset..offset + 6].copy_from_slice(&[0x12, 0x00, 0x02, 0x00, 0x01, 0x00]);
+    file.open_stream("/Workbook")
+        .unwrap()
+        .write_all(&stream)
+        .unwrap();
+    offset
+}
+
+/// Make the first sheet of an xls workbook very hidden. Returns the offset of its sheet record
+/// within the workbook stream, the state being four bytes into the data
+fn hide_sheet_97(filename: &Path) -> usize {
+    let mut file = cfb::open_rw(filename).unwrap();
+    let mut stream = Vec::new();
+    file.open_stream("/Workbook")
+        .unwrap()
+        .read_to_end(&mut stream)
+        .unwrap();
+    let mut pos = 0;
+    let offset = loop {
+        let record = u16::from_le_bytes([stream[pos], stream[pos + 1]]);
+        let length = usize::from(u16::from_le_bytes([stream[pos + 2], stream[pos + 3]]));
+        if record == 0x0085 {
+            break pos;
+        }
+        pos += 4 + length;
+    };
+    stream[offset + 8] = 2;
+    file.open_stream("/Workbook")
+        .unwrap()
+        .write_all(&stream)
+        .unwrap();
+    offset
+}
+
+/// Mark an xls workbook as having a password to write to it, by turning the first record with no
+/// data into the record that says so. Returns the offset of the record within the workbook stream
+fn write_protect_97(filename: &Path) -> usize {
+    let mut file = cfb::open_rw(filename).unwrap();
+    let mut stream = Vec::new();
+    file.open_stream("/Workbook")
+        .unwrap()
+        .read_to_end(&mut stream)
+        .unwrap();
+    let mut pos = 0;
+    let offset = loop {
+        let length = usize::from(u16::from_le_bytes([stream[pos + 2], stream[pos + 3]]));
+        if length == 0 {
+            break pos;
+        }
+        pos += 4 + length;
+    };
+    stream[offset..offset + 2].copy_from_slice(&[0x86, 0x00]);
+    file.open_stream("/Workbook")
+        .unwrap()
+        .write_all(&stream)
+        .unwrap();
+    offset
+}
+
+/// Protect the sharing of an xls workbook, by turning the first two byte record after the start of
+/// the globals into the record that says so
+fn protect_shared_97(filename: &Path) {
+    let mut file = cfb::open_rw(filename).unwrap();
+    let mut stream = Vec::new();
+    file.open_stream("/Workbook")
+        .unwrap()
+        .read_to_end(&mut stream)
+        .unwrap();
+    let mut pos = 0;
+    let offset = loop {
+        let length = usize::from(u16::from_le_bytes([stream[pos + 2], stream[pos + 3]]));
+        if pos > 0 && length == 2 {
+            break pos;
+        }
+        pos += 4 + length;
+    };
+    stream[offset..offset + 6].copy_from_slice(&[0xAF, 0x01, 0x02, 0x00, 0x01, 0x00]);
+    file.open_stream("/Workbook")
+        .unwrap()
+        .write_all(&stream)
+        .unwrap();
+}
+
+fn record_97(filename: &Path, offset: usize) -> Vec<u8> {
+    workbook_stream_97(filename)[offset + 4..offset + 6].to_vec()
+}
+
+fn workbook_stream_97(filename: &Path) -> Vec<u8> {
+    let mut file = cfb::open(filename).unwrap();
+    let mut stream = Vec::new();
+    file.open_stream("/Workbook")
+        .unwrap()
+        .read_to_end(&mut stream)
+        .unwrap();
+    stream
+}
+
+/// Where the state of the first sheet is within an xlsb workbook part. It is the start of the
+/// data of the first sheet record, whose type and one byte size are `9C 01 xx`
+fn sheet_state_12(data: &[u8]) -> usize {
+    data.windows(2).position(|w| w == [0x9C, 0x01]).unwrap() + 3
+}
+
+fn zip_part(filename: &Path, name: &str) -> Vec<u8> {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(filename).unwrap()).unwrap();
+    let mut contents = Vec::new();
+    archive
+        .by_name(name)
+        .unwrap()
+        .read_to_end(&mut contents)
+        .unwrap();
+    contents
+}
+
+fn zip_entry(filename: &Path, name: &str) -> String {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(filename).unwrap()).unwrap();
+    let mut contents = String::new();
+    archive
+        .by_name(name)
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    contents
+}
+
+fn create_temp_dir(source: &dyn AsRef<Path>, unique_num: u8) -> (PathBuf, PathBuf) {
+    let source = source.as_ref();
+    let mut folder = source.parent().unwrap().to_path_buf();
+    folder.push(format!("temp_{unique_num}"));
+    let mut copied_file = folder.clone();
+    copied_file.push(source.file_name().unwrap());
+    std::fs::create_dir(&folder).unwrap();
+    let _ = std::fs::copy(source, &copied_file);
+    (folder, copied_file)
+}
@@ -0,0 +1,243 @@
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use unlock_excel::error::UnlockError;
+use unlock_excel::unprotect_sheets::{xl, xl_97, xl_97_to, xl_to};
+
+const SHEET: &str = "xl/worksheets/sheet1.xml";
+const PROTECTION: &str =
+    r#"<sheetProtection password="CC1A" sheet="1" objects="1" scenarios="1"/>"#;
+
+/*
+* XLSM
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn unprotect_sheets_inplace_xlsm() {
+    let file = "tests/data/xlsm/Unlocked_with_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 111);
+    protect_sheet(&temp_file);
+    assert!(contains(
+        &zip_entry(&temp_file, SHEET),
+        PROTECTION.as_bytes()
+    ));
+    xl(&temp_file, &[], true).unwrap();
+    let sheet = String::from_utf8(zip_entry(&temp_file, SHEET)).unwrap();
+    assert!(!sheet.contains("sheetProtection"));
+    assert!(sheet.contains("<sheetData/><pageMargins"));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn unprotect_sheets_copy_xlsm() {
+    let file = "tests/data/xlsm/Unlocked_with_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 112);
+    protect_sheet(&temp_file);
+    xl(&temp_file, &[], false).unwrap();
+    let unprotected = temp_dir.join("Unlocked_with_macro_unprotected.xlsm");
+    assert!(!contains(
+        &zip_entry(&unprotected, SHEET),
+        b"sheetProtection"
+    ));
+    assert!(contains(&zip_entry(&temp_file, SHEET), b"sheetProtection"));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn unprotect_named_sheet_xlsm() {
+    let file = "tests/data/xlsm/Unlocked_with_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 113);
+    protect_sheet(&temp_file);
+    xl(&temp_file, &["sheet1".to_string()], true).unwrap();
+    assert!(!contains(&zip_entry(&temp_file, SHEET), b"sheetProtection"));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn unprotect_missing_sheet_xlsm() {
+    let file = "tests/data/xlsm/Unlocked_with_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 114);
+    protect_sheet(&temp_file);
+    let sheets = ["Sheet1".to_string(), "Missing".to_string()];
+    assert!(matches!(
+        xl(&temp_file, &sheets, true),
+        Err(UnlockError::NoSheet(name)) if name == "Missing"
+    ));
+    assert!(contains(&zip_entry(&temp_file, SHEET), b"sheetProtection"));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+/*
+* XLSB
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn unprotect_sheets_xlsb() {
+    let file = "tests/data/xlsb/Unlocked_with_macro.xlsb";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 111);
+    let output = temp_dir.join("Unprotected.xlsb");
+    xl_to(&temp_file, &[], &output).unwrap();
+
+    // The sheet protection record is 66 bytes long, plus 3 for its type and length
+    let sheet = "xl/worksheets/sheet1.bin";
+    assert_eq!(
+        zip_entry(&temp_file, sheet).len() - zip_entry(&output, sheet).len(),
+        69
+    );
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn unprotect_named_sheet_xlsb() {
+    let file = "tests/data/xlsb/Unlocked_with_macro.xlsb";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 112);
+    let output = temp_dir.join("Unprotected.xlsb");
+    xl_to(&temp_file, &["Sheet1".to_string()], &output).unwrap();
+    let sheet = "xl/worksheets/sheet1.bin";
+    assert_eq!(
+        zip_entry(&temp_file, sheet).len() - zip_entry(&output, sheet).len(),
+        69
+    );
+    assert!(matches!(
+        xl_to(&temp_file, &["Sheet2".to_string()], &output),
+        Err(UnlockError::NoSheet(_))
+    ));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+/*
+* XLS
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn unprotect_sheets_xls() {
+    let file = "tests/data/xls/Unlocked_with_macro.xls";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 111);
+    let offset = protect_sheet_97(&temp_file);
+    assert_eq!(record_97(&temp_file, offset), (0x0012, vec![1, 0]));
+    xl_97(&temp_file, &[], true).unwrap();
+    assert_eq!(record_97(&temp_file, offset), (0x0012, vec![0, 0]));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn unprotect_sheets_unprotected_xls() {
+    let file = "tests/data/xls/Unlocked_with_macro.xls";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 112);
+    let output = temp_dir.join("Unprotected.xls");
+    xl_97_to(&temp_file, &[], &output).unwrap();
+    assert_eq!(
+        std::fs::read(&temp_file).unwrap(),
+        std::fs::read(&output).unwrap()
+    );
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn unprotect_named_sheet_xls() {
+    let file = "tests/data/xls/Unlocked_with_macro.xls";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 113);
+    let offset = protect_sheet_97(&temp_file);
+    assert!(matches!(
+        xl_97(&temp_file, &["Sheet2".to_string()], true),
+        Err(UnlockError::NoSheet(_))
+    ));
+    assert_eq!(record_97(&temp_file, offset), (0x0012, vec![1, 0]));
+    xl_97(&temp_file, &["Sheet1".to_string()], true).unwrap();
+    assert_eq!(record_97(&temp_file, offset), (0x0012, vec![0, 0]));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+/// Add sheet protection to the first sheet of an xlsm file
+fn protect_sheet(filename: &Path) {
+    let data = std::fs::read(filename).unwrap();
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+    let mut new_archive = zip::ZipWriter::new(std::fs::File::create(filename).unwrap());
+    for i in 0..archive.len() {
+        let name = archive.by_index_raw(i).unwrap().name().to_string();
+        if name == SHEET {
+            let mut xml = String::new();
+            archive
+                .by_index(i)
+                .unwrap()
+                .read_to_string(&mut xml)
+                .unwrap();
+            let xml = xml.replace("<sheetData/>", &format!("<sheetData/>{PROTECTION}"));
+            new_archive
+                .start_file(name, zip::write::FileOptions::default())
+                .unwrap();
+            new_archive.write_all(xml.as_bytes()).unwrap();
+        } else {
+            new_archive
+                .raw_copy_file(archive.by_index_raw(i).unwrap())
+                .unwrap();
+        }
+    }
+    new_archive.finish().unwrap();
+}
+
+/// Protect the first sheet of an xls file, by turning a two byte record of the sheet into a
+/// protection record. Returns the offset of the record within the workbook stream
+fn protect_sheet_97(filename: &Path) -> usize {
+    let mut file = cfb::open_rw(filename).unwrap();
+    let mut stream = Vec::new();
+    file.open_stream("/Workbook")
+        .unwrap()
+        .read_to_end(&mut stream)
+        .unwrap();
+    let (mut pos, mut globals) = (0, true);
+    let offset = loop {
+        let record = u16::from_le_bytes([stream[pos], stream[pos + 1]]);
+        let length = usize::from(u16::from_le_bytes([stream[pos + 2], stream[pos + 3]]));
+        if !globals && length == 2 && record != 0x0809 {
+            break pos;
+        }
+        globals &= record != 0x000A;
+        pos += 4 + length;
+    };
+    stream[offset..offset + 6].copy_from_slice(&[0x12, 0x00, 0x02, 0x00, 0x01, 0x00]);
+    file.open_stream("/Workbook")
+        .unwrap()
+        .write_all(&stream)
+        .unwrap();
+    offset
+}
+
+fn record_97(filename: &Path, offset: usize) -> (u16, Vec<u8>) {
+    let mut file = cfb::open(filename).unwrap();
+    let mut stream = Vec::new();
+    file.open_stream("/Workbook")
+        .unwrap()
+        .read_to_end(&mut stream)
+        .unwrap();
+    let record = u16::from_le_bytes([stream[offset], stream[offset + 1]]);
+    (record, stream[offset + 4..offset + 6].to_vec())
+}
+
+fn zip_entry(filename: &Path, name: &str) -> Vec<u8> {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(filename).unwrap()).unwrap();
+    let mut contents = Vec::new();
+    archive
+        .by_name(name)
+        .unwrap()
+        .read_to_end(&mut contents)
+        .unwrap();
+    contents
+}
+
+fn contains(data: &[u8], text: &[u8]) -> bool {
+    data.windows(text.len()).any(|w| w == text)
+}
+
+fn create_temp_dir(source: &dyn AsRef<Path>, unique_num: u8) -> (PathBuf, PathBuf) {
+    let source = source.as_ref();
+    let mut folder = source.parent().unwrap().to_path_buf();
+    folder.push(format!("temp_{unique_num}"));
+    let mut copied_file = folder.clone();
+    copied_file.push(source.file_name().unwrap());
+    std::fs::create_dir(&folder).unwrap();
+    let _ = std::fs::copy(source, &copied_file);
+    (folder, copied_file)
+}
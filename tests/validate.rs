@@ -0,0 +1,93 @@
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use unlock_excel::error::UnlockError;
+use unlock_excel::validate::{xl, xl_97};
+
+/*
+* XLSM
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn validate_xlsm() {
+    for file in [
+        "tests/data/xlsm/Locked_with_macro.xlsm",
+        "tests/data/xlsm/Unlocked_with_macro.xlsm",
+    ] {
+        assert!(xl(Path::new(file)).unwrap().is_empty());
+    }
+}
+
+#[test]
+fn validate_no_vba_xlsm() {
+    let file = "tests/data/xlsm/Unlocked_no_macro.xlsm";
+    assert!(matches!(xl(Path::new(file)), Err(UnlockError::NoVBAFile)));
+}
+
+/*
+* XLS
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn validate_xls() {
+    for file in [
+        "tests/data/xls/Locked_with_macro.xls",
+        "tests/data/xls/Unlocked_with_macro.xls",
+    ] {
+        assert!(xl_97(Path::new(file)).unwrap().is_empty());
+    }
+}
+
+#[test]
+fn validate_no_vba_xls() {
+    let file = "tests/data/xls/Unlocked_no_macro.xls";
+    assert!(matches!(
+        xl_97(Path::new(file)),
+        Err(UnlockError::NoVBAFile)
+    ));
+}
+
+#[test]
+fn validate_broken_xls() {
+    let file = "tests/data/xls/Unlocked_with_macro.xls";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 97);
+    let mut cfb = cfb::open_rw(&temp_file).unwrap();
+    let mut project = String::new();
+    cfb.open_stream("/_VBA_PROJECT_CUR/PROJECT")
+        .unwrap()
+        .read_to_string(&mut project)
+        .unwrap();
+    let project = project
+        .replace("Name=\"VBAProject\"", "Name=\"Renamed\"\r\nTool=\"Mine\"")
+        .replace("\r\n", "\n");
+    cfb.create_stream("/_VBA_PROJECT_CUR/PROJECT")
+        .unwrap()
+        .write_all(project.as_bytes())
+        .unwrap();
+    cfb.remove_stream("/_VBA_PROJECT_CUR/VBA/Module1").unwrap();
+    cfb.flush().unwrap();
+    drop(cfb);
+
+    assert_eq!(
+        xl_97(&temp_file).unwrap(),
+        vec![
+            "PROJECT stream: Tool=\"Mine\" is not a property in the specification",
+            "PROJECT stream: lines MUST end with CRLF or LFCR, not LF",
+            "the project is named VBAProject in the dir stream but Renamed in the PROJECT stream",
+            "module Module1 has no stream, at /_VBA_PROJECT_CUR/VBA/Module1",
+        ]
+    );
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+fn create_temp_dir(source: &dyn AsRef<Path>, unique_num: u8) -> (PathBuf, PathBuf) {
+    let source = source.as_ref();
+    let mut folder = source.parent().unwrap().to_path_buf();
+    folder.push(format!("temp_{unique_num}"));
+    let mut copied_file = folder.clone();
+    copied_file.push(source.file_name().unwrap());
+    std::fs::create_dir(&folder).unwrap();
+    let _ = std::fs::copy(source, &copied_file);
+    (folder, copied_file)
+}
@@ -0,0 +1,97 @@
+use std::path::{Path, PathBuf};
+use unlock_excel::error::UnlockError;
+use unlock_excel::lenient::Options;
+use unlock_excel::verify::{xl, xl_97};
+use unlock_excel::{remove, strip};
+
+/*
+* XLSM
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn verify_removed_xlsm() {
+    let file = "tests/data/xlsm/Locked_with_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 101);
+    let output = temp_dir.join("Unlocked.xlsm");
+    remove::xl_to(&temp_file, &output, &Options::default()).unwrap();
+    assert!(xl(&temp_file, &output, &Options::default())
+        .unwrap()
+        .is_empty());
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn verify_still_locked_xlsm() {
+    let file = "tests/data/xlsm/Locked_with_macro.xlsm";
+    assert_eq!(
+        xl(Path::new(file), Path::new(file), &Options::default()).unwrap(),
+        vec!["The VBA project is still locked"]
+    );
+}
+
+#[test]
+fn verify_different_source_xlsm() {
+    let file = "tests/data/xlsm/Locked_with_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 102);
+    let output = temp_dir.join("Unlocked.xlsm");
+    remove::xl_to(&temp_file, &output, &Options::default()).unwrap();
+    let other = Path::new("tests/data/xlsm/Locked_with_macro_and_complex_password.xlsm");
+    let problems = xl(other, &output, &Options::default()).unwrap();
+    assert!(problems.contains(&"xl/workbook.xml differs from the source".to_string()));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn verify_no_vba_xlsm() {
+    let file = "tests/data/xlsm/Locked_with_macro.xlsm";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 103);
+    let output = temp_dir.join("Stripped.xlsm");
+    strip::xl_to(&temp_file, &output).unwrap();
+    assert!(matches!(
+        xl(&temp_file, &output, &Options::default()),
+        Err(UnlockError::NoVBAFile)
+    ));
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+/*
+* XLS
+* ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+*/
+
+#[test]
+fn verify_removed_xls() {
+    let file = "tests/data/xls/Locked_with_macro.xls";
+    let (temp_dir, temp_file) = create_temp_dir(&file, 101);
+    let output = temp_dir.join("Unlocked.xls");
+    remove::xl_97_to(&temp_file, &output, &Options::default()).unwrap();
+    assert!(xl_97(&temp_file, &output, &Options::default())
+        .unwrap()
+        .is_empty());
+    let _ = std::fs::remove_dir_all(temp_dir);
+}
+
+#[test]
+fn verify_different_file_xls() {
+    let source = Path::new("tests/data/xls/Locked_with_macro.xls");
+    let output = Path::new("tests/data/xls/Unlocked_with_macro.xls");
+    let problems = xl_97(source, output, &Options::default()).unwrap();
+    assert!(!problems.is_empty());
+    assert!(problems
+        .iter()
+        .all(|p| p.ends_with("differs from the source")
+            || p.ends_with("is missing from the output")
+            || p.ends_with("is not in the source")));
+}
+
+fn create_temp_dir(source: &dyn AsRef<Path>, unique_num: u8) -> (PathBuf, PathBuf) {
+    let source = source.as_ref();
+    let mut folder = source.parent().unwrap().to_path_buf();
+    folder.push(format!("temp_{unique_num}"));
+    let mut copied_file = folder.clone();
+    copied_file.push(source.file_name().unwrap());
+    std::fs::create_dir(&folder).unwrap();
+    let _ = std::fs::copy(source, &copied_file);
+    (folder, copied_file)
+}
@@ -0,0 +1,46 @@
+use std::path::{Path, PathBuf};
+use unlock_excel::crack::{xl, Options};
+use unlock_excel::error::UnlockError;
+use unlock_excel::lenient;
+use unlock_excel::wordlists;
+
+// The cache directory is shared by the whole process, so it is checked in a single test
+#[test]
+fn cached_wordlists() {
+    let cache = PathBuf::from("tests/data/temp_88");
+    std::env::set_var("UNLOCK_EXCEL_CACHE", &cache);
+    let cached = wordlists::path("company").unwrap();
+    assert_eq!(cached, cache.join("wordlists").join("company.txt"));
+    std::fs::create_dir_all(cached.parent().unwrap()).unwrap();
+    std::fs::write(&cached, "letmein\nP@ssw0rd\n").unwrap();
+
+    assert_eq!(wordlists::resolve(Path::new("company")), cached);
+    let path = Path::new("tests/data/xlsm/Locked_with_macro.xlsm");
+    assert_eq!(wordlists::resolve(path), path);
+    assert_eq!(
+        wordlists::resolve(Path::new("missing")),
+        Path::new("missing")
+    );
+
+    let options = Options {
+        wordlists: vec![PathBuf::from("company")],
+        ..Options::default()
+    };
+    assert_eq!(
+        "P@ssw0rd",
+        xl(path, &options, &lenient::Options::default()).unwrap()
+    );
+    let _ = std::fs::remove_dir_all(cache);
+}
+
+#[test]
+fn unknown_wordlists() {
+    assert!(matches!(
+        wordlists::fetch("no_such_list", None, None),
+        Err(UnlockError::UnknownWordlist(_))
+    ));
+    assert!(matches!(
+        wordlists::fetch("../escape", Some("https://example.com/list.txt"), None),
+        Err(UnlockError::UnknownWordlist(_))
+    ));
+}